@@ -0,0 +1,2 @@
+-- Multi-year totals overflow int4 cents; amounts are stored as 64-bit cents.
+ALTER TABLE expenses.payments ALTER COLUMN amount TYPE bigint;
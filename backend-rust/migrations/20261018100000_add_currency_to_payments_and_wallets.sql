@@ -0,0 +1,9 @@
+-- Payments carry an ISO 4217 currency code; wallets provide the default for new payments.
+-- Existing rows were implicitly in euros.
+ALTER TABLE expenses.wallets
+    ADD COLUMN default_currency varchar(3) NOT NULL DEFAULT 'EUR'
+    CONSTRAINT wallets_default_currency_iso4217 CHECK (default_currency ~ '^[A-Z]{3}$');
+
+ALTER TABLE expenses.payments
+    ADD COLUMN currency varchar(3) NOT NULL DEFAULT 'EUR'
+    CONSTRAINT payments_currency_iso4217 CHECK (currency ~ '^[A-Z]{3}$');
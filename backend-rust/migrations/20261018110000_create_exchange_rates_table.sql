@@ -0,0 +1,15 @@
+-- Daily exchange rates: 1 base_currency = rate quote_currency on rate_date.
+-- Rates are shared by all users and maintained through the admin API or a rates file.
+CREATE TABLE IF NOT EXISTS expenses.exchange_rates (
+    base_currency varchar(3) NOT NULL CHECK (base_currency ~ '^[A-Z]{3}$'),
+    quote_currency varchar(3) NOT NULL CHECK (quote_currency ~ '^[A-Z]{3}$'),
+    rate_date date NOT NULL,
+    rate double precision NOT NULL CHECK (rate > 0),
+    updated_at timestamptz NOT NULL DEFAULT now(),
+    CONSTRAINT exchange_rates_pk PRIMARY KEY (base_currency, quote_currency, rate_date),
+    CONSTRAINT exchange_rates_distinct_currencies CHECK (base_currency <> quote_currency)
+);
+
+-- Nearest-previous-date lookups scan a pair's rates backwards from a date.
+CREATE INDEX IF NOT EXISTS idx_exchange_rates_quote_date
+    ON expenses.exchange_rates (quote_currency, rate_date);
@@ -0,0 +1,7 @@
+-- A transfer between two wallets is stored as two payments sharing the same transfer_id:
+-- a debit in the source wallet and a credit in the destination wallet.
+ALTER TABLE expenses.payments ADD COLUMN transfer_id uuid NULL;
+
+CREATE INDEX IF NOT EXISTS idx_payments_transfer_id
+    ON expenses.payments (transfer_id)
+    WHERE transfer_id IS NOT NULL;
@@ -0,0 +1,25 @@
+-- Line items of a payment spread over several categories (e.g. a supermarket receipt).
+-- When a payment has splits, they replace its own category in category filters and
+-- breakdowns; their amounts always add up to the payment amount.
+CREATE TABLE expenses.payment_splits (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    payment_id uuid NOT NULL REFERENCES expenses.payments (id) ON DELETE CASCADE,
+    category_id uuid NOT NULL REFERENCES expenses.categories (id),
+    amount bigint NOT NULL,
+    description varchar NULL,
+    position integer NOT NULL,
+    user_id text NOT NULL,
+    CONSTRAINT payment_splits_payment_position_key UNIQUE (payment_id, position)
+);
+
+CREATE INDEX IF NOT EXISTS idx_payment_splits_category_id ON expenses.payment_splits (category_id);
+CREATE INDEX IF NOT EXISTS idx_payment_splits_user_id ON expenses.payment_splits (user_id);
+
+-- One row per category line: the splits of split payments, the payment itself otherwise.
+CREATE VIEW expenses.payment_lines AS
+SELECT s.payment_id, s.category_id, s.amount
+FROM expenses.payment_splits s
+UNION ALL
+SELECT p.id AS payment_id, p.category_id, p.amount
+FROM expenses.payments p
+WHERE NOT EXISTS (SELECT 1 FROM expenses.payment_splits s WHERE s.payment_id = p.id);
@@ -0,0 +1,38 @@
+-- Recurring payment templates (rent, subscriptions, salary...) materialised
+-- into expenses.payments by the scheduler, one payment per occurrence.
+CREATE TABLE expenses.recurring_payments (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id text NOT NULL,
+    merchant_name varchar NOT NULL,
+    description varchar NULL,
+    amount bigint NOT NULL,
+    currency varchar(3) NOT NULL,
+    category_id uuid NOT NULL REFERENCES expenses.categories(id),
+    wallet_id uuid NULL REFERENCES expenses.wallets(id),
+    -- [{"key": ..., "value": ...}] copied onto every generated payment
+    tags jsonb NOT NULL DEFAULT '[]',
+    -- RRULE subset, e.g. FREQ=MONTHLY;BYMONTHDAY=1
+    recurrence text NOT NULL,
+    start_date date NOT NULL,
+    status text NOT NULL DEFAULT 'active',
+    -- NULL once the series has ended
+    next_occurrence date NULL,
+    created_at timestamp NOT NULL DEFAULT now(),
+    CONSTRAINT recurring_payments_status CHECK (status IN ('active', 'paused')),
+    CONSTRAINT recurring_payments_currency_iso4217 CHECK (currency ~ '^[A-Z]{3}$')
+);
+
+CREATE INDEX idx_recurring_payments_user_id ON expenses.recurring_payments (user_id);
+CREATE INDEX idx_recurring_payments_due ON expenses.recurring_payments (next_occurrence)
+    WHERE status = 'active';
+
+-- One row per handled occurrence: the primary key makes materialisation
+-- idempotent, and skipped occurrences are recorded without a payment.
+CREATE TABLE expenses.recurring_payment_occurrences (
+    recurring_payment_id uuid NOT NULL REFERENCES expenses.recurring_payments(id) ON DELETE CASCADE,
+    occurrence_date date NOT NULL,
+    payment_id uuid NULL REFERENCES expenses.payments(id) ON DELETE SET NULL,
+    skipped boolean NOT NULL DEFAULT false,
+    created_at timestamp NOT NULL DEFAULT now(),
+    PRIMARY KEY (recurring_payment_id, occurrence_date)
+);
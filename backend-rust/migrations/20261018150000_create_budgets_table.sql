@@ -0,0 +1,22 @@
+-- Spending limits per period, optionally restricted to a category and/or a wallet.
+CREATE TABLE expenses.budgets (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id text NOT NULL,
+    period text NOT NULL DEFAULT 'monthly',
+    category_id uuid NULL REFERENCES expenses.categories(id),
+    wallet_id uuid NULL REFERENCES expenses.wallets(id),
+    limit_amount bigint NOT NULL,
+    currency varchar(3) NOT NULL DEFAULT 'EUR',
+    created_at timestamp NOT NULL DEFAULT now(),
+    CONSTRAINT budgets_period CHECK (period IN ('monthly')),
+    CONSTRAINT budgets_limit_positive CHECK (limit_amount > 0),
+    CONSTRAINT budgets_currency_iso4217 CHECK (currency ~ '^[A-Z]{3}$')
+);
+
+-- One budget per scope: NULL category or wallet means "all of them".
+CREATE UNIQUE INDEX budgets_scope_key ON expenses.budgets (
+    user_id,
+    period,
+    COALESCE(category_id, '00000000-0000-0000-0000-000000000000'::uuid),
+    COALESCE(wallet_id, '00000000-0000-0000-0000-000000000000'::uuid)
+);
@@ -0,0 +1,19 @@
+-- Budget thresholds reached, one row per budget, period and threshold so that
+-- each alert is sent at most once per period.
+CREATE TABLE expenses.budget_alert_events (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    budget_id uuid NOT NULL REFERENCES expenses.budgets(id) ON DELETE CASCADE,
+    user_id text NOT NULL,
+    period_start date NOT NULL,
+    threshold smallint NOT NULL,
+    spent bigint NOT NULL,
+    limit_amount bigint NOT NULL,
+    currency varchar(3) NOT NULL,
+    percent_used double precision NOT NULL,
+    created_at timestamp NOT NULL DEFAULT now(),
+    -- NULL until a notifier delivered the alert
+    notified_at timestamp NULL,
+    CONSTRAINT budget_alert_events_once_per_period UNIQUE (budget_id, period_start, threshold)
+);
+
+CREATE INDEX idx_budget_alert_events_user_id ON expenses.budget_alert_events (user_id);
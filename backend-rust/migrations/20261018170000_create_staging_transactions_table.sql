@@ -0,0 +1,27 @@
+-- Bank transactions waiting for review before being imported as payments
+-- (see docs/DESIGN_REVIEW_PSD2.md).
+CREATE TABLE expenses.staging_transactions (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id text NOT NULL,
+    bank_transaction_id text NOT NULL,
+    amount_in_cents bigint NOT NULL,
+    currency varchar(3) NOT NULL DEFAULT 'EUR',
+    booking_date date NOT NULL,
+    value_date date NULL,
+    creditor_name text NULL,
+    debtor_name text NULL,
+    remittance_info text NULL,
+    suggested_category text NULL,
+    suggested_merchant text NULL,
+    status text NOT NULL DEFAULT 'pending',
+    imported_payment_id uuid NULL REFERENCES expenses.payments(id) ON DELETE SET NULL,
+    created_at timestamptz NOT NULL DEFAULT now(),
+    updated_at timestamptz NOT NULL DEFAULT now(),
+    CONSTRAINT staging_transactions_status CHECK (status IN ('pending', 'reviewed', 'imported', 'rejected')),
+    CONSTRAINT staging_transactions_currency_iso4217 CHECK (currency ~ '^[A-Z]{3}$'),
+    -- Syncs are idempotent: a bank transaction is staged once per user.
+    CONSTRAINT staging_transactions_bank_transaction_key UNIQUE (user_id, bank_transaction_id)
+);
+
+CREATE INDEX idx_staging_user_status ON expenses.staging_transactions(user_id, status);
+CREATE INDEX idx_staging_booking_date ON expenses.staging_transactions(booking_date);
@@ -0,0 +1,18 @@
+-- Bank accounts connected through an open-banking provider (see docs/DESIGN_REVIEW_PSD2.md).
+CREATE TABLE expenses.bank_connections (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id text NOT NULL,
+    -- Name of the configured provider, e.g. `mock`
+    provider text NOT NULL,
+    -- Provider account to sync; every account of the consent when NULL
+    account_id text NULL,
+    encrypted_refresh_token bytea NULL,
+    scopes text[] NOT NULL DEFAULT '{}',
+    -- Provider cursor of the last synced transaction, by account id
+    sync_cursors jsonb NOT NULL DEFAULT '{}',
+    last_sync_at timestamptz NULL,
+    created_at timestamptz NOT NULL DEFAULT now(),
+    updated_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX idx_bank_connections_user ON expenses.bank_connections(user_id);
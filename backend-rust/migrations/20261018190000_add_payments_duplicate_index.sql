@@ -0,0 +1,4 @@
+-- Duplicate detection looks up payments of a user with the same amount on the same day.
+CREATE INDEX IF NOT EXISTS idx_payments_user_amount_day
+    ON expenses.payments (user_id, amount, (accounting_date::date))
+    WHERE transfer_id IS NULL;
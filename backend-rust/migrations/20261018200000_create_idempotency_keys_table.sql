@@ -0,0 +1,17 @@
+-- Responses of requests sent with an `Idempotency-Key` header, replayed when
+-- the client retries with the same key (see `idempotency.rs`).
+CREATE TABLE expenses.idempotency_keys (
+    user_id text NOT NULL,
+    idempotency_key text NOT NULL,
+    -- SHA-256 of the method, URI and body of the first request
+    request_hash bytea NOT NULL,
+    -- NULL while the first request is being processed
+    response_status smallint NULL,
+    response_content_type text NULL,
+    response_body bytea NULL,
+    created_at timestamptz NOT NULL DEFAULT now(),
+    expires_at timestamptz NOT NULL,
+    PRIMARY KEY (user_id, idempotency_key)
+);
+
+CREATE INDEX idx_idempotency_keys_expires_at ON expenses.idempotency_keys(expires_at);
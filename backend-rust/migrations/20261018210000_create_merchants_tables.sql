@@ -0,0 +1,43 @@
+-- Canonical merchants: the spellings of a merchant ("AMAZON EU", "Amazon.it")
+-- resolve to one row through its normalised name and its aliases.
+CREATE TABLE expenses.merchants (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id text NOT NULL,
+    name varchar NOT NULL,
+    -- Lower-cased, whitespace runs collapsed (normalise_merchant_name)
+    normalised_name varchar NOT NULL,
+    created_at timestamp NOT NULL DEFAULT now(),
+    CONSTRAINT merchants_normalised_name_key UNIQUE (user_id, normalised_name)
+);
+
+-- Other normalised spellings of a merchant.
+CREATE TABLE expenses.merchant_aliases (
+    user_id text NOT NULL,
+    alias varchar NOT NULL,
+    merchant_id uuid NOT NULL REFERENCES expenses.merchants(id) ON DELETE CASCADE,
+    created_at timestamp NOT NULL DEFAULT now(),
+    CONSTRAINT merchant_aliases_pk PRIMARY KEY (user_id, alias)
+);
+
+CREATE INDEX idx_merchant_aliases_merchant_id ON expenses.merchant_aliases (merchant_id);
+
+ALTER TABLE expenses.payments
+    ADD COLUMN merchant_id uuid NULL REFERENCES expenses.merchants(id);
+
+CREATE INDEX idx_payments_merchant_id ON expenses.payments (merchant_id);
+
+-- One merchant per distinct normalised name of the existing payments;
+-- transfer legs name a wallet, not a merchant.
+INSERT INTO expenses.merchants (user_id, name, normalised_name)
+SELECT user_id, min(btrim(merchant_name)),
+       lower(regexp_replace(btrim(merchant_name), '\s+', ' ', 'g')) AS normalised_name
+FROM expenses.payments
+WHERE transfer_id IS NULL AND btrim(coalesce(merchant_name, '')) <> ''
+GROUP BY user_id, normalised_name;
+
+UPDATE expenses.payments p
+SET merchant_id = m.id
+FROM expenses.merchants m
+WHERE p.transfer_id IS NULL
+  AND m.user_id = p.user_id
+  AND m.normalised_name = lower(regexp_replace(btrim(p.merchant_name), '\s+', ' ', 'g'));
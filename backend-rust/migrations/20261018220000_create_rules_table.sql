@@ -0,0 +1,16 @@
+-- User-defined auto-categorisation rules: when all the conditions of a rule
+-- match a payment, its actions are applied. Rules apply in position order.
+CREATE TABLE expenses.rules (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id text NOT NULL,
+    name varchar NOT NULL,
+    position integer NOT NULL,
+    enabled boolean NOT NULL DEFAULT true,
+    -- Tagged JSON objects, see domain::RuleCondition and domain::RuleAction
+    conditions jsonb NOT NULL,
+    actions jsonb NOT NULL,
+    created_at timestamp NOT NULL DEFAULT now(),
+    updated_at timestamp NOT NULL DEFAULT now()
+);
+
+CREATE INDEX idx_rules_user_id_position ON expenses.rules (user_id, position);
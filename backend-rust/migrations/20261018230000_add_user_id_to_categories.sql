@@ -0,0 +1,42 @@
+-- Categories belong to a user; those without user_id are global defaults,
+-- visible to every user and read-only.
+ALTER TABLE expenses.categories ADD COLUMN user_id text NULL;
+
+CREATE INDEX idx_categories_user_id ON expenses.categories (user_id);
+
+-- Names are unique per user, and among the global defaults
+DROP INDEX expenses.categories_lower_name_idx;
+CREATE UNIQUE INDEX categories_user_id_lower_name_idx
+    ON expenses.categories (user_id, lower(name)) WHERE user_id IS NOT NULL;
+CREATE UNIQUE INDEX categories_global_lower_name_idx
+    ON expenses.categories (lower(name)) WHERE user_id IS NULL;
+
+-- Backfill: a category used (by payments, splits, recurring payments, budgets
+-- or rules) by a single user becomes theirs. Categories shared by several
+-- users, unused ones and the category of transfers stay global defaults.
+WITH usages AS (
+    SELECT category_id, user_id FROM expenses.payments
+    WHERE category_id IS NOT NULL AND transfer_id IS NULL
+    UNION
+    SELECT category_id, user_id FROM expenses.payment_splits
+    UNION
+    SELECT category_id, user_id FROM expenses.recurring_payments
+    UNION
+    SELECT category_id, user_id FROM expenses.budgets WHERE category_id IS NOT NULL
+    UNION
+    SELECT (a.action->>'categoryId')::uuid, r.user_id
+    FROM expenses.rules r, jsonb_array_elements(r.actions) AS a(action)
+    WHERE a.action->>'type' = 'setCategory'
+), owners AS (
+    SELECT category_id, min(user_id) AS user_id
+    FROM usages
+    WHERE user_id IS NOT NULL
+    GROUP BY category_id
+    HAVING count(DISTINCT user_id) = 1
+)
+UPDATE expenses.categories c
+SET user_id = o.user_id
+FROM owners o
+WHERE c.id = o.category_id
+  AND NOT EXISTS (SELECT 1 FROM expenses.payments t
+                  WHERE t.category_id = c.id AND t.transfer_id IS NOT NULL);
@@ -0,0 +1,4 @@
+-- Display colour of a category, as a `#rrggbb` hex code.
+ALTER TABLE expenses.categories ADD COLUMN colour text NULL;
+ALTER TABLE expenses.categories
+    ADD CONSTRAINT categories_colour_hex CHECK (colour ~ '^#[0-9a-f]{6}$');
@@ -0,0 +1,6 @@
+-- Subcategories, e.g. "Food > Restaurants". Cycles are prevented by the application.
+ALTER TABLE expenses.categories
+    ADD COLUMN parent_id uuid NULL REFERENCES expenses.categories(id),
+    ADD CONSTRAINT categories_parent_not_self CHECK (parent_id <> id);
+
+CREATE INDEX IF NOT EXISTS idx_categories_parent_id ON expenses.categories(parent_id);
@@ -0,0 +1,3 @@
+-- Tag listings, renames and filters look tags up by key and value within a user
+CREATE INDEX IF NOT EXISTS idx_payments_tags_user_id_key_value
+    ON expenses.payments_tags (user_id, key, value);
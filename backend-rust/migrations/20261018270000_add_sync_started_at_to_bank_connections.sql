@@ -0,0 +1,3 @@
+-- Claim of a running sync, so that provider calls happen outside of any transaction.
+-- Claims older than the sync timeout are considered abandoned.
+ALTER TABLE expenses.bank_connections ADD COLUMN sync_started_at timestamptz NULL;
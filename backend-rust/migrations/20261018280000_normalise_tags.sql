@@ -0,0 +1,67 @@
+-- Tags are validated on write through domain::TagKey and domain::TagValue:
+-- trimmed, at most 64 characters, and without ':' in keys (the separator of
+-- `key:value` filters). Stored tags are brought in line so that payments
+-- echoing them back, recurring payments and rules keep being accepted.
+-- Separators in keys become '_', longer tags are cut, and tags left empty
+-- are dropped.
+CREATE FUNCTION pg_temp.normalise_tag(tag text, is_key boolean) RETURNS text AS $$
+    SELECT regexp_replace(
+        left(
+            regexp_replace(
+                CASE WHEN is_key THEN replace(tag, ':', '_') ELSE tag END,
+                '^\s+|\s+$', '', 'g'
+            ),
+            64
+        ),
+        '\s+$', ''
+    )
+$$ LANGUAGE sql IMMUTABLE;
+
+-- Payments, keeping a single copy of repeated tags
+DELETE FROM expenses.payments_tags
+WHERE pg_temp.normalise_tag(key, true) = '' OR pg_temp.normalise_tag(value, false) = '';
+
+UPDATE expenses.payments_tags
+SET key = pg_temp.normalise_tag(key, true),
+    value = pg_temp.normalise_tag(value, false)
+WHERE key <> pg_temp.normalise_tag(key, true) OR value <> pg_temp.normalise_tag(value, false);
+
+DELETE FROM expenses.payments_tags t
+USING expenses.payments_tags o
+WHERE o.payment_id = t.payment_id AND o.key = t.key AND o.value = t.value AND o.id < t.id;
+
+-- Recurring payment templates
+UPDATE expenses.recurring_payments r
+SET tags = COALESCE((
+    SELECT jsonb_agg(jsonb_build_object('key', n.key, 'value', n.value) ORDER BY n.position)
+    FROM (
+        SELECT pg_temp.normalise_tag(t.tag->>'key', true) AS key,
+               pg_temp.normalise_tag(t.tag->>'value', false) AS value,
+               min(t.position) AS position
+        FROM jsonb_array_elements(r.tags) WITH ORDINALITY AS t(tag, position)
+        GROUP BY 1, 2
+    ) n
+    WHERE n.key <> '' AND n.value <> ''
+), '[]')
+WHERE jsonb_typeof(r.tags) = 'array' AND jsonb_array_length(r.tags) > 0;
+
+-- addTag actions of rules
+UPDATE expenses.rules r
+SET actions = COALESCE((
+    SELECT jsonb_agg(
+        CASE WHEN a.action->>'type' = 'addTag'
+             THEN a.action || jsonb_build_object(
+                 'key', pg_temp.normalise_tag(a.action->>'key', true),
+                 'value', pg_temp.normalise_tag(a.action->>'value', false))
+             ELSE a.action
+        END
+        ORDER BY a.position)
+    FROM jsonb_array_elements(r.actions) WITH ORDINALITY AS a(action, position)
+    WHERE a.action->>'type' IS DISTINCT FROM 'addTag'
+       OR (pg_temp.normalise_tag(a.action->>'key', true) <> ''
+           AND pg_temp.normalise_tag(a.action->>'value', false) <> '')
+), '[]')
+WHERE EXISTS (
+    SELECT 1 FROM jsonb_array_elements(r.actions) AS a(action)
+    WHERE a.action->>'type' = 'addTag'
+);
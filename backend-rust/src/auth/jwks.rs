@@ -0,0 +1,283 @@
+use crate::configuration::AuthSettings;
+use jsonwebtoken::jwk::{JwkSet, PublicKeyUse};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// Algorithms accepted for bearer tokens. Symmetric algorithms and `none`
+/// are never accepted.
+const ALLOWED_ALGORITHMS: [Algorithm; 2] = [Algorithm::RS256, Algorithm::ES256];
+
+/// Minimum delay between two JWKS refreshes triggered by an unknown `kid`,
+/// so that tokens with random key ids cannot be used to hammer the provider.
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
+pub enum JwksSource {
+    Url(String),
+    File(String),
+}
+
+impl JwksSource {
+    pub fn from_settings(settings: &AuthSettings) -> Result<Self, String> {
+        match (&settings.jwks_url, &settings.jwks_path) {
+            (Some(url), None) => Ok(Self::Url(url.clone())),
+            (None, Some(path)) => Ok(Self::File(path.clone())),
+            (Some(_), Some(_)) => Err("Only one of jwks_url and jwks_path can be set".to_string()),
+            (None, None) => Err("One of jwks_url or jwks_path must be set".to_string()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum AuthError {
+    /// The token is malformed, has an invalid signature or invalid claims.
+    InvalidToken(String),
+    /// The signing keys could not be loaded from the configured source.
+    KeysUnavailable(String),
+}
+
+impl std::fmt::Display for AuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthError::InvalidToken(reason) => write!(f, "Invalid token: {}", reason),
+            AuthError::KeysUnavailable(reason) => write!(f, "Signing keys unavailable: {}", reason),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    #[serde(default)]
+    pub realm_access: Option<RealmAccess>,
+}
+
+/// Realm roles granted by Keycloak.
+#[derive(Debug, Default, Deserialize)]
+pub struct RealmAccess {
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+struct VerificationKey {
+    algorithm: Option<Algorithm>,
+    key: DecodingKey,
+}
+
+#[derive(Default)]
+struct KeyCache {
+    keys: HashMap<String, VerificationKey>,
+    fetched_at: Option<Instant>,
+}
+
+/// Verifies bearer tokens against the keys published in a JWKS document.
+///
+/// Keys are cached for `jwks_cache_ttl_seconds` and refreshed on expiry or
+/// when a token references a `kid` that is not in the cache (key rotation).
+pub struct JwtVerifier {
+    source: JwksSource,
+    issuer: String,
+    audience: String,
+    leeway: u64,
+    admin_role: String,
+    cache_ttl: Duration,
+    http_client: reqwest::Client,
+    cache: RwLock<KeyCache>,
+}
+
+impl JwtVerifier {
+    pub fn new(settings: &AuthSettings) -> Result<Self, String> {
+        Ok(Self {
+            source: JwksSource::from_settings(settings)?,
+            issuer: settings.issuer.clone(),
+            audience: settings.audience.clone(),
+            leeway: settings.leeway_seconds,
+            admin_role: settings.admin_role.clone(),
+            cache_ttl: Duration::from_secs(settings.jwks_cache_ttl_seconds),
+            http_client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .map_err(|e| format!("Failed to build JWKS http client: {}", e))?,
+            cache: RwLock::new(KeyCache::default()),
+        })
+    }
+
+    pub fn admin_role(&self) -> &str {
+        &self.admin_role
+    }
+
+    #[tracing::instrument(name = "Verifying bearer token", skip(self, token))]
+    pub async fn verify(&self, token: &str) -> Result<Claims, AuthError> {
+        let header = decode_header(token).map_err(|e| AuthError::InvalidToken(e.to_string()))?;
+        if !ALLOWED_ALGORITHMS.contains(&header.alg) {
+            return Err(AuthError::InvalidToken(format!(
+                "Algorithm {:?} is not allowed",
+                header.alg
+            )));
+        }
+        let kid = header
+            .kid
+            .ok_or_else(|| AuthError::InvalidToken("Missing kid in token header".to_string()))?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.audience]);
+        validation.set_required_spec_claims(&["exp", "sub", "iss", "aud"]);
+        validation.validate_nbf = true;
+        validation.leeway = self.leeway;
+
+        self.refresh_if_needed(&kid).await?;
+
+        let cache = self.cache.read().await;
+        let verification_key = cache
+            .keys
+            .get(&kid)
+            .ok_or_else(|| AuthError::InvalidToken(format!("Unknown signing key {}", kid)))?;
+        if let Some(algorithm) = verification_key.algorithm {
+            if algorithm != header.alg {
+                return Err(AuthError::InvalidToken(format!(
+                    "Key {} does not sign with {:?}",
+                    kid, header.alg
+                )));
+            }
+        }
+
+        decode::<Claims>(token, &verification_key.key, &validation)
+            .map(|data| data.claims)
+            .map_err(|e| AuthError::InvalidToken(e.to_string()))
+    }
+
+    async fn refresh_if_needed(&self, kid: &str) -> Result<(), AuthError> {
+        {
+            let cache = self.cache.read().await;
+            if !self.needs_refresh(&cache, kid) {
+                return Ok(());
+            }
+        }
+
+        let mut cache = self.cache.write().await;
+        // Another request may have refreshed the keys while we were waiting for the lock.
+        if !self.needs_refresh(&cache, kid) {
+            return Ok(());
+        }
+
+        match self.load_keys().await {
+            Ok(keys) => {
+                tracing::info!("Loaded {} signing keys from JWKS", keys.len());
+                cache.keys = keys;
+                cache.fetched_at = Some(Instant::now());
+                Ok(())
+            }
+            Err(e) => {
+                tracing::error!("Failed to load JWKS: {}", e);
+                if cache.keys.is_empty() {
+                    return Err(AuthError::KeysUnavailable(e));
+                }
+                // Keep serving the previously loaded keys, but do not retry on every request.
+                cache.fetched_at = Some(Instant::now());
+                Ok(())
+            }
+        }
+    }
+
+    fn needs_refresh(&self, cache: &KeyCache, kid: &str) -> bool {
+        match cache.fetched_at {
+            None => true,
+            Some(fetched_at) => {
+                let age = fetched_at.elapsed();
+                age >= self.cache_ttl
+                    || (!cache.keys.contains_key(kid) && age >= MIN_REFRESH_INTERVAL)
+            }
+        }
+    }
+
+    async fn load_keys(&self) -> Result<HashMap<String, VerificationKey>, String> {
+        let document = match &self.source {
+            JwksSource::Url(url) => self
+                .http_client
+                .get(url)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|e| format!("Failed to fetch {}: {}", url, e))?
+                .text()
+                .await
+                .map_err(|e| format!("Failed to read {}: {}", url, e))?,
+            JwksSource::File(path) => tokio::fs::read_to_string(path)
+                .await
+                .map_err(|e| format!("Failed to read {}: {}", path, e))?,
+        };
+        parse_jwks(&document)
+    }
+}
+
+fn parse_jwks(document: &str) -> Result<HashMap<String, VerificationKey>, String> {
+    let jwks: JwkSet =
+        serde_json::from_str(document).map_err(|e| format!("Invalid JWKS document: {}", e))?;
+
+    let mut keys = HashMap::new();
+    for jwk in jwks.keys {
+        let Some(kid) = jwk.common.key_id.clone() else {
+            tracing::warn!("Skipping JWKS key without kid");
+            continue;
+        };
+        if jwk.common.public_key_use == Some(PublicKeyUse::Encryption) {
+            continue;
+        }
+        let algorithm = match jwk.common.key_algorithm {
+            Some(key_algorithm) => match key_algorithm.to_string().parse::<Algorithm>() {
+                Ok(algorithm) if ALLOWED_ALGORITHMS.contains(&algorithm) => Some(algorithm),
+                _ => {
+                    tracing::debug!("Skipping JWKS key {} with unsupported algorithm", kid);
+                    continue;
+                }
+            },
+            None => None,
+        };
+        match DecodingKey::from_jwk(&jwk) {
+            Ok(key) => {
+                keys.insert(kid, VerificationKey { algorithm, key });
+            }
+            Err(e) => tracing::warn!("Skipping unusable JWKS key {}: {}", kid, e),
+        }
+    }
+
+    if keys.is_empty() {
+        return Err("JWKS document contains no usable signing keys".to_string());
+    }
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_jwks;
+    use claims::assert_ok;
+
+    #[test]
+    fn rsa_and_ec_keys_are_loaded() {
+        let document = include_str!("../../tests/api/fixtures/jwks.json");
+        let keys = assert_ok!(parse_jwks(document));
+        assert!(keys.contains_key("test-rs256"));
+        assert!(keys.contains_key("test-es256"));
+    }
+
+    #[test]
+    fn keys_without_kid_are_skipped() {
+        let document = r#"{"keys":[{"kty":"RSA","alg":"RS256","n":"AQAB","e":"AQAB"}]}"#;
+        assert!(parse_jwks(document).is_err());
+    }
+
+    #[test]
+    fn symmetric_keys_are_skipped() {
+        let document = r#"{"keys":[{"kty":"oct","kid":"hs","alg":"HS256","k":"c2VjcmV0"}]}"#;
+        assert!(parse_jwks(document).is_err());
+    }
+
+    #[test]
+    fn invalid_document_is_rejected() {
+        assert!(parse_jwks("not json").is_err());
+    }
+}
@@ -0,0 +1,86 @@
+mod jwks;
+
+pub use jwks::{AuthError, Claims, JwksSource, JwtVerifier, RealmAccess};
+
+use actix_web::error::{ErrorForbidden, ErrorInternalServerError, ErrorUnauthorized};
+use actix_web::{dev::Payload, web, FromRequest, HttpRequest};
+use serde::Deserialize;
+use std::future::Future;
+use std::pin::Pin;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthenticatedUser {
+    pub sub: String,
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<AuthenticatedUser, actix_web::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        // Expect Authorization: Bearer <token>
+        let token = req
+            .headers()
+            .get(actix_web::http::header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|s| s.strip_prefix("Bearer "))
+            .map(str::to_owned);
+        let verifier = req.app_data::<web::Data<JwtVerifier>>().cloned();
+
+        Box::pin(async move {
+            let token = token.ok_or_else(|| ErrorUnauthorized("Missing Authorization header"))?;
+            let verifier = verifier.ok_or_else(|| {
+                tracing::error!("JwtVerifier is not registered as application data");
+                ErrorInternalServerError("Authentication is not configured")
+            })?;
+
+            // The signature, issuer, audience, exp and nbf are all checked against
+            // the configured JWKS: the service may be reached without the gateway.
+            match verifier.verify(&token).await {
+                Ok(claims) => Ok(AuthenticatedUser {
+                    sub: claims.sub,
+                    roles: claims.realm_access.unwrap_or_default().roles,
+                }),
+                Err(AuthError::InvalidToken(reason)) => {
+                    tracing::debug!("Rejected bearer token: {}", reason);
+                    Err(ErrorUnauthorized("Invalid token"))
+                }
+                Err(e @ AuthError::KeysUnavailable(_)) => {
+                    tracing::error!("{}", e);
+                    Err(ErrorInternalServerError("Unable to verify token"))
+                }
+            }
+        })
+    }
+}
+
+/// An authenticated user holding the configured admin realm role.
+/// Requests from other users are rejected with 403.
+#[derive(Debug, Clone)]
+pub struct AdminUser(pub AuthenticatedUser);
+
+impl FromRequest for AdminUser {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<AdminUser, actix_web::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let user = AuthenticatedUser::from_request(req, payload);
+        let verifier = req.app_data::<web::Data<JwtVerifier>>().cloned();
+
+        Box::pin(async move {
+            let user = user.await?;
+            let admin_role = verifier
+                .as_ref()
+                .map(|v| v.admin_role())
+                .ok_or_else(|| ErrorInternalServerError("Authentication is not configured"))?;
+            if user.roles.iter().any(|role| role == admin_role) {
+                Ok(AdminUser(user))
+            } else {
+                tracing::warn!("User {} is not allowed to call admin endpoints", user.sub);
+                Err(ErrorForbidden("Admin role required"))
+            }
+        })
+    }
+}
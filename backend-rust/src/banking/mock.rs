@@ -0,0 +1,219 @@
+use crate::banking::{
+    BankAccount, BankProvider, BankProviderError, ProviderTokens, TransactionPage,
+};
+use async_trait::async_trait;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use std::path::PathBuf;
+
+const ACCESS_TOKEN: &str = "mock-access-token";
+const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// A bank served from a JSON file, for demos and tests without network access:
+///
+/// ```json
+/// {
+///   "refreshToken": "secret",
+///   "accounts": [{
+///     "id": "acc-1", "name": "Checking", "currency": "EUR",
+///     "transactions": [{"transactionId": "tx-1", "bookingDate": "2026-03-01",
+///                       "amountInCents": -1250, "creditorName": "Bakery"}]
+///   }]
+/// }
+/// ```
+///
+/// The file is read on every call, so transactions can be appended between syncs.
+/// Cursors are the number of transactions of the account already returned.
+pub struct MockBankProvider {
+    path: PathBuf,
+    page_size: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MockBank {
+    /// Refresh token accepted by the bank; any token is accepted when unset.
+    refresh_token: Option<String>,
+    /// Refresh token handed out in place of the used one, as rotating
+    /// providers do; it is accepted as well.
+    rotated_refresh_token: Option<String>,
+    #[serde(default)]
+    accounts: Vec<MockAccount>,
+}
+
+#[derive(Deserialize)]
+struct MockAccount {
+    #[serde(flatten)]
+    account: BankAccount,
+    #[serde(default)]
+    transactions: Vec<serde_json::Value>,
+}
+
+impl MockBankProvider {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            page_size: DEFAULT_PAGE_SIZE,
+        }
+    }
+
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    async fn load(&self, access_token: &SecretString) -> Result<MockBank, BankProviderError> {
+        if access_token.expose_secret() != ACCESS_TOKEN {
+            return Err(BankProviderError::Unauthorized);
+        }
+        self.read().await
+    }
+
+    async fn read(&self) -> Result<MockBank, BankProviderError> {
+        let document = tokio::fs::read_to_string(&self.path).await.map_err(|e| {
+            BankProviderError::Unavailable(format!("cannot read {}: {}", self.path.display(), e))
+        })?;
+        serde_json::from_str(&document).map_err(|e| {
+            BankProviderError::Unavailable(format!("invalid {}: {}", self.path.display(), e))
+        })
+    }
+}
+
+#[async_trait]
+impl BankProvider for MockBankProvider {
+    async fn refresh_token(
+        &self,
+        refresh_token: &SecretString,
+    ) -> Result<ProviderTokens, BankProviderError> {
+        let bank = self.read().await?;
+        let accepted = [&bank.refresh_token, &bank.rotated_refresh_token]
+            .into_iter()
+            .flatten()
+            .any(|token| token == refresh_token.expose_secret());
+        if bank.refresh_token.is_some() && !accepted {
+            return Err(BankProviderError::Unauthorized);
+        }
+        Ok(ProviderTokens {
+            access_token: SecretString::from(ACCESS_TOKEN),
+            refresh_token: bank.rotated_refresh_token.map(SecretString::from),
+        })
+    }
+
+    async fn list_accounts(
+        &self,
+        access_token: &SecretString,
+    ) -> Result<Vec<BankAccount>, BankProviderError> {
+        let bank = self.load(access_token).await?;
+        Ok(bank.accounts.into_iter().map(|a| a.account).collect())
+    }
+
+    async fn fetch_transactions(
+        &self,
+        access_token: &SecretString,
+        account_id: &str,
+        cursor: Option<&str>,
+    ) -> Result<TransactionPage, BankProviderError> {
+        let bank = self.load(access_token).await?;
+        let account = bank
+            .accounts
+            .into_iter()
+            .find(|a| a.account.id == account_id)
+            .ok_or_else(|| {
+                BankProviderError::Unavailable(format!("unknown account {}", account_id))
+            })?;
+        let start = match cursor {
+            Some(cursor) => cursor.parse::<usize>().map_err(|_| {
+                BankProviderError::Unavailable(format!("invalid cursor {}", cursor))
+            })?,
+            None => 0,
+        };
+        let end = (start + self.page_size).min(account.transactions.len());
+        let transactions = account
+            .transactions
+            .get(start..end)
+            .unwrap_or_default()
+            .iter()
+            .map(|value| {
+                serde_json::from_value(value.clone())
+                    .map_err(|e| format!("Unreadable bank transaction: {}", e))
+            })
+            .collect();
+        Ok(TransactionPage {
+            transactions,
+            cursor: end.max(start).to_string(),
+            has_more: end < account.transactions.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MockBankProvider;
+    use crate::banking::{BankProvider, BankProviderError};
+    use claims::{assert_matches, assert_ok};
+    use secrecy::SecretString;
+
+    fn bank_file(document: serde_json::Value) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("mock-bank-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, document.to_string()).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn transactions_are_paged_from_the_cursor() {
+        let transactions: Vec<_> = (1..=3)
+            .map(|i| {
+                serde_json::json!({
+                    "transactionId": format!("tx-{}", i),
+                    "bookingDate": "2026-03-01",
+                    "amountInCents": -i
+                })
+            })
+            .collect();
+        let path = bank_file(serde_json::json!({
+            "accounts": [{"id": "acc-1", "transactions": transactions}]
+        }));
+        let provider = MockBankProvider::new(path).with_page_size(2);
+        let tokens = assert_ok!(provider.refresh_token(&SecretString::from("any")).await);
+
+        let first = assert_ok!(
+            provider
+                .fetch_transactions(&tokens.access_token, "acc-1", None)
+                .await
+        );
+        let second = assert_ok!(
+            provider
+                .fetch_transactions(&tokens.access_token, "acc-1", Some(&first.cursor))
+                .await
+        );
+        let third = assert_ok!(
+            provider
+                .fetch_transactions(&tokens.access_token, "acc-1", Some(&second.cursor))
+                .await
+        );
+
+        assert_eq!(first.transactions.len(), 2);
+        assert!(first.has_more);
+        assert_eq!(
+            second.transactions[0].as_ref().unwrap().transaction_id,
+            "tx-3"
+        );
+        assert!(!second.has_more);
+        assert!(third.transactions.is_empty());
+        assert_eq!(third.cursor, "3");
+    }
+
+    #[tokio::test]
+    async fn unknown_refresh_tokens_are_rejected() {
+        let path = bank_file(serde_json::json!({"refreshToken": "secret", "accounts": []}));
+        let provider = MockBankProvider::new(path);
+
+        let result = provider.refresh_token(&SecretString::from("stolen")).await;
+
+        assert!(matches!(result, Err(BankProviderError::Unauthorized)));
+        let accounts = provider
+            .list_accounts(&SecretString::from("not-an-access-token"))
+            .await;
+        assert_matches!(accounts, Err(BankProviderError::Unauthorized));
+    }
+}
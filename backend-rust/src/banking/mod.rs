@@ -0,0 +1,189 @@
+//! Open-banking (PSD2) providers and the sync of their transactions into the
+//! staging area (see docs/DESIGN_REVIEW_PSD2.md).
+
+mod mock;
+mod sync;
+
+pub use mock::MockBankProvider;
+pub use sync::{sync_connection, SyncError, SyncSummary};
+
+use crate::configuration::BankingSettings;
+use crate::domain::{AmountInCents, Currency, Money, StagingStatus, StagingTransaction};
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use secrecy::SecretString;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// An account at the bank, as reported by the provider.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BankAccount {
+    pub id: String,
+    pub name: Option<String>,
+    pub iban: Option<String>,
+    pub currency: Option<String>,
+}
+
+/// A booked transaction, as reported by the provider.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BankTransaction {
+    pub transaction_id: String,
+    pub booking_date: NaiveDate,
+    pub value_date: Option<NaiveDate>,
+    pub amount_in_cents: i64,
+    pub currency: Option<String>,
+    pub creditor_name: Option<String>,
+    pub debtor_name: Option<String>,
+    pub remittance_information: Option<String>,
+}
+
+impl BankTransaction {
+    /// The pending staging transaction of `user_id` for this bank transaction.
+    pub fn into_staging(
+        self,
+        user_id: &str,
+        default_currency: Option<&str>,
+    ) -> Result<StagingTransaction, String> {
+        if self.transaction_id.trim().is_empty() {
+            return Err("Bank transaction without id".to_string());
+        }
+        let currency = self
+            .currency
+            .as_deref()
+            .or(default_currency)
+            .map(|code| Currency::parse(code.to_string()))
+            .transpose()?
+            .unwrap_or_default();
+        Ok(StagingTransaction {
+            bank_transaction_id: self.transaction_id,
+            amount: Money::new(AmountInCents::new(self.amount_in_cents), currency),
+            booking_date: self.booking_date,
+            value_date: self.value_date,
+            creditor_name: self.creditor_name,
+            debtor_name: self.debtor_name,
+            remittance_info: self.remittance_information,
+            suggested_category: None,
+            suggested_merchant: None,
+            status: StagingStatus::Pending,
+            user_id: user_id.to_string(),
+        })
+    }
+}
+
+/// Transactions booked after a cursor.
+#[derive(Debug)]
+pub struct TransactionPage {
+    /// Entries that cannot be read are kept, so that syncs report them.
+    pub transactions: Vec<Result<BankTransaction, String>>,
+    /// Where the next fetch resumes, including after the last page.
+    pub cursor: String,
+    pub has_more: bool,
+}
+
+/// Tokens obtained from a refresh token.
+pub struct ProviderTokens {
+    pub access_token: SecretString,
+    /// Set when the provider rotates refresh tokens.
+    pub refresh_token: Option<SecretString>,
+}
+
+#[derive(Debug)]
+pub enum BankProviderError {
+    /// The consent expired or was revoked: the user must connect the bank again.
+    Unauthorized,
+    /// The provider could not be reached or answered unexpectedly.
+    Unavailable(String),
+}
+
+impl std::fmt::Display for BankProviderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unauthorized => write!(f, "The bank consent expired or was revoked"),
+            Self::Unavailable(e) => write!(f, "The bank provider is unavailable: {}", e),
+        }
+    }
+}
+
+/// An open-banking aggregator (Nordigen/GoCardless...).
+#[async_trait]
+pub trait BankProvider: Send + Sync {
+    /// Exchanges the refresh token stored with a connection for an access token.
+    async fn refresh_token(
+        &self,
+        refresh_token: &SecretString,
+    ) -> Result<ProviderTokens, BankProviderError>;
+
+    async fn list_accounts(
+        &self,
+        access_token: &SecretString,
+    ) -> Result<Vec<BankAccount>, BankProviderError>;
+
+    /// Transactions of the account booked after `cursor`, from the oldest one.
+    async fn fetch_transactions(
+        &self,
+        access_token: &SecretString,
+        account_id: &str,
+        cursor: Option<&str>,
+    ) -> Result<TransactionPage, BankProviderError>;
+}
+
+/// The configured providers, by the name stored in `bank_connections.provider`.
+#[derive(Clone, Default)]
+pub struct BankProviders(HashMap<String, Arc<dyn BankProvider>>);
+
+impl BankProviders {
+    pub fn from_settings(settings: &BankingSettings) -> Self {
+        let mut providers = Self::default();
+        if let Some(path) = &settings.mock_provider_file {
+            providers.insert("mock", Arc::new(MockBankProvider::new(path.into())));
+        }
+        providers
+    }
+
+    pub fn insert(&mut self, name: &str, provider: Arc<dyn BankProvider>) {
+        self.0.insert(name.to_string(), provider);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn BankProvider>> {
+        self.0.get(name).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BankTransaction;
+    use chrono::NaiveDate;
+    use claims::{assert_err, assert_ok};
+
+    fn transaction(id: &str, currency: Option<&str>) -> BankTransaction {
+        BankTransaction {
+            transaction_id: id.to_string(),
+            booking_date: NaiveDate::from_ymd_opt(2026, 3, 1).unwrap(),
+            value_date: None,
+            amount_in_cents: -1250,
+            currency: currency.map(str::to_string),
+            creditor_name: Some("Bakery".to_string()),
+            debtor_name: None,
+            remittance_information: None,
+        }
+    }
+
+    #[test]
+    fn transactions_default_to_the_account_currency() {
+        let staged = assert_ok!(transaction("tx-1", None).into_staging("user", Some("USD")));
+        assert_eq!(staged.amount.currency().as_ref(), "USD");
+        let staged = assert_ok!(transaction("tx-1", Some("GBP")).into_staging("user", Some("USD")));
+        assert_eq!(staged.amount.currency().as_ref(), "GBP");
+        assert_eq!(staged.bank_transaction_id, "tx-1");
+        assert_eq!(staged.user_id, "user");
+    }
+
+    #[test]
+    fn invalid_transactions_are_rejected() {
+        assert_err!(transaction(" ", None).into_staging("user", None));
+        assert_err!(transaction("tx-1", Some("euro")).into_staging("user", None));
+    }
+}
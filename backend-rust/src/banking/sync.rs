@@ -0,0 +1,324 @@
+use crate::banking::{BankProviderError, BankProviders};
+use crate::crypto::{decrypt_token, encrypt_token, CryptoError, KeyRing};
+use crate::domain::StagingTransaction;
+use crate::routes::PaymentScorer;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Outcome of the sync of a bank connection.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncSummary {
+    pub connection_id: Uuid,
+    pub accounts: usize,
+    /// Transactions added to the staging area.
+    pub new: usize,
+    /// Transactions already staged by a previous sync.
+    pub duplicates: usize,
+    /// Transactions that could not be staged.
+    pub errors: usize,
+    pub last_sync_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub enum SyncError {
+    NotFound,
+    /// Another sync of the connection is running.
+    InProgress,
+    /// The connection has no refresh token to authenticate with.
+    NotConnected,
+    ProviderNotConfigured(String),
+    Provider(BankProviderError),
+    /// The refresh token could not be decrypted or encrypted.
+    Encryption(CryptoError),
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for SyncError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+impl From<CryptoError> for SyncError {
+    fn from(e: CryptoError) -> Self {
+        Self::Encryption(e)
+    }
+}
+
+impl From<BankProviderError> for SyncError {
+    fn from(e: BankProviderError) -> Self {
+        Self::Provider(e)
+    }
+}
+
+/// Minutes after which the claim of a sync that never finished, e.g. because
+/// the process died, no longer blocks new syncs.
+const SYNC_CLAIM_TIMEOUT_MINUTES: i32 = 15;
+
+/// A connection claimed for a sync.
+struct ClaimedConnection {
+    provider: String,
+    account_id: Option<String>,
+    encrypted_refresh_token: Option<Vec<u8>>,
+    sync_cursors: serde_json::Value,
+    /// Identifies the claim, so that only its owner finishes the sync.
+    sync_started_at: DateTime<Utc>,
+}
+
+/// Fetches the transactions booked since the last sync of the connection and
+/// stages them.
+///
+/// The connection is claimed by a short statement and the provider is called
+/// outside of any transaction. Staged rows, cursors and `last_sync_at` are
+/// then committed at once: a failed sync stages nothing and is retried from
+/// the same cursors. A refresh token rotated by the provider is stored as soon
+/// as it is received, since the previous one is no longer valid.
+#[tracing::instrument(
+    name = "Syncing a bank connection",
+    skip(connection_pool, providers, key_ring)
+)]
+pub async fn sync_connection(
+    connection_id: Uuid,
+    user_id: &str,
+    connection_pool: &PgPool,
+    providers: &BankProviders,
+    key_ring: &KeyRing,
+) -> Result<SyncSummary, SyncError> {
+    let connection = claim_connection(connection_id, user_id, connection_pool).await?;
+    let result = sync_claimed_connection(
+        connection_id,
+        user_id,
+        &connection,
+        connection_pool,
+        providers,
+        key_ring,
+    )
+    .await;
+    if result.is_err() {
+        if let Err(e) =
+            release_connection(connection_id, connection.sync_started_at, connection_pool).await
+        {
+            tracing::error!("Failed to release the bank connection: {:?}", e);
+        }
+    }
+    result
+}
+
+/// Marks the connection as being synced, unless another sync holds it.
+async fn claim_connection(
+    connection_id: Uuid,
+    user_id: &str,
+    connection_pool: &PgPool,
+) -> Result<ClaimedConnection, SyncError> {
+    let claimed = sqlx::query_as!(
+        ClaimedConnection,
+        r#"
+        UPDATE expenses.bank_connections
+        SET sync_started_at = now()
+        WHERE id = $1 AND user_id = $2
+          AND (sync_started_at IS NULL OR sync_started_at < now() - make_interval(mins => $3))
+        RETURNING provider, account_id, encrypted_refresh_token, sync_cursors,
+                  sync_started_at as "sync_started_at!"
+        "#,
+        connection_id,
+        user_id,
+        SYNC_CLAIM_TIMEOUT_MINUTES
+    )
+    .fetch_optional(connection_pool)
+    .await?;
+    if let Some(claimed) = claimed {
+        return Ok(claimed);
+    }
+    let exists = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM expenses.bank_connections WHERE id = $1 AND user_id = $2
+        ) as "exists!"
+        "#,
+        connection_id,
+        user_id
+    )
+    .fetch_one(connection_pool)
+    .await?;
+    Err(if exists {
+        SyncError::InProgress
+    } else {
+        SyncError::NotFound
+    })
+}
+
+/// Gives up the claim of a failed sync.
+async fn release_connection(
+    connection_id: Uuid,
+    sync_started_at: DateTime<Utc>,
+    connection_pool: &PgPool,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE expenses.bank_connections SET sync_started_at = NULL
+        WHERE id = $1 AND sync_started_at = $2
+        "#,
+        connection_id,
+        sync_started_at
+    )
+    .execute(connection_pool)
+    .await?;
+    Ok(())
+}
+
+async fn sync_claimed_connection(
+    connection_id: Uuid,
+    user_id: &str,
+    connection: &ClaimedConnection,
+    connection_pool: &PgPool,
+    providers: &BankProviders,
+    key_ring: &KeyRing,
+) -> Result<SyncSummary, SyncError> {
+    let provider = providers
+        .get(&connection.provider)
+        .ok_or_else(|| SyncError::ProviderNotConfigured(connection.provider.clone()))?;
+    let refresh_token = connection
+        .encrypted_refresh_token
+        .as_ref()
+        .map(|token| decrypt_token(key_ring, token))
+        .transpose()?
+        .ok_or(SyncError::NotConnected)?;
+    let mut cursors: HashMap<String, String> =
+        serde_json::from_value(connection.sync_cursors.clone()).unwrap_or_default();
+
+    let tokens = provider.refresh_token(&refresh_token).await?;
+    // The provider may rotate the refresh token, invalidating the stored one
+    if let Some(rotated) = &tokens.refresh_token {
+        let encrypted = encrypt_token(key_ring, rotated)?;
+        sqlx::query!(
+            r#"
+            UPDATE expenses.bank_connections
+            SET encrypted_refresh_token = $2, updated_at = now()
+            WHERE id = $1
+            "#,
+            connection_id,
+            encrypted
+        )
+        .execute(connection_pool)
+        .await?;
+    }
+
+    let accounts: Vec<_> = provider
+        .list_accounts(&tokens.access_token)
+        .await?
+        .into_iter()
+        .filter(|account| {
+            connection
+                .account_id
+                .as_ref()
+                .is_none_or(|id| *id == account.id)
+        })
+        .collect();
+
+    let mut transactions = Vec::new();
+    let mut errors = 0;
+    for account in &accounts {
+        let mut cursor = cursors.get(&account.id).cloned();
+        loop {
+            let page = provider
+                .fetch_transactions(&tokens.access_token, &account.id, cursor.as_deref())
+                .await?;
+            for transaction in page.transactions {
+                match transaction.and_then(|t| t.into_staging(user_id, account.currency.as_deref()))
+                {
+                    Ok(staged) => transactions.push(staged),
+                    Err(e) => {
+                        tracing::warn!(account_id = %account.id, "Skipped bank transaction: {}", e);
+                        errors += 1;
+                    }
+                }
+            }
+            cursor = Some(page.cursor);
+            if !page.has_more {
+                break;
+            }
+        }
+        if let Some(cursor) = cursor {
+            cursors.insert(account.id.clone(), cursor);
+        }
+    }
+
+    // New transactions get the category the user's history suggests
+    let scorer = PaymentScorer::for_user(connection_pool, user_id, Utc::now().date_naive()).await?;
+    let (mut new, mut duplicates) = (0, 0);
+    let mut tx = connection_pool.begin().await?;
+    for mut staged in transactions {
+        staged.suggested_category = staged
+            .merchant_name()
+            .and_then(|name| scorer.likely_category(name, staged.amount.amount().cents()));
+        if stage_transaction(&mut tx, &staged).await? {
+            new += 1;
+        } else {
+            duplicates += 1;
+        }
+    }
+    let last_sync_at = sqlx::query_scalar!(
+        r#"
+        UPDATE expenses.bank_connections
+        SET sync_cursors = $2,
+            sync_started_at = NULL,
+            last_sync_at = now(),
+            updated_at = now()
+        WHERE id = $1 AND sync_started_at = $3
+        RETURNING last_sync_at as "last_sync_at!"
+        "#,
+        connection_id,
+        serde_json::json!(cursors),
+        connection.sync_started_at
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    // The claim timed out and another sync took the connection over
+    .ok_or(SyncError::InProgress)?;
+    tx.commit().await?;
+
+    tracing::info!(new, duplicates, errors, "Bank connection synced");
+    Ok(SyncSummary {
+        connection_id,
+        accounts: accounts.len(),
+        new,
+        duplicates,
+        errors,
+        last_sync_at,
+    })
+}
+
+/// Stages a transaction unless it was staged before (same bank transaction id
+/// for the user), keeping the review of the staged one. Returns whether it is new.
+async fn stage_transaction(
+    tx: &mut Transaction<'_, Postgres>,
+    transaction: &StagingTransaction,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        INSERT INTO expenses.staging_transactions
+            (user_id, bank_transaction_id, amount_in_cents, currency, booking_date, value_date,
+             creditor_name, debtor_name, remittance_info, suggested_category, status)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        ON CONFLICT (user_id, bank_transaction_id) DO UPDATE SET updated_at = now()
+        RETURNING (xmax = 0) as "inserted!"
+        "#,
+        transaction.user_id,
+        transaction.bank_transaction_id,
+        transaction.amount.amount().cents(),
+        transaction.amount.currency().as_ref(),
+        transaction.booking_date,
+        transaction.value_date,
+        transaction.creditor_name,
+        transaction.debtor_name,
+        transaction.remittance_info,
+        transaction.suggested_category,
+        transaction.status.as_str()
+    )
+    .fetch_one(&mut **tx)
+    .await
+}
@@ -1,11 +1,25 @@
 use secrecy::{ExposeSecret, SecretString};
 use serde::Deserialize;
+use std::collections::HashMap;
 
 #[derive(Deserialize, Clone)]
 pub struct Settings {
     pub database: DatabaseSettings,
     pub application: ApplicationSettings,
     pub otlp: TelemetrySettings,
+    pub auth: AuthSettings,
+    #[serde(default)]
+    pub exchange_rates: ExchangeRatesSettings,
+    #[serde(default)]
+    pub recurring_payments: RecurringPaymentsSettings,
+    /// Where budget alerts are delivered.
+    #[serde(default)]
+    pub notifications: NotifierSettings,
+    #[serde(default)]
+    pub banking: BankingSettings,
+    /// Keys encrypting third-party secrets (bank refresh tokens) at rest.
+    #[serde(default)]
+    pub encryption: EncryptionSettings,
 }
 
 #[derive(Deserialize, Clone)]
@@ -26,6 +40,119 @@ pub struct TelemetrySettings {
     pub service_name: String,
 }
 
+#[derive(Deserialize, Clone)]
+pub struct AuthSettings {
+    /// URL of the JWKS document published by the identity provider.
+    pub jwks_url: Option<String>,
+    /// Path of a local JWKS document, used when the identity provider is not reachable.
+    pub jwks_path: Option<String>,
+    pub issuer: String,
+    pub audience: String,
+    #[serde(default = "default_jwks_cache_ttl_seconds")]
+    pub jwks_cache_ttl_seconds: u64,
+    #[serde(default = "default_leeway_seconds")]
+    pub leeway_seconds: u64,
+    /// Realm role (from the `realm_access.roles` claim) required by admin endpoints.
+    #[serde(default = "default_admin_role")]
+    pub admin_role: String,
+}
+
+fn default_jwks_cache_ttl_seconds() -> u64 {
+    300
+}
+
+fn default_leeway_seconds() -> u64 {
+    30
+}
+
+fn default_admin_role() -> String {
+    "admin".to_string()
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct ExchangeRatesSettings {
+    /// CSV or JSON rates file loaded into the database at startup, so that
+    /// conversions work offline. See `routes::parse_exchange_rates` for the formats.
+    pub file: Option<String>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct RecurringPaymentsSettings {
+    /// Generates due recurring payments from within the server. Leave it off
+    /// when the `materialise-recurring-payments` command runs as a CronJob.
+    pub scheduler_enabled: bool,
+    pub scheduler_interval_seconds: u64,
+}
+
+impl Default for RecurringPaymentsSettings {
+    fn default() -> Self {
+        Self {
+            scheduler_enabled: false,
+            scheduler_interval_seconds: 3600,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Default)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum NotifierSettings {
+    /// Alerts are only written to the application log.
+    #[default]
+    Log,
+    /// Alerts are POSTed as JSON to `url`.
+    Webhook {
+        url: String,
+        #[serde(default = "default_webhook_timeout_seconds")]
+        timeout_seconds: u64,
+    },
+    /// Alerts are sent by e-mail.
+    Smtp(SmtpSettings),
+}
+
+fn default_webhook_timeout_seconds() -> u64 {
+    10
+}
+
+#[derive(Deserialize, Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<SecretString>,
+    pub from: String,
+    pub to: String,
+    /// Upgrades the connection with STARTTLS. Disable only for local relays.
+    #[serde(default = "default_smtp_starttls")]
+    pub starttls: bool,
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+fn default_smtp_starttls() -> bool {
+    true
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct BankingSettings {
+    /// JSON file served by the `mock` bank provider (demos, tests), see
+    /// `banking::MockBankProvider`. Bank syncs are unavailable without a provider.
+    pub mock_provider_file: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct EncryptionSettings {
+    /// Base64-encoded AES-256 keys by key id. Keep retired keys until
+    /// `reencrypt-secrets` has run, so that older ciphertexts still decrypt.
+    #[serde(default)]
+    pub keys: HashMap<String, SecretString>,
+    /// Id of the newest key, the one new secrets are encrypted with.
+    pub current_key_id: Option<String>,
+}
+
 #[derive(Deserialize, Clone)]
 pub struct DatabaseSettings {
     pub username: String,
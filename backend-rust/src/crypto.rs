@@ -0,0 +1,319 @@
+//! Encryption at rest of third-party secrets (bank refresh tokens) with
+//! AES-256-GCM, under keys that can be rotated (see docs/DESIGN_REVIEW_PSD2.md).
+
+use crate::configuration::EncryptionSettings;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+/// First byte of every ciphertext, so that the layout can evolve:
+/// `version | key id length | key id | nonce | encrypted secret and tag`.
+const FORMAT_VERSION: u8 = 1;
+const NONCE_LENGTH: usize = 12;
+const KEY_LENGTH: usize = 32;
+
+#[derive(Debug, PartialEq)]
+pub enum CryptoError {
+    InvalidKey(String),
+    /// No key is configured to encrypt with.
+    NoCurrentKey,
+    /// The secret was encrypted with a key that is no longer configured.
+    UnknownKey(String),
+    /// The secret was stored before secrets were encrypted.
+    Unencrypted,
+    Malformed,
+    /// Wrong key, or the ciphertext was tampered with.
+    Decryption,
+}
+
+impl std::fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidKey(e) => write!(f, "{e}"),
+            Self::NoCurrentKey => write!(f, "No encryption key is configured"),
+            Self::UnknownKey(id) => write!(f, "Unknown encryption key '{id}'"),
+            Self::Unencrypted => write!(f, "The secret is not encrypted"),
+            Self::Malformed => write!(f, "Malformed ciphertext"),
+            Self::Decryption => write!(f, "The secret could not be decrypted"),
+        }
+    }
+}
+
+/// The encryption keys by id. Secrets are encrypted with the current key and
+/// decrypted with the key whose id is recorded in their ciphertext.
+#[derive(Clone, Default)]
+pub struct KeyRing {
+    current_key_id: Option<String>,
+    keys: HashMap<String, Aes256Gcm>,
+}
+
+impl KeyRing {
+    pub fn from_settings(settings: &EncryptionSettings) -> Result<Self, CryptoError> {
+        let mut keys = HashMap::new();
+        for (id, key) in &settings.keys {
+            if id.is_empty() || id.len() > u8::MAX as usize {
+                return Err(CryptoError::InvalidKey(format!(
+                    "'{id}' is not a valid encryption key id"
+                )));
+            }
+            let bytes = STANDARD.decode(key.expose_secret().trim()).map_err(|_| {
+                CryptoError::InvalidKey(format!("Encryption key '{id}' is not valid base64"))
+            })?;
+            if bytes.len() != KEY_LENGTH {
+                return Err(CryptoError::InvalidKey(format!(
+                    "Encryption key '{id}' must be {KEY_LENGTH} bytes long"
+                )));
+            }
+            keys.insert(
+                id.clone(),
+                Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&bytes)),
+            );
+        }
+        match &settings.current_key_id {
+            Some(id) if !keys.contains_key(id) => Err(CryptoError::InvalidKey(format!(
+                "The current encryption key '{id}' is not configured"
+            ))),
+            None if !keys.is_empty() => Err(CryptoError::InvalidKey(
+                "current_key_id must name the newest encryption key".to_string(),
+            )),
+            _ => Ok(Self {
+                current_key_id: settings.current_key_id.clone(),
+                keys,
+            }),
+        }
+    }
+
+    pub fn current_key_id(&self) -> Option<&str> {
+        self.current_key_id.as_deref()
+    }
+}
+
+/// Encrypts `token` with the current key of the ring.
+pub fn encrypt_token(key_ring: &KeyRing, token: &SecretString) -> Result<Vec<u8>, CryptoError> {
+    let key_id = key_ring.current_key_id().ok_or(CryptoError::NoCurrentKey)?;
+    let cipher = &key_ring.keys[key_id];
+    let mut ciphertext = vec![FORMAT_VERSION, key_id.len() as u8];
+    ciphertext.extend_from_slice(key_id.as_bytes());
+    let header_length = ciphertext.len();
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    // The header is authenticated, so that the key id cannot be swapped
+    let encrypted = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: token.expose_secret().as_bytes(),
+                aad: &ciphertext[..header_length],
+            },
+        )
+        .map_err(|_| CryptoError::Malformed)?;
+    ciphertext.extend_from_slice(&nonce);
+    ciphertext.extend_from_slice(&encrypted);
+    Ok(ciphertext)
+}
+
+/// Decrypts a secret encrypted by `encrypt_token` with any key of the ring.
+pub fn decrypt_token(key_ring: &KeyRing, ciphertext: &[u8]) -> Result<SecretString, CryptoError> {
+    let key_id = key_id(ciphertext)?;
+    let cipher = key_ring
+        .keys
+        .get(key_id)
+        .ok_or_else(|| CryptoError::UnknownKey(key_id.to_string()))?;
+    let header_length = 2 + key_id.len();
+    let (nonce, encrypted) = ciphertext[header_length..].split_at(NONCE_LENGTH);
+    let plaintext = cipher
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: encrypted,
+                aad: &ciphertext[..header_length],
+            },
+        )
+        .map_err(|_| CryptoError::Decryption)?;
+    String::from_utf8(plaintext)
+        .map(SecretString::from)
+        .map_err(|_| CryptoError::Decryption)
+}
+
+/// Id of the key a secret was encrypted with.
+pub fn key_id(ciphertext: &[u8]) -> Result<&str, CryptoError> {
+    match ciphertext {
+        [FORMAT_VERSION, length, rest @ ..] if rest.len() > *length as usize + NONCE_LENGTH => {
+            std::str::from_utf8(&rest[..*length as usize]).map_err(|_| CryptoError::Malformed)
+        }
+        [FORMAT_VERSION, ..] => Err(CryptoError::Malformed),
+        _ => Err(CryptoError::Unencrypted),
+    }
+}
+
+/// Re-encrypts every stored secret under the current key: secrets encrypted
+/// with older keys, and those stored before secrets were encrypted. Returns the
+/// number of secrets re-encrypted; older keys can be removed afterwards.
+#[tracing::instrument(name = "Re-encrypting stored secrets", skip_all)]
+pub async fn reencrypt_secrets(
+    connection_pool: &PgPool,
+    key_ring: &KeyRing,
+) -> Result<usize, String> {
+    let current_key_id = key_ring
+        .current_key_id()
+        .ok_or_else(|| CryptoError::NoCurrentKey.to_string())?;
+    let mut tx = connection_pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start a transaction: {e}"))?;
+    let connections = sqlx::query!(
+        r#"
+        SELECT id, encrypted_refresh_token as "encrypted_refresh_token!"
+        FROM expenses.bank_connections
+        WHERE encrypted_refresh_token IS NOT NULL
+        FOR UPDATE
+        "#
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to read the bank connections: {e}"))?;
+
+    let mut reencrypted = 0;
+    for connection in connections {
+        let token = match key_id(&connection.encrypted_refresh_token) {
+            Ok(id) if id == current_key_id => continue,
+            Err(CryptoError::Unencrypted) => String::from_utf8(connection.encrypted_refresh_token)
+                .map(SecretString::from)
+                .map_err(|_| CryptoError::Malformed),
+            _ => decrypt_token(key_ring, &connection.encrypted_refresh_token),
+        }
+        .map_err(|e| format!("Bank connection {}: {e}", connection.id))?;
+        let encrypted = encrypt_token(key_ring, &token).map_err(|e| e.to_string())?;
+        sqlx::query!(
+            r#"
+            UPDATE expenses.bank_connections
+            SET encrypted_refresh_token = $2, updated_at = now()
+            WHERE id = $1
+            "#,
+            connection.id,
+            encrypted
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to update bank connection {}: {e}", connection.id))?;
+        reencrypted += 1;
+    }
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit the re-encryption: {e}"))?;
+    tracing::info!(reencrypted, key_id = current_key_id, "Secrets re-encrypted");
+    Ok(reencrypted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claims::{assert_err_eq, assert_ok};
+
+    const OLD_KEY: &str = "MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=";
+    const NEW_KEY: &str = "ZmVkY2JhOTg3NjU0MzIxMGZlZGNiYTk4NzY1NDMyMTA=";
+
+    fn key_ring(keys: &[(&str, &str)], current_key_id: &str) -> KeyRing {
+        KeyRing::from_settings(&EncryptionSettings {
+            keys: keys
+                .iter()
+                .map(|(id, key)| (id.to_string(), SecretString::from(*key)))
+                .collect(),
+            current_key_id: Some(current_key_id.to_string()),
+        })
+        .unwrap()
+    }
+
+    fn token(value: &str) -> SecretString {
+        SecretString::from(value)
+    }
+
+    #[test]
+    fn tokens_round_trip() {
+        let key_ring = key_ring(&[("v1", OLD_KEY)], "v1");
+
+        let first = encrypt_token(&key_ring, &token("refresh-token")).unwrap();
+        let second = encrypt_token(&key_ring, &token("refresh-token")).unwrap();
+
+        assert_ne!(first, second, "every encryption uses a new nonce");
+        assert!(!first.windows(13).any(|w| w == b"refresh-token"));
+        assert_eq!(key_id(&first), Ok("v1"));
+        let decrypted = decrypt_token(&key_ring, &first).unwrap();
+        assert_eq!(decrypted.expose_secret(), "refresh-token");
+    }
+
+    #[test]
+    fn secrets_of_older_keys_decrypt_after_a_rotation() {
+        let legacy = encrypt_token(&key_ring(&[("v1", OLD_KEY)], "v1"), &token("old")).unwrap();
+        let rotated_key_ring = key_ring(&[("v1", OLD_KEY), ("v2", NEW_KEY)], "v2");
+
+        let rotated = encrypt_token(&rotated_key_ring, &token("new")).unwrap();
+
+        assert_eq!(key_id(&rotated), Ok("v2"));
+        let decrypted = decrypt_token(&rotated_key_ring, &legacy).unwrap();
+        assert_eq!(decrypted.expose_secret(), "old");
+        let decrypted = decrypt_token(&rotated_key_ring, &rotated).unwrap();
+        assert_eq!(decrypted.expose_secret(), "new");
+    }
+
+    #[test]
+    fn secrets_of_removed_keys_do_not_decrypt() {
+        let legacy = encrypt_token(&key_ring(&[("v1", OLD_KEY)], "v1"), &token("old")).unwrap();
+
+        let result = decrypt_token(&key_ring(&[("v2", NEW_KEY)], "v2"), &legacy);
+
+        assert_err_eq!(
+            result.map(|_| ()),
+            CryptoError::UnknownKey("v1".to_string())
+        );
+    }
+
+    #[test]
+    fn tampered_ciphertexts_are_rejected() {
+        let key_ring = key_ring(&[("v1", OLD_KEY), ("v2", NEW_KEY)], "v1");
+        let ciphertext = encrypt_token(&key_ring, &token("refresh-token")).unwrap();
+        let mut tampered = ciphertext.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        let mut other_key = ciphertext.clone();
+        other_key[3] = b'2';
+
+        for ciphertext in [tampered, other_key] {
+            let result = decrypt_token(&key_ring, &ciphertext);
+            assert_err_eq!(result.map(|_| ()), CryptoError::Decryption);
+        }
+        let result = decrypt_token(&key_ring, &ciphertext[..10]);
+        assert_err_eq!(result.map(|_| ()), CryptoError::Malformed);
+        let result = decrypt_token(&key_ring, b"plain-refresh-token");
+        assert_err_eq!(result.map(|_| ()), CryptoError::Unencrypted);
+    }
+
+    #[test]
+    fn invalid_key_rings_are_rejected() {
+        let settings = |keys: &[(&str, &str)], current_key_id: Option<&str>| EncryptionSettings {
+            keys: keys
+                .iter()
+                .map(|(id, key)| (id.to_string(), SecretString::from(*key)))
+                .collect(),
+            current_key_id: current_key_id.map(str::to_string),
+        };
+
+        assert_ok!(KeyRing::from_settings(&settings(&[], None)));
+        for invalid in [
+            settings(&[("v1", "not base64!")], Some("v1")),
+            settings(&[("v1", "c2hvcnQ=")], Some("v1")),
+            settings(&[("v1", OLD_KEY)], Some("v2")),
+            settings(&[("v1", OLD_KEY)], None),
+        ] {
+            assert!(matches!(
+                KeyRing::from_settings(&invalid),
+                Err(CryptoError::InvalidKey(_))
+            ));
+        }
+        let empty = KeyRing::from_settings(&settings(&[], None)).unwrap();
+        let result = encrypt_token(&empty, &token("refresh-token"));
+        assert_err_eq!(result, CryptoError::NoCurrentKey);
+    }
+}
@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+
+/// An amount of money in cents: positive for income, negative for expenses.
+///
+/// Backed by an `i64` (`bigint` in the database) so that multi-year totals
+/// cannot overflow. Arithmetic is checked and returns `None` on overflow.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AmountInCents(i64);
+
+impl AmountInCents {
+    pub const ZERO: AmountInCents = AmountInCents(0);
+
+    pub fn new(cents: i64) -> Self {
+        Self(cents)
+    }
+
+    pub fn cents(&self) -> i64 {
+        self.0
+    }
+
+    pub fn checked_add(self, other: AmountInCents) -> Option<AmountInCents> {
+        self.0.checked_add(other.0).map(Self)
+    }
+
+    pub fn checked_sub(self, other: AmountInCents) -> Option<AmountInCents> {
+        self.0.checked_sub(other.0).map(Self)
+    }
+
+    pub fn checked_neg(self) -> Option<AmountInCents> {
+        self.0.checked_neg().map(Self)
+    }
+
+    /// Sums the amounts, returning `None` if the total overflows.
+    pub fn checked_sum<I: IntoIterator<Item = AmountInCents>>(amounts: I) -> Option<AmountInCents> {
+        amounts
+            .into_iter()
+            .try_fold(Self::ZERO, |total, amount| total.checked_add(amount))
+    }
+}
+
+impl From<i64> for AmountInCents {
+    fn from(cents: i64) -> Self {
+        Self(cents)
+    }
+}
+
+impl From<i32> for AmountInCents {
+    fn from(cents: i32) -> Self {
+        Self(cents.into())
+    }
+}
+
+impl std::fmt::Display for AmountInCents {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AmountInCents;
+    use claims::{assert_none, assert_some_eq};
+
+    #[test]
+    fn amounts_beyond_i32_are_added() {
+        let amount = AmountInCents::new(i32::MAX as i64);
+        assert_some_eq!(
+            amount.checked_add(amount),
+            AmountInCents::new(2 * i32::MAX as i64)
+        );
+    }
+
+    #[test]
+    fn addition_overflow_is_detected() {
+        assert_none!(AmountInCents::new(i64::MAX).checked_add(AmountInCents::new(1)));
+        assert_none!(AmountInCents::new(i64::MIN).checked_add(AmountInCents::new(-1)));
+    }
+
+    #[test]
+    fn subtraction_overflow_is_detected() {
+        assert_none!(AmountInCents::new(i64::MIN).checked_sub(AmountInCents::new(1)));
+        assert_some_eq!(
+            AmountInCents::new(-5).checked_sub(AmountInCents::new(10)),
+            AmountInCents::new(-15)
+        );
+    }
+
+    #[test]
+    fn negating_the_minimum_overflows() {
+        assert_none!(AmountInCents::new(i64::MIN).checked_neg());
+        assert_some_eq!(
+            AmountInCents::new(-250).checked_neg(),
+            AmountInCents::new(250)
+        );
+    }
+
+    #[test]
+    fn sum_of_an_empty_list_is_zero() {
+        assert_some_eq!(AmountInCents::checked_sum(Vec::new()), AmountInCents::ZERO);
+    }
+
+    #[test]
+    fn sum_overflow_is_detected() {
+        let amounts = vec![
+            AmountInCents::new(i64::MAX - 1),
+            AmountInCents::new(1),
+            AmountInCents::new(1),
+        ];
+        assert_none!(AmountInCents::checked_sum(amounts));
+    }
+
+    #[test]
+    fn amounts_are_serialized_as_plain_integers() {
+        let amount = AmountInCents::new(5_000_000_000);
+        assert_eq!(serde_json::to_string(&amount).unwrap(), "5000000000");
+        let parsed: AmountInCents = serde_json::from_str("-5000000000").unwrap();
+        assert_eq!(parsed, AmountInCents::new(-5_000_000_000));
+    }
+}
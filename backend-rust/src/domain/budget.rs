@@ -0,0 +1,186 @@
+use crate::domain::{AmountInCents, Money};
+use chrono::{Datelike, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    #[default]
+    Monthly,
+}
+
+impl BudgetPeriod {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "monthly" => Ok(Self::Monthly),
+            _ => Err(format!("{s} is not a valid budget period.")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Monthly => "monthly",
+        }
+    }
+}
+
+/// Spending limit over a period, for all payments or only those of a
+/// category and/or a wallet.
+#[derive(Debug)]
+pub struct Budget {
+    pub category_id: Option<Uuid>,
+    pub wallet_id: Option<Uuid>,
+    pub period: BudgetPeriod,
+    pub limit: Money,
+    pub user_id: String,
+}
+
+impl Budget {
+    pub fn parse(
+        category_id: Option<Uuid>,
+        wallet_id: Option<Uuid>,
+        period: BudgetPeriod,
+        limit: Money,
+        user_id: String,
+    ) -> Result<Budget, String> {
+        if limit.amount() <= AmountInCents::ZERO {
+            return Err(format!(
+                "The budget limit must be positive, got {}.",
+                limit.amount()
+            ));
+        }
+        Ok(Self {
+            category_id,
+            wallet_id,
+            period,
+            limit,
+            user_id,
+        })
+    }
+}
+
+/// Consumption of a budget limit during one month.
+#[derive(Debug, PartialEq)]
+pub struct BudgetStatus {
+    pub spent: AmountInCents,
+    /// Negative once the limit is exceeded.
+    pub remaining: AmountInCents,
+    pub percent_used: f64,
+    /// Spending at the end of the month if it goes on at the same daily pace.
+    pub projected: AmountInCents,
+}
+
+impl BudgetStatus {
+    /// `spent` is the positive amount spent in the month starting on `month`,
+    /// as of `today`. Past months are not projected; neither are future ones.
+    pub fn compute(
+        limit: AmountInCents,
+        spent: AmountInCents,
+        month: NaiveDate,
+        today: NaiveDate,
+    ) -> Result<BudgetStatus, String> {
+        let overflow = || "Budget status overflows".to_string();
+        let remaining = limit.checked_sub(spent).ok_or_else(overflow)?;
+        let percent_used = if limit > AmountInCents::ZERO {
+            (spent.cents() as f64 / limit.cents() as f64 * 10000.0).round() / 100.0
+        } else {
+            0.0
+        };
+        let days_in_month = month
+            .checked_add_months(Months::new(1))
+            .and_then(|next| next.pred_opt())
+            .map(|last| last.day())
+            .ok_or_else(overflow)?;
+        let projected = if today.year() == month.year() && today.month() == month.month() {
+            let projected =
+                i128::from(spent.cents()) * i128::from(days_in_month) / i128::from(today.day());
+            AmountInCents::new(i64::try_from(projected).map_err(|_| overflow())?)
+        } else {
+            spent
+        };
+        Ok(Self {
+            spent,
+            remaining,
+            percent_used,
+            projected,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Budget, BudgetPeriod, BudgetStatus};
+    use crate::domain::{AmountInCents, Currency, Money};
+    use chrono::NaiveDate;
+    use claims::{assert_err, assert_ok, assert_ok_eq};
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn cents(cents: i64) -> AmountInCents {
+        AmountInCents::new(cents)
+    }
+
+    fn budget(limit: i64) -> Result<Budget, String> {
+        Budget::parse(
+            None,
+            None,
+            BudgetPeriod::Monthly,
+            Money::new(cents(limit), Currency::default()),
+            "user".to_string(),
+        )
+    }
+
+    #[test]
+    fn limits_must_be_positive() {
+        assert_err!(budget(0));
+        assert_err!(budget(-100));
+        assert_ok!(budget(1));
+    }
+    #[test]
+    fn periods_round_trip() {
+        assert_ok_eq!(
+            BudgetPeriod::parse(BudgetPeriod::Monthly.as_str()),
+            BudgetPeriod::Monthly
+        );
+        assert_err!(BudgetPeriod::parse("weekly"));
+    }
+    #[test]
+    fn the_current_month_is_projected_at_the_daily_pace() {
+        let status = BudgetStatus::compute(
+            cents(60000),
+            cents(15000),
+            date("2026-04-01"),
+            date("2026-04-10"),
+        )
+        .unwrap();
+        assert_eq!(status.remaining, cents(45000));
+        assert_eq!(status.percent_used, 25.0);
+        // 150.00 in 10 days, over 30 days
+        assert_eq!(status.projected, cents(45000));
+    }
+    #[test]
+    fn past_months_are_not_projected() {
+        let status = BudgetStatus::compute(
+            cents(30000),
+            cents(40000),
+            date("2026-02-01"),
+            date("2026-04-10"),
+        )
+        .unwrap();
+        assert_eq!(status.remaining, cents(-10000));
+        assert_eq!(status.percent_used, 133.33);
+        assert_eq!(status.projected, cents(40000));
+    }
+    #[test]
+    fn overflowing_projections_are_reported() {
+        assert_err!(BudgetStatus::compute(
+            cents(1),
+            cents(i64::MAX / 2),
+            date("2026-01-01"),
+            date("2026-01-01")
+        ));
+    }
+}
@@ -0,0 +1,128 @@
+use crate::domain::{AmountInCents, BudgetStatus};
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Shares of a budget limit, in percent, at which an alert is sent once per period.
+pub const BUDGET_ALERT_THRESHOLDS: [u8; 2] = [80, 100];
+
+/// Thresholds reached by the spending of a budget, lowest first.
+pub fn reached_thresholds(status: &BudgetStatus) -> Vec<u8> {
+    let spent = i128::from(status.spent.cents());
+    let limit = spent + i128::from(status.remaining.cents());
+    BUDGET_ALERT_THRESHOLDS
+        .into_iter()
+        .filter(|threshold| limit > 0 && spent * 100 >= limit * i128::from(*threshold))
+        .collect()
+}
+
+/// A budget threshold crossed during a month, as sent to notifiers.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetAlert {
+    pub budget_id: Uuid,
+    pub user_id: String,
+    pub threshold: u8,
+    /// `YYYY-MM`
+    pub month: String,
+    pub spent_in_cents: AmountInCents,
+    pub limit_in_cents: AmountInCents,
+    pub currency: String,
+    pub percent_used: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wallet: Option<String>,
+}
+
+impl BudgetAlert {
+    /// Human readable scope, e.g. `groceries in Checking`.
+    pub fn scope(&self) -> String {
+        match (&self.category, &self.wallet) {
+            (Some(category), Some(wallet)) => format!("{category} in {wallet}"),
+            (Some(category), None) => category.clone(),
+            (None, Some(wallet)) => format!("wallet {wallet}"),
+            (None, None) => "all spending".to_string(),
+        }
+    }
+
+    pub fn subject(&self) -> String {
+        format!(
+            "Budget for {} at {}% in {}",
+            self.scope(),
+            self.threshold,
+            self.month
+        )
+    }
+
+    pub fn message(&self) -> String {
+        format!(
+            "The {} budget for {} has reached {}%: {} of {} {} spent ({}% used).",
+            self.month,
+            self.scope(),
+            self.threshold,
+            units(self.spent_in_cents),
+            units(self.limit_in_cents),
+            self.currency,
+            self.percent_used
+        )
+    }
+}
+
+/// `12345` cents as `123.45`.
+fn units(amount: AmountInCents) -> String {
+    let cents = amount.cents();
+    let sign = if cents < 0 { "-" } else { "" };
+    let cents = cents.unsigned_abs();
+    format!("{}{}.{:02}", sign, cents / 100, cents % 100)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{reached_thresholds, BudgetAlert};
+    use crate::domain::{AmountInCents, BudgetStatus};
+    use uuid::Uuid;
+
+    fn status(spent: i64, limit: i64) -> BudgetStatus {
+        BudgetStatus {
+            spent: AmountInCents::new(spent),
+            remaining: AmountInCents::new(limit - spent),
+            percent_used: 0.0,
+            projected: AmountInCents::new(spent),
+        }
+    }
+
+    #[test]
+    fn no_threshold_is_reached_below_80_percent() {
+        assert!(reached_thresholds(&status(7999, 10000)).is_empty());
+    }
+    #[test]
+    fn thresholds_are_reached_at_their_exact_share() {
+        assert_eq!(reached_thresholds(&status(8000, 10000)), vec![80]);
+        assert_eq!(reached_thresholds(&status(10000, 10000)), vec![80, 100]);
+        assert_eq!(reached_thresholds(&status(25000, 10000)), vec![80, 100]);
+    }
+    #[test]
+    fn the_scope_names_the_category_and_wallet() {
+        let mut alert = BudgetAlert {
+            budget_id: Uuid::nil(),
+            user_id: "user".to_string(),
+            threshold: 80,
+            month: "2026-03".to_string(),
+            spent_in_cents: AmountInCents::new(8000),
+            limit_in_cents: AmountInCents::new(10000),
+            currency: "EUR".to_string(),
+            percent_used: 80.0,
+            category: Some("groceries".to_string()),
+            wallet: Some("Checking".to_string()),
+        };
+        assert_eq!(alert.scope(), "groceries in Checking");
+        assert_eq!(
+            alert.message(),
+            "The 2026-03 budget for groceries in Checking has reached 80%: 80.00 of 100.00 EUR spent (80% used)."
+        );
+        alert.category = None;
+        assert_eq!(alert.scope(), "wallet Checking");
+        alert.wallet = None;
+        assert_eq!(alert.subject(), "Budget for all spending at 80% in 2026-03");
+    }
+}
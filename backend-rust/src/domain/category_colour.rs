@@ -0,0 +1,45 @@
+/// Display colour of a category, as a `#rrggbb` hex code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CategoryColour(String);
+
+impl AsRef<str> for CategoryColour {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl CategoryColour {
+    /// Accepts upper- or lower-case digits and stores them lower-cased.
+    pub fn parse(s: String) -> Result<CategoryColour, String> {
+        let trimmed = s.trim();
+        let is_valid = trimmed.len() == 7
+            && trimmed.starts_with('#')
+            && trimmed[1..].chars().all(|c| c.is_ascii_hexdigit());
+        if is_valid {
+            Ok(Self(trimmed.to_ascii_lowercase()))
+        } else {
+            Err(format!("{} is not a valid colour, expected #rrggbb.", s))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::category_colour::CategoryColour;
+    use claims::{assert_err, assert_ok_eq};
+
+    #[test]
+    fn hex_codes_are_lower_cased() {
+        assert_ok_eq!(
+            CategoryColour::parse(" #1A2b3C ".to_string()),
+            CategoryColour("#1a2b3c".to_string())
+        );
+    }
+
+    #[test]
+    fn other_formats_are_rejected() {
+        for s in ["", "1a2b3c", "#1a2b3", "#1a2b3c4", "#1a2b3g", "red", "#ééé"] {
+            assert_err!(CategoryColour::parse(s.to_string()));
+        }
+    }
+}
@@ -7,3 +7,35 @@ pub enum CategoryKind {
     Expense,
     Income,
 }
+
+impl CategoryKind {
+    /// The value stored in `expenses.categories.kind`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Expense => "expense",
+            Self::Income => "income",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "expense" => Ok(Self::Expense),
+            "income" => Ok(Self::Income),
+            other => Err(format!("{} is not a valid category kind.", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CategoryKind;
+    use claims::{assert_err, assert_ok_eq};
+
+    #[test]
+    fn kinds_round_trip_through_their_column_value() {
+        for kind in [CategoryKind::Expense, CategoryKind::Income] {
+            assert_ok_eq!(CategoryKind::parse(kind.as_str()), kind);
+        }
+        assert_err!(CategoryKind::parse("transfer"));
+    }
+}
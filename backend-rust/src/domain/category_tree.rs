@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Separates the names of a category path, e.g. `Food > Restaurants`.
+pub const CATEGORY_PATH_SEPARATOR: &str = " > ";
+
+/// The categories visible to a user, linked to their parents.
+#[derive(Debug, Default)]
+pub struct CategoryTree {
+    /// Parent and name of each category.
+    nodes: HashMap<Uuid, (Option<Uuid>, String)>,
+}
+
+impl CategoryTree {
+    pub fn new(categories: impl IntoIterator<Item = (Uuid, Option<Uuid>, String)>) -> Self {
+        Self {
+            nodes: categories
+                .into_iter()
+                .map(|(id, parent_id, name)| (id, (parent_id, name)))
+                .collect(),
+        }
+    }
+
+    /// The category followed by its parent, grandparent and so on. Stops at
+    /// parents missing from the tree, and at cycles should the data have any.
+    pub fn ancestors(&self, id: Uuid) -> Vec<Uuid> {
+        let mut ancestors = Vec::new();
+        let mut current = Some(id);
+        while let Some(id) = current.filter(|id| !ancestors.contains(id)) {
+            let Some((parent_id, _)) = self.nodes.get(&id) else {
+                break;
+            };
+            ancestors.push(id);
+            current = *parent_id;
+        }
+        ancestors
+    }
+
+    /// Names from the root down to the category, e.g. `Food > Restaurants`.
+    pub fn path(&self, id: Uuid) -> String {
+        let names: Vec<&str> = self
+            .ancestors(id)
+            .iter()
+            .rev()
+            .filter_map(|id| self.nodes.get(id).map(|(_, name)| name.as_str()))
+            .collect();
+        names.join(CATEGORY_PATH_SEPARATOR)
+    }
+
+    /// Whether `id` is `ancestor` or one of its subcategories.
+    pub fn is_within(&self, id: Uuid, ancestor: Uuid) -> bool {
+        self.ancestors(id).contains(&ancestor)
+    }
+
+    /// Checks that `parent_id` can become the parent of `id` without creating
+    /// a cycle.
+    pub fn check_parent(&self, id: Uuid, parent_id: Uuid) -> Result<(), String> {
+        if parent_id == id {
+            Err("A category cannot be its own parent.".to_string())
+        } else if self.is_within(parent_id, id) {
+            Err("A category cannot be moved under one of its subcategories.".to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claims::{assert_err, assert_ok};
+
+    /// Food > Restaurants > Pizza, and Travel.
+    fn tree() -> (CategoryTree, [Uuid; 4]) {
+        let ids = [
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+        ];
+        let tree = CategoryTree::new([
+            (ids[0], None, "Food".to_string()),
+            (ids[1], Some(ids[0]), "Restaurants".to_string()),
+            (ids[2], Some(ids[1]), "Pizza".to_string()),
+            (ids[3], None, "Travel".to_string()),
+        ]);
+        (tree, ids)
+    }
+
+    #[test]
+    fn paths_go_from_the_root_down() {
+        let (tree, [food, restaurants, pizza, _]) = tree();
+
+        assert_eq!(tree.path(food), "Food");
+        assert_eq!(tree.path(restaurants), "Food > Restaurants");
+        assert_eq!(tree.path(pizza), "Food > Restaurants > Pizza");
+        assert_eq!(tree.ancestors(pizza), vec![pizza, restaurants, food]);
+    }
+
+    #[test]
+    fn subcategories_are_within_their_ancestors() {
+        let (tree, [food, restaurants, pizza, travel]) = tree();
+
+        assert!(tree.is_within(pizza, food));
+        assert!(tree.is_within(food, food));
+        assert!(!tree.is_within(food, restaurants));
+        assert!(!tree.is_within(pizza, travel));
+    }
+
+    #[test]
+    fn parents_cannot_create_cycles() {
+        let (tree, [food, restaurants, pizza, travel]) = tree();
+
+        assert_err!(tree.check_parent(food, food));
+        assert_err!(tree.check_parent(food, pizza));
+        assert_err!(tree.check_parent(restaurants, pizza));
+        assert_ok!(tree.check_parent(pizza, food));
+        assert_ok!(tree.check_parent(food, travel));
+    }
+
+    #[test]
+    fn cycles_in_the_data_do_not_loop_forever() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let tree =
+            CategoryTree::new([(a, Some(b), "A".to_string()), (b, Some(a), "B".to_string())]);
+
+        assert_eq!(tree.ancestors(a), vec![a, b]);
+        assert_eq!(tree.path(a), "B > A");
+    }
+}
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+/// Currency assumed for payments and wallets created before currencies were tracked.
+pub const DEFAULT_CURRENCY: &str = "EUR";
+
+/// An ISO 4217 alphabetic currency code, e.g. `EUR`, `USD` or `GBP`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Currency(String);
+
+impl Currency {
+    pub fn parse(code: String) -> Result<Self, String> {
+        // ISO 4217 alphabetic codes are exactly three upper-case latin letters.
+        let is_valid = code.len() == 3 && code.chars().all(|c| c.is_ascii_uppercase());
+        if is_valid {
+            Ok(Self(code))
+        } else {
+            Err(format!("{code} is not a valid ISO 4217 currency code."))
+        }
+    }
+}
+
+impl Default for Currency {
+    fn default() -> Self {
+        Self(DEFAULT_CURRENCY.to_string())
+    }
+}
+
+impl AsRef<str> for Currency {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for Currency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Currency;
+    use claims::{assert_err, assert_ok};
+
+    #[test]
+    fn common_currency_codes_are_valid() {
+        for code in ["EUR", "USD", "GBP", "CHF", "JPY"] {
+            assert_ok!(Currency::parse(code.to_string()));
+        }
+    }
+    #[test]
+    fn lowercase_codes_are_rejected() {
+        assert_err!(Currency::parse("eur".to_string()));
+    }
+    #[test]
+    fn codes_with_the_wrong_length_are_rejected() {
+        for code in ["", "EU", "EURO"] {
+            assert_err!(Currency::parse(code.to_string()));
+        }
+    }
+    #[test]
+    fn codes_with_non_letters_are_rejected() {
+        for code in ["E1R", "EU ", "€UR", "ÉUR"] {
+            assert_err!(Currency::parse(code.to_string()));
+        }
+    }
+    #[test]
+    fn default_currency_is_euro() {
+        assert_eq!(Currency::default().as_ref(), "EUR");
+    }
+}
@@ -0,0 +1,303 @@
+use crate::domain::{AmountInCents, Currency, Money};
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// `1 base = rate quote` on `date`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExchangeRate {
+    pub base: Currency,
+    pub quote: Currency,
+    pub date: NaiveDate,
+    pub rate: f64,
+}
+
+impl ExchangeRate {
+    pub fn parse(
+        base: Currency,
+        quote: Currency,
+        date: NaiveDate,
+        rate: f64,
+    ) -> Result<ExchangeRate, String> {
+        if base == quote {
+            Err(format!(
+                "Exchange rate from {base} to itself is not allowed."
+            ))
+        } else if !rate.is_finite() || rate <= 0.0 {
+            Err(format!(
+                "{rate} is not a valid exchange rate from {base} to {quote}."
+            ))
+        } else {
+            Ok(Self {
+                base,
+                quote,
+                date,
+                rate,
+            })
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ConversionError {
+    /// No rate was published for the pair on or before the date.
+    MissingRate {
+        from: Currency,
+        to: Currency,
+        date: NaiveDate,
+    },
+    Overflow(String),
+}
+
+impl std::fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConversionError::MissingRate { from, to, date } => write!(
+                f,
+                "No exchange rate from {} to {} on or before {}",
+                from, to, date
+            ),
+            ConversionError::Overflow(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+/// In-memory set of exchange rates used to convert amounts.
+///
+/// A rate is looked up on the requested date or, when none was published that
+/// day (weekends, holidays), on the nearest previous date. Pairs can be used
+/// in both directions, and currencies without a direct rate are converted
+/// through a common currency (e.g. USD -> EUR -> GBP with an ECB feed).
+#[derive(Debug, Default)]
+pub struct ExchangeRateTable {
+    rates: BTreeMap<(Currency, Currency), BTreeMap<NaiveDate, f64>>,
+    currencies: BTreeSet<Currency>,
+}
+
+impl ExchangeRateTable {
+    pub fn new(rates: impl IntoIterator<Item = ExchangeRate>) -> Self {
+        let mut table = Self::default();
+        for rate in rates {
+            table.insert(rate);
+        }
+        table
+    }
+
+    pub fn insert(&mut self, rate: ExchangeRate) {
+        self.currencies.insert(rate.base.clone());
+        self.currencies.insert(rate.quote.clone());
+        self.rates
+            .entry((rate.base, rate.quote))
+            .or_default()
+            .insert(rate.date, rate.rate);
+    }
+
+    /// Rate to convert `from` into `to` on `date`, with the date it was published.
+    pub fn rate(
+        &self,
+        from: &Currency,
+        to: &Currency,
+        date: NaiveDate,
+    ) -> Option<(f64, NaiveDate)> {
+        if from == to {
+            return Some((1.0, date));
+        }
+        if let Some(direct) = self.leg(from, to, date) {
+            return Some(direct);
+        }
+        // Cross rate through the common currency with the most recent rates.
+        self.currencies
+            .iter()
+            .filter(|pivot| *pivot != from && *pivot != to)
+            .filter_map(|pivot| {
+                let (first, first_date) = self.leg(from, pivot, date)?;
+                let (second, second_date) = self.leg(pivot, to, date)?;
+                Some((first * second, first_date.min(second_date)))
+            })
+            .max_by_key(|(_, published)| *published)
+    }
+
+    pub fn convert(
+        &self,
+        amount: &Money,
+        to: &Currency,
+        date: NaiveDate,
+    ) -> Result<Money, ConversionError> {
+        if amount.currency() == to {
+            return Ok(amount.clone());
+        }
+        let (rate, _) =
+            self.rate(amount.currency(), to, date)
+                .ok_or_else(|| ConversionError::MissingRate {
+                    from: amount.currency().clone(),
+                    to: to.clone(),
+                    date,
+                })?;
+        let converted = (amount.amount().cents() as f64 * rate).round();
+        // `i64::MAX as f64` rounds up to 2^63, which is already out of range.
+        if !converted.is_finite() || converted >= i64::MAX as f64 || converted < i64::MIN as f64 {
+            return Err(ConversionError::Overflow(format!(
+                "Converting {} {} to {} overflows",
+                amount.amount(),
+                amount.currency(),
+                to
+            )));
+        }
+        Ok(Money::new(AmountInCents::new(converted as i64), to.clone()))
+    }
+
+    /// Direct or inverse rate of a pair, preferring the most recently published one.
+    fn leg(&self, from: &Currency, to: &Currency, date: NaiveDate) -> Option<(f64, NaiveDate)> {
+        let direct = self.latest(from, to, date);
+        let inverse = self
+            .latest(to, from, date)
+            .map(|(rate, published)| (1.0 / rate, published));
+        match (direct, inverse) {
+            (Some(direct), Some(inverse)) if inverse.1 > direct.1 => Some(inverse),
+            (Some(direct), _) => Some(direct),
+            (None, inverse) => inverse,
+        }
+    }
+
+    fn latest(
+        &self,
+        base: &Currency,
+        quote: &Currency,
+        date: NaiveDate,
+    ) -> Option<(f64, NaiveDate)> {
+        self.rates
+            .get(&(base.clone(), quote.clone()))?
+            .range(..=date)
+            .next_back()
+            .map(|(published, rate)| (*rate, *published))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConversionError, ExchangeRate, ExchangeRateTable};
+    use crate::domain::{AmountInCents, Currency, Money};
+    use chrono::NaiveDate;
+    use claims::{assert_err, assert_none, assert_ok, assert_ok_eq, assert_some_eq};
+
+    fn currency(code: &str) -> Currency {
+        Currency::parse(code.to_string()).unwrap()
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn rate(base: &str, quote: &str, on: &str, rate: f64) -> ExchangeRate {
+        ExchangeRate::parse(currency(base), currency(quote), date(on), rate).unwrap()
+    }
+
+    fn money(cents: i64, code: &str) -> Money {
+        Money::new(AmountInCents::new(cents), currency(code))
+    }
+
+    fn ecb_table() -> ExchangeRateTable {
+        ExchangeRateTable::new(vec![
+            rate("EUR", "USD", "2026-01-02", 1.25),
+            rate("EUR", "USD", "2026-01-05", 1.10),
+            rate("EUR", "GBP", "2026-01-02", 0.80),
+        ])
+    }
+
+    #[test]
+    fn rates_must_be_positive_and_finite() {
+        for value in [0.0, -1.2, f64::NAN, f64::INFINITY] {
+            assert_err!(ExchangeRate::parse(
+                currency("EUR"),
+                currency("USD"),
+                date("2026-01-02"),
+                value
+            ));
+        }
+        assert_ok!(ExchangeRate::parse(
+            currency("EUR"),
+            currency("USD"),
+            date("2026-01-02"),
+            1.08
+        ));
+    }
+
+    #[test]
+    fn rates_between_the_same_currency_are_rejected() {
+        assert_err!(ExchangeRate::parse(
+            currency("EUR"),
+            currency("EUR"),
+            date("2026-01-02"),
+            1.0
+        ));
+    }
+
+    #[test]
+    fn the_nearest_previous_rate_is_used() {
+        let table = ecb_table();
+        // Saturday and Sunday fall back to Friday's rate
+        assert_some_eq!(
+            table.rate(&currency("EUR"), &currency("USD"), date("2026-01-04")),
+            (1.25, date("2026-01-02"))
+        );
+        assert_some_eq!(
+            table.rate(&currency("EUR"), &currency("USD"), date("2026-01-05")),
+            (1.10, date("2026-01-05"))
+        );
+    }
+
+    #[test]
+    fn no_rate_is_found_before_the_first_published_date() {
+        assert_none!(ecb_table().rate(&currency("EUR"), &currency("USD"), date("2026-01-01")));
+    }
+
+    #[test]
+    fn inverse_rates_are_used() {
+        assert_ok_eq!(
+            ecb_table().convert(&money(1250, "USD"), &currency("EUR"), date("2026-01-02")),
+            money(1000, "EUR")
+        );
+    }
+
+    #[test]
+    fn cross_rates_go_through_a_common_currency() {
+        // 1 USD = 1 / 1.25 EUR = 0.8 / 1.25 GBP
+        assert_ok_eq!(
+            ecb_table().convert(&money(-10000, "USD"), &currency("GBP"), date("2026-01-03")),
+            money(-6400, "GBP")
+        );
+    }
+
+    #[test]
+    fn same_currency_is_not_converted() {
+        assert_ok_eq!(
+            ExchangeRateTable::default().convert(
+                &money(42, "CHF"),
+                &currency("CHF"),
+                date("2026-01-02")
+            ),
+            money(42, "CHF")
+        );
+    }
+
+    #[test]
+    fn a_missing_rate_is_reported() {
+        assert_eq!(
+            ecb_table().convert(&money(100, "JPY"), &currency("EUR"), date("2026-01-02")),
+            Err(ConversionError::MissingRate {
+                from: currency("JPY"),
+                to: currency("EUR"),
+                date: date("2026-01-02"),
+            })
+        );
+    }
+
+    #[test]
+    fn conversion_overflow_is_reported() {
+        let table = ExchangeRateTable::new(vec![rate("EUR", "JPY", "2026-01-02", 160.0)]);
+        assert_err!(table.convert(
+            &money(i64::MAX / 2, "EUR"),
+            &currency("JPY"),
+            date("2026-01-02")
+        ));
+    }
+}
@@ -0,0 +1,45 @@
+use crate::domain::{normalise_merchant_name, PaymentMerchant};
+
+/// A spelling of a merchant name, stored normalised so that case and
+/// whitespace do not matter when payments are resolved to their merchant.
+#[derive(Debug, PartialEq)]
+pub struct MerchantAlias(String);
+
+impl MerchantAlias {
+    /// Aliases follow the rules of merchant names.
+    pub fn parse(alias: String) -> Result<Self, String> {
+        let merchant_name = PaymentMerchant::parse(alias)?;
+        Ok(Self::from(&merchant_name))
+    }
+}
+
+impl From<&PaymentMerchant> for MerchantAlias {
+    fn from(merchant_name: &PaymentMerchant) -> Self {
+        Self(normalise_merchant_name(merchant_name.as_ref()))
+    }
+}
+
+impl AsRef<str> for MerchantAlias {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MerchantAlias;
+    use claims::assert_err;
+
+    #[test]
+    fn aliases_are_normalised() {
+        let alias = MerchantAlias::parse("  AMAZON   EU ".to_string()).unwrap();
+        assert_eq!(alias.as_ref(), "amazon eu");
+    }
+
+    #[test]
+    fn invalid_merchant_names_are_rejected() {
+        for alias in ["", "   ", "Amazon (EU)"] {
+            assert_err!(MerchantAlias::parse(alias.to_string()));
+        }
+    }
+}
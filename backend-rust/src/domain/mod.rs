@@ -1,17 +1,53 @@
+mod amount_in_cents;
+mod budget;
+mod budget_alert;
+mod category_colour;
 mod category_kind;
+mod category_tree;
+mod currency;
+mod exchange_rate;
+mod merchant_alias;
+mod money;
 pub mod payment;
 mod payment_category;
 mod payment_category_icon;
 mod payment_description;
+mod payment_duplicate;
 mod payment_merchant;
+mod payment_split;
+mod payment_suggestion;
+mod recurrence;
+mod recurring_payment;
+mod rule;
+mod staging_transaction;
 mod tag;
+mod transfer;
 mod wallet;
 
+pub use amount_in_cents::AmountInCents;
+pub use budget::{Budget, BudgetPeriod, BudgetStatus};
+pub use budget_alert::{reached_thresholds, BudgetAlert, BUDGET_ALERT_THRESHOLDS};
+pub use category_colour::CategoryColour;
 pub use category_kind::CategoryKind;
+pub use category_tree::{CategoryTree, CATEGORY_PATH_SEPARATOR};
+pub use currency::{Currency, DEFAULT_CURRENCY};
+pub use exchange_rate::{ConversionError, ExchangeRate, ExchangeRateTable};
+pub use merchant_alias::MerchantAlias;
+pub use money::Money;
 pub use payment::Payment;
 pub use payment_category::PaymentCategory;
 pub use payment_category_icon::PaymentCategoryIcon;
 pub use payment_description::PaymentDescription;
+pub use payment_duplicate::{
+    cluster_by_merchant_name, merchant_names_are_similar, normalise_merchant_name,
+};
 pub use payment_merchant::PaymentMerchant;
-pub use tag::{Tag, TagKey, TagValue};
+pub use payment_split::PaymentSplit;
+pub use payment_suggestion::{payment_features, recency_weight, NaiveBayes};
+pub use recurrence::{Frequency, Recurrence};
+pub use recurring_payment::{RecurringPayment, RecurringPaymentStatus};
+pub use rule::{apply_rules, Rule, RuleAction, RuleCondition, RuleOutcome, RuleSubject};
+pub use staging_transaction::{StagingStatus, StagingTransaction};
+pub use tag::{Tag, TagKey, TagValue, TAG_SEPARATOR};
+pub use transfer::Transfer;
 pub use wallet::{Wallet, WalletName};
@@ -0,0 +1,71 @@
+use crate::domain::{AmountInCents, Currency};
+
+/// An amount in cents together with its currency.
+///
+/// Amounts in different currencies are never added together: they must be
+/// converted first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Money {
+    amount: AmountInCents,
+    currency: Currency,
+}
+
+impl Money {
+    pub fn new(amount: AmountInCents, currency: Currency) -> Self {
+        Self { amount, currency }
+    }
+
+    pub fn amount(&self) -> AmountInCents {
+        self.amount
+    }
+
+    pub fn currency(&self) -> &Currency {
+        &self.currency
+    }
+
+    /// Adds two amounts of the same currency.
+    /// Fails on a currency mismatch or if the sum overflows.
+    pub fn checked_add(&self, other: &Money) -> Result<Money, String> {
+        if self.currency != other.currency {
+            return Err(format!(
+                "Cannot add {} to {} without converting",
+                other.currency, self.currency
+            ));
+        }
+        let amount = self
+            .amount
+            .checked_add(other.amount)
+            .ok_or_else(|| format!("Sum of {} amounts overflows", self.currency))?;
+        Ok(Self::new(amount, self.currency.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Money;
+    use crate::domain::{AmountInCents, Currency};
+    use claims::{assert_err, assert_ok_eq};
+
+    fn money(cents: i64, currency: &str) -> Money {
+        Money::new(
+            AmountInCents::new(cents),
+            Currency::parse(currency.to_string()).unwrap(),
+        )
+    }
+
+    #[test]
+    fn amounts_in_the_same_currency_are_added() {
+        assert_ok_eq!(
+            money(150, "USD").checked_add(&money(-50, "USD")),
+            money(100, "USD")
+        );
+    }
+    #[test]
+    fn amounts_in_different_currencies_are_not_added() {
+        assert_err!(money(150, "USD").checked_add(&money(50, "EUR")));
+    }
+    #[test]
+    fn overflow_is_reported() {
+        assert_err!(money(i64::MAX, "GBP").checked_add(&money(1, "GBP")));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::domain::{PaymentDescription, PaymentMerchant};
+use crate::domain::{Money, PaymentDescription, PaymentMerchant, PaymentSplit};
 use chrono::NaiveDateTime;
 use uuid::Uuid;
 
@@ -6,9 +6,11 @@ use uuid::Uuid;
 pub struct Payment {
     pub description: Option<PaymentDescription>,
     pub category_id: Uuid,
-    pub amount_in_cents: i32,
+    pub amount: Money,
     pub merchant_name: PaymentMerchant,
     pub accounting_date: NaiveDateTime,
     pub wallet_id: Option<Uuid>,
     pub user_id: String,
+    /// Empty unless the payment is spread over several categories.
+    pub splits: Vec<PaymentSplit>,
 }
@@ -0,0 +1,119 @@
+/// Largest edit distance between the merchant names of two duplicates.
+const MAX_MERCHANT_EDIT_DISTANCE: usize = 2;
+
+/// Lower-cased merchant name with runs of whitespace collapsed.
+pub fn normalise_merchant_name(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Whether two merchant names likely designate the same merchant: equal once
+/// normalised, or a couple of typos apart (at most a quarter of the characters,
+/// so that short names such as "Bus" and "Gas" stay distinct).
+pub fn merchant_names_are_similar(a: &str, b: &str) -> bool {
+    let (a, b) = (normalise_merchant_name(a), normalise_merchant_name(b));
+    if a == b {
+        return true;
+    }
+    let longest = a.chars().count().max(b.chars().count());
+    let distance = edit_distance(&a, &b);
+    distance <= MAX_MERCHANT_EDIT_DISTANCE && distance * 4 <= longest
+}
+
+/// Levenshtein distance, in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Groups the items whose merchant names are similar, directly or through other
+/// items of the group. Items without any similar item are left out.
+pub fn cluster_by_merchant_name<T>(
+    items: Vec<T>,
+    merchant_name: impl Fn(&T) -> &str,
+) -> Vec<Vec<T>> {
+    let mut clusters: Vec<Vec<usize>> = Vec::new();
+    for i in 0..items.len() {
+        let (similar, mut others): (Vec<_>, Vec<_>) = clusters.into_iter().partition(|cluster| {
+            cluster.iter().any(|&j| {
+                merchant_names_are_similar(merchant_name(&items[i]), merchant_name(&items[j]))
+            })
+        });
+        let mut merged: Vec<usize> = similar.into_iter().flatten().collect();
+        merged.push(i);
+        merged.sort_unstable();
+        others.push(merged);
+        clusters = others;
+    }
+    clusters.sort_unstable_by_key(|cluster| cluster[0]);
+
+    let mut items: Vec<Option<T>> = items.into_iter().map(Some).collect();
+    clusters
+        .into_iter()
+        .filter(|cluster| cluster.len() > 1)
+        .map(|cluster| {
+            cluster
+                .into_iter()
+                .filter_map(|i| items[i].take())
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merchant_names_are_normalised() {
+        assert_eq!(normalise_merchant_name("  Corner   SHOP\t"), "corner shop");
+    }
+
+    #[test]
+    fn similar_merchant_names_are_detected() {
+        for (a, b) in [
+            ("Bakery", "bakery "),
+            ("Corner Shop", "corner  shop"),
+            ("Supermarket", "Supermarkt"),
+            ("Amazon EU", "Amazon.EU"),
+            ("Café Lumière", "Cafe Lumiere"),
+        ] {
+            assert!(merchant_names_are_similar(a, b), "{} / {}", a, b);
+        }
+    }
+
+    #[test]
+    fn different_merchant_names_are_not_similar() {
+        for (a, b) in [
+            ("Bus", "Gas"),
+            ("Bakery", "Butcher"),
+            ("Amazon", "Amazon Prime"),
+            ("Shell", "Total"),
+        ] {
+            assert!(!merchant_names_are_similar(a, b), "{} / {}", a, b);
+        }
+    }
+
+    #[test]
+    fn clusters_group_transitively_similar_names() {
+        let names = vec!["Bakery", "Butcher", "bakery", "Bakeri", "Gas", "Baker y"];
+
+        let clusters = cluster_by_merchant_name(names, |name| name);
+
+        assert_eq!(
+            clusters,
+            vec![vec!["Bakery", "bakery", "Bakeri", "Baker y"]]
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use crate::domain::{AmountInCents, PaymentDescription};
+use uuid::Uuid;
+
+/// A line item of a payment, assigning part of its amount to a category.
+#[derive(Debug)]
+pub struct PaymentSplit {
+    pub category_id: Uuid,
+    pub amount: AmountInCents,
+    pub description: Option<PaymentDescription>,
+}
+
+impl PaymentSplit {
+    pub fn parse(
+        category_id: Uuid,
+        amount: AmountInCents,
+        description: Option<PaymentDescription>,
+    ) -> Result<Self, String> {
+        if amount == AmountInCents::ZERO {
+            return Err("Split amounts cannot be zero.".to_string());
+        }
+        Ok(Self {
+            category_id,
+            amount,
+            description,
+        })
+    }
+
+    /// Checks that the splits of a payment add up to its amount.
+    /// A payment without splits is always valid.
+    pub fn validate_total(splits: &[PaymentSplit], total: AmountInCents) -> Result<(), String> {
+        if splits.is_empty() {
+            return Ok(());
+        }
+        match AmountInCents::checked_sum(splits.iter().map(|split| split.amount)) {
+            Some(sum) if sum == total => Ok(()),
+            Some(sum) => Err(format!(
+                "Splits add up to {sum} but the payment amount is {total}."
+            )),
+            None => Err("The sum of the splits overflows.".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PaymentSplit;
+    use crate::domain::AmountInCents;
+    use claims::{assert_err, assert_ok};
+    use uuid::Uuid;
+
+    fn split(cents: i64) -> PaymentSplit {
+        PaymentSplit::parse(Uuid::new_v4(), AmountInCents::new(cents), None).unwrap()
+    }
+
+    #[test]
+    fn zero_amounts_are_rejected() {
+        assert_err!(PaymentSplit::parse(
+            Uuid::new_v4(),
+            AmountInCents::ZERO,
+            None
+        ));
+    }
+    #[test]
+    fn splits_adding_up_to_the_payment_are_valid() {
+        let splits = vec![split(-3000), split(-1500), split(-500)];
+        assert_ok!(PaymentSplit::validate_total(
+            &splits,
+            AmountInCents::new(-5000)
+        ));
+    }
+    #[test]
+    fn splits_may_have_mixed_signs() {
+        // e.g. a deposit refunded on the same receipt
+        let splits = vec![split(-3000), split(250)];
+        assert_ok!(PaymentSplit::validate_total(
+            &splits,
+            AmountInCents::new(-2750)
+        ));
+    }
+    #[test]
+    fn splits_not_adding_up_to_the_payment_are_rejected() {
+        let splits = vec![split(-3000), split(-1500)];
+        assert_err!(PaymentSplit::validate_total(
+            &splits,
+            AmountInCents::new(-5000)
+        ));
+    }
+    #[test]
+    fn overflowing_splits_are_rejected() {
+        let splits = vec![split(i64::MAX), split(1), split(-1)];
+        assert_err!(PaymentSplit::validate_total(
+            &splits,
+            AmountInCents::new(i64::MAX)
+        ));
+    }
+    #[test]
+    fn no_splits_is_valid() {
+        assert_ok!(PaymentSplit::validate_total(&[], AmountInCents::new(-5000)));
+    }
+}
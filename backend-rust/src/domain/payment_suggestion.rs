@@ -0,0 +1,199 @@
+use crate::domain::normalise_merchant_name;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+/// Age, in days, at which a past payment counts half as much as a payment of today.
+const RECENCY_HALF_LIFE_DAYS: f64 = 180.0;
+/// Additive smoothing of the feature likelihoods.
+const SMOOTHING: f64 = 1.0;
+
+/// Features of a payment used to predict its category, wallet or tags: the
+/// tokens of its merchant name and, when known, the sign and order of
+/// magnitude of its amount.
+pub fn payment_features(merchant_name: &str, amount_in_cents: Option<i64>) -> Vec<String> {
+    let merchant_name = normalise_merchant_name(merchant_name);
+    let mut features: Vec<String> = merchant_name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| token.chars().count() > 1 && !token.chars().all(|c| c.is_numeric()))
+        .map(|token| format!("merchant:{}", token))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    features.sort_unstable();
+    if let Some(cents) = amount_in_cents {
+        let sign = if cents < 0 { "-" } else { "+" };
+        let digits = cents.unsigned_abs().to_string().len();
+        features.push(format!("amount:{}{}", sign, digits));
+    }
+    features
+}
+
+/// Weight of a payment `age_in_days` old: recent habits win over old ones.
+pub fn recency_weight(age_in_days: i64) -> f64 {
+    0.5_f64.powf(age_in_days.max(0) as f64 / RECENCY_HALF_LIFE_DAYS)
+}
+
+/// Multinomial naive Bayes classifier over weighted examples.
+#[derive(Debug)]
+pub struct NaiveBayes<L> {
+    labels: HashMap<L, LabelCounts>,
+    vocabulary: HashSet<String>,
+    total_weight: f64,
+}
+
+#[derive(Debug, Default)]
+struct LabelCounts {
+    weight: f64,
+    feature_weights: HashMap<String, f64>,
+    total_feature_weight: f64,
+}
+
+impl<L: Eq + Hash + Clone> Default for NaiveBayes<L> {
+    fn default() -> Self {
+        Self {
+            labels: HashMap::new(),
+            vocabulary: HashSet::new(),
+            total_weight: 0.0,
+        }
+    }
+}
+
+impl<L: Eq + Hash + Clone> NaiveBayes<L> {
+    pub fn train(&mut self, features: &[String], label: L, weight: f64) {
+        let counts = self.labels.entry(label).or_default();
+        counts.weight += weight;
+        for feature in features {
+            *counts.feature_weights.entry(feature.clone()).or_default() += weight;
+            counts.total_feature_weight += weight;
+            self.vocabulary.insert(feature.clone());
+        }
+        self.total_weight += weight;
+    }
+
+    /// Whether some training example had the feature.
+    pub fn knows(&self, feature: &str) -> bool {
+        self.vocabulary.contains(feature)
+    }
+
+    /// Labels with their probability given `features`, most likely first.
+    /// Features never seen in training are ignored.
+    pub fn rank(&self, features: &[String]) -> Vec<(L, f64)> {
+        let features: Vec<&String> = features
+            .iter()
+            .filter(|feature| self.vocabulary.contains(*feature))
+            .collect();
+        let vocabulary_size = self.vocabulary.len() as f64;
+        let log_scores: Vec<(&L, f64)> = self
+            .labels
+            .iter()
+            .map(|(label, counts)| {
+                let prior = (counts.weight / self.total_weight).ln();
+                let likelihood: f64 = features
+                    .iter()
+                    .map(|feature| {
+                        let weight = counts.feature_weights.get(*feature).unwrap_or(&0.0);
+                        ((weight + SMOOTHING)
+                            / (counts.total_feature_weight + SMOOTHING * vocabulary_size))
+                            .ln()
+                    })
+                    .sum();
+                (label, prior + likelihood)
+            })
+            .collect();
+
+        // Normalised in the log domain to avoid underflows
+        let max = log_scores
+            .iter()
+            .map(|(_, score)| *score)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let total: f64 = log_scores
+            .iter()
+            .map(|(_, score)| (score - max).exp())
+            .sum();
+        let mut ranked: Vec<(L, f64)> = log_scores
+            .into_iter()
+            .map(|(label, score)| (label.clone(), (score - max).exp() / total))
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features(merchant_name: &str, amount_in_cents: i64) -> Vec<String> {
+        payment_features(merchant_name, Some(amount_in_cents))
+    }
+
+    #[test]
+    fn features_are_merchant_tokens_and_amount_magnitude() {
+        assert_eq!(
+            features("  AMAZON.it  Marketplace 42 x", -12_50),
+            vec![
+                "merchant:amazon",
+                "merchant:it",
+                "merchant:marketplace",
+                "amount:-4"
+            ]
+        );
+        assert_eq!(payment_features("Salary", None), vec!["merchant:salary"]);
+        assert_eq!(
+            features("Employer", 250_000),
+            vec!["merchant:employer", "amount:+6"]
+        );
+    }
+
+    #[test]
+    fn recent_payments_weigh_more() {
+        assert_eq!(recency_weight(0), 1.0);
+        assert_eq!(recency_weight(-3), 1.0);
+        assert!((recency_weight(180) - 0.5).abs() < 1e-9);
+        assert!(recency_weight(30) > recency_weight(31));
+    }
+
+    #[test]
+    fn labels_are_ranked_by_probability() {
+        let mut classifier = NaiveBayes::default();
+        for _ in 0..5 {
+            classifier.train(&features("Bakery Dupont", -450), "Food", 1.0);
+        }
+        classifier.train(&features("Shell station", -6000), "Transport", 1.0);
+        classifier.train(&features("Total station", -5500), "Transport", 1.0);
+
+        let ranked = classifier.rank(&features("Esso station", -5000));
+
+        assert_eq!(ranked[0].0, "Transport");
+        assert!(ranked[0].1 > 0.9, "{:?}", ranked);
+        let total: f64 = ranked.iter().map(|(_, p)| p).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn recency_weights_decide_between_habits() {
+        let mut classifier = NaiveBayes::default();
+        let bakery = features("Bakery", -450);
+        for _ in 0..3 {
+            classifier.train(&bakery, "Food", recency_weight(720));
+        }
+        classifier.train(&bakery, "Snacks", recency_weight(1));
+
+        assert_eq!(classifier.rank(&bakery)[0].0, "Snacks");
+    }
+
+    #[test]
+    fn untrained_classifiers_rank_nothing() {
+        let classifier: NaiveBayes<&str> = NaiveBayes::default();
+        assert!(classifier.rank(&features("Bakery", -450)).is_empty());
+    }
+
+    #[test]
+    fn features_seen_in_training_are_known() {
+        let mut classifier = NaiveBayes::default();
+        classifier.train(&features("Bakery", -450), "Food", 1.0);
+
+        assert!(classifier.knows("merchant:bakery"));
+        assert!(!classifier.knows("merchant:hotel"));
+    }
+}
@@ -0,0 +1,286 @@
+use chrono::{Datelike, Duration, Months, NaiveDate};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl Frequency {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        }
+    }
+}
+
+/// A recurrence rule, using the subset of RFC 5545 `RRULE` needed for bills
+/// and subscriptions: `FREQ` (`DAILY`, `WEEKLY`, `MONTHLY`, `YEARLY`),
+/// `INTERVAL`, `BYMONTHDAY` (monthly rules only), `COUNT` and `UNTIL`.
+///
+/// Occurrences are counted from a start date. Days that do not exist in a
+/// month (e.g. the 31st, or February 29th) fall on the last day of the month.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recurrence {
+    frequency: Frequency,
+    interval: u32,
+    by_month_day: Option<u32>,
+    count: Option<u32>,
+    until: Option<NaiveDate>,
+}
+
+impl Recurrence {
+    /// Parses a rule such as `FREQ=MONTHLY;BYMONTHDAY=1` (an `RRULE:` prefix is allowed).
+    pub fn parse(rule: &str) -> Result<Self, String> {
+        let rule = rule.trim();
+        let rule = rule.strip_prefix("RRULE:").unwrap_or(rule);
+        let mut frequency = None;
+        let mut interval = 1;
+        let mut by_month_day = None;
+        let mut count = None;
+        let mut until = None;
+
+        for part in rule.split(';').filter(|part| !part.is_empty()) {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("{part} is not a NAME=VALUE recurrence part."))?;
+            let number = || {
+                value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .ok_or_else(|| format!("{name} must be a positive number, got {value}."))
+            };
+            match name.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(format!("{value} is not a supported frequency.")),
+                    })
+                }
+                "INTERVAL" => interval = number()?,
+                "BYMONTHDAY" => {
+                    let day = number()?;
+                    if day > 31 {
+                        return Err(format!("{day} is not a valid day of the month."));
+                    }
+                    by_month_day = Some(day);
+                }
+                "COUNT" => count = Some(number()?),
+                "UNTIL" => {
+                    // Date only: a time part is ignored, payments are booked per day.
+                    let date = value.get(..8).unwrap_or(value);
+                    until = Some(
+                        NaiveDate::parse_from_str(date, "%Y%m%d")
+                            .map_err(|_| format!("{value} is not a valid UNTIL date."))?,
+                    );
+                }
+                _ => return Err(format!("{name} is not a supported recurrence part.")),
+            }
+        }
+
+        let frequency = frequency.ok_or_else(|| "The recurrence needs a FREQ.".to_string())?;
+        if by_month_day.is_some() && frequency != Frequency::Monthly {
+            return Err("BYMONTHDAY is only supported with FREQ=MONTHLY.".to_string());
+        }
+        if count.is_some() && until.is_some() {
+            return Err("COUNT and UNTIL cannot be used together.".to_string());
+        }
+        Ok(Self {
+            frequency,
+            interval,
+            by_month_day,
+            count,
+            until,
+        })
+    }
+
+    /// The `n`-th occurrence (starting at 0) of a series starting on `start`,
+    /// or `None` once the series is over.
+    pub fn nth(&self, start: NaiveDate, n: u32) -> Option<NaiveDate> {
+        if self.count.is_some_and(|count| n >= count) {
+            return None;
+        }
+        let date = match self.frequency {
+            Frequency::Daily => {
+                start.checked_add_signed(Duration::days(i64::from(n) * i64::from(self.interval)))?
+            }
+            Frequency::Weekly => start
+                .checked_add_signed(Duration::weeks(i64::from(n) * i64::from(self.interval)))?,
+            Frequency::Monthly => {
+                let day = self.by_month_day.unwrap_or(start.day());
+                // A BYMONTHDAY before the start day begins the series in the next period.
+                let skip = u32::from(month_day(start, 0, day)? < start);
+                month_day(start, (n + skip).checked_mul(self.interval)?, day)?
+            }
+            Frequency::Yearly => month_day(start, n.checked_mul(12 * self.interval)?, start.day())?,
+        };
+        match self.until {
+            Some(until) if date > until => None,
+            _ => Some(date),
+        }
+    }
+
+    /// The first occurrence on or after `date`, if the series has not ended.
+    pub fn next_on_or_after(&self, start: NaiveDate, date: NaiveDate) -> Option<NaiveDate> {
+        (0..)
+            .map(|n| self.nth(start, n))
+            .find(|occurrence| occurrence.is_none_or(|occurrence| occurrence >= date))
+            .flatten()
+    }
+
+    /// The first occurrence strictly after `date`, if the series has not ended.
+    pub fn next_after(&self, start: NaiveDate, date: NaiveDate) -> Option<NaiveDate> {
+        self.next_on_or_after(start, date.succ_opt()?)
+    }
+}
+
+/// `day` of the month `months` after the month of `start`, clamped to the month length.
+fn month_day(start: NaiveDate, months: u32, day: u32) -> Option<NaiveDate> {
+    let first = start.with_day(1)?.checked_add_months(Months::new(months))?;
+    let last = first.checked_add_months(Months::new(1))?.pred_opt()?.day();
+    first.with_day(day.min(last))
+}
+
+impl std::fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FREQ={}", self.frequency.as_str())?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if let Some(day) = self.by_month_day {
+            write!(f, ";BYMONTHDAY={}", day)?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={}", count)?;
+        }
+        if let Some(until) = self.until {
+            write!(f, ";UNTIL={}", until.format("%Y%m%d"))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Recurrence;
+    use chrono::NaiveDate;
+    use claims::{assert_err, assert_none, assert_ok, assert_some_eq};
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn occurrences(rule: &str, start: &str, n: u32) -> Vec<NaiveDate> {
+        let recurrence = Recurrence::parse(rule).unwrap();
+        (0..n)
+            .map_while(|i| recurrence.nth(date(start), i))
+            .collect()
+    }
+
+    #[test]
+    fn supported_rules_are_parsed() {
+        for rule in [
+            "FREQ=DAILY",
+            "FREQ=WEEKLY;INTERVAL=2",
+            "RRULE:FREQ=MONTHLY;BYMONTHDAY=1",
+            "FREQ=YEARLY;COUNT=3",
+            "freq=monthly;until=20261231T000000Z",
+        ] {
+            assert_ok!(Recurrence::parse(rule));
+        }
+    }
+    #[test]
+    fn invalid_rules_are_rejected() {
+        for rule in [
+            "",
+            "INTERVAL=2",
+            "FREQ=HOURLY",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=MONTHLY;BYMONTHDAY=32",
+            "FREQ=WEEKLY;BYMONTHDAY=1",
+            "FREQ=DAILY;BYDAY=MO",
+            "FREQ=DAILY;COUNT=2;UNTIL=20261231",
+            "FREQ=DAILY;UNTIL=tomorrow",
+            "FREQ",
+        ] {
+            assert_err!(Recurrence::parse(rule));
+        }
+    }
+    #[test]
+    fn rules_are_displayed_in_canonical_form() {
+        let recurrence = Recurrence::parse("RRULE:freq=monthly;interval=1;bymonthday=5").unwrap();
+        assert_eq!(recurrence.to_string(), "FREQ=MONTHLY;BYMONTHDAY=5");
+    }
+    #[test]
+    fn weekly_occurrences_follow_the_interval() {
+        assert_eq!(
+            occurrences("FREQ=WEEKLY;INTERVAL=2", "2026-01-05", 3),
+            vec![date("2026-01-05"), date("2026-01-19"), date("2026-02-02")]
+        );
+    }
+    #[test]
+    fn monthly_occurrences_are_clamped_to_the_end_of_the_month() {
+        assert_eq!(
+            occurrences("FREQ=MONTHLY", "2026-01-31", 4),
+            vec![
+                date("2026-01-31"),
+                date("2026-02-28"),
+                date("2026-03-31"),
+                date("2026-04-30")
+            ]
+        );
+    }
+    #[test]
+    fn a_month_day_before_the_start_begins_the_next_month() {
+        assert_eq!(
+            occurrences("FREQ=MONTHLY;BYMONTHDAY=1", "2026-01-15", 2),
+            vec![date("2026-02-01"), date("2026-03-01")]
+        );
+    }
+    #[test]
+    fn yearly_occurrences_on_february_29th_fall_back_to_the_28th() {
+        assert_eq!(
+            occurrences("FREQ=YEARLY", "2028-02-29", 2),
+            vec![date("2028-02-29"), date("2029-02-28")]
+        );
+    }
+    #[test]
+    fn count_and_until_end_the_series() {
+        assert_eq!(occurrences("FREQ=DAILY;COUNT=2", "2026-01-01", 5).len(), 2);
+        assert_eq!(
+            occurrences("FREQ=MONTHLY;UNTIL=20260301", "2026-01-01", 5),
+            vec![date("2026-01-01"), date("2026-02-01"), date("2026-03-01")]
+        );
+    }
+    #[test]
+    fn next_occurrence_is_found_from_any_date() {
+        let recurrence = Recurrence::parse("FREQ=MONTHLY;BYMONTHDAY=10").unwrap();
+        let start = date("2026-01-10");
+        assert_some_eq!(
+            recurrence.next_on_or_after(start, date("2026-03-10")),
+            date("2026-03-10")
+        );
+        assert_some_eq!(
+            recurrence.next_after(start, date("2026-03-10")),
+            date("2026-04-10")
+        );
+        assert_some_eq!(
+            recurrence.next_on_or_after(start, date("2025-06-01")),
+            start
+        );
+    }
+    #[test]
+    fn no_next_occurrence_after_the_series_ended() {
+        let recurrence = Recurrence::parse("FREQ=WEEKLY;COUNT=2").unwrap();
+        assert_none!(recurrence.next_after(date("2026-01-01"), date("2026-01-08")));
+    }
+}
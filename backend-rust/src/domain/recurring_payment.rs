@@ -0,0 +1,72 @@
+use crate::domain::{Money, PaymentDescription, PaymentMerchant, Recurrence, TagKey, TagValue};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RecurringPaymentStatus {
+    #[default]
+    Active,
+    Paused,
+}
+
+impl RecurringPaymentStatus {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "active" => Ok(Self::Active),
+            "paused" => Ok(Self::Paused),
+            _ => Err(format!("{s} is not a valid recurring payment status.")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Active => "active",
+            Self::Paused => "paused",
+        }
+    }
+}
+
+/// Template of a payment repeated on a schedule (rent, subscriptions, salary...).
+/// Every occurrence becomes a regular payment booked on the occurrence date.
+#[derive(Debug)]
+pub struct RecurringPayment {
+    pub description: Option<PaymentDescription>,
+    pub category_id: Uuid,
+    pub amount: Money,
+    pub merchant_name: PaymentMerchant,
+    pub wallet_id: Option<Uuid>,
+    pub tags: Vec<(TagKey, TagValue)>,
+    pub recurrence: Recurrence,
+    pub start_date: NaiveDate,
+    pub status: RecurringPaymentStatus,
+    pub user_id: String,
+}
+
+impl RecurringPayment {
+    /// The first occurrence on or after `date`, if the series has not ended.
+    pub fn next_occurrence_from(&self, date: NaiveDate) -> Option<NaiveDate> {
+        self.recurrence.next_on_or_after(self.start_date, date)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RecurringPaymentStatus;
+    use claims::{assert_err, assert_ok_eq};
+
+    #[test]
+    fn statuses_round_trip() {
+        for status in [
+            RecurringPaymentStatus::Active,
+            RecurringPaymentStatus::Paused,
+        ] {
+            assert_ok_eq!(RecurringPaymentStatus::parse(status.as_str()), status);
+        }
+    }
+    #[test]
+    fn unknown_statuses_are_rejected() {
+        assert_err!(RecurringPaymentStatus::parse("cancelled"));
+    }
+}
@@ -0,0 +1,370 @@
+use crate::domain::{PaymentDescription, TagKey, TagValue};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+const MAX_PATTERN_LENGTH: usize = 256;
+
+/// What a payment must look like for a rule to apply. Patterns are
+/// case-insensitive regular expressions searched anywhere in the text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum RuleCondition {
+    Merchant {
+        pattern: String,
+    },
+    /// Never matches payments without a description.
+    Description {
+        pattern: String,
+    },
+    /// Inclusive bounds; expenses are negative.
+    Amount {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        min_in_cents: Option<i64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_in_cents: Option<i64>,
+    },
+    /// Name of the wallet, case ignored.
+    Wallet {
+        wallet: String,
+    },
+}
+
+/// What a rule changes on the payments it matches.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum RuleAction {
+    SetCategory { category_id: Uuid },
+    SetWallet { wallet: String },
+    AddTag { key: String, value: String },
+    SetDescription { description: String },
+}
+
+/// The fields of a payment that conditions look at.
+#[derive(Debug)]
+pub struct RuleSubject<'a> {
+    pub merchant_name: &'a str,
+    pub description: Option<&'a str>,
+    pub amount_in_cents: i64,
+    pub wallet: Option<&'a str>,
+}
+
+/// A user-defined auto-categorisation rule: when all its conditions match a
+/// payment, its actions are applied to it.
+#[derive(Debug)]
+pub struct Rule {
+    conditions: Vec<RuleCondition>,
+    actions: Vec<RuleAction>,
+    /// Compiled patterns, one per condition (`None` for the others).
+    patterns: Vec<Option<Regex>>,
+}
+
+impl Rule {
+    pub fn parse(conditions: Vec<RuleCondition>, actions: Vec<RuleAction>) -> Result<Self, String> {
+        if conditions.is_empty() {
+            return Err("A rule needs at least one condition.".to_string());
+        }
+        if actions.is_empty() {
+            return Err("A rule needs at least one action.".to_string());
+        }
+        let patterns = conditions
+            .iter()
+            .map(|condition| match condition {
+                RuleCondition::Merchant { pattern } | RuleCondition::Description { pattern } => {
+                    compile(pattern).map(Some)
+                }
+                RuleCondition::Amount {
+                    min_in_cents: Some(min),
+                    max_in_cents: Some(max),
+                } if min > max => Err(format!(
+                    "The amount range {}..{} of a rule is empty.",
+                    min, max
+                )),
+                RuleCondition::Amount {
+                    min_in_cents: None,
+                    max_in_cents: None,
+                } => Err("An amount condition needs a minimum or a maximum.".to_string()),
+                RuleCondition::Wallet { wallet } if wallet.trim().is_empty() => {
+                    Err("A wallet condition needs a wallet.".to_string())
+                }
+                _ => Ok(None),
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        for action in &actions {
+            match action {
+                RuleAction::SetCategory { .. } => {}
+                RuleAction::SetWallet { wallet } if wallet.trim().is_empty() => {
+                    return Err("A wallet action needs a wallet.".to_string());
+                }
+                RuleAction::SetWallet { .. } => {}
+                RuleAction::AddTag { key, value } => {
+                    TagKey::parse(key.clone())?;
+                    TagValue::parse(value.clone())?;
+                }
+                RuleAction::SetDescription { description } => {
+                    PaymentDescription::parse(description.clone())?;
+                }
+            }
+        }
+        Ok(Self {
+            conditions,
+            actions,
+            patterns,
+        })
+    }
+
+    pub fn conditions(&self) -> &[RuleCondition] {
+        &self.conditions
+    }
+
+    pub fn actions(&self) -> &[RuleAction] {
+        &self.actions
+    }
+
+    pub fn matches(&self, subject: &RuleSubject) -> bool {
+        self.conditions
+            .iter()
+            .zip(&self.patterns)
+            .all(|(condition, pattern)| match (condition, pattern) {
+                (RuleCondition::Merchant { .. }, Some(pattern)) => {
+                    pattern.is_match(subject.merchant_name)
+                }
+                (RuleCondition::Description { .. }, Some(pattern)) => subject
+                    .description
+                    .is_some_and(|description| pattern.is_match(description)),
+                (
+                    RuleCondition::Amount {
+                        min_in_cents,
+                        max_in_cents,
+                    },
+                    _,
+                ) => {
+                    min_in_cents.is_none_or(|min| subject.amount_in_cents >= min)
+                        && max_in_cents.is_none_or(|max| subject.amount_in_cents <= max)
+                }
+                (RuleCondition::Wallet { wallet }, _) => subject
+                    .wallet
+                    .is_some_and(|name| name.trim().eq_ignore_ascii_case(wallet.trim())),
+                _ => false,
+            })
+    }
+}
+
+fn compile(pattern: &str) -> Result<Regex, String> {
+    if pattern.is_empty() || pattern.len() > MAX_PATTERN_LENGTH {
+        return Err(format!(
+            "Rule patterns must have 1 to {} characters.",
+            MAX_PATTERN_LENGTH
+        ));
+    }
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| format!("{} is not a valid pattern: {}", pattern, e))
+}
+
+/// The changes of the rules matching a payment.
+#[derive(Debug, Default, PartialEq)]
+pub struct RuleOutcome {
+    pub category_id: Option<Uuid>,
+    pub wallet: Option<String>,
+    pub description: Option<String>,
+    /// At most one value per key.
+    pub tags: Vec<(String, String)>,
+}
+
+impl RuleOutcome {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Applies the matching rules in order. A field set by a rule is not changed
+/// by the following ones, so earlier rules take precedence.
+pub fn apply_rules<'a>(
+    rules: impl IntoIterator<Item = &'a Rule>,
+    subject: &RuleSubject,
+) -> RuleOutcome {
+    let mut outcome = RuleOutcome::default();
+    for rule in rules.into_iter().filter(|rule| rule.matches(subject)) {
+        for action in &rule.actions {
+            match action {
+                RuleAction::SetCategory { category_id } => {
+                    outcome.category_id.get_or_insert(*category_id);
+                }
+                RuleAction::SetWallet { wallet } => {
+                    outcome.wallet.get_or_insert_with(|| wallet.clone());
+                }
+                RuleAction::SetDescription { description } => {
+                    outcome
+                        .description
+                        .get_or_insert_with(|| description.clone());
+                }
+                RuleAction::AddTag { key, value } => {
+                    if !outcome.tags.iter().any(|(k, _)| k == key) {
+                        outcome.tags.push((key.clone(), value.clone()));
+                    }
+                }
+            }
+        }
+    }
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claims::{assert_err, assert_ok};
+
+    fn merchant(pattern: &str) -> RuleCondition {
+        RuleCondition::Merchant {
+            pattern: pattern.to_string(),
+        }
+    }
+
+    fn tag(key: &str, value: &str) -> RuleAction {
+        RuleAction::AddTag {
+            key: key.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    fn subject(merchant_name: &str, amount_in_cents: i64) -> RuleSubject<'_> {
+        RuleSubject {
+            merchant_name,
+            description: None,
+            amount_in_cents,
+            wallet: Some("Card"),
+        }
+    }
+
+    #[test]
+    fn all_conditions_must_match() {
+        let rule = Rule::parse(
+            vec![
+                merchant("netflix"),
+                RuleCondition::Amount {
+                    min_in_cents: None,
+                    max_in_cents: Some(-1),
+                },
+                RuleCondition::Wallet {
+                    wallet: "card".to_string(),
+                },
+            ],
+            vec![tag("provider", "netflix")],
+        )
+        .unwrap();
+
+        assert!(rule.matches(&subject("NETFLIX.COM", -1299)));
+        assert!(!rule.matches(&subject("NETFLIX.COM", 1299)));
+        assert!(!rule.matches(&subject("Spotify", -999)));
+        let other_wallet = RuleSubject {
+            wallet: Some("Cash"),
+            ..subject("Netflix", -1299)
+        };
+        assert!(!rule.matches(&other_wallet));
+    }
+
+    #[test]
+    fn description_conditions_need_a_description() {
+        let rule = Rule::parse(
+            vec![RuleCondition::Description {
+                pattern: "^rent".to_string(),
+            }],
+            vec![tag("kind", "fixed")],
+        )
+        .unwrap();
+        let with_description = RuleSubject {
+            description: Some("Rent March"),
+            ..subject("Landlord", -90000)
+        };
+
+        assert!(rule.matches(&with_description));
+        assert!(!rule.matches(&subject("Landlord", -90000)));
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        let amount = |min, max| RuleCondition::Amount {
+            min_in_cents: min,
+            max_in_cents: max,
+        };
+        let actions = || vec![tag("kind", "fixed")];
+
+        assert_err!(Rule::parse(vec![], actions()));
+        assert_err!(Rule::parse(vec![merchant("netflix")], vec![]));
+        assert_err!(Rule::parse(vec![merchant("(netflix")], actions()));
+        assert_err!(Rule::parse(vec![merchant("")], actions()));
+        assert_err!(Rule::parse(vec![amount(Some(10), Some(-10))], actions()));
+        assert_err!(Rule::parse(vec![amount(None, None)], actions()));
+        assert_err!(Rule::parse(vec![merchant("a")], vec![tag(" ", "fixed")]));
+        assert_ok!(Rule::parse(vec![amount(Some(-10), Some(-10))], actions()));
+    }
+
+    #[test]
+    fn earlier_rules_take_precedence() {
+        let category = Uuid::new_v4();
+        let rules = vec![
+            Rule::parse(
+                vec![merchant("netflix")],
+                vec![
+                    RuleAction::SetCategory {
+                        category_id: category,
+                    },
+                    tag("provider", "netflix"),
+                ],
+            )
+            .unwrap(),
+            Rule::parse(
+                vec![merchant(".")],
+                vec![
+                    RuleAction::SetCategory {
+                        category_id: Uuid::new_v4(),
+                    },
+                    RuleAction::SetWallet {
+                        wallet: "Card".to_string(),
+                    },
+                    tag("provider", "other"),
+                    tag("reviewed", "no"),
+                ],
+            )
+            .unwrap(),
+        ];
+
+        let outcome = apply_rules(&rules, &subject("Netflix", -1299));
+
+        assert_eq!(
+            outcome,
+            RuleOutcome {
+                category_id: Some(category),
+                wallet: Some("Card".to_string()),
+                description: None,
+                tags: vec![
+                    ("provider".to_string(), "netflix".to_string()),
+                    ("reviewed".to_string(), "no".to_string())
+                ],
+            }
+        );
+        assert!(apply_rules(&rules[..1], &subject("Spotify", -999)).is_empty());
+    }
+
+    #[test]
+    fn rules_round_trip_through_json() {
+        let json = serde_json::json!([
+            {"type": "merchant", "pattern": "netflix"},
+            {"type": "amount", "maxInCents": -1}
+        ]);
+        let conditions: Vec<RuleCondition> = serde_json::from_value(json.clone()).unwrap();
+
+        assert_eq!(conditions[0], merchant("netflix"));
+        assert_eq!(serde_json::to_value(&conditions).unwrap(), json);
+    }
+}
@@ -0,0 +1,144 @@
+use crate::domain::Money;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// Review state of a transaction fetched from a bank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StagingStatus {
+    /// Waiting for review.
+    #[default]
+    Pending,
+    /// Approved by the user, ready to be imported.
+    Reviewed,
+    /// Imported as a payment.
+    Imported,
+    Rejected,
+}
+
+impl StagingStatus {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "pending" => Ok(Self::Pending),
+            "reviewed" => Ok(Self::Reviewed),
+            "imported" => Ok(Self::Imported),
+            "rejected" => Ok(Self::Rejected),
+            _ => Err(format!("{s} is not a valid staging transaction status.")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Reviewed => "reviewed",
+            Self::Imported => "imported",
+            Self::Rejected => "rejected",
+        }
+    }
+
+    /// Whether a review can move a transaction from this status to `next`.
+    /// Imported transactions are final, and only the import marks them so.
+    pub fn can_be_reviewed_as(&self, next: StagingStatus) -> Result<(), String> {
+        match (self, next) {
+            (Self::Imported, _) => Err("The transaction has already been imported.".to_string()),
+            (_, Self::Imported) => {
+                Err("Transactions are marked as imported by the import only.".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A bank transaction waiting in the staging area until it is reviewed and
+/// imported as a payment, or rejected.
+#[derive(Debug)]
+pub struct StagingTransaction {
+    /// Identifier of the transaction at the bank, unique per user.
+    pub bank_transaction_id: String,
+    pub amount: Money,
+    pub booking_date: NaiveDate,
+    pub value_date: Option<NaiveDate>,
+    pub creditor_name: Option<String>,
+    pub debtor_name: Option<String>,
+    pub remittance_info: Option<String>,
+    pub suggested_category: Option<String>,
+    pub suggested_merchant: Option<String>,
+    pub status: StagingStatus,
+    pub user_id: String,
+}
+
+impl StagingTransaction {
+    /// Merchant of the payment: the one chosen during the review, otherwise the
+    /// counterparty (the creditor of an expense, the debtor of an income).
+    pub fn merchant_name(&self) -> Option<&str> {
+        let counterparty = if self.amount.amount().cents() < 0 {
+            &self.creditor_name
+        } else {
+            &self.debtor_name
+        };
+        [&self.suggested_merchant, counterparty]
+            .into_iter()
+            .flatten()
+            .map(|name| name.trim())
+            .find(|name| !name.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StagingStatus, StagingTransaction};
+    use crate::domain::{AmountInCents, Currency, Money};
+    use chrono::NaiveDate;
+    use claims::{assert_err, assert_ok, assert_ok_eq};
+
+    fn transaction(cents: i64) -> StagingTransaction {
+        StagingTransaction {
+            bank_transaction_id: "tx-1".to_string(),
+            amount: Money::new(AmountInCents::new(cents), Currency::default()),
+            booking_date: NaiveDate::from_ymd_opt(2026, 3, 5).unwrap(),
+            value_date: None,
+            creditor_name: Some("Bakery".to_string()),
+            debtor_name: Some("Employer".to_string()),
+            remittance_info: None,
+            suggested_category: None,
+            suggested_merchant: None,
+            status: StagingStatus::Pending,
+            user_id: "user".to_string(),
+        }
+    }
+
+    #[test]
+    fn statuses_round_trip() {
+        for status in [
+            StagingStatus::Pending,
+            StagingStatus::Reviewed,
+            StagingStatus::Imported,
+            StagingStatus::Rejected,
+        ] {
+            assert_ok_eq!(StagingStatus::parse(status.as_str()), status);
+        }
+        assert_err!(StagingStatus::parse("approved"));
+    }
+
+    #[test]
+    fn imported_is_set_by_the_import_only() {
+        assert_ok!(StagingStatus::Pending.can_be_reviewed_as(StagingStatus::Reviewed));
+        assert_ok!(StagingStatus::Rejected.can_be_reviewed_as(StagingStatus::Pending));
+        assert_err!(StagingStatus::Reviewed.can_be_reviewed_as(StagingStatus::Imported));
+        assert_err!(StagingStatus::Imported.can_be_reviewed_as(StagingStatus::Rejected));
+    }
+
+    #[test]
+    fn the_merchant_defaults_to_the_counterparty() {
+        assert_eq!(transaction(-100).merchant_name(), Some("Bakery"));
+        assert_eq!(transaction(100).merchant_name(), Some("Employer"));
+
+        let mut reviewed = transaction(-100);
+        reviewed.suggested_merchant = Some("Local bakery".to_string());
+        assert_eq!(reviewed.merchant_name(), Some("Local bakery"));
+
+        let mut anonymous = transaction(-100);
+        anonymous.creditor_name = Some(" ".to_string());
+        assert_eq!(anonymous.merchant_name(), None);
+    }
+}
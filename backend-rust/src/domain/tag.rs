@@ -1,5 +1,11 @@
+use unicode_segmentation::UnicodeSegmentation;
 use uuid::Uuid;
 
+/// Separates the key from the value of a tag written as `key:value`.
+pub const TAG_SEPARATOR: char = ':';
+
+const MAX_TAG_LENGTH: usize = 64;
+
 #[derive(Debug, Clone)]
 pub struct Tag {
     pub id: Option<Uuid>,
@@ -7,16 +13,42 @@ pub struct Tag {
     pub value: TagValue,
 }
 
-#[derive(Debug, Clone)]
+impl Tag {
+    pub fn parse(key: String, value: String) -> Result<Tag, String> {
+        Ok(Self {
+            id: None,
+            key: TagKey::parse(key)?,
+            value: TagValue::parse(value)?,
+        })
+    }
+
+    /// Parses a tag written as `key:value`. The value may itself contain the
+    /// separator, keys cannot.
+    pub fn parse_key_value(s: &str) -> Result<Tag, String> {
+        match s.split_once(TAG_SEPARATOR) {
+            Some((key, value)) => Self::parse(key.to_string(), value.to_string()),
+            None => Err(format!("{s} is not a valid tag, expected key:value.")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TagKey(String);
 
 impl TagKey {
+    /// Parses a string as a TagKey, without surrounding whitespace.
+    /// Returns an error if it is empty, longer than 64 graphemes or contains
+    /// the `:` separator.
     pub fn parse(s: String) -> Result<TagKey, String> {
-        let is_empty_or_whitespace = s.trim().is_empty();
-        if is_empty_or_whitespace {
+        let s = s.trim();
+        if s.is_empty() {
             Err("Tag key cannot be empty".to_string())
+        } else if s.graphemes(true).count() > MAX_TAG_LENGTH {
+            Err("Tag key is too long".to_string())
+        } else if s.contains(TAG_SEPARATOR) {
+            Err(format!("Tag key cannot contain '{TAG_SEPARATOR}'"))
         } else {
-            Ok(Self(s))
+            Ok(Self(s.to_string()))
         }
     }
 }
@@ -27,16 +59,20 @@ impl AsRef<str> for TagKey {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TagValue(String);
 
 impl TagValue {
+    /// Parses a string as a TagValue, without surrounding whitespace.
+    /// Returns an error if it is empty or longer than 64 graphemes.
     pub fn parse(s: String) -> Result<TagValue, String> {
-        let is_empty_or_whitespace = s.trim().is_empty();
-        if is_empty_or_whitespace {
+        let s = s.trim();
+        if s.is_empty() {
             Err("Tag value cannot be empty".to_string())
+        } else if s.graphemes(true).count() > MAX_TAG_LENGTH {
+            Err("Tag value is too long".to_string())
         } else {
-            Ok(Self(s))
+            Ok(Self(s.to_string()))
         }
     }
 }
@@ -46,3 +82,42 @@ impl AsRef<str> for TagValue {
         &self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claims::{assert_err, assert_ok};
+
+    #[test]
+    fn keys_and_values_are_trimmed() {
+        let tag = Tag::parse(" trip ".to_string(), "rome\n".to_string()).unwrap();
+
+        assert_eq!(tag.key.as_ref(), "trip");
+        assert_eq!(tag.value.as_ref(), "rome");
+    }
+
+    #[test]
+    fn empty_or_too_long_tags_are_rejected() {
+        assert_err!(TagKey::parse(" ".to_string()));
+        assert_err!(TagValue::parse(String::new()));
+        assert_ok!(TagKey::parse("ё".repeat(64)));
+        assert_err!(TagKey::parse("ё".repeat(65)));
+        assert_err!(TagValue::parse("a".repeat(65)));
+    }
+
+    #[test]
+    fn keys_cannot_contain_the_separator() {
+        assert_err!(TagKey::parse("a:b".to_string()));
+        assert_ok!(TagValue::parse("10:30".to_string()));
+    }
+
+    #[test]
+    fn key_value_pairs_split_on_the_first_separator() {
+        let tag = Tag::parse_key_value("meeting:10:30").unwrap();
+
+        assert_eq!(tag.key.as_ref(), "meeting");
+        assert_eq!(tag.value.as_ref(), "10:30");
+        assert_err!(Tag::parse_key_value("trip"));
+        assert_err!(Tag::parse_key_value(":rome"));
+    }
+}
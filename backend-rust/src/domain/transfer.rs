@@ -0,0 +1,130 @@
+use crate::domain::{AmountInCents, Money, PaymentDescription, PaymentMerchant, WalletName};
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+/// Money moved from one wallet to another.
+///
+/// It is stored as two linked payments: a debit of `amount` in the source
+/// wallet and a credit of the same amount in the destination wallet. Neither
+/// leg counts as income or expense.
+#[derive(Debug)]
+pub struct Transfer {
+    pub from_wallet_id: Uuid,
+    pub to_wallet_id: Uuid,
+    /// Always positive.
+    pub amount: Money,
+    pub accounting_date: NaiveDateTime,
+    pub description: Option<PaymentDescription>,
+    pub user_id: String,
+}
+
+impl Transfer {
+    pub fn parse(
+        from_wallet_id: Uuid,
+        to_wallet_id: Uuid,
+        amount: Money,
+        accounting_date: NaiveDateTime,
+        description: Option<PaymentDescription>,
+        user_id: String,
+    ) -> Result<Self, String> {
+        if from_wallet_id == to_wallet_id {
+            return Err("A transfer needs two different wallets.".to_string());
+        }
+        if amount.amount() <= AmountInCents::ZERO {
+            return Err(format!(
+                "{} is not a valid transfer amount: it must be positive.",
+                amount.amount()
+            ));
+        }
+        Ok(Self {
+            from_wallet_id,
+            to_wallet_id,
+            amount,
+            accounting_date,
+            description,
+            user_id,
+        })
+    }
+
+    /// Amount of the leg in the source wallet.
+    pub fn debit(&self) -> AmountInCents {
+        // Cannot overflow: the amount is positive.
+        AmountInCents::new(-self.amount.amount().cents())
+    }
+
+    /// Amount of the leg in the destination wallet.
+    pub fn credit(&self) -> AmountInCents {
+        self.amount.amount()
+    }
+
+    /// Merchant name shown on a leg, naming the wallet on the other side.
+    pub fn leg_merchant(counterpart: &WalletName, outgoing: bool) -> PaymentMerchant {
+        let name = if outgoing {
+            format!("Transfer to {counterpart}")
+        } else {
+            format!("Transfer from {counterpart}")
+        };
+        PaymentMerchant::parse(name)
+            .or_else(|_| PaymentMerchant::parse("Transfer".to_string()))
+            .expect("a constant merchant name is valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Transfer;
+    use crate::domain::{AmountInCents, Currency, Money, WalletName};
+    use chrono::NaiveDateTime;
+    use claims::{assert_err, assert_ok};
+    use uuid::Uuid;
+
+    fn money(cents: i64) -> Money {
+        Money::new(AmountInCents::new(cents), Currency::default())
+    }
+
+    fn transfer(from: Uuid, to: Uuid, cents: i64) -> Result<Transfer, String> {
+        Transfer::parse(
+            from,
+            to,
+            money(cents),
+            NaiveDateTime::default(),
+            None,
+            "user".to_string(),
+        )
+    }
+
+    #[test]
+    fn a_transfer_between_two_wallets_is_valid() {
+        let transfer = assert_ok!(transfer(Uuid::new_v4(), Uuid::new_v4(), 2500));
+        assert_eq!(transfer.debit(), AmountInCents::new(-2500));
+        assert_eq!(transfer.credit(), AmountInCents::new(2500));
+    }
+    #[test]
+    fn a_transfer_to_the_same_wallet_is_rejected() {
+        let wallet = Uuid::new_v4();
+        assert_err!(transfer(wallet, wallet, 2500));
+    }
+    #[test]
+    fn zero_and_negative_amounts_are_rejected() {
+        for cents in [0, -2500, i64::MIN] {
+            assert_err!(transfer(Uuid::new_v4(), Uuid::new_v4(), cents));
+        }
+    }
+    #[test]
+    fn leg_merchant_names_the_other_wallet() {
+        let wallet = WalletName::parse("Savings".to_string()).unwrap();
+        assert_eq!(
+            Transfer::leg_merchant(&wallet, true).as_ref(),
+            "Transfer to Savings"
+        );
+        assert_eq!(
+            Transfer::leg_merchant(&wallet, false).as_ref(),
+            "Transfer from Savings"
+        );
+    }
+    #[test]
+    fn leg_merchant_falls_back_when_the_wallet_name_is_not_a_valid_merchant() {
+        let wallet = WalletName::parse("Cash (EUR)".to_string()).unwrap();
+        assert_eq!(Transfer::leg_merchant(&wallet, true).as_ref(), "Transfer");
+    }
+}
@@ -1,3 +1,4 @@
+use crate::domain::Currency;
 use serde::{Deserialize, Serialize};
 use unicode_segmentation::UnicodeSegmentation;
 use uuid::Uuid;
@@ -8,6 +9,8 @@ pub struct Wallet {
     pub id: Option<Uuid>,
     pub user_id: String,
     pub name: WalletName,
+    /// Currency of payments recorded in this wallet without an explicit currency.
+    pub default_currency: Currency,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
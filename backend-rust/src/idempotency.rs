@@ -0,0 +1,246 @@
+//! `Idempotency-Key` support: the first response to a request sent with a key
+//! is stored per user and replayed when the client retries with the same key,
+//! so that retries on flaky networks do not create rows twice.
+//!
+//! Wrap a route with [`idempotent`]:
+//! `web::post().to(create_payment).wrap(from_fn(idempotent))`.
+
+use crate::auth::AuthenticatedUser;
+use actix_web::body::{to_bytes, BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderValue, CONTENT_TYPE};
+use actix_web::http::StatusCode;
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpResponse};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+/// Set on replayed responses.
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "Idempotent-Replayed";
+/// How long responses are kept for replays.
+const IDEMPOTENCY_KEY_TTL_HOURS: i32 = 24;
+const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 255;
+
+/// Middleware making a route idempotent for the requests with an `Idempotency-Key`
+/// header. Requests without the header, or without a valid bearer token (the
+/// handler rejects them), go through unchanged.
+pub async fn idempotent(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let Some(key) = req.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+    let Some(key) = parse_key(key) else {
+        let response = HttpResponse::BadRequest().body(format!(
+            "{} must be 1 to {} visible ASCII characters",
+            IDEMPOTENCY_KEY_HEADER, MAX_IDEMPOTENCY_KEY_LENGTH
+        ));
+        return Ok(req.into_response(response));
+    };
+    let Ok(user) = req.extract::<AuthenticatedUser>().await else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+    let Some(connection_pool) = req.app_data::<web::Data<PgPool>>().cloned() else {
+        tracing::error!("The connection pool is not registered as application data");
+        return Ok(req.into_response(HttpResponse::InternalServerError().finish()));
+    };
+
+    // The body is read to fingerprint the request, then handed back to the handler
+    let body = req.extract::<web::Bytes>().await?;
+    let request_hash = request_hash(&req, &body);
+    req.set_payload(body.into());
+
+    match begin_request(&user.sub, &key, &request_hash, &connection_pool).await {
+        Ok(Begin::New) => {}
+        Ok(Begin::Replay(response)) => return Ok(req.into_response(response)),
+        Err(e) => {
+            tracing::error!("Failed to look up the idempotency key: {:?}", e);
+            return Ok(req.into_response(HttpResponse::InternalServerError().finish()));
+        }
+    }
+
+    let response = match next.call(req).await {
+        Ok(response) => response,
+        Err(e) => {
+            forget_request(&user.sub, &key, &connection_pool).await;
+            return Err(e);
+        }
+    };
+    let (req, response) = response.into_parts();
+    let (response, body) = response.into_parts();
+    let body = match to_bytes(body).await {
+        Ok(body) => body,
+        Err(e) => {
+            forget_request(&user.sub, &key, &connection_pool).await;
+            let e: Box<dyn std::error::Error> = e.into();
+            tracing::error!("Failed to read the response body: {}", e);
+            return Ok(ServiceResponse::new(
+                req,
+                HttpResponse::InternalServerError().finish(),
+            ));
+        }
+    };
+    // Server errors are not replayed: the client may retry them with the same key
+    if response.status().is_server_error() {
+        forget_request(&user.sub, &key, &connection_pool).await;
+    } else {
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok());
+        if let Err(e) = sqlx::query!(
+            r#"
+            UPDATE expenses.idempotency_keys
+            SET response_status = $3, response_content_type = $4, response_body = $5
+            WHERE user_id = $1 AND idempotency_key = $2
+            "#,
+            user.sub,
+            key,
+            response.status().as_u16() as i16,
+            content_type,
+            body.as_ref()
+        )
+        .execute(connection_pool.get_ref())
+        .await
+        {
+            // The response is still returned; a retry gets a 409 until the key expires
+            tracing::error!("Failed to save the idempotent response: {:?}", e);
+        }
+    }
+    Ok(ServiceResponse::new(
+        req,
+        response.set_body(body).map_into_boxed_body(),
+    ))
+}
+
+fn parse_key(value: &HeaderValue) -> Option<String> {
+    let key = value.to_str().ok()?.trim();
+    let valid = !key.is_empty()
+        && key.len() <= MAX_IDEMPOTENCY_KEY_LENGTH
+        && key.chars().all(|c| c.is_ascii_graphic());
+    valid.then(|| key.to_string())
+}
+
+/// A key reused for another request is an error, whatever the endpoint.
+fn request_hash(req: &ServiceRequest, body: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(req.method().as_str());
+    hasher.update(b" ");
+    hasher.update(req.uri().to_string());
+    hasher.update(b"\n");
+    hasher.update(body);
+    hasher.finalize().to_vec()
+}
+
+enum Begin {
+    /// First request with this key: the handler runs.
+    New,
+    /// The key was used before: this response is returned instead.
+    Replay(HttpResponse),
+}
+
+#[tracing::instrument(
+    name = "Claiming an idempotency key",
+    skip(request_hash, connection_pool)
+)]
+async fn begin_request(
+    user_id: &str,
+    key: &str,
+    request_hash: &[u8],
+    connection_pool: &PgPool,
+) -> Result<Begin, sqlx::Error> {
+    // Expired keys of the user are purged as they are reused
+    sqlx::query!(
+        "DELETE FROM expenses.idempotency_keys WHERE user_id = $1 AND expires_at < now()",
+        user_id
+    )
+    .execute(connection_pool)
+    .await?;
+    let claimed = sqlx::query!(
+        r#"
+        INSERT INTO expenses.idempotency_keys (user_id, idempotency_key, request_hash, expires_at)
+        VALUES ($1, $2, $3, now() + make_interval(hours => $4))
+        ON CONFLICT (user_id, idempotency_key) DO NOTHING
+        "#,
+        user_id,
+        key,
+        request_hash,
+        IDEMPOTENCY_KEY_TTL_HOURS
+    )
+    .execute(connection_pool)
+    .await?
+    .rows_affected()
+        == 1;
+    if claimed {
+        return Ok(Begin::New);
+    }
+
+    let Some(previous) = sqlx::query!(
+        r#"
+        SELECT request_hash, response_status, response_content_type, response_body
+        FROM expenses.idempotency_keys
+        WHERE user_id = $1 AND idempotency_key = $2
+        "#,
+        user_id,
+        key
+    )
+    .fetch_optional(connection_pool)
+    .await?
+    else {
+        // Forgotten after a server error in the meantime
+        return Ok(Begin::Replay(
+            HttpResponse::Conflict().body("The request with this idempotency key failed, retry"),
+        ));
+    };
+    if previous.request_hash != request_hash {
+        return Ok(Begin::Replay(HttpResponse::UnprocessableEntity().body(
+            format!(
+                "{} was already used for a different request",
+                IDEMPOTENCY_KEY_HEADER
+            ),
+        )));
+    }
+    let (Some(status), Some(body)) = (previous.response_status, previous.response_body) else {
+        return Ok(Begin::Replay(HttpResponse::Conflict().body(
+            "A request with this idempotency key is still being processed",
+        )));
+    };
+    let status = StatusCode::from_u16(status as u16).unwrap_or(StatusCode::OK);
+    let mut response = HttpResponse::build(status);
+    response.insert_header((IDEMPOTENT_REPLAYED_HEADER, "true"));
+    if let Some(content_type) = previous.response_content_type {
+        response.insert_header((CONTENT_TYPE, content_type));
+    }
+    Ok(Begin::Replay(response.body(body)))
+}
+
+/// Releases the key, so that the client can retry a failed request with it.
+async fn forget_request(user_id: &str, key: &str, connection_pool: &PgPool) {
+    if let Err(e) = sqlx::query!(
+        "DELETE FROM expenses.idempotency_keys WHERE user_id = $1 AND idempotency_key = $2",
+        user_id,
+        key
+    )
+    .execute(connection_pool)
+    .await
+    {
+        tracing::error!("Failed to release the idempotency key: {:?}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_must_be_visible_ascii() {
+        let key = |value: &str| parse_key(&HeaderValue::from_str(value).unwrap());
+
+        assert_eq!(key(" 3f2a-retry-1 "), Some("3f2a-retry-1".to_string()));
+        assert_eq!(key(""), None);
+        assert_eq!(key("two words"), None);
+        assert_eq!(key(&"k".repeat(MAX_IDEMPOTENCY_KEY_LENGTH + 1)), None);
+    }
+}
@@ -1,6 +1,11 @@
 pub mod auth;
+pub mod banking;
 pub mod configuration;
+pub mod crypto;
 pub mod domain;
+pub mod idempotency;
+pub mod notifier;
 pub mod routes;
+pub mod scheduler;
 pub mod startup;
 pub mod telemetry;
@@ -1,9 +1,15 @@
 use expenses_monitor_be::configuration::get_configuration;
-use expenses_monitor_be::startup::Application;
+use expenses_monitor_be::crypto::{reencrypt_secrets, KeyRing};
+use expenses_monitor_be::routes::materialise_recurring_payments;
+use expenses_monitor_be::startup::{get_connection_pool, Application};
 use expenses_monitor_be::telemetry::{get_subscriber, init_subscriber};
 
+const USAGE: &str =
+    "usage: expenses-monitor-be [serve | materialise-recurring-payments | reencrypt-secrets]";
+
 #[tokio::main]
 async fn main() -> Result<(), std::io::Error> {
+    let command = std::env::args().nth(1);
     let configuration = get_configuration().expect("Failed to read configuration");
 
     // logger configuration
@@ -15,6 +21,45 @@ async fn main() -> Result<(), std::io::Error> {
     );
     init_subscriber(subscriber);
 
-    let application = Application::build(configuration).await?;
-    application.run_until_stopped().await
+    match command.as_deref() {
+        None | Some("serve") => {
+            let application = Application::build(configuration).await?;
+            application.run_until_stopped().await
+        }
+        // One-shot run, e.g. from a Kubernetes CronJob
+        Some("materialise-recurring-payments") => {
+            let connection_pool = get_connection_pool(&configuration);
+            let summary =
+                materialise_recurring_payments(&connection_pool, chrono::Utc::now().date_naive())
+                    .await
+                    .map_err(std::io::Error::other)?;
+            tracing::info!(
+                "Created {} payments from recurring payments",
+                summary.created
+            );
+            if summary.failed > 0 {
+                return Err(std::io::Error::other(format!(
+                    "{} recurring payments could not be materialised",
+                    summary.failed
+                )));
+            }
+            Ok(())
+        }
+        // After adding a key and making it current, before removing the old one
+        Some("reencrypt-secrets") => {
+            let key_ring = KeyRing::from_settings(&configuration.encryption).map_err(|e| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string())
+            })?;
+            let connection_pool = get_connection_pool(&configuration);
+            let reencrypted = reencrypt_secrets(&connection_pool, &key_ring)
+                .await
+                .map_err(std::io::Error::other)?;
+            tracing::info!("Re-encrypted {} secrets", reencrypted);
+            Ok(())
+        }
+        Some(other) => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("unknown command '{}'\n{}", other, USAGE),
+        )),
+    }
 }
@@ -0,0 +1,129 @@
+use crate::configuration::{NotifierSettings, SmtpSettings};
+use crate::domain::BudgetAlert;
+use async_trait::async_trait;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use secrecy::ExposeSecret;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Delivers budget alerts to the user.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn notify(&self, alert: &BudgetAlert) -> Result<(), String>;
+}
+
+/// Builds the notifier selected in the configuration.
+pub fn build_notifier(settings: &NotifierSettings) -> Result<Arc<dyn Notifier>, String> {
+    Ok(match settings {
+        NotifierSettings::Log => Arc::new(LogNotifier),
+        NotifierSettings::Webhook {
+            url,
+            timeout_seconds,
+        } => Arc::new(WebhookNotifier::new(
+            url.clone(),
+            Duration::from_secs(*timeout_seconds),
+        )?),
+        NotifierSettings::Smtp(smtp) => Arc::new(SmtpNotifier::new(smtp)?),
+    })
+}
+
+/// Only writes alerts to the application log.
+pub struct LogNotifier;
+
+#[async_trait]
+impl Notifier for LogNotifier {
+    async fn notify(&self, alert: &BudgetAlert) -> Result<(), String> {
+        tracing::info!(
+            budget_id = %alert.budget_id,
+            threshold = alert.threshold,
+            "{}",
+            alert.message()
+        );
+        Ok(())
+    }
+}
+
+/// POSTs each alert as JSON to a URL (chat integrations, automation tools).
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: String, timeout: Duration) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| format!("Failed to build the webhook client: {e}"))?;
+        Ok(Self { client, url })
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    async fn notify(&self, alert: &BudgetAlert) -> Result<(), String> {
+        self.client
+            .post(&self.url)
+            .json(alert)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("Failed to call the alert webhook: {e}"))?;
+        Ok(())
+    }
+}
+
+/// Sends each alert by e-mail.
+pub struct SmtpNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Mailbox,
+}
+
+impl SmtpNotifier {
+    pub fn new(settings: &SmtpSettings) -> Result<Self, String> {
+        let builder = if settings.starttls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&settings.host)
+                .map_err(|e| format!("Invalid SMTP relay {}: {e}", settings.host))?
+        } else {
+            // Plain connection, for local relays and mail catchers only.
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.host)
+        };
+        let builder = match (&settings.username, &settings.password) {
+            (Some(username), Some(password)) => builder.credentials(Credentials::new(
+                username.clone(),
+                password.expose_secret().to_string(),
+            )),
+            _ => builder,
+        };
+        let parse = |address: &str| {
+            address
+                .parse::<Mailbox>()
+                .map_err(|e| format!("Invalid e-mail address {address}: {e}"))
+        };
+        Ok(Self {
+            transport: builder.port(settings.port).build(),
+            from: parse(&settings.from)?,
+            to: parse(&settings.to)?,
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for SmtpNotifier {
+    async fn notify(&self, alert: &BudgetAlert) -> Result<(), String> {
+        let email = Message::builder()
+            .from(self.from.clone())
+            .to(self.to.clone())
+            .subject(alert.subject())
+            .body(alert.message())
+            .map_err(|e| format!("Failed to build the alert e-mail: {e}"))?;
+        self.transport
+            .send(email)
+            .await
+            .map_err(|e| format!("Failed to send the alert e-mail: {e}"))?;
+        Ok(())
+    }
+}
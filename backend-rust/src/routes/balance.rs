@@ -1,8 +1,13 @@
+use crate::auth::AuthenticatedUser;
+use crate::domain::{AmountInCents, ConversionError, Currency, ExchangeRateTable, Money};
+use crate::routes::load_exchange_rate_table;
 use actix_web::{web, HttpResponse, Responder};
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use std::collections::{BTreeMap, BTreeSet};
 use std::ops::Deref;
+use uuid::Uuid;
 
 #[derive(Deserialize)]
 pub struct BalanceQuery {
@@ -10,143 +15,547 @@ pub struct BalanceQuery {
     start_date: Option<NaiveDate>,
     #[serde(rename = "endDate")]
     end_date: Option<NaiveDate>,
+    wallet: Option<String>,
+    category: Option<String>,
+    #[serde(rename = "groupBy")]
+    group_by: Option<BalanceGroupBy>,
+    /// Converts every payment into this currency using the rate of its accounting date.
+    #[serde(rename = "targetCurrency")]
+    target_currency: Option<String>,
 }
 
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BalanceGroupBy {
+    Wallet,
+    Category,
+    Month,
+}
+
+impl BalanceGroupBy {
+    fn as_str(&self) -> &'static str {
+        match self {
+            BalanceGroupBy::Wallet => "wallet",
+            BalanceGroupBy::Category => "category",
+            BalanceGroupBy::Month => "month",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct BalanceFilters {
+    start_date: Option<NaiveDate>,
+    end_date: Option<NaiveDate>,
+    wallet: Option<String>,
+    // Category filter accepts either a UUID or a name, like the payments listing.
+    category_id: Option<Uuid>,
+    category_name: Option<String>,
+}
+
+impl From<&BalanceQuery> for BalanceFilters {
+    fn from(query: &BalanceQuery) -> Self {
+        let category_id = query.category.as_ref().and_then(|c| c.parse::<Uuid>().ok());
+        Self {
+            start_date: query.start_date,
+            end_date: query.end_date,
+            wallet: query.wallet.clone(),
+            category_id,
+            category_name: query.category.clone().filter(|_| category_id.is_none()),
+        }
+    }
+}
+
+/// Balance of the filtered payments, with one set of totals per currency.
+///
+/// Amounts in different currencies are never added together. When the
+/// payments use at most one currency, the top-level totals repeat that
+/// currency's totals (or are zero); with several currencies they are omitted.
+///
+/// Transfers between wallets are neither income nor expenses: their net
+/// amount is reported separately and included in the total, so it only
+/// shows up when filtering or grouping by wallet.
 #[derive(Serialize, Deserialize)]
 pub struct BalanceResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    #[serde(
+        rename = "totalInCents",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub total_in_cents: Option<AmountInCents>,
+    #[serde(
+        rename = "incomeInCents",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub income_in_cents: Option<AmountInCents>,
+    #[serde(
+        rename = "expensesInCents",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub expenses_in_cents: Option<AmountInCents>,
+    #[serde(
+        rename = "transfersInCents",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub transfers_in_cents: Option<AmountInCents>,
+    pub currencies: Vec<CurrencyBalance>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub breakdown: Option<Vec<BalanceBreakdownEntry>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CurrencyBalance {
+    pub currency: String,
+    #[serde(rename = "totalInCents")]
+    pub total_in_cents: AmountInCents,
+    #[serde(rename = "incomeInCents")]
+    pub income_in_cents: AmountInCents,
+    #[serde(rename = "expensesInCents")]
+    pub expenses_in_cents: AmountInCents,
+    #[serde(rename = "transfersInCents")]
+    pub transfers_in_cents: AmountInCents,
+}
+
+/// Totals for one wallet, category or month (`YYYY-MM`) and currency when `groupBy` is requested.
+///
+/// The totals of a category include those of its subcategories.
+#[derive(Serialize, Deserialize)]
+pub struct BalanceBreakdownEntry {
+    /// Wallet id, category id or month. `None` groups payments without a wallet.
+    pub key: Option<String>,
+    /// Wallet name, category name or month.
+    pub label: Option<String>,
+    /// Id of the parent category, for subcategories.
+    #[serde(rename = "parentKey", default, skip_serializing_if = "Option::is_none")]
+    pub parent_key: Option<String>,
+    pub currency: String,
     #[serde(rename = "totalInCents")]
-    pub total_in_cents: i32,
+    pub total_in_cents: AmountInCents,
     #[serde(rename = "incomeInCents")]
-    pub income_in_cents: i32,
+    pub income_in_cents: AmountInCents,
     #[serde(rename = "expensesInCents")]
-    pub expenses_in_cents: i32,
+    pub expenses_in_cents: AmountInCents,
+    #[serde(rename = "transfersInCents")]
+    pub transfers_in_cents: AmountInCents,
+}
+
+#[derive(Debug)]
+enum BalanceError {
+    Database(sqlx::Error),
+    Conversion(ConversionError),
+}
+
+impl From<sqlx::Error> for BalanceError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+impl From<ConversionError> for BalanceError {
+    fn from(e: ConversionError) -> Self {
+        Self::Conversion(e)
+    }
 }
 
 #[tracing::instrument(name = "Retrieve overall balance", skip(connection_pool, query))]
 pub async fn get_balance(
     query: web::Query<BalanceQuery>,
+    user: AuthenticatedUser,
     connection_pool: web::Data<PgPool>,
 ) -> impl Responder {
-    match get_balance_from_db(connection_pool.deref(), query.start_date, query.end_date).await {
+    let filters = BalanceFilters::from(query.deref());
+    let target_currency = match query
+        .target_currency
+        .clone()
+        .map(Currency::parse)
+        .transpose()
+    {
+        Ok(target_currency) => target_currency,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+
+    match get_balance_from_db(
+        connection_pool.deref(),
+        &user.sub,
+        &filters,
+        query.group_by,
+        target_currency,
+    )
+    .await
+    {
         Ok(balance) => HttpResponse::Ok().json(balance),
-        Err(e) => {
-            tracing::error!("Failed to execute query: {:?}", e);
+        Err(BalanceError::Conversion(e @ ConversionError::MissingRate { .. })) => {
+            tracing::warn!("{}", e);
+            HttpResponse::UnprocessableEntity().body(e.to_string())
+        }
+        Err(BalanceError::Conversion(e)) => {
+            tracing::error!("Failed to convert balance: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Err(BalanceError::Database(e)) => {
+            tracing::error!("Failed to compute balance: {:?}", e);
             HttpResponse::InternalServerError().finish()
         }
     }
 }
 
+/// Income, expenses and net transfers of one currency on one day, as summed by the database.
+struct DailyTotals {
+    currency: String,
+    day: Option<NaiveDate>,
+    income: i64,
+    expenses: i64,
+    transfers: i64,
+}
+
+/// Converts daily sums into the target currency, if one was requested.
+struct Conversion {
+    rates: ExchangeRateTable,
+    target: Currency,
+}
+
+/// Income, expenses and net transfers in a single currency.
+struct Flows {
+    income: Money,
+    expenses: Money,
+    transfers: Money,
+}
+
+impl Flows {
+    fn convert(&self, conversion: &Conversion, day: NaiveDate) -> Result<Flows, ConversionError> {
+        let convert = |money: &Money| conversion.rates.convert(money, &conversion.target, day);
+        Ok(Flows {
+            income: convert(&self.income)?,
+            expenses: convert(&self.expenses)?,
+            transfers: convert(&self.transfers)?,
+        })
+    }
+
+    fn checked_add(&self, other: &Flows) -> Result<Flows, ConversionError> {
+        let add = |total: &Money, amount: &Money| {
+            total.checked_add(amount).map_err(ConversionError::Overflow)
+        };
+        Ok(Flows {
+            income: add(&self.income, &other.income)?,
+            expenses: add(&self.expenses, &other.expenses)?,
+            transfers: add(&self.transfers, &other.transfers)?,
+        })
+    }
+
+    fn into_balance(self) -> Result<CurrencyBalance, ConversionError> {
+        let total = self
+            .income
+            .checked_add(&self.expenses)
+            .and_then(|total| total.checked_add(&self.transfers))
+            .map_err(ConversionError::Overflow)?;
+        Ok(CurrencyBalance {
+            currency: total.currency().to_string(),
+            total_in_cents: total.amount(),
+            income_in_cents: self.income.amount(),
+            expenses_in_cents: self.expenses.amount(),
+            transfers_in_cents: self.transfers.amount(),
+        })
+    }
+}
+
+/// Running totals per currency.
+#[derive(Default)]
+struct Totals(BTreeMap<Currency, Flows>);
+
+/// Running totals of one wallet, category or month of a breakdown.
+struct BreakdownGroup {
+    key: Option<String>,
+    label: Option<String>,
+    parent_key: Option<String>,
+    totals: Totals,
+}
+
+impl Totals {
+    fn add(
+        &mut self,
+        daily: &DailyTotals,
+        conversion: Option<&Conversion>,
+    ) -> Result<(), BalanceError> {
+        let currency =
+            Currency::parse(daily.currency.clone()).map_err(|e| sqlx::Error::Decode(e.into()))?;
+        let money = |cents: i64| Money::new(AmountInCents::new(cents), currency.clone());
+        let mut flows = Flows {
+            income: money(daily.income),
+            expenses: money(daily.expenses),
+            transfers: money(daily.transfers),
+        };
+        if let Some(conversion) = conversion {
+            // Payments without an accounting date use the latest rates.
+            let day = daily.day.unwrap_or_else(|| chrono::Utc::now().date_naive());
+            flows = flows.convert(conversion, day)?;
+        }
+
+        let currency = flows.income.currency().clone();
+        let flows = match self.0.remove(&currency) {
+            Some(total) => total.checked_add(&flows)?,
+            None => flows,
+        };
+        self.0.insert(currency, flows);
+        Ok(())
+    }
+
+    fn into_balances(self) -> Result<Vec<CurrencyBalance>, BalanceError> {
+        self.0
+            .into_values()
+            .map(|flows| Ok(flows.into_balance()?))
+            .collect()
+    }
+}
+
 #[tracing::instrument(
     name = "Retrieving balance from database",
     skip(connection_pool),
-    fields(start_date = ?start_date, end_date = ?end_date)
+    fields(start_date = ?filters.start_date, end_date = ?filters.end_date)
 )]
 async fn get_balance_from_db(
     connection_pool: &PgPool,
-    start_date: Option<NaiveDate>,
-    end_date: Option<NaiveDate>,
-) -> Result<BalanceResponse, sqlx::Error> {
-    let (total, income, expenses) = match (start_date, end_date) {
-        (Some(start), Some(end)) => {
-            let result = sqlx::query!(
-                r#"
-                SELECT 
-                    COALESCE(SUM(amount), 0) as total,
-                    COALESCE(SUM(CASE WHEN amount > 0 THEN amount ELSE 0 END), 0) as income,
-                    COALESCE(SUM(CASE WHEN amount < 0 THEN amount ELSE 0 END), 0) as expenses
-                FROM expenses.payments
-                WHERE accounting_date >= $1 AND accounting_date <= $2
-                "#,
-                start as NaiveDate,
-                end as NaiveDate
-            )
-            .fetch_one(connection_pool)
-            .await
-            .map_err(|e| {
-                tracing::error!("Failed to execute query: {:?}", e);
-                e
-            })?;
-            (
-                result.total.unwrap_or(0),
-                result.income.unwrap_or(0),
-                result.expenses.unwrap_or(0),
-            )
-        }
-        (Some(start), None) => {
-            let result = sqlx::query!(
-                r#"
-                SELECT 
-                    COALESCE(SUM(amount), 0) as total,
-                    COALESCE(SUM(CASE WHEN amount > 0 THEN amount ELSE 0 END), 0) as income,
-                    COALESCE(SUM(CASE WHEN amount < 0 THEN amount ELSE 0 END), 0) as expenses
-                FROM expenses.payments
-                WHERE accounting_date >= $1
-                "#,
-                start as NaiveDate
-            )
-            .fetch_one(connection_pool)
-            .await
-            .map_err(|e| {
-                tracing::error!("Failed to execute query: {:?}", e);
-                e
-            })?;
-            (
-                result.total.unwrap_or(0),
-                result.income.unwrap_or(0),
-                result.expenses.unwrap_or(0),
-            )
-        }
-        (None, Some(end)) => {
-            let result = sqlx::query!(
-                r#"
-                SELECT 
-                    COALESCE(SUM(amount), 0) as total,
-                    COALESCE(SUM(CASE WHEN amount > 0 THEN amount ELSE 0 END), 0) as income,
-                    COALESCE(SUM(CASE WHEN amount < 0 THEN amount ELSE 0 END), 0) as expenses
-                FROM expenses.payments
-                WHERE accounting_date <= $1
-                "#,
-                end as NaiveDate
-            )
-            .fetch_one(connection_pool)
-            .await
-            .map_err(|e| {
-                tracing::error!("Failed to execute query: {:?}", e);
-                e
-            })?;
-            (
-                result.total.unwrap_or(0),
-                result.income.unwrap_or(0),
-                result.expenses.unwrap_or(0),
-            )
-        }
-        (None, None) => {
-            let result = sqlx::query!(
-                r#"
-                SELECT 
-                    COALESCE(SUM(amount), 0) as total,
-                    COALESCE(SUM(CASE WHEN amount > 0 THEN amount ELSE 0 END), 0) as income,
-                    COALESCE(SUM(CASE WHEN amount < 0 THEN amount ELSE 0 END), 0) as expenses
-                FROM expenses.payments
-                "#
-            )
-            .fetch_one(connection_pool)
-            .await
-            .map_err(|e| {
-                tracing::error!("Failed to execute query: {:?}", e);
-                e
-            })?;
-            (
-                result.total.unwrap_or(0),
-                result.income.unwrap_or(0),
-                result.expenses.unwrap_or(0),
+    user_id: &str,
+    filters: &BalanceFilters,
+    group_by: Option<BalanceGroupBy>,
+    target_currency: Option<Currency>,
+) -> Result<BalanceResponse, BalanceError> {
+    // Each filter is skipped when its parameter is NULL, so a single statement
+    // covers every combination of date bounds, wallet and category.
+    // Sums are kept per day so that each day can be converted at its own rate.
+    // Split payments contribute one line per split, so categories use the split amounts.
+    let rows = sqlx::query_as!(
+        DailyTotals,
+        r#"
+        SELECT
+            p.currency,
+            DATE(p.accounting_date) as day,
+            COALESCE(SUM(CASE WHEN p.transfer_id IS NULL AND l.amount > 0 THEN l.amount ELSE 0 END), 0)::bigint as "income!",
+            COALESCE(SUM(CASE WHEN p.transfer_id IS NULL AND l.amount < 0 THEN l.amount ELSE 0 END), 0)::bigint as "expenses!",
+            COALESCE(SUM(CASE WHEN p.transfer_id IS NOT NULL THEN l.amount ELSE 0 END), 0)::bigint as "transfers!"
+        FROM expenses.payments p
+        JOIN expenses.payment_lines l ON l.payment_id = p.id
+        LEFT JOIN expenses.wallets w ON p.wallet_id = w.id AND w.user_id = p.user_id
+        LEFT JOIN expenses.categories c ON l.category_id = c.id
+        WHERE p.user_id = $1
+          AND ($2::date IS NULL OR p.accounting_date >= $2::timestamp)
+          AND ($3::date IS NULL OR p.accounting_date < ($3 + 1)::timestamp)
+          AND ($4::text IS NULL OR w.name = $4)
+          AND ($5::uuid IS NULL OR l.category_id = $5)
+          AND ($6::text IS NULL OR LOWER(c.name) = LOWER($6))
+        GROUP BY 1, 2
+        "#,
+        user_id,
+        filters.start_date as Option<NaiveDate>,
+        filters.end_date as Option<NaiveDate>,
+        filters.wallet,
+        filters.category_id,
+        filters.category_name
+    )
+    .fetch_all(connection_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+
+    let conversion = match target_currency {
+        Some(target) => Some(load_conversion(connection_pool, &rows, target).await?),
+        None => None,
+    };
+
+    let mut totals = Totals::default();
+    for row in &rows {
+        totals.add(row, conversion.as_ref())?;
+    }
+    let mut currencies = totals.into_balances()?;
+    if let (Some(conversion), true) = (&conversion, currencies.is_empty()) {
+        currencies.push(CurrencyBalance {
+            currency: conversion.target.to_string(),
+            total_in_cents: AmountInCents::ZERO,
+            income_in_cents: AmountInCents::ZERO,
+            expenses_in_cents: AmountInCents::ZERO,
+            transfers_in_cents: AmountInCents::ZERO,
+        });
+    }
+
+    let breakdown = match group_by {
+        Some(group_by) => Some(
+            get_balance_breakdown_from_db(
+                connection_pool,
+                user_id,
+                filters,
+                group_by,
+                conversion.as_ref(),
             )
-        }
+            .await?,
+        ),
+        None => None,
+    };
+
+    let (currency, top_level) = match currencies.as_slice() {
+        [] => (None, Some([AmountInCents::ZERO; 4])),
+        [only] => (
+            Some(only.currency.clone()),
+            Some([
+                only.total_in_cents,
+                only.income_in_cents,
+                only.expenses_in_cents,
+                only.transfers_in_cents,
+            ]),
+        ),
+        _ => (None, None),
     };
 
     Ok(BalanceResponse {
-        total_in_cents: total as i32,
-        income_in_cents: income as i32,
-        expenses_in_cents: expenses as i32,
+        currency,
+        total_in_cents: top_level.map(|amounts| amounts[0]),
+        income_in_cents: top_level.map(|amounts| amounts[1]),
+        expenses_in_cents: top_level.map(|amounts| amounts[2]),
+        transfers_in_cents: top_level.map(|amounts| amounts[3]),
+        currencies,
+        breakdown,
     })
 }
+
+/// Loads the rates needed to convert the given daily sums into `target`.
+async fn load_conversion(
+    connection_pool: &PgPool,
+    rows: &[DailyTotals],
+    target: Currency,
+) -> Result<Conversion, BalanceError> {
+    let mut currencies: BTreeSet<Currency> = rows
+        .iter()
+        .filter_map(|row| Currency::parse(row.currency.clone()).ok())
+        .collect();
+    currencies.insert(target.clone());
+    let until = rows
+        .iter()
+        .filter_map(|row| row.day)
+        .max()
+        .into_iter()
+        .chain(std::iter::once(chrono::Utc::now().date_naive()))
+        .max()
+        .expect("today is always present");
+
+    let currencies: Vec<Currency> = currencies.into_iter().collect();
+    let rates = load_exchange_rate_table(connection_pool, &currencies, until).await?;
+    Ok(Conversion { rates, target })
+}
+
+#[tracing::instrument(
+    name = "Retrieving balance breakdown from database",
+    skip(connection_pool, conversion)
+)]
+async fn get_balance_breakdown_from_db(
+    connection_pool: &PgPool,
+    user_id: &str,
+    filters: &BalanceFilters,
+    group_by: BalanceGroupBy,
+    conversion: Option<&Conversion>,
+) -> Result<Vec<BalanceBreakdownEntry>, BalanceError> {
+    // Lines count in their category and in each of its ancestors.
+    let rows = sqlx::query!(
+        r#"
+        WITH RECURSIVE ancestors AS (
+            SELECT id as category_id, id as ancestor_id FROM expenses.categories
+            WHERE user_id = $1 OR user_id IS NULL
+            UNION
+            SELECT a.category_id, c.parent_id FROM ancestors a
+            JOIN expenses.categories c ON c.id = a.ancestor_id
+            WHERE c.parent_id IS NOT NULL
+        )
+        SELECT
+            CASE $7
+                WHEN 'wallet' THEN p.wallet_id::text
+                WHEN 'category' THEN a.ancestor_id::text
+                ELSE to_char(p.accounting_date, 'YYYY-MM')
+            END as key,
+            CASE $7
+                WHEN 'wallet' THEN w.name
+                WHEN 'category' THEN ac.name
+                ELSE to_char(p.accounting_date, 'YYYY-MM')
+            END as label,
+            CASE $7 WHEN 'category' THEN ac.parent_id::text END as parent_key,
+            p.currency,
+            DATE(p.accounting_date) as day,
+            COALESCE(SUM(CASE WHEN p.transfer_id IS NULL AND l.amount > 0 THEN l.amount ELSE 0 END), 0)::bigint as "income!",
+            COALESCE(SUM(CASE WHEN p.transfer_id IS NULL AND l.amount < 0 THEN l.amount ELSE 0 END), 0)::bigint as "expenses!",
+            COALESCE(SUM(CASE WHEN p.transfer_id IS NOT NULL THEN l.amount ELSE 0 END), 0)::bigint as "transfers!"
+        FROM expenses.payments p
+        JOIN expenses.payment_lines l ON l.payment_id = p.id
+        LEFT JOIN expenses.wallets w ON p.wallet_id = w.id AND w.user_id = p.user_id
+        LEFT JOIN expenses.categories c ON l.category_id = c.id
+        LEFT JOIN ancestors a ON $7 = 'category' AND a.category_id = l.category_id
+        LEFT JOIN expenses.categories ac ON ac.id = a.ancestor_id
+        WHERE p.user_id = $1
+          AND ($2::date IS NULL OR p.accounting_date >= $2::timestamp)
+          AND ($3::date IS NULL OR p.accounting_date < ($3 + 1)::timestamp)
+          AND ($4::text IS NULL OR w.name = $4)
+          AND ($5::uuid IS NULL OR l.category_id = $5)
+          AND ($6::text IS NULL OR LOWER(c.name) = LOWER($6))
+        GROUP BY 1, 2, 3, 4, 5
+        ORDER BY 2 NULLS LAST, 1
+        "#,
+        user_id,
+        filters.start_date as Option<NaiveDate>,
+        filters.end_date as Option<NaiveDate>,
+        filters.wallet,
+        filters.category_id,
+        filters.category_name,
+        group_by.as_str()
+    )
+    .fetch_all(connection_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+
+    // Rows are ordered by group, so each group's days are contiguous.
+    let mut groups: Vec<BreakdownGroup> = Vec::new();
+    for row in rows {
+        let daily = DailyTotals {
+            currency: row.currency,
+            day: row.day,
+            income: row.income,
+            expenses: row.expenses,
+            transfers: row.transfers,
+        };
+        match groups.last_mut() {
+            Some(group) if group.key == row.key && group.label == row.label => {
+                group.totals.add(&daily, conversion)?
+            }
+            _ => {
+                let mut totals = Totals::default();
+                totals.add(&daily, conversion)?;
+                groups.push(BreakdownGroup {
+                    key: row.key,
+                    label: row.label,
+                    parent_key: row.parent_key,
+                    totals,
+                });
+            }
+        }
+    }
+
+    let mut breakdown = Vec::new();
+    for group in groups {
+        for balance in group.totals.into_balances()? {
+            breakdown.push(BalanceBreakdownEntry {
+                key: group.key.clone(),
+                label: group.label.clone(),
+                parent_key: group.parent_key.clone(),
+                currency: balance.currency,
+                total_in_cents: balance.total_in_cents,
+                income_in_cents: balance.income_in_cents,
+                expenses_in_cents: balance.expenses_in_cents,
+                transfers_in_cents: balance.transfers_in_cents,
+            });
+        }
+    }
+    Ok(breakdown)
+}
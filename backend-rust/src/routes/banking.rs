@@ -0,0 +1,58 @@
+use crate::auth::AuthenticatedUser;
+use crate::banking::{sync_connection, BankProviderError, BankProviders, SyncError};
+use crate::crypto::KeyRing;
+use actix_web::{web, HttpResponse, Responder};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[tracing::instrument(
+    name = "Syncing a bank connection on demand",
+    skip(path, user, connection_pool, providers, key_ring),
+    fields(connection_id = %path)
+)]
+pub async fn sync_bank_connection(
+    path: web::Path<Uuid>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+    providers: web::Data<BankProviders>,
+    key_ring: web::Data<KeyRing>,
+) -> impl Responder {
+    match sync_connection(
+        path.into_inner(),
+        &user.sub,
+        connection_pool.get_ref(),
+        providers.get_ref(),
+        key_ring.get_ref(),
+    )
+    .await
+    {
+        Ok(summary) => HttpResponse::Ok().json(summary),
+        Err(SyncError::NotFound) => HttpResponse::NotFound().finish(),
+        Err(SyncError::InProgress) => {
+            HttpResponse::Conflict().body("A sync of this connection is already running")
+        }
+        Err(
+            e @ (SyncError::NotConnected | SyncError::Provider(BankProviderError::Unauthorized)),
+        ) => {
+            tracing::warn!("Bank connection needs a new consent: {:?}", e);
+            HttpResponse::Conflict().body("The bank connection must be renewed")
+        }
+        Err(SyncError::ProviderNotConfigured(provider)) => {
+            tracing::error!("Bank provider {} is not configured", provider);
+            HttpResponse::ServiceUnavailable()
+                .body(format!("Bank provider '{}' is not available", provider))
+        }
+        Err(SyncError::Provider(e)) => {
+            tracing::error!("Bank sync failed: {}", e);
+            HttpResponse::BadGateway().body(e.to_string())
+        }
+        Err(SyncError::Encryption(e)) => {
+            tracing::error!("Failed to handle the refresh token: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Err(SyncError::Database(e)) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
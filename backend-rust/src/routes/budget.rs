@@ -0,0 +1,599 @@
+use crate::auth::AuthenticatedUser;
+use crate::domain::{
+    reached_thresholds, AmountInCents, Budget, BudgetAlert, BudgetPeriod, BudgetStatus,
+    ConversionError, Currency, Money,
+};
+use crate::notifier::Notifier;
+use crate::routes::load_exchange_rate_table;
+use crate::routes::payment::{find_category_id, CategoryIdentifier};
+use crate::routes::wallet::get_wallet_by_name;
+use actix_web::web::Json;
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{Datelike, Months, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Deserialize, Debug)]
+pub struct BudgetDto {
+    // Budgets without a category or wallet cover all of them.
+    #[serde(rename = "categoryId")]
+    category_id: Option<CategoryIdentifier>,
+    wallet: Option<String>,
+    #[serde(rename = "limitInCents")]
+    limit_in_cents: AmountInCents,
+    // ISO 4217 code; defaults to the wallet's currency when omitted.
+    currency: Option<String>,
+    #[serde(default)]
+    period: BudgetPeriod,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BudgetResponseDto {
+    pub id: Uuid,
+    #[serde(rename = "categoryId", skip_serializing_if = "Option::is_none")]
+    pub category_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wallet: Option<String>,
+    pub period: BudgetPeriod,
+    #[serde(rename = "limitInCents")]
+    pub limit_in_cents: AmountInCents,
+    pub currency: String,
+}
+
+#[derive(Deserialize)]
+pub struct BudgetStatusQuery {
+    /// `YYYY-MM`, defaults to the current month.
+    month: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BudgetStatusResponse {
+    pub month: String,
+    pub budgets: Vec<BudgetStatusDto>,
+}
+
+/// Consumption of a budget during the month. Amounts spent in other
+/// currencies are converted into the budget currency at each payment date.
+#[derive(Serialize, Deserialize)]
+pub struct BudgetStatusDto {
+    #[serde(flatten)]
+    pub budget: BudgetResponseDto,
+    #[serde(rename = "spentInCents")]
+    pub spent_in_cents: AmountInCents,
+    #[serde(rename = "remainingInCents")]
+    pub remaining_in_cents: AmountInCents,
+    #[serde(rename = "percentUsed")]
+    pub percent_used: f64,
+    #[serde(rename = "projectedInCents")]
+    pub projected_in_cents: AmountInCents,
+}
+
+#[derive(Debug)]
+pub(crate) enum BudgetError {
+    Validation(String),
+    Database(sqlx::Error),
+    Conversion(ConversionError),
+}
+
+impl From<sqlx::Error> for BudgetError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+impl From<ConversionError> for BudgetError {
+    fn from(e: ConversionError) -> Self {
+        Self::Conversion(e)
+    }
+}
+
+impl BudgetError {
+    fn into_response(self) -> HttpResponse {
+        match self {
+            Self::Validation(e) => HttpResponse::BadRequest().body(e),
+            Self::Conversion(e @ ConversionError::MissingRate { .. }) => {
+                tracing::warn!("{}", e);
+                HttpResponse::UnprocessableEntity().body(e.to_string())
+            }
+            Self::Conversion(e) => {
+                tracing::error!("Failed to convert budget spending: {}", e);
+                HttpResponse::InternalServerError().finish()
+            }
+            Self::Database(e) => {
+                tracing::error!("Failed to execute query: {:?}", e);
+                // Unique violation: a budget with the same scope already exists
+                if e.as_database_error().and_then(|e| e.code()).as_deref() == Some("23505") {
+                    return HttpResponse::Conflict()
+                        .body("A budget already exists for this category and wallet");
+                }
+                HttpResponse::InternalServerError().finish()
+            }
+        }
+    }
+}
+
+/// Validates the budget and resolves its category and wallet.
+async fn parse_budget(
+    dto: BudgetDto,
+    user_id: String,
+    connection_pool: &PgPool,
+) -> Result<Budget, BudgetError> {
+    let (wallet_id, default_currency) = match &dto.wallet {
+        Some(name) => match get_wallet_by_name(name, connection_pool, &user_id).await? {
+            Some(wallet) => (wallet.id, wallet.default_currency),
+            None => {
+                return Err(BudgetError::Validation(format!(
+                    "Wallet '{}' not found",
+                    name
+                )))
+            }
+        },
+        None => (None, Currency::default()),
+    };
+    let category_id = match &dto.category_id {
+        Some(identifier) => Some(
+            find_category_id(identifier, &user_id, connection_pool)
+                .await?
+                .ok_or_else(|| BudgetError::Validation("categoryId not found".to_string()))?,
+        ),
+        None => None,
+    };
+    let currency = match dto.currency {
+        Some(code) => Currency::parse(code).map_err(BudgetError::Validation)?,
+        None => default_currency,
+    };
+    Budget::parse(
+        category_id,
+        wallet_id,
+        dto.period,
+        Money::new(dto.limit_in_cents, currency),
+        user_id,
+    )
+    .map_err(BudgetError::Validation)
+}
+
+#[tracing::instrument(name = "Creating a budget", skip(payload, user, connection_pool))]
+pub async fn create_budget(
+    payload: Json<BudgetDto>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    let user_id = user.sub;
+    let result = async {
+        let budget = parse_budget(payload.into_inner(), user_id.clone(), &connection_pool).await?;
+        Ok::<_, BudgetError>(insert_budget(&budget, connection_pool.get_ref()).await?)
+    }
+    .await;
+    match result {
+        Ok(id) => respond_with_budget(id, &user_id, &connection_pool).await,
+        Err(e) => e.into_response(),
+    }
+}
+
+#[tracing::instrument(name = "Retrieving budgets", skip(user, connection_pool))]
+pub async fn get_budgets(
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    match get_budgets_from_db(None, &user.sub, &connection_pool).await {
+        Ok(budgets) => HttpResponse::Ok().json(budgets),
+        Err(e) => BudgetError::from(e).into_response(),
+    }
+}
+
+#[tracing::instrument(name = "Retrieving a budget", skip(user, connection_pool))]
+pub async fn get_budget(
+    path: web::Path<Uuid>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    respond_with_budget(path.into_inner(), &user.sub, &connection_pool).await
+}
+
+#[tracing::instrument(name = "Updating a budget", skip(payload, user, connection_pool))]
+pub async fn update_budget(
+    path: web::Path<Uuid>,
+    payload: Json<BudgetDto>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let user_id = user.sub;
+    let result = async {
+        let budget = parse_budget(payload.into_inner(), user_id.clone(), &connection_pool).await?;
+        Ok::<_, BudgetError>(update_budget_query(id, &budget, connection_pool.get_ref()).await?)
+    }
+    .await;
+    match result {
+        Ok(true) => respond_with_budget(id, &user_id, &connection_pool).await,
+        Ok(false) => HttpResponse::NotFound().finish(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[tracing::instrument(name = "Deleting a budget", skip(user, connection_pool))]
+pub async fn delete_budget(
+    path: web::Path<Uuid>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    match sqlx::query!(
+        "DELETE FROM expenses.budgets WHERE id = $1 AND user_id = $2",
+        path.into_inner(),
+        user.sub
+    )
+    .execute(connection_pool.get_ref())
+    .await
+    {
+        Ok(result) if result.rows_affected() == 0 => HttpResponse::NotFound().finish(),
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => BudgetError::from(e).into_response(),
+    }
+}
+
+#[tracing::instrument(name = "Retrieving budget status", skip(query, user, connection_pool))]
+pub async fn get_budget_status(
+    query: web::Query<BudgetStatusQuery>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    let today = Utc::now().date_naive();
+    let month = match &query.month {
+        Some(month) => match parse_month(month) {
+            Ok(month) => month,
+            Err(e) => return HttpResponse::BadRequest().body(e),
+        },
+        None => today.with_day(1).expect("every month has a first day"),
+    };
+
+    match get_budget_statuses(&user.sub, month, today, connection_pool.get_ref()).await {
+        Ok(budgets) => HttpResponse::Ok().json(BudgetStatusResponse {
+            month: month.format("%Y-%m").to_string(),
+            budgets,
+        }),
+        Err(e) => e.into_response(),
+    }
+}
+
+/// First day of a `YYYY-MM` month.
+fn parse_month(month: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d")
+        .map_err(|_| format!("{} is not a valid month, expected YYYY-MM.", month))
+}
+
+/// Status of every budget of the user for the month starting on `month`.
+///
+/// Spending follows the balance conventions: negative amounts that are not
+/// transfers, split payments counting per split category.
+#[tracing::instrument(name = "Computing budget statuses", skip(connection_pool))]
+pub(crate) async fn get_budget_statuses(
+    user_id: &str,
+    month: NaiveDate,
+    today: NaiveDate,
+    connection_pool: &PgPool,
+) -> Result<Vec<BudgetStatusDto>, BudgetError> {
+    let budgets = get_budgets_from_db(None, user_id, connection_pool).await?;
+    let month_end = month
+        .checked_add_months(Months::new(1))
+        .ok_or_else(|| BudgetError::Validation("Month out of range".to_string()))?;
+    // Category budgets also cover the subcategories of their category.
+    let rows = sqlx::query!(
+        r#"
+        WITH RECURSIVE budget_categories AS (
+            SELECT id as budget_id, category_id FROM expenses.budgets
+            WHERE user_id = $1 AND category_id IS NOT NULL
+            UNION
+            SELECT bc.budget_id, c.id FROM budget_categories bc
+            JOIN expenses.categories c ON c.parent_id = bc.category_id
+            WHERE c.user_id = $1 OR c.user_id IS NULL
+        )
+        SELECT
+            b.id as budget_id,
+            p.currency,
+            DATE(p.accounting_date) as "day!",
+            COALESCE(-SUM(l.amount), 0)::bigint as "spent!"
+        FROM expenses.budgets b
+        JOIN expenses.payments p
+          ON p.user_id = b.user_id
+         AND p.transfer_id IS NULL
+         AND p.accounting_date >= $2 AND p.accounting_date < $3
+         AND (b.wallet_id IS NULL OR p.wallet_id = b.wallet_id)
+        JOIN expenses.payment_lines l
+          ON l.payment_id = p.id
+         AND l.amount < 0
+         AND (b.category_id IS NULL OR EXISTS (
+             SELECT 1 FROM budget_categories bc
+             WHERE bc.budget_id = b.id AND bc.category_id = l.category_id))
+        WHERE b.user_id = $1
+        GROUP BY 1, 2, 3
+        "#,
+        user_id,
+        month.and_hms_opt(0, 0, 0),
+        month_end.and_hms_opt(0, 0, 0)
+    )
+    .fetch_all(connection_pool)
+    .await?;
+
+    // Rates are only needed when payments and budgets use different currencies.
+    let mut currencies: BTreeSet<String> = rows.iter().map(|row| row.currency.clone()).collect();
+    currencies.extend(budgets.iter().map(|budget| budget.currency.clone()));
+    let currencies = currencies
+        .into_iter()
+        .map(Currency::parse)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| sqlx::Error::Decode(e.into()))?;
+    let rates = load_exchange_rate_table(connection_pool, &currencies, month_end).await?;
+
+    let mut spent: HashMap<Uuid, Vec<(Money, NaiveDate)>> = HashMap::new();
+    for row in rows {
+        let currency = Currency::parse(row.currency).map_err(|e| sqlx::Error::Decode(e.into()))?;
+        spent
+            .entry(row.budget_id)
+            .or_default()
+            .push((Money::new(AmountInCents::new(row.spent), currency), row.day));
+    }
+
+    let mut statuses = Vec::with_capacity(budgets.len());
+    for budget in budgets {
+        let currency =
+            Currency::parse(budget.currency.clone()).map_err(|e| sqlx::Error::Decode(e.into()))?;
+        let mut total = Money::new(AmountInCents::ZERO, currency.clone());
+        for (amount, day) in spent.remove(&budget.id).unwrap_or_default() {
+            let converted = rates.convert(&amount, &currency, day)?;
+            total = total
+                .checked_add(&converted)
+                .map_err(ConversionError::Overflow)?;
+        }
+        let status = BudgetStatus::compute(budget.limit_in_cents, total.amount(), month, today)
+            .map_err(ConversionError::Overflow)?;
+        statuses.push(BudgetStatusDto {
+            budget,
+            spent_in_cents: status.spent,
+            remaining_in_cents: status.remaining,
+            percent_used: status.percent_used,
+            projected_in_cents: status.projected,
+        });
+    }
+    Ok(statuses)
+}
+
+/// Evaluates the budgets of the user in the month of `date`, after one of its
+/// payments was created or updated, and notifies every threshold reached for
+/// the first time in that month.
+///
+/// Runs in the background: alerts never fail nor slow down the payment request.
+pub(crate) fn spawn_budget_alerts(
+    user_id: String,
+    date: NaiveDate,
+    connection_pool: PgPool,
+    notifier: Arc<dyn Notifier>,
+) {
+    let span = tracing::info_span!("Evaluating budget alerts", %user_id, %date);
+    tokio::spawn(tracing::Instrument::instrument(
+        async move {
+            match record_budget_alerts(&user_id, date, &connection_pool).await {
+                Ok(alerts) => {
+                    for (event_id, alert) in alerts {
+                        notify_budget_alert(event_id, &alert, &connection_pool, notifier.as_ref())
+                            .await
+                    }
+                }
+                Err(BudgetError::Conversion(e)) => {
+                    tracing::warn!("Budget alerts not evaluated: {}", e)
+                }
+                Err(e) => tracing::error!("Failed to evaluate budget alerts: {:?}", e),
+            }
+        },
+        span,
+    ));
+}
+
+/// [`spawn_budget_alerts`] after a batch of payments (statement imports),
+/// evaluating each month of `dates` once.
+pub(crate) fn spawn_budget_alerts_for_dates(
+    user_id: &str,
+    dates: impl IntoIterator<Item = NaiveDate>,
+    connection_pool: &PgPool,
+    notifier: Arc<dyn Notifier>,
+) {
+    let months: BTreeSet<NaiveDate> = dates
+        .into_iter()
+        .filter_map(|date| date.with_day(1))
+        .collect();
+    for month in months {
+        spawn_budget_alerts(
+            user_id.to_string(),
+            month,
+            connection_pool.clone(),
+            notifier.clone(),
+        );
+    }
+}
+
+/// Records the thresholds reached in the month of `date` and returns those
+/// that were not recorded yet, with their event id.
+async fn record_budget_alerts(
+    user_id: &str,
+    date: NaiveDate,
+    connection_pool: &PgPool,
+) -> Result<Vec<(Uuid, BudgetAlert)>, BudgetError> {
+    let month = date.with_day(1).expect("every month has a first day");
+    let statuses =
+        get_budget_statuses(user_id, month, Utc::now().date_naive(), connection_pool).await?;
+
+    let mut alerts = Vec::new();
+    for status in statuses {
+        let thresholds = reached_thresholds(&BudgetStatus {
+            spent: status.spent_in_cents,
+            remaining: status.remaining_in_cents,
+            percent_used: status.percent_used,
+            projected: status.projected_in_cents,
+        });
+        for threshold in thresholds {
+            let event_id = sqlx::query_scalar!(
+                r#"
+                INSERT INTO expenses.budget_alert_events
+                    (budget_id, user_id, period_start, threshold, spent, limit_amount, currency, percent_used)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                ON CONFLICT (budget_id, period_start, threshold) DO NOTHING
+                RETURNING id
+                "#,
+                status.budget.id,
+                user_id,
+                month,
+                i16::from(threshold),
+                status.spent_in_cents.cents(),
+                status.budget.limit_in_cents.cents(),
+                status.budget.currency,
+                status.percent_used
+            )
+            .fetch_optional(connection_pool)
+            .await?;
+            if let Some(event_id) = event_id {
+                alerts.push((
+                    event_id,
+                    BudgetAlert {
+                        budget_id: status.budget.id,
+                        user_id: user_id.to_string(),
+                        threshold,
+                        month: month.format("%Y-%m").to_string(),
+                        spent_in_cents: status.spent_in_cents,
+                        limit_in_cents: status.budget.limit_in_cents,
+                        currency: status.budget.currency.clone(),
+                        percent_used: status.percent_used,
+                        category: status.budget.category.clone(),
+                        wallet: status.budget.wallet.clone(),
+                    },
+                ));
+            }
+        }
+    }
+    Ok(alerts)
+}
+
+async fn notify_budget_alert(
+    event_id: Uuid,
+    alert: &BudgetAlert,
+    connection_pool: &PgPool,
+    notifier: &dyn Notifier,
+) {
+    if let Err(e) = notifier.notify(alert).await {
+        tracing::error!("Failed to notify budget alert {}: {}", event_id, e);
+        return;
+    }
+    if let Err(e) = sqlx::query!(
+        "UPDATE expenses.budget_alert_events SET notified_at = now() WHERE id = $1",
+        event_id
+    )
+    .execute(connection_pool)
+    .await
+    {
+        tracing::error!(
+            "Failed to mark budget alert {} as notified: {:?}",
+            event_id,
+            e
+        );
+    }
+}
+
+async fn respond_with_budget(id: Uuid, user_id: &str, connection_pool: &PgPool) -> HttpResponse {
+    match get_budgets_from_db(Some(id), user_id, connection_pool).await {
+        Ok(mut budgets) => match budgets.pop() {
+            Some(budget) => HttpResponse::Ok().json(budget),
+            None => HttpResponse::NotFound().finish(),
+        },
+        Err(e) => BudgetError::from(e).into_response(),
+    }
+}
+
+#[tracing::instrument(
+    name = "Inserting a budget in the database",
+    skip(budget, connection_pool)
+)]
+async fn insert_budget(budget: &Budget, connection_pool: &PgPool) -> Result<Uuid, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        INSERT INTO expenses.budgets (user_id, period, category_id, wallet_id, limit_amount, currency)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id
+        "#,
+        budget.user_id,
+        budget.period.as_str(),
+        budget.category_id,
+        budget.wallet_id,
+        budget.limit.amount().cents(),
+        budget.limit.currency().as_ref()
+    )
+    .fetch_one(connection_pool)
+    .await
+}
+
+#[tracing::instrument(
+    name = "Updating a budget in the database",
+    skip(budget, connection_pool)
+)]
+async fn update_budget_query(
+    id: Uuid,
+    budget: &Budget,
+    connection_pool: &PgPool,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE expenses.budgets
+        SET period = $3, category_id = $4, wallet_id = $5, limit_amount = $6, currency = $7
+        WHERE id = $1 AND user_id = $2
+        "#,
+        id,
+        budget.user_id,
+        budget.period.as_str(),
+        budget.category_id,
+        budget.wallet_id,
+        budget.limit.amount().cents(),
+        budget.limit.currency().as_ref()
+    )
+    .execute(connection_pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[tracing::instrument(name = "Retrieving budgets from the database", skip(connection_pool))]
+async fn get_budgets_from_db(
+    id: Option<Uuid>,
+    user_id: &str,
+    connection_pool: &PgPool,
+) -> Result<Vec<BudgetResponseDto>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT b.id, b.category_id, c.name as "category?", w.name as "wallet?", b.period,
+               b.limit_amount, b.currency
+        FROM expenses.budgets b
+        LEFT JOIN expenses.categories c ON c.id = b.category_id
+        LEFT JOIN expenses.wallets w ON w.id = b.wallet_id
+        WHERE b.user_id = $1 AND ($2::uuid IS NULL OR b.id = $2)
+        ORDER BY c.name NULLS FIRST, w.name NULLS FIRST, b.created_at
+        "#,
+        user_id,
+        id
+    )
+    .fetch_all(connection_pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| BudgetResponseDto {
+            id: row.id,
+            category_id: row.category_id,
+            category: row.category,
+            wallet: row.wallet,
+            // Guaranteed by the budgets_period check constraint.
+            period: BudgetPeriod::parse(&row.period).unwrap_or_default(),
+            limit_in_cents: AmountInCents::new(row.limit_amount),
+            currency: row.currency,
+        })
+        .collect())
+}
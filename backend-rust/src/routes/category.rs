@@ -0,0 +1,717 @@
+use crate::auth::AuthenticatedUser;
+use crate::domain::{
+    CategoryColour, CategoryKind, CategoryTree, PaymentCategory, PaymentCategoryIcon,
+};
+use crate::notifier::Notifier;
+use crate::routes::budget::spawn_budget_alerts_for_dates;
+use actix_web::web::Json;
+use actix_web::{web, HttpResponse, Responder};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, PgPool, Postgres, Transaction};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CategoryView {
+    /// Every category, ordered by path.
+    #[default]
+    Flat,
+    /// The root categories with their subcategories nested.
+    Tree,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CategoryQuery {
+    #[serde(rename = "type")]
+    category_type: Option<String>,
+    #[serde(default)]
+    view: CategoryView,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryDto {
+    id: Uuid,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icon: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    colour: Option<String>,
+    kind: CategoryKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent_id: Option<Uuid>,
+    /// Names from the root category down, e.g. `Food > Restaurants`.
+    path: String,
+    /// Global defaults are shared by every user and read-only.
+    global: bool,
+}
+
+/// A category with its subcategories.
+#[derive(Serialize, Debug)]
+pub struct CategoryTreeDto {
+    #[serde(flatten)]
+    category: CategoryDto,
+    children: Vec<CategoryTreeDto>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryRequestDto {
+    name: String,
+    icon: Option<String>,
+    /// `#rrggbb` hex code.
+    colour: Option<String>,
+    #[serde(default)]
+    kind: CategoryKind,
+    /// Category of the user or global default; a root category without it.
+    parent_id: Option<Uuid>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryDeleteQuery {
+    /// Category receiving the payments, budgets, recurring payments and rules
+    /// of the deleted one; required when it is still in use.
+    reassign_to: Option<Uuid>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryMergeDto {
+    /// Category absorbed by the category of the path, then deleted.
+    category_id: Uuid,
+}
+
+struct NewCategory {
+    name: PaymentCategory,
+    icon: Option<PaymentCategoryIcon>,
+    colour: Option<CategoryColour>,
+    kind: CategoryKind,
+    parent_id: Option<Uuid>,
+}
+
+impl TryFrom<CategoryRequestDto> for NewCategory {
+    type Error = String;
+
+    fn try_from(dto: CategoryRequestDto) -> Result<Self, Self::Error> {
+        Ok(Self {
+            name: PaymentCategory::parse(dto.name.trim().to_string())?,
+            icon: dto.icon.map(PaymentCategoryIcon::parse).transpose()?,
+            colour: dto.colour.map(CategoryColour::parse).transpose()?,
+            kind: dto.kind,
+            parent_id: dto.parent_id,
+        })
+    }
+}
+
+enum CategoryError {
+    Validation(String),
+    NotFound,
+    /// Global defaults cannot be changed by users.
+    ReadOnly,
+    Conflict(String),
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for CategoryError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+impl CategoryError {
+    fn into_response(self) -> HttpResponse {
+        match self {
+            Self::Validation(e) => HttpResponse::BadRequest().body(e),
+            Self::NotFound => HttpResponse::NotFound().finish(),
+            Self::ReadOnly => HttpResponse::Forbidden().body("Global categories are read-only"),
+            Self::Conflict(e) => HttpResponse::Conflict().body(e),
+            Self::Database(e) => {
+                tracing::error!("Failed to execute query: {:?}", e);
+                HttpResponse::InternalServerError().finish()
+            }
+        }
+    }
+
+    /// Maps unique violations, e.g. of the name per user, to a conflict.
+    fn on_unique_violation(e: sqlx::Error, message: impl FnOnce() -> String) -> Self {
+        if e.as_database_error().and_then(|e| e.code()).as_deref() == Some("23505") {
+            Self::Conflict(message())
+        } else {
+            Self::Database(e)
+        }
+    }
+}
+
+#[tracing::instrument(name = "Retrieve all categories", skip(user, connection_pool))]
+pub async fn get_categories(
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+    query: web::Query<CategoryQuery>,
+) -> impl Responder {
+    // Unknown types return every category
+    let kind = query
+        .category_type
+        .as_deref()
+        .and_then(|kind| CategoryKind::parse(kind).ok());
+    match get_categories_from_db(connection_pool.get_ref(), &user.sub).await {
+        Ok(categories) => {
+            let categories: Vec<CategoryDto> = categories
+                .into_iter()
+                .filter(|category| kind.is_none_or(|kind| category.kind == kind))
+                .collect();
+            match query.view {
+                CategoryView::Flat => HttpResponse::Ok().json(categories),
+                CategoryView::Tree => HttpResponse::Ok().json(into_tree(categories)),
+            }
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Categories of the user and global defaults, ordered by path.
+#[tracing::instrument(name = "Retrieving all categories from database", skip(executor))]
+async fn get_categories_from_db(
+    executor: impl PgExecutor<'_>,
+    user_id: &str,
+) -> Result<Vec<CategoryDto>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, name, icon, colour, kind, parent_id, user_id IS NULL as "global!"
+        FROM expenses.categories
+        WHERE user_id = $1 OR user_id IS NULL
+        "#,
+        user_id
+    )
+    .fetch_all(executor)
+    .await?;
+    let tree = CategoryTree::new(
+        rows.iter()
+            .map(|row| (row.id, row.parent_id, row.name.clone())),
+    );
+    let mut categories: Vec<CategoryDto> = rows
+        .into_iter()
+        .map(|row| CategoryDto {
+            path: tree.path(row.id),
+            id: row.id,
+            name: row.name,
+            icon: row.icon,
+            colour: row.colour,
+            kind: CategoryKind::parse(&row.kind).unwrap_or_default(),
+            parent_id: row.parent_id,
+            global: row.global,
+        })
+        .collect();
+    categories.sort_by_cached_key(|category| (category.path.to_lowercase(), category.id));
+    Ok(categories)
+}
+
+/// Category of the user or global default.
+async fn get_category(
+    executor: impl PgExecutor<'_>,
+    user_id: &str,
+    category_id: Uuid,
+) -> Result<CategoryDto, CategoryError> {
+    get_categories_from_db(executor, user_id)
+        .await?
+        .into_iter()
+        .find(|category| category.id == category_id)
+        .ok_or(CategoryError::NotFound)
+}
+
+/// Nests the categories under their parents, keeping their order. Categories
+/// whose parent is not in the list become roots.
+fn into_tree(categories: Vec<CategoryDto>) -> Vec<CategoryTreeDto> {
+    fn nest(
+        parent_id: Option<Uuid>,
+        children: &mut HashMap<Option<Uuid>, Vec<CategoryDto>>,
+    ) -> Vec<CategoryTreeDto> {
+        children
+            .remove(&parent_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|category| CategoryTreeDto {
+                children: nest(Some(category.id), children),
+                category,
+            })
+            .collect()
+    }
+
+    let ids: HashSet<Uuid> = categories.iter().map(|category| category.id).collect();
+    let mut children: HashMap<Option<Uuid>, Vec<CategoryDto>> = HashMap::new();
+    for category in categories {
+        let parent_id = category.parent_id.filter(|id| ids.contains(id));
+        children.entry(parent_id).or_default().push(category);
+    }
+    nest(None, &mut children)
+}
+
+/// The categories of the user and global defaults, linked to their parents.
+async fn load_category_tree(
+    executor: impl PgExecutor<'_>,
+    user_id: &str,
+) -> Result<CategoryTree, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, parent_id, name FROM expenses.categories
+        WHERE user_id = $1 OR user_id IS NULL
+        "#,
+        user_id
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(CategoryTree::new(
+        rows.into_iter()
+            .map(|row| (row.id, row.parent_id, row.name)),
+    ))
+}
+
+/// Checks that the parent is a category of the user or a global default, and
+/// that an existing category would not end up under itself. The categories
+/// of the user are locked first so that concurrent moves cannot form a cycle.
+async fn check_parent(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: &str,
+    category_id: Option<Uuid>,
+    parent_id: Uuid,
+) -> Result<(), CategoryError> {
+    sqlx::query!(
+        "SELECT id FROM expenses.categories WHERE user_id = $1 FOR UPDATE",
+        user_id
+    )
+    .fetch_all(&mut **tx)
+    .await?;
+    let tree = load_category_tree(&mut **tx, user_id).await?;
+    if tree.ancestors(parent_id).is_empty() {
+        return Err(CategoryError::Validation(format!(
+            "parentId {} not found",
+            parent_id
+        )));
+    }
+    match category_id {
+        Some(category_id) => tree
+            .check_parent(category_id, parent_id)
+            .map_err(CategoryError::Validation),
+        None => Ok(()),
+    }
+}
+
+/// Locks a category of the user; global defaults are refused.
+async fn lock_own_category(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: &str,
+    category_id: Uuid,
+) -> Result<(), CategoryError> {
+    let owner = sqlx::query_scalar!(
+        r#"
+        SELECT user_id FROM expenses.categories
+        WHERE id = $1 AND (user_id = $2 OR user_id IS NULL)
+        FOR UPDATE
+        "#,
+        category_id,
+        user_id
+    )
+    .fetch_optional(&mut **tx)
+    .await?
+    .ok_or(CategoryError::NotFound)?;
+    owner.map(|_| ()).ok_or(CategoryError::ReadOnly)
+}
+
+#[tracing::instrument(
+    name = "Creating a category",
+    skip(payload, user, connection_pool),
+    fields(name = %payload.name)
+)]
+pub async fn create_category(
+    payload: Json<CategoryRequestDto>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    match insert_category(payload.into_inner(), &user.sub, connection_pool.get_ref()).await {
+        Ok(category) => HttpResponse::Created().json(category),
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn insert_category(
+    dto: CategoryRequestDto,
+    user_id: &str,
+    connection_pool: &PgPool,
+) -> Result<CategoryDto, CategoryError> {
+    let category = NewCategory::try_from(dto).map_err(CategoryError::Validation)?;
+
+    let mut tx = connection_pool.begin().await?;
+    if let Some(parent_id) = category.parent_id {
+        check_parent(&mut tx, user_id, None, parent_id).await?;
+    }
+    let category_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO expenses.categories (user_id, name, icon, colour, kind, parent_id)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id
+        "#,
+        user_id,
+        category.name.as_ref(),
+        category.icon.as_ref().map(|icon| icon.as_ref()),
+        category.colour.as_ref().map(|colour| colour.as_ref()),
+        category.kind.as_str(),
+        category.parent_id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        CategoryError::on_unique_violation(e, || {
+            format!(
+                "A category named '{}' already exists",
+                category.name.as_ref()
+            )
+        })
+    })?;
+    let category = get_category(&mut *tx, user_id, category_id).await?;
+    tx.commit().await?;
+    Ok(category)
+}
+
+#[tracing::instrument(
+    name = "Updating a category",
+    skip(payload, user, connection_pool),
+    fields(name = %payload.name)
+)]
+pub async fn update_category(
+    path: web::Path<Uuid>,
+    payload: Json<CategoryRequestDto>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    match update_category_in_db(
+        path.into_inner(),
+        payload.into_inner(),
+        &user.sub,
+        connection_pool.get_ref(),
+    )
+    .await
+    {
+        Ok(category) => HttpResponse::Ok().json(category),
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn update_category_in_db(
+    category_id: Uuid,
+    dto: CategoryRequestDto,
+    user_id: &str,
+    connection_pool: &PgPool,
+) -> Result<CategoryDto, CategoryError> {
+    let category = NewCategory::try_from(dto).map_err(CategoryError::Validation)?;
+
+    let mut tx = connection_pool.begin().await?;
+    lock_own_category(&mut tx, user_id, category_id).await?;
+    if let Some(parent_id) = category.parent_id {
+        check_parent(&mut tx, user_id, Some(category_id), parent_id).await?;
+    }
+    sqlx::query!(
+        r#"
+        UPDATE expenses.categories
+        SET name = $2, icon = $3, colour = $4, kind = $5, parent_id = $6
+        WHERE id = $1
+        "#,
+        category_id,
+        category.name.as_ref(),
+        category.icon.as_ref().map(|icon| icon.as_ref()),
+        category.colour.as_ref().map(|colour| colour.as_ref()),
+        category.kind.as_str(),
+        category.parent_id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| {
+        CategoryError::on_unique_violation(e, || {
+            format!(
+                "A category named '{}' already exists",
+                category.name.as_ref()
+            )
+        })
+    })?;
+    let category = get_category(&mut *tx, user_id, category_id).await?;
+    tx.commit().await?;
+    Ok(category)
+}
+
+#[tracing::instrument(
+    name = "Deleting a category",
+    skip(path, user, connection_pool, notifier),
+    fields(category_id = %path)
+)]
+pub async fn delete_category(
+    path: web::Path<Uuid>,
+    query: web::Query<CategoryDeleteQuery>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+    notifier: web::Data<dyn Notifier>,
+) -> impl Responder {
+    match delete_category_from_db(
+        path.into_inner(),
+        query.reassign_to,
+        &user.sub,
+        connection_pool.get_ref(),
+    )
+    .await
+    {
+        Ok(dates) => {
+            spawn_budget_alerts_for_dates(
+                &user.sub,
+                dates,
+                connection_pool.get_ref(),
+                notifier.into_inner(),
+            );
+            HttpResponse::NoContent().finish()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Also returns the accounting dates of the reassigned payments.
+async fn delete_category_from_db(
+    category_id: Uuid,
+    reassign_to: Option<Uuid>,
+    user_id: &str,
+    connection_pool: &PgPool,
+) -> Result<Vec<NaiveDate>, CategoryError> {
+    let mut tx = connection_pool.begin().await?;
+    lock_own_category(&mut tx, user_id, category_id).await?;
+    let dates = match reassign_to {
+        Some(target_id) if target_id == category_id => {
+            return Err(CategoryError::Validation(
+                "A category cannot be reassigned to itself".to_string(),
+            ));
+        }
+        Some(target_id) => {
+            get_category(&mut *tx, user_id, target_id)
+                .await
+                .map_err(|e| match e {
+                    CategoryError::NotFound => {
+                        CategoryError::Validation(format!("reassignTo {} not found", target_id))
+                    }
+                    e => e,
+                })?;
+            move_category_references(&mut tx, category_id, target_id, user_id).await?
+        }
+        None if category_is_used(&mut *tx, category_id, user_id).await? => {
+            return Err(CategoryError::Conflict(
+                "The category is still in use; pass reassignTo to move its payments, budgets, recurring payments and rules".to_string(),
+            ));
+        }
+        None => vec![],
+    };
+    // Subcategories move up to the parent of the deleted category
+    sqlx::query!(
+        r#"
+        UPDATE expenses.categories
+        SET parent_id = (SELECT parent_id FROM expenses.categories WHERE id = $1)
+        WHERE parent_id = $1
+        "#,
+        category_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!("DELETE FROM expenses.categories WHERE id = $1", category_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(dates)
+}
+
+#[tracing::instrument(
+    name = "Merging categories",
+    skip(path, payload, user, connection_pool, notifier),
+    fields(category_id = %path, merged_category_id = %payload.category_id)
+)]
+pub async fn merge_categories(
+    path: web::Path<Uuid>,
+    payload: Json<CategoryMergeDto>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+    notifier: web::Data<dyn Notifier>,
+) -> impl Responder {
+    match merge_category_into(
+        payload.category_id,
+        path.into_inner(),
+        &user.sub,
+        connection_pool.get_ref(),
+    )
+    .await
+    {
+        Ok((category, dates)) => {
+            spawn_budget_alerts_for_dates(
+                &user.sub,
+                dates,
+                connection_pool.get_ref(),
+                notifier.into_inner(),
+            );
+            HttpResponse::Ok().json(category)
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Moves everything in `source_id`, subcategories included, to `target_id`,
+/// then deletes it. The target may be a global default, the source must
+/// belong to the user.
+async fn merge_category_into(
+    source_id: Uuid,
+    target_id: Uuid,
+    user_id: &str,
+    connection_pool: &PgPool,
+) -> Result<(CategoryDto, Vec<NaiveDate>), CategoryError> {
+    if source_id == target_id {
+        return Err(CategoryError::Validation(
+            "A category cannot be merged into itself".to_string(),
+        ));
+    }
+
+    let mut tx = connection_pool.begin().await?;
+    lock_own_category(&mut tx, user_id, source_id).await?;
+    let target = get_category(&mut *tx, user_id, target_id).await?;
+    if load_category_tree(&mut *tx, user_id)
+        .await?
+        .is_within(target_id, source_id)
+    {
+        return Err(CategoryError::Validation(
+            "A category cannot be merged into one of its subcategories".to_string(),
+        ));
+    }
+    let dates = move_category_references(&mut tx, source_id, target_id, user_id).await?;
+    sqlx::query!(
+        "UPDATE expenses.categories SET parent_id = $2 WHERE parent_id = $1",
+        source_id,
+        target_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!("DELETE FROM expenses.categories WHERE id = $1", source_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok((target, dates))
+}
+
+async fn category_is_used(
+    executor: impl PgExecutor<'_>,
+    category_id: Uuid,
+    user_id: &str,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (SELECT 1 FROM expenses.payments WHERE category_id = $1 AND user_id = $2)
+            OR EXISTS (SELECT 1 FROM expenses.payment_splits WHERE category_id = $1 AND user_id = $2)
+            OR EXISTS (SELECT 1 FROM expenses.recurring_payments WHERE category_id = $1 AND user_id = $2)
+            OR EXISTS (SELECT 1 FROM expenses.budgets WHERE category_id = $1 AND user_id = $2)
+            OR EXISTS (
+                SELECT 1 FROM expenses.rules
+                WHERE user_id = $2
+                  AND actions @> jsonb_build_array(
+                      jsonb_build_object('type', 'setCategory', 'categoryId', $1::text))
+            ) as "used!"
+        "#,
+        category_id,
+        user_id
+    )
+    .fetch_one(executor)
+    .await
+}
+
+/// Points the payments, splits, recurring payments, budgets and rules of the
+/// user from one category to another. Returns the accounting dates of the
+/// moved payments and splits.
+async fn move_category_references(
+    tx: &mut Transaction<'_, Postgres>,
+    source_id: Uuid,
+    target_id: Uuid,
+    user_id: &str,
+) -> Result<Vec<NaiveDate>, CategoryError> {
+    let mut dates = sqlx::query_scalar!(
+        r#"
+        UPDATE expenses.payments SET category_id = $2
+        WHERE category_id = $1 AND user_id = $3
+        RETURNING accounting_date
+        "#,
+        source_id,
+        target_id,
+        user_id
+    )
+    .fetch_all(&mut **tx)
+    .await?;
+    dates.extend(
+        sqlx::query_scalar!(
+            r#"
+            UPDATE expenses.payment_splits s SET category_id = $2
+            FROM expenses.payments p
+            WHERE p.id = s.payment_id AND s.category_id = $1 AND s.user_id = $3
+            RETURNING p.accounting_date
+            "#,
+            source_id,
+            target_id,
+            user_id
+        )
+        .fetch_all(&mut **tx)
+        .await?,
+    );
+    sqlx::query!(
+        r#"
+        UPDATE expenses.recurring_payments SET category_id = $2
+        WHERE category_id = $1 AND user_id = $3
+        "#,
+        source_id,
+        target_id,
+        user_id
+    )
+    .execute(&mut **tx)
+    .await?;
+    sqlx::query!(
+        r#"
+        UPDATE expenses.budgets SET category_id = $2
+        WHERE category_id = $1 AND user_id = $3
+        "#,
+        source_id,
+        target_id,
+        user_id
+    )
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| {
+        CategoryError::on_unique_violation(e, || {
+            "A budget already exists for the target category; delete one of the budgets first"
+                .to_string()
+        })
+    })?;
+    sqlx::query!(
+        r#"
+        UPDATE expenses.rules r SET actions = (
+            SELECT jsonb_agg(
+                CASE WHEN a.action->>'type' = 'setCategory' AND a.action->>'categoryId' = $1::text
+                     THEN jsonb_set(a.action, '{categoryId}', to_jsonb($2::text))
+                     ELSE a.action END
+                ORDER BY a.position)
+            FROM jsonb_array_elements(r.actions) WITH ORDINALITY AS a(action, position)
+        ), updated_at = now()
+        WHERE r.user_id = $3
+          AND r.actions @> jsonb_build_array(
+              jsonb_build_object('type', 'setCategory', 'categoryId', $1::text))
+        "#,
+        source_id.to_string(),
+        target_id.to_string(),
+        user_id
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(dates
+        .into_iter()
+        .flatten()
+        .map(|date| date.date())
+        .collect())
+}
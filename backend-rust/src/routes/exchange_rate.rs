@@ -0,0 +1,530 @@
+use crate::auth::{AdminUser, AuthenticatedUser};
+use crate::domain::{Currency, ExchangeRate, ExchangeRateTable, DEFAULT_CURRENCY};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Supported bulk formats for exchange rates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExchangeRatesFormat {
+    /// Either `date,base,quote,rate` rows, or an ECB-style sheet with a `Date`
+    /// column followed by one column per quote currency.
+    Csv,
+    /// A daily document `{"base", "date", "rates": {...}}`, a list of them,
+    /// or a list of `{"baseCurrency", "quoteCurrency", "date", "rate"}` records.
+    Json,
+}
+
+impl ExchangeRatesFormat {
+    fn from_content_type(content_type: &str) -> Option<Self> {
+        match content_type {
+            "text/csv" | "application/csv" | "text/plain" => Some(Self::Csv),
+            "application/json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ExchangeRatesImportQuery {
+    /// Base currency of ECB-style CSV sheets and daily JSON documents without one.
+    base: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ExchangeRatesImportResponse {
+    pub imported: usize,
+}
+
+#[tracing::instrument(
+    name = "Importing exchange rates",
+    skip(request, body, query, admin, connection_pool),
+    fields(admin = %admin.0.sub, bytes = body.len())
+)]
+pub async fn upsert_exchange_rates(
+    request: HttpRequest,
+    body: web::Bytes,
+    query: web::Query<ExchangeRatesImportQuery>,
+    admin: AdminUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    let Some(format) = ExchangeRatesFormat::from_content_type(request.content_type()) else {
+        return HttpResponse::UnsupportedMediaType()
+            .body("Exchange rates must be sent as text/csv or application/json");
+    };
+    let default_base = match query.base.clone().map(Currency::parse) {
+        Some(Ok(base)) => base,
+        Some(Err(e)) => return HttpResponse::BadRequest().body(e),
+        None => Currency::default(),
+    };
+    let document = match std::str::from_utf8(&body) {
+        Ok(document) => document,
+        Err(_) => return HttpResponse::BadRequest().body("Exchange rates must be UTF-8 encoded"),
+    };
+
+    let rates = match parse_exchange_rates(document, format, &default_base) {
+        Ok(rates) => rates,
+        Err(e) => {
+            tracing::warn!("Rejected exchange rates: {}", e);
+            return HttpResponse::BadRequest().body(e);
+        }
+    };
+
+    match insert_exchange_rates(connection_pool.get_ref(), &rates).await {
+        Ok(imported) => HttpResponse::Ok().json(ExchangeRatesImportResponse { imported }),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ExchangeRateQuery {
+    date: Option<NaiveDate>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ExchangeRateResponse {
+    pub base: String,
+    pub quote: String,
+    pub date: NaiveDate,
+    /// Date the rate was published: the requested date or the nearest previous one.
+    #[serde(rename = "rateDate")]
+    pub rate_date: NaiveDate,
+    pub rate: f64,
+}
+
+#[tracing::instrument(name = "Retrieve exchange rate", skip(connection_pool, _user))]
+pub async fn get_exchange_rate(
+    path: web::Path<(String, String)>,
+    query: web::Query<ExchangeRateQuery>,
+    _user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    let (base, quote) = path.into_inner();
+    let (base, quote) = match (Currency::parse(base), Currency::parse(quote)) {
+        (Ok(base), Ok(quote)) => (base, quote),
+        (Err(e), _) | (_, Err(e)) => return HttpResponse::BadRequest().body(e),
+    };
+    let date = query
+        .date
+        .unwrap_or_else(|| chrono::Utc::now().date_naive());
+
+    let table = match load_exchange_rate_table(
+        connection_pool.get_ref(),
+        &[base.clone(), quote.clone()],
+        date,
+    )
+    .await
+    {
+        Ok(table) => table,
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    match table.rate(&base, &quote, date) {
+        Some((rate, rate_date)) => HttpResponse::Ok().json(ExchangeRateResponse {
+            base: base.to_string(),
+            quote: quote.to_string(),
+            date,
+            rate_date,
+            rate,
+        }),
+        None => HttpResponse::NotFound().body(format!(
+            "No exchange rate from {} to {} on or before {}",
+            base, quote, date
+        )),
+    }
+}
+
+/// Loads the rates of the given currencies published up to `until`, including
+/// the rates against any currency they share, so cross rates can be computed.
+#[tracing::instrument(name = "Loading exchange rates from database", skip(connection_pool))]
+pub async fn load_exchange_rate_table(
+    connection_pool: &PgPool,
+    currencies: &[Currency],
+    until: NaiveDate,
+) -> Result<ExchangeRateTable, sqlx::Error> {
+    let codes: Vec<String> = currencies.iter().map(|c| c.as_ref().to_string()).collect();
+    let rows = sqlx::query!(
+        r#"
+        SELECT base_currency, quote_currency, rate_date, rate
+        FROM expenses.exchange_rates
+        WHERE (base_currency = ANY($1) OR quote_currency = ANY($1))
+          AND rate_date <= $2
+        "#,
+        &codes,
+        until
+    )
+    .fetch_all(connection_pool)
+    .await?;
+
+    let rates = rows.into_iter().filter_map(|row| {
+        let base = Currency::parse(row.base_currency).ok()?;
+        let quote = Currency::parse(row.quote_currency).ok()?;
+        ExchangeRate::parse(base, quote, row.rate_date, row.rate).ok()
+    });
+    Ok(ExchangeRateTable::new(rates))
+}
+
+/// Inserts or replaces rates, returning the number of distinct rates written.
+#[tracing::instrument(name = "Upserting exchange rates", skip(connection_pool, rates), fields(count = rates.len()))]
+pub async fn insert_exchange_rates(
+    connection_pool: &PgPool,
+    rates: &[ExchangeRate],
+) -> Result<usize, sqlx::Error> {
+    // A single statement cannot update the same row twice: the last rate given for a day wins.
+    let unique: BTreeMap<(&str, &str, NaiveDate), f64> = rates
+        .iter()
+        .map(|r| ((r.base.as_ref(), r.quote.as_ref(), r.date), r.rate))
+        .collect();
+
+    let mut bases = Vec::with_capacity(unique.len());
+    let mut quotes = Vec::with_capacity(unique.len());
+    let mut dates = Vec::with_capacity(unique.len());
+    let mut values = Vec::with_capacity(unique.len());
+    for ((base, quote, date), rate) in &unique {
+        bases.push(base.to_string());
+        quotes.push(quote.to_string());
+        dates.push(*date);
+        values.push(*rate);
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO expenses.exchange_rates (base_currency, quote_currency, rate_date, rate)
+        SELECT * FROM UNNEST($1::varchar[], $2::varchar[], $3::date[], $4::float8[])
+        ON CONFLICT (base_currency, quote_currency, rate_date)
+        DO UPDATE SET rate = EXCLUDED.rate, updated_at = now()
+        "#,
+        &bases,
+        &quotes,
+        &dates,
+        &values
+    )
+    .execute(connection_pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to upsert exchange rates: {:?}", e);
+        e
+    })?;
+
+    Ok(unique.len())
+}
+
+/// Loads a rates file (`.csv` or `.json`) into the database, so conversions
+/// work without reaching any rates provider. CSV sheets without a base
+/// currency are read as ECB feeds, i.e. against EUR.
+#[tracing::instrument(name = "Importing exchange rates file", skip(connection_pool))]
+pub async fn import_exchange_rates_file(
+    path: &str,
+    connection_pool: &PgPool,
+) -> Result<usize, String> {
+    let format = ExchangeRatesFormat::from_path(Path::new(path)).ok_or_else(|| {
+        format!(
+            "Unsupported exchange rates file {}: expected .csv or .json",
+            path
+        )
+    })?;
+    let document =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let base = Currency::parse(DEFAULT_CURRENCY.to_string())?;
+    let rates = parse_exchange_rates(&document, format, &base)
+        .map_err(|e| format!("Invalid exchange rates file {}: {}", path, e))?;
+    insert_exchange_rates(connection_pool, &rates)
+        .await
+        .map_err(|e| format!("Failed to store exchange rates from {}: {}", path, e))
+}
+
+pub fn parse_exchange_rates(
+    document: &str,
+    format: ExchangeRatesFormat,
+    default_base: &Currency,
+) -> Result<Vec<ExchangeRate>, String> {
+    let rates = match format {
+        ExchangeRatesFormat::Csv => parse_csv(document, default_base)?,
+        ExchangeRatesFormat::Json => parse_json(document, default_base)?,
+    };
+    if rates.is_empty() {
+        return Err("No exchange rates found".to_string());
+    }
+    Ok(rates)
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonRates {
+    Daily(DailyRates),
+    DailyList(Vec<DailyRates>),
+    Records(Vec<RateRecord>),
+}
+
+#[derive(Deserialize)]
+struct DailyRates {
+    base: Option<String>,
+    date: String,
+    rates: BTreeMap<String, f64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RateRecord {
+    base_currency: String,
+    quote_currency: String,
+    date: String,
+    rate: f64,
+}
+
+fn parse_json(document: &str, default_base: &Currency) -> Result<Vec<ExchangeRate>, String> {
+    let parsed: JsonRates = serde_json::from_str(document)
+        .map_err(|_| "Unrecognised exchange rates JSON document".to_string())?;
+    let days = match parsed {
+        JsonRates::Daily(day) => vec![day],
+        JsonRates::DailyList(days) => days,
+        JsonRates::Records(records) => {
+            return records
+                .into_iter()
+                .map(|r| {
+                    ExchangeRate::parse(
+                        Currency::parse(r.base_currency)?,
+                        Currency::parse(r.quote_currency)?,
+                        parse_date(&r.date)?,
+                        r.rate,
+                    )
+                })
+                .collect();
+        }
+    };
+
+    let mut rates = Vec::new();
+    for day in days {
+        let base = match day.base {
+            Some(base) => Currency::parse(base)?,
+            None => default_base.clone(),
+        };
+        let date = parse_date(&day.date)?;
+        for (quote, rate) in day.rates {
+            if Currency::parse(quote.clone())? == base {
+                continue;
+            }
+            rates.push(ExchangeRate::parse(
+                base.clone(),
+                Currency::parse(quote)?,
+                date,
+                rate,
+            )?);
+        }
+    }
+    Ok(rates)
+}
+
+fn parse_csv(document: &str, default_base: &Currency) -> Result<Vec<ExchangeRate>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(document.as_bytes());
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|e| format!("Invalid CSV header: {}", e))?
+        .iter()
+        .map(str::to_string)
+        .collect();
+    let lowercase: Vec<String> = headers.iter().map(|h| h.to_lowercase()).collect();
+
+    let mut rates = Vec::new();
+    if lowercase == ["date", "base", "quote", "rate"] {
+        for (line, record) in reader.records().enumerate() {
+            let record = record.map_err(|e| format!("Invalid CSV: {}", e))?;
+            let field = |i: usize| record.get(i).unwrap_or_default().to_string();
+            let rate = parse_rate(&field(3))
+                .ok_or_else(|| format!("Line {}: {} is not a valid rate", line + 2, field(3)))?;
+            let parsed = ExchangeRate::parse(
+                Currency::parse(field(1))?,
+                Currency::parse(field(2))?,
+                parse_date(&field(0))?,
+                rate,
+            )
+            .map_err(|e| format!("Line {}: {}", line + 2, e))?;
+            rates.push(parsed);
+        }
+    } else if lowercase.first().map(String::as_str) == Some("date") {
+        // ECB sheets end each line with a separator, hence an empty last column.
+        let quotes = headers[1..]
+            .iter()
+            .map(|code| {
+                if code.is_empty() {
+                    Ok(None)
+                } else {
+                    Currency::parse(code.clone()).map(Some)
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for (line, record) in reader.records().enumerate() {
+            let record = record.map_err(|e| format!("Invalid CSV: {}", e))?;
+            let date = parse_date(record.get(0).unwrap_or_default())
+                .map_err(|e| format!("Line {}: {}", line + 2, e))?;
+            for (quote, value) in quotes.iter().zip(record.iter().skip(1)) {
+                let Some(quote) = quote else { continue };
+                // Currencies that were not quoted on a day are left empty or marked N/A.
+                if value.is_empty() || value.eq_ignore_ascii_case("n/a") || quote == default_base {
+                    continue;
+                }
+                let rate = parse_rate(value).ok_or_else(|| {
+                    format!("Line {}: {} is not a valid {} rate", line + 2, value, quote)
+                })?;
+                let parsed = ExchangeRate::parse(default_base.clone(), quote.clone(), date, rate)
+                    .map_err(|e| format!("Line {}: {}", line + 2, e))?;
+                rates.push(parsed);
+            }
+        }
+    } else {
+        return Err(
+            "CSV header must be `date,base,quote,rate` or `Date` followed by currency codes"
+                .to_string(),
+        );
+    }
+    Ok(rates)
+}
+
+fn parse_rate(value: &str) -> Option<f64> {
+    value.parse::<f64>().ok()
+}
+
+/// Accepts ISO dates and the long form used by the ECB daily feed (`17 October 2026`).
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(value, "%d %B %Y"))
+        .map_err(|_| format!("{} is not a valid date", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_exchange_rates, ExchangeRatesFormat};
+    use crate::domain::Currency;
+    use chrono::NaiveDate;
+    use claims::{assert_err, assert_ok};
+
+    fn eur() -> Currency {
+        Currency::default()
+    }
+
+    #[test]
+    fn ecb_daily_csv_is_parsed() {
+        let document = "Date, USD, JPY, GBP, \n17 October 2026, 1.0812, 161.52, N/A, \n";
+        let rates = assert_ok!(parse_exchange_rates(
+            document,
+            ExchangeRatesFormat::Csv,
+            &eur()
+        ));
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates[0].base.as_ref(), "EUR");
+        assert_eq!(rates[0].quote.as_ref(), "USD");
+        assert_eq!(
+            rates[0].date,
+            NaiveDate::from_ymd_opt(2026, 10, 17).unwrap()
+        );
+        assert_eq!(rates[1].rate, 161.52);
+    }
+
+    #[test]
+    fn ecb_history_csv_is_parsed() {
+        let document = "Date,USD,GBP,\n2026-10-16,1.08,0.86,\n2026-10-15,1.09,,\n";
+        let rates = assert_ok!(parse_exchange_rates(
+            document,
+            ExchangeRatesFormat::Csv,
+            &eur()
+        ));
+        assert_eq!(rates.len(), 3);
+    }
+
+    #[test]
+    fn long_csv_is_parsed() {
+        let document = "date,base,quote,rate\n2026-10-16,USD,JPY,149.3\n";
+        let rates = assert_ok!(parse_exchange_rates(
+            document,
+            ExchangeRatesFormat::Csv,
+            &eur()
+        ));
+        assert_eq!(rates[0].base.as_ref(), "USD");
+        assert_eq!(rates[0].quote.as_ref(), "JPY");
+    }
+
+    #[test]
+    fn invalid_csv_values_are_rejected() {
+        for document in [
+            "Date,USD\n2026-10-16,abc\n",
+            "Date,USD\nyesterday,1.08\n",
+            "Date,usd\n2026-10-16,1.08\n",
+            "date,base,quote,rate\n2026-10-16,EUR,USD,-1\n",
+            "currency,rate\nUSD,1.08\n",
+            "Date,USD\n",
+        ] {
+            assert_err!(parse_exchange_rates(
+                document,
+                ExchangeRatesFormat::Csv,
+                &eur()
+            ));
+        }
+    }
+
+    #[test]
+    fn json_documents_are_parsed() {
+        let daily = r#"{"base": "EUR", "date": "2026-10-16", "rates": {"USD": 1.08, "GBP": 0.86}}"#;
+        let daily_list = r#"[{"date": "2026-10-16", "rates": {"USD": 1.08}}, {"date": "2026-10-15", "rates": {"USD": 1.09}}]"#;
+        let records = r#"[{"baseCurrency": "USD", "quoteCurrency": "CHF", "date": "2026-10-16", "rate": 0.79}]"#;
+        assert_eq!(
+            assert_ok!(parse_exchange_rates(
+                daily,
+                ExchangeRatesFormat::Json,
+                &eur()
+            ))
+            .len(),
+            2
+        );
+        assert_eq!(
+            assert_ok!(parse_exchange_rates(
+                daily_list,
+                ExchangeRatesFormat::Json,
+                &eur()
+            ))
+            .len(),
+            2
+        );
+        let parsed = assert_ok!(parse_exchange_rates(
+            records,
+            ExchangeRatesFormat::Json,
+            &eur()
+        ));
+        assert_eq!(parsed[0].base.as_ref(), "USD");
+    }
+
+    #[test]
+    fn invalid_json_documents_are_rejected() {
+        for document in [
+            r#"{"rates": {"USD": 1.08}}"#,
+            r#"{"date": "2026-10-16", "rates": {"USD": 0}}"#,
+            r#"{"date": "2026-10-16", "rates": {"DOLLAR": 1.08}}"#,
+            "[]",
+        ] {
+            assert_err!(parse_exchange_rates(
+                document,
+                ExchangeRatesFormat::Json,
+                &eur()
+            ));
+        }
+    }
+}
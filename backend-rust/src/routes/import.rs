@@ -0,0 +1,684 @@
+use crate::auth::AuthenticatedUser;
+use crate::domain::{
+    apply_rules, AmountInCents, Money, Payment, PaymentDescription, PaymentMerchant, Rule,
+    RuleSubject, Tag,
+};
+use crate::notifier::Notifier;
+use crate::routes::budget::spawn_budget_alerts_for_dates;
+use crate::routes::payment::{
+    insert_payment_in_transaction, insert_payment_tags_in_transaction, parse_tags,
+    resolve_category_id, CategoryResolutionError, TagDto,
+};
+use crate::routes::payment_duplicate::{duplicate_error, find_duplicate_payments};
+use crate::routes::rule::load_rules;
+use crate::routes::wallet::get_wallet_by_name;
+use crate::routes::CategoryIdentifier;
+use actix_multipart::form::{bytes::Bytes, text::Text, MultipartForm};
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// A bank statement upload: the CSV file and how to read it.
+#[derive(MultipartForm)]
+pub struct CsvImportForm {
+    #[multipart(limit = "5MiB")]
+    file: Bytes,
+    /// JSON object naming the statement columns, see [`ColumnMapping`].
+    mapping: Text<String>,
+    /// chrono format of the date column, `%Y-%m-%d` by default.
+    #[multipart(rename = "dateFormat")]
+    date_format: Option<Text<String>>,
+    /// `.` (default) or `,`; the other one is ignored as a thousands separator.
+    #[multipart(rename = "decimalSeparator")]
+    decimal_separator: Option<Text<String>>,
+    #[multipart(rename = "signConvention")]
+    sign_convention: Option<Text<String>>,
+    /// Field delimiter, `,` by default.
+    delimiter: Option<Text<String>>,
+    wallet: Text<String>,
+    /// Category of the rows without a mapped category column value.
+    category: Option<Text<String>>,
+}
+
+/// Header names of the statement columns.
+#[derive(Deserialize, Debug)]
+pub struct ColumnMapping {
+    date: String,
+    amount: String,
+    merchant: String,
+    description: Option<String>,
+    category: Option<String>,
+}
+
+/// How the statement signs its amounts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SignConvention {
+    /// Expenses are negative, as in this application.
+    ExpensesNegative,
+    /// Expenses are positive (debit columns), so every amount is negated.
+    ExpensesPositive,
+}
+
+impl SignConvention {
+    fn parse(s: &str) -> Result<Self, String> {
+        match s.trim() {
+            "expensesNegative" => Ok(Self::ExpensesNegative),
+            "expensesPositive" => Ok(Self::ExpensesPositive),
+            other => Err(format!(
+                "Unknown sign convention '{}': expected expensesNegative or expensesPositive",
+                other
+            )),
+        }
+    }
+}
+
+/// Everything needed to turn statement rows into payments.
+#[derive(Debug)]
+struct StatementFormat {
+    mapping: ColumnMapping,
+    date_format: String,
+    decimal_separator: char,
+    sign_convention: SignConvention,
+    delimiter: u8,
+}
+
+impl StatementFormat {
+    fn from_form(form: &CsvImportForm) -> Result<Self, String> {
+        let mapping: ColumnMapping = serde_json::from_str(&form.mapping)
+            .map_err(|e| format!("Invalid column mapping: {}", e))?;
+        let date_format = form
+            .date_format
+            .as_ref()
+            .map(|f| f.trim().to_string())
+            .filter(|f| !f.is_empty())
+            .unwrap_or_else(|| DEFAULT_DATE_FORMAT.to_string());
+        let decimal_separator = match form.decimal_separator.as_ref().map(|s| s.trim()) {
+            None | Some(".") => '.',
+            Some(",") => ',',
+            Some(other) => {
+                return Err(format!(
+                    "Unsupported decimal separator '{}': expected '.' or ','",
+                    other
+                ))
+            }
+        };
+        let sign_convention = form
+            .sign_convention
+            .as_ref()
+            .map(|s| SignConvention::parse(s))
+            .transpose()?
+            .unwrap_or(SignConvention::ExpensesNegative);
+        let delimiter = match form.delimiter.as_ref().map(|s| s.as_str()) {
+            None => b',',
+            Some("\\t") => b'\t',
+            Some(d) if d.len() == 1 => d.as_bytes()[0],
+            Some(d) => return Err(format!("Unsupported delimiter '{}'", d)),
+        };
+        Ok(Self {
+            mapping,
+            date_format,
+            decimal_separator,
+            sign_convention,
+            delimiter,
+        })
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CsvImportQuery {
+    /// Inserts the valid rows instead of returning a preview.
+    #[serde(default)]
+    commit: bool,
+    /// Accepts the rows that look like payments already recorded.
+    #[serde(default)]
+    force: bool,
+}
+
+/// A statement row as it would be imported, with what prevents it from being imported.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedRowDto {
+    /// Line in the file, the header being line 1.
+    pub line: usize,
+    pub accounting_date: Option<NaiveDateTime>,
+    pub amount_in_cents: Option<i64>,
+    pub merchant_name: Option<String>,
+    pub description: Option<String>,
+    pub category: Option<String>,
+    /// Tags added by rules.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<TagDto>,
+    pub errors: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payment_id: Option<Uuid>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CsvImportResponse {
+    pub committed: bool,
+    pub wallet: String,
+    pub currency: String,
+    pub accepted_rows: usize,
+    pub rejected_rows: usize,
+    pub rows: Vec<ImportedRowDto>,
+}
+
+enum ImportError {
+    Validation(String),
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for ImportError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+impl ImportError {
+    fn into_response(self) -> HttpResponse {
+        match self {
+            Self::Validation(e) => HttpResponse::BadRequest().body(e),
+            Self::Database(e) => {
+                tracing::error!("Failed to execute query: {:?}", e);
+                HttpResponse::InternalServerError().finish()
+            }
+        }
+    }
+}
+
+/// A parsed row: the payment fields are set only when the row has no errors.
+struct StatementRow {
+    dto: ImportedRowDto,
+    merchant_name: Option<PaymentMerchant>,
+    description: Option<PaymentDescription>,
+    tags: Vec<Tag>,
+}
+
+#[tracing::instrument(
+    name = "Importing a CSV bank statement",
+    skip(form, query, user, connection_pool, notifier),
+    fields(user_id = %user.sub, wallet = %form.wallet.as_str(), commit = query.commit)
+)]
+pub async fn import_csv(
+    MultipartForm(form): MultipartForm<CsvImportForm>,
+    query: web::Query<CsvImportQuery>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+    notifier: web::Data<dyn Notifier>,
+) -> impl Responder {
+    match import_statement(
+        &form,
+        query.commit,
+        query.force,
+        &user.sub,
+        connection_pool.get_ref(),
+    )
+    .await
+    {
+        Ok(response) => {
+            if response.committed {
+                spawn_budget_alerts_for_dates(
+                    &user.sub,
+                    response
+                        .rows
+                        .iter()
+                        .filter(|row| row.payment_id.is_some())
+                        .filter_map(|row| row.accounting_date)
+                        .map(|date| date.date()),
+                    connection_pool.get_ref(),
+                    notifier.into_inner(),
+                );
+            }
+            HttpResponse::Ok().json(response)
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn import_statement(
+    form: &CsvImportForm,
+    commit: bool,
+    force: bool,
+    user_id: &str,
+    connection_pool: &PgPool,
+) -> Result<CsvImportResponse, ImportError> {
+    use ImportError::Validation;
+
+    let format = StatementFormat::from_form(form).map_err(Validation)?;
+    let wallet = get_wallet_by_name(form.wallet.trim(), connection_pool, user_id)
+        .await?
+        .ok_or_else(|| Validation(format!("Wallet '{}' not found", form.wallet.trim())))?;
+    let document = std::str::from_utf8(&form.file.data)
+        .map_err(|_| Validation("The statement must be UTF-8 encoded".to_string()))?;
+    let default_category = form
+        .category
+        .as_ref()
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty());
+    let rules = load_rules(connection_pool, user_id).await?;
+    let mut rows = parse_statement(
+        document,
+        &format,
+        default_category.as_deref(),
+        &rules,
+        wallet.name.as_ref(),
+    )?;
+
+    // Categories are resolved on commit only, so that a preview does not create any,
+    // and within the transaction of the import, so that a failed import does not either.
+    let mut tx = connection_pool.begin().await?;
+    let mut category_ids = HashMap::new();
+    for row in rows.iter_mut().filter(|row| row.dto.errors.is_empty()) {
+        let Some(name) = row.dto.category.clone() else {
+            continue;
+        };
+        let identifier = CategoryIdentifier::from(name.as_str());
+        let category_id = match (category_ids.get(&name), &identifier, commit) {
+            (Some(id), _, _) => Ok(Some(*id)),
+            (None, CategoryIdentifier::Name(_), false) => Ok(Some(Uuid::nil())),
+            // Only names create categories: ids are safe to resolve in a preview
+            (None, _, _) => resolve_category_id(&identifier, user_id, &mut *tx).await,
+        };
+        match category_id {
+            Ok(Some(id)) => {
+                category_ids.insert(name, id);
+            }
+            Ok(None) => row
+                .dto
+                .errors
+                .push(format!("Category '{}' not found", name)),
+            Err(CategoryResolutionError::InvalidName(e)) => row.dto.errors.push(e),
+            Err(CategoryResolutionError::Database(e)) => return Err(e.into()),
+        }
+    }
+
+    let mut payments = Vec::new();
+    for (index, row) in rows.iter_mut().enumerate() {
+        if !row.dto.errors.is_empty() {
+            continue;
+        }
+        let (Some(merchant_name), Some(accounting_date), Some(amount), Some(category)) = (
+            row.merchant_name.take(),
+            row.dto.accounting_date,
+            row.dto.amount_in_cents,
+            row.dto.category.as_ref(),
+        ) else {
+            continue;
+        };
+        let payment = Payment {
+            description: row.description.take(),
+            category_id: category_ids[category],
+            amount: Money::new(AmountInCents::new(amount), wallet.default_currency.clone()),
+            merchant_name,
+            accounting_date,
+            wallet_id: wallet.id,
+            user_id: user_id.to_string(),
+            splits: Vec::new(),
+        };
+        // Statements overlapping a previous import, or payments already entered by hand
+        if !force {
+            let duplicates = find_duplicate_payments(&payment, &mut *tx).await?;
+            if !duplicates.is_empty() {
+                row.dto.errors.push(duplicate_error(&duplicates));
+                continue;
+            }
+        }
+        payments.push((index, payment));
+    }
+
+    if commit {
+        for (index, payment) in &payments {
+            let payment_id = insert_payment_in_transaction(&mut tx, payment).await?;
+            insert_payment_tags_in_transaction(&mut tx, payment_id, &rows[*index].tags, user_id)
+                .await?;
+            rows[*index].dto.payment_id = Some(payment_id);
+        }
+        tx.commit().await?;
+    }
+
+    let rows: Vec<ImportedRowDto> = rows.into_iter().map(|row| row.dto).collect();
+    let accepted_rows = rows.iter().filter(|row| row.errors.is_empty()).count();
+    Ok(CsvImportResponse {
+        committed: commit,
+        wallet: wallet.name.as_ref().to_string(),
+        currency: wallet.default_currency.as_ref().to_string(),
+        accepted_rows,
+        rejected_rows: rows.len() - accepted_rows,
+        rows,
+    })
+}
+
+/// Parses every row of the statement. Problems with a single row are reported on
+/// that row; only an unreadable header or mapping fails the whole statement.
+/// The rules of the user set the category, description and tags of the rows
+/// they match; rows stay in the wallet of the import.
+fn parse_statement(
+    document: &str,
+    format: &StatementFormat,
+    default_category: Option<&str>,
+    rules: &[Rule],
+    wallet: &str,
+) -> Result<Vec<StatementRow>, ImportError> {
+    use ImportError::Validation;
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(format.delimiter)
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(document.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|e| Validation(format!("Invalid statement header: {}", e)))?
+        .clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|header| header.eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| Validation(format!("Column '{}' not found in the statement", name)))
+    };
+    let mapping = &format.mapping;
+    let date_column = column(&mapping.date)?;
+    let amount_column = column(&mapping.amount)?;
+    let merchant_column = column(&mapping.merchant)?;
+    let description_column = mapping.description.as_deref().map(column).transpose()?;
+    let category_column = mapping.category.as_deref().map(column).transpose()?;
+
+    let mut rows = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let line = index + 2;
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                rows.push(StatementRow::rejected(
+                    line,
+                    format!("Unreadable row: {}", e),
+                ));
+                continue;
+            }
+        };
+        let field = |column: usize| record.get(column).unwrap_or_default();
+        let optional_field = |column: Option<usize>| {
+            column
+                .map(field)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        let mut errors = Vec::new();
+
+        let accounting_date = parse_date(field(date_column), &format.date_format)
+            .map_err(|e| errors.push(e))
+            .ok();
+        let amount_in_cents = parse_amount(field(amount_column), format.decimal_separator)
+            .and_then(|amount| match format.sign_convention {
+                SignConvention::ExpensesNegative => Ok(amount),
+                SignConvention::ExpensesPositive => amount
+                    .checked_neg()
+                    .ok_or_else(|| format!("{} is out of range", amount)),
+            })
+            .map_err(|e| errors.push(e))
+            .ok();
+        let merchant = field(merchant_column).to_string();
+        let merchant_name = PaymentMerchant::parse(merchant.clone())
+            .map_err(|e| errors.push(e))
+            .ok();
+        let mut description = optional_field(description_column);
+        let outcome = match (&merchant_name, amount_in_cents) {
+            (Some(merchant_name), Some(amount_in_cents)) => apply_rules(
+                rules,
+                &RuleSubject {
+                    merchant_name: merchant_name.as_ref(),
+                    description: description.as_deref(),
+                    amount_in_cents,
+                    wallet: Some(wallet),
+                },
+            ),
+            _ => Default::default(),
+        };
+        if let Some(rule_description) = outcome.description {
+            description = Some(rule_description);
+        }
+        let parsed_description = description
+            .clone()
+            .map(PaymentDescription::parse)
+            .transpose()
+            .map_err(|e| errors.push(e))
+            .ok()
+            .flatten();
+        let tags: Vec<TagDto> = outcome.tags.into_iter().map(TagDto::from).collect();
+        let parsed_tags = parse_tags(Some(tags.clone()))
+            .map_err(|e| errors.push(e))
+            .unwrap_or_default();
+        let category = outcome
+            .category_id
+            .map(|id| id.to_string())
+            .or_else(|| optional_field(category_column))
+            .or_else(|| default_category.map(str::to_string));
+        if category.is_none() {
+            errors.push("No category: map a category column or choose a category".to_string());
+        }
+
+        rows.push(StatementRow {
+            dto: ImportedRowDto {
+                line,
+                accounting_date,
+                amount_in_cents,
+                merchant_name: Some(merchant).filter(|m| !m.is_empty()),
+                description,
+                category,
+                tags,
+                errors,
+                payment_id: None,
+            },
+            merchant_name,
+            description: parsed_description,
+            tags: parsed_tags,
+        });
+    }
+    Ok(rows)
+}
+
+impl StatementRow {
+    fn rejected(line: usize, error: String) -> Self {
+        Self {
+            dto: ImportedRowDto {
+                line,
+                accounting_date: None,
+                amount_in_cents: None,
+                merchant_name: None,
+                description: None,
+                category: None,
+                tags: Vec::new(),
+                errors: vec![error],
+                payment_id: None,
+            },
+            merchant_name: None,
+            description: None,
+            tags: Vec::new(),
+        }
+    }
+}
+
+/// Parses a date, or a date and time, with a chrono format.
+fn parse_date(value: &str, date_format: &str) -> Result<NaiveDateTime, String> {
+    NaiveDateTime::parse_from_str(value, date_format)
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value, date_format)
+                .map(|date| date.and_hms_opt(0, 0, 0).expect("midnight is valid"))
+        })
+        .map_err(|_| format!("'{}' does not match the date format {}", value, date_format))
+}
+
+/// Parses a decimal amount such as `-1,234.56` (or `-1.234,56` with a `,` decimal
+/// separator) into cents.
+fn parse_amount(value: &str, decimal_separator: char) -> Result<i64, String> {
+    let invalid = || format!("'{}' is not a valid amount", value);
+    let thousands_separator = if decimal_separator == ',' { '.' } else { ',' };
+    let cleaned: String = value
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != thousands_separator && *c != '\'')
+        .collect();
+    let (negative, digits) = match cleaned.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, cleaned.strip_prefix('+').unwrap_or(&cleaned)),
+    };
+    let (units, fraction) = digits.split_once(decimal_separator).unwrap_or((digits, ""));
+    let is_number = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if units.is_empty() || !is_number(units) || !is_number(fraction) || fraction.len() > 2 {
+        return Err(invalid());
+    }
+    let cents = format!("{:0<2}", fraction)
+        .parse::<i64>()
+        .map_err(|_| invalid())?;
+    let amount = units
+        .parse::<i64>()
+        .ok()
+        .and_then(|units| units.checked_mul(100))
+        .and_then(|units| units.checked_add(cents))
+        .ok_or_else(invalid)?;
+    Ok(if negative { -amount } else { amount })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{RuleAction, RuleCondition};
+    use claims::{assert_err, assert_ok_eq};
+
+    fn format(mapping: &str) -> StatementFormat {
+        StatementFormat {
+            mapping: serde_json::from_str(mapping).unwrap(),
+            date_format: "%d/%m/%Y".to_string(),
+            decimal_separator: ',',
+            sign_convention: SignConvention::ExpensesPositive,
+            delimiter: b';',
+        }
+    }
+
+    #[test]
+    fn amounts_are_parsed_with_either_decimal_separator() {
+        assert_ok_eq!(parse_amount("-1,234.56", '.'), -123456);
+        assert_ok_eq!(parse_amount("1.234,5", ','), 123450);
+        assert_ok_eq!(parse_amount("+12", '.'), 1200);
+        assert_ok_eq!(parse_amount("1 000,00", ','), 100000);
+        assert_ok_eq!(parse_amount("0.07", '.'), 7);
+    }
+
+    #[test]
+    fn malformed_amounts_are_rejected() {
+        for amount in [
+            "",
+            "-",
+            "12.345",
+            "1e3",
+            "12 EUR",
+            "--5",
+            "99999999999999999999",
+        ] {
+            assert_err!(parse_amount(amount, '.'), "{}", amount);
+        }
+    }
+
+    #[test]
+    fn dates_may_carry_a_time() {
+        assert_ok_eq!(
+            parse_date("05/03/2026", "%d/%m/%Y"),
+            NaiveDate::from_ymd_opt(2026, 3, 5)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+        );
+        assert_ok_eq!(
+            parse_date("2026-03-05 14:30", "%Y-%m-%d %H:%M"),
+            NaiveDate::from_ymd_opt(2026, 3, 5)
+                .unwrap()
+                .and_hms_opt(14, 30, 0)
+                .unwrap()
+        );
+        assert_err!(parse_date("2026-03-05", "%d/%m/%Y"));
+    }
+
+    #[test]
+    fn rows_are_parsed_and_annotated() {
+        let document = "Date;Debit;Payee;Memo\n\
+                        05/03/2026;12,50;Bakery;Croissants\n\
+                        06/03/2026;abc;Bad (merchant);\n";
+        let rows = parse_statement(
+            document,
+            &format(r#"{"date": "date", "amount": "Debit", "merchant": "Payee", "description": "Memo"}"#),
+            Some("Food"),
+            &[],
+            "Card",
+        )
+        .map_err(|_| ())
+        .unwrap();
+
+        assert_eq!(rows.len(), 2);
+        let bakery = &rows[0].dto;
+        assert_eq!(bakery.line, 2);
+        assert_eq!(bakery.amount_in_cents, Some(-1250));
+        assert_eq!(bakery.description.as_deref(), Some("Croissants"));
+        assert_eq!(bakery.category.as_deref(), Some("Food"));
+        assert!(bakery.errors.is_empty());
+        let invalid = &rows[1].dto;
+        assert_eq!(invalid.line, 3);
+        assert_eq!(invalid.amount_in_cents, None);
+        assert_eq!(invalid.description, None);
+        assert_eq!(invalid.errors.len(), 2);
+    }
+
+    #[test]
+    fn unknown_columns_reject_the_statement() {
+        let result = parse_statement(
+            "Date;Amount\n05/03/2026;1\n",
+            &format(r#"{"date": "Date", "amount": "Amount", "merchant": "Payee"}"#),
+            Some("Food"),
+            &[],
+            "Card",
+        );
+        assert!(matches!(result, Err(ImportError::Validation(e)) if e.contains("Payee")));
+    }
+
+    #[test]
+    fn rules_fill_in_the_matching_rows() {
+        let category_id = Uuid::new_v4();
+        let rule = Rule::parse(
+            vec![RuleCondition::Merchant {
+                pattern: "^netflix".to_string(),
+            }],
+            vec![
+                RuleAction::SetCategory { category_id },
+                RuleAction::AddTag {
+                    key: "provider".to_string(),
+                    value: "netflix".to_string(),
+                },
+            ],
+        )
+        .unwrap();
+        let document = "Date;Debit;Payee\n\
+                        05/03/2026;12,99;NETFLIX.COM\n\
+                        06/03/2026;3,50;Bakery\n";
+        let rows = parse_statement(
+            document,
+            &format(r#"{"date": "Date", "amount": "Debit", "merchant": "Payee"}"#),
+            None,
+            &[rule],
+            "Card",
+        )
+        .map_err(|_| ())
+        .unwrap();
+
+        let netflix = &rows[0].dto;
+        assert_eq!(netflix.category, Some(category_id.to_string()));
+        assert_eq!(netflix.tags.len(), 1);
+        assert!(netflix.errors.is_empty());
+        // Without a rule or a default category, the row cannot be imported
+        assert!(rows[1].dto.tags.is_empty());
+        assert_eq!(rows[1].dto.errors.len(), 1);
+    }
+}
@@ -0,0 +1,386 @@
+use crate::auth::AuthenticatedUser;
+use crate::domain::{normalise_merchant_name, MerchantAlias, Payment, PaymentMerchant};
+use actix_web::web::Json;
+use actix_web::{web, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MerchantDto {
+    id: Uuid,
+    name: String,
+    /// Normalised spellings resolving to the merchant, besides its name.
+    aliases: Vec<String>,
+    payment_count: i64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MerchantAliasDto {
+    alias: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MerchantMergeDto {
+    /// Merchant absorbed by the merchant of the path, then deleted.
+    merchant_id: Uuid,
+}
+
+/// Canonical merchant a payment is attached to.
+pub(crate) struct MerchantRef {
+    pub(crate) id: Uuid,
+    pub(crate) name: String,
+}
+
+enum MerchantError {
+    Validation(String),
+    NotFound,
+    Conflict(String),
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for MerchantError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+impl MerchantError {
+    fn into_response(self) -> HttpResponse {
+        match self {
+            Self::Validation(e) => HttpResponse::BadRequest().body(e),
+            Self::NotFound => HttpResponse::NotFound().finish(),
+            Self::Conflict(e) => HttpResponse::Conflict().body(e),
+            Self::Database(e) => {
+                tracing::error!("Failed to execute query: {:?}", e);
+                HttpResponse::InternalServerError().finish()
+            }
+        }
+    }
+}
+
+/// Merchant of the user that `merchant_name` resolves to, by its name or one
+/// of its aliases.
+pub(crate) async fn find_merchant(
+    executor: impl PgExecutor<'_>,
+    user_id: &str,
+    merchant_name: &str,
+) -> Result<Option<MerchantRef>, sqlx::Error> {
+    sqlx::query_as!(
+        MerchantRef,
+        r#"
+        SELECT m.id, m.name
+        FROM expenses.merchants m
+        WHERE m.user_id = $1
+          AND (m.normalised_name = $2 OR m.id = (
+              SELECT a.merchant_id FROM expenses.merchant_aliases a
+              WHERE a.user_id = $1 AND a.alias = $2
+          ))
+        "#,
+        user_id,
+        normalise_merchant_name(merchant_name)
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// Merchant of the user that `merchant_name` resolves to, created on first use.
+#[tracing::instrument(name = "Resolving merchant", skip(tx))]
+pub(crate) async fn resolve_merchant(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: &str,
+    merchant_name: &str,
+) -> Result<MerchantRef, sqlx::Error> {
+    if let Some(merchant) = find_merchant(&mut **tx, user_id, merchant_name).await? {
+        return Ok(merchant);
+    }
+    // Safe under concurrency thanks to the unique normalised name per user.
+    sqlx::query!(
+        r#"
+        INSERT INTO expenses.merchants (user_id, name, normalised_name)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, normalised_name) DO NOTHING
+        "#,
+        user_id,
+        merchant_name.trim(),
+        normalise_merchant_name(merchant_name)
+    )
+    .execute(&mut **tx)
+    .await?;
+    find_merchant(&mut **tx, user_id, merchant_name)
+        .await?
+        .ok_or(sqlx::Error::RowNotFound)
+}
+
+/// Replaces the merchant name of the payment with the name of its merchant,
+/// when it resolves to one, so that responses and duplicate checks use it.
+pub(crate) async fn use_canonical_merchant_name(
+    payment: &mut Payment,
+    connection_pool: &PgPool,
+) -> Result<(), sqlx::Error> {
+    let merchant = find_merchant(
+        connection_pool,
+        &payment.user_id,
+        payment.merchant_name.as_ref(),
+    )
+    .await?;
+    if let Some(Ok(merchant_name)) = merchant.map(|m| PaymentMerchant::parse(m.name)) {
+        payment.merchant_name = merchant_name;
+    }
+    Ok(())
+}
+
+#[tracing::instrument(name = "Retrieving merchants", skip(user, connection_pool))]
+pub async fn get_merchants(
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    match get_merchants_from_db(connection_pool.get_ref(), &user.sub, None).await {
+        Ok(merchants) => HttpResponse::Ok().json(merchants),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Merchants of the user (only `merchant_id` if given), most used first.
+async fn get_merchants_from_db(
+    executor: impl PgExecutor<'_>,
+    user_id: &str,
+    merchant_id: Option<Uuid>,
+) -> Result<Vec<MerchantDto>, sqlx::Error> {
+    sqlx::query_as!(
+        MerchantDto,
+        r#"
+        SELECT m.id, m.name,
+               COALESCE(
+                   (SELECT array_agg(a.alias ORDER BY a.alias)
+                    FROM expenses.merchant_aliases a WHERE a.merchant_id = m.id),
+                   '{}'
+               ) as "aliases!",
+               (SELECT count(*) FROM expenses.payments p WHERE p.merchant_id = m.id)
+                   as "payment_count!"
+        FROM expenses.merchants m
+        WHERE m.user_id = $1 AND ($2::uuid IS NULL OR m.id = $2)
+        ORDER BY 4 DESC, lower(m.name), m.id
+        "#,
+        user_id,
+        merchant_id
+    )
+    .fetch_all(executor)
+    .await
+}
+
+async fn get_merchant(
+    executor: impl PgExecutor<'_>,
+    user_id: &str,
+    merchant_id: Uuid,
+) -> Result<MerchantDto, MerchantError> {
+    get_merchants_from_db(executor, user_id, Some(merchant_id))
+        .await?
+        .pop()
+        .ok_or(MerchantError::NotFound)
+}
+
+#[tracing::instrument(
+    name = "Adding a merchant alias",
+    skip(path, payload, user, connection_pool),
+    fields(merchant_id = %path, alias = %payload.alias)
+)]
+pub async fn add_merchant_alias(
+    path: web::Path<Uuid>,
+    payload: Json<MerchantAliasDto>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    match insert_merchant_alias(
+        path.into_inner(),
+        payload.into_inner().alias,
+        &user.sub,
+        connection_pool.get_ref(),
+    )
+    .await
+    {
+        Ok(merchant) => HttpResponse::Ok().json(merchant),
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn insert_merchant_alias(
+    merchant_id: Uuid,
+    alias: String,
+    user_id: &str,
+    connection_pool: &PgPool,
+) -> Result<MerchantDto, MerchantError> {
+    let alias = MerchantAlias::parse(alias).map_err(MerchantError::Validation)?;
+
+    let mut tx = connection_pool.begin().await?;
+    sqlx::query_scalar!(
+        "SELECT id FROM expenses.merchants WHERE id = $1 AND user_id = $2 FOR UPDATE",
+        merchant_id,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(MerchantError::NotFound)?;
+    match find_merchant(&mut *tx, user_id, alias.as_ref()).await? {
+        // Already resolving to this merchant: nothing to do
+        Some(merchant) if merchant.id == merchant_id => {}
+        Some(merchant) => {
+            return Err(MerchantError::Conflict(format!(
+                "'{}' already resolves to the merchant '{}'; merge them instead",
+                alias.as_ref(),
+                merchant.name
+            )));
+        }
+        None => {
+            sqlx::query!(
+                r#"
+                INSERT INTO expenses.merchant_aliases (user_id, alias, merchant_id)
+                VALUES ($1, $2, $3)
+                "#,
+                user_id,
+                alias.as_ref(),
+                merchant_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+    let merchant = get_merchant(&mut *tx, user_id, merchant_id).await?;
+    tx.commit().await?;
+    Ok(merchant)
+}
+
+#[tracing::instrument(
+    name = "Removing a merchant alias",
+    skip(path, user, connection_pool),
+    fields(merchant_id = %path.0, alias = %path.1)
+)]
+pub async fn delete_merchant_alias(
+    path: web::Path<(Uuid, String)>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    let (merchant_id, alias) = path.into_inner();
+    match sqlx::query!(
+        r#"
+        DELETE FROM expenses.merchant_aliases
+        WHERE user_id = $1 AND merchant_id = $2 AND alias = $3
+        "#,
+        user.sub,
+        merchant_id,
+        normalise_merchant_name(&alias)
+    )
+    .execute(connection_pool.get_ref())
+    .await
+    {
+        Ok(result) if result.rows_affected() == 0 => HttpResponse::NotFound().finish(),
+        // Payments keep their merchant; only new ones stop resolving through the alias
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[tracing::instrument(
+    name = "Merging merchants",
+    skip(path, payload, user, connection_pool),
+    fields(merchant_id = %path, merged_merchant_id = %payload.merchant_id)
+)]
+pub async fn merge_merchants(
+    path: web::Path<Uuid>,
+    payload: Json<MerchantMergeDto>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    match merge_merchant_into(
+        payload.merchant_id,
+        path.into_inner(),
+        &user.sub,
+        connection_pool.get_ref(),
+    )
+    .await
+    {
+        Ok(merchant) => HttpResponse::Ok().json(merchant),
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Moves the aliases and payments of `source_id` to `target_id`, then deletes
+/// it. The name of the merged merchant becomes an alias of the target, and its
+/// payments are renamed after the target.
+async fn merge_merchant_into(
+    source_id: Uuid,
+    target_id: Uuid,
+    user_id: &str,
+    connection_pool: &PgPool,
+) -> Result<MerchantDto, MerchantError> {
+    if source_id == target_id {
+        return Err(MerchantError::Validation(
+            "A merchant cannot be merged into itself".to_string(),
+        ));
+    }
+
+    let mut tx = connection_pool.begin().await?;
+    let merchants = sqlx::query!(
+        r#"
+        SELECT id, name, normalised_name FROM expenses.merchants
+        WHERE user_id = $1 AND id = ANY($2)
+        FOR UPDATE
+        "#,
+        user_id,
+        &[source_id, target_id]
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    let (Some(source), Some(target)) = (
+        merchants.iter().find(|m| m.id == source_id),
+        merchants.iter().find(|m| m.id == target_id),
+    ) else {
+        return Err(MerchantError::NotFound);
+    };
+
+    sqlx::query!(
+        "UPDATE expenses.merchant_aliases SET merchant_id = $2 WHERE merchant_id = $1",
+        source_id,
+        target_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        r#"
+        UPDATE expenses.payments SET merchant_id = $2, merchant_name = $3
+        WHERE merchant_id = $1 AND user_id = $4
+        "#,
+        source_id,
+        target_id,
+        target.name,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!("DELETE FROM expenses.merchants WHERE id = $1", source_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO expenses.merchant_aliases (user_id, alias, merchant_id)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, alias) DO NOTHING
+        "#,
+        user_id,
+        source.normalised_name,
+        target_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    let merchant = get_merchant(&mut *tx, user_id, target_id).await?;
+    tx.commit().await?;
+    Ok(merchant)
+}
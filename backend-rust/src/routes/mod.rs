@@ -1,15 +1,43 @@
 mod admin;
 mod balance;
+mod banking;
+mod budget;
+mod category;
 mod debug;
+mod exchange_rate;
 mod greet;
 mod health_check;
+mod import;
+mod merchant;
 mod payment;
+mod payment_duplicate;
+mod payment_suggestion;
+mod recurring_payment;
+mod report;
+mod rule;
+mod staging;
+mod tag;
+mod transfer;
 mod wallet;
 
 pub use admin::*;
 pub use balance::*;
+pub use banking::*;
+pub use budget::*;
+pub use category::*;
 pub use debug::*;
+pub use exchange_rate::*;
 pub use greet::*;
 pub use health_check::*;
+pub use import::*;
+pub use merchant::*;
 pub use payment::*;
+pub use payment_duplicate::*;
+pub use payment_suggestion::*;
+pub use recurring_payment::*;
+pub use report::*;
+pub use rule::*;
+pub use staging::*;
+pub use tag::*;
+pub use transfer::*;
 pub use wallet::*;
@@ -1,7 +1,17 @@
-use crate::domain::{Payment, PaymentDescription, PaymentMerchant};
-use crate::routes::wallet::get_wallet_id_by_name;
+use crate::domain::{
+    AmountInCents, Currency, Money, Payment, PaymentCategory, PaymentDescription, PaymentMerchant,
+    PaymentSplit, Tag,
+};
+use crate::notifier::Notifier;
+use crate::routes::budget::spawn_budget_alerts;
+use crate::routes::merchant::{resolve_merchant, use_canonical_merchant_name};
+use crate::routes::payment_duplicate::{
+    find_duplicate_payments, DuplicatePaymentConflictDto, ForceQuery,
+};
+use crate::routes::rule::apply_rules_to_payment;
+use crate::routes::wallet::get_wallet_by_name;
 use actix_web::web::Json;
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use chrono::NaiveDateTime;
 use serde::Deserialize;
 use serde_json;
@@ -9,29 +19,49 @@ use sqlx::{Error, PgPool, Row};
 use std::ops::Deref;
 use uuid::Uuid;
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TagDto {
-    key: String,
-    value: String,
+    pub(crate) key: String,
+    pub(crate) value: String,
+}
+
+impl From<(String, String)> for TagDto {
+    fn from((key, value): (String, String)) -> Self {
+        Self { key, value }
+    }
 }
 
 #[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct PaymentDto {
-    description: Option<String>,
+    pub(crate) description: Option<String>,
     // Accept either a UUID or a category name for backward compatibility.
     #[serde(rename = "categoryId")]
-    category_id: CategoryIdentifier,
+    pub(crate) category_id: CategoryIdentifier,
     #[serde(rename = "amountInCents")]
-    amount_in_cents: i32,
+    pub(crate) amount_in_cents: AmountInCents,
+    // ISO 4217 code; defaults to the wallet's currency when omitted.
+    pub(crate) currency: Option<String>,
     #[serde(rename = "merchantName")]
-    merchant_name: String,
+    pub(crate) merchant_name: String,
     #[serde(rename = "accountingDate")]
-    accounting_date: NaiveDateTime,
+    pub(crate) accounting_date: NaiveDateTime,
     #[serde(skip_serializing_if = "Option::is_none")]
-    wallet: Option<String>,
+    pub(crate) wallet: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    tags: Option<Vec<TagDto>>,
+    pub(crate) tags: Option<Vec<TagDto>>,
+    // Line items spreading the amount over several categories; they must add up to the amount.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) splits: Option<Vec<PaymentSplitDto>>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PaymentSplitDto {
+    #[serde(rename = "categoryId")]
+    category_id: CategoryIdentifier,
+    #[serde(rename = "amountInCents")]
+    amount_in_cents: AmountInCents,
+    description: Option<String>,
 }
 
 // CategoryIdentifier accepts either a UUID or a name string from clients.
@@ -42,12 +72,23 @@ pub enum CategoryIdentifier {
     Name(String),
 }
 
+impl From<&str> for CategoryIdentifier {
+    fn from(value: &str) -> Self {
+        match Uuid::parse_str(value) {
+            Ok(uid) => Self::Uid(uid),
+            Err(_) => Self::Name(value.to_string()),
+        }
+    }
+}
+
 impl Payment {
     // Build Payment from DTO after resolving the canonical category_id (Uuid).
-    fn try_from_dto(
+    pub(crate) fn try_from_dto(
         dto: PaymentDto,
         wallet_id: Option<Uuid>,
+        default_currency: Currency,
         category_id: Uuid,
+        split_category_ids: Vec<Uuid>,
         user_id: String,
     ) -> Result<Self, String> {
         // Parse description only if provided and non-empty
@@ -57,14 +98,34 @@ impl Payment {
             .map(PaymentDescription::parse)
             .transpose()?;
         let merchant_name = PaymentMerchant::parse(dto.merchant_name.clone())?;
+        let currency = match dto.currency {
+            Some(code) => Currency::parse(code)?,
+            None => default_currency,
+        };
+        let splits = dto
+            .splits
+            .unwrap_or_default()
+            .into_iter()
+            .zip(split_category_ids)
+            .map(|(split, category_id)| {
+                let description = split
+                    .description
+                    .filter(|s| !s.trim().is_empty())
+                    .map(PaymentDescription::parse)
+                    .transpose()?;
+                PaymentSplit::parse(category_id, split.amount_in_cents, description)
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        PaymentSplit::validate_total(&splits, dto.amount_in_cents)?;
         Ok(Self {
             description,
             category_id,
-            amount_in_cents: dto.amount_in_cents,
+            amount: Money::new(dto.amount_in_cents, currency),
             merchant_name,
             accounting_date: dto.accounting_date,
             wallet_id,
             user_id,
+            splits,
         })
     }
 }
@@ -80,7 +141,7 @@ impl TryFrom<Json<PaymentDto>> for Payment {
 
 #[tracing::instrument(
     name = "Creating a new payment",
-    skip(payload, connection_pool),
+    skip(payload, connection_pool, notifier),
     fields(
         merchant_name = %payload.merchant_name,
         category_id = ?payload.category_id
@@ -88,17 +149,29 @@ impl TryFrom<Json<PaymentDto>> for Payment {
 )]
 pub async fn create_payment(
     payload: Json<PaymentDto>,
+    query: web::Query<ForceQuery>,
     user: crate::auth::AuthenticatedUser,
     connection_pool: web::Data<PgPool>,
+    notifier: web::Data<dyn Notifier>,
 ) -> impl Responder {
     let user_id = user.sub;
-    let tags = payload.0.tags.clone();
-    let wallet_name_input = payload.0.wallet.clone();
+    let mut payment_data = payload.into_inner();
+    if let Err(e) =
+        apply_rules_to_payment(&mut payment_data, &user_id, connection_pool.get_ref()).await
+    {
+        tracing::error!("Failed to apply the rules: {:?}", e);
+        return HttpResponse::InternalServerError().finish();
+    }
+    let tags = match parse_tags(payment_data.tags.clone()) {
+        Ok(tags) => tags,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    let wallet_name_input = payment_data.wallet.clone();
 
-    // Resolve wallet_id from wallet name
-    let wallet_id = if let Some(name) = &wallet_name_input {
-        match get_wallet_id_by_name(name, connection_pool.get_ref(), &user_id).await {
-            Ok(Some(id)) => Some(id),
+    // Resolve wallet from wallet name
+    let wallet = if let Some(name) = &wallet_name_input {
+        match get_wallet_by_name(name, connection_pool.get_ref(), &user_id).await {
+            Ok(Some(wallet)) => Some(wallet),
             Ok(None) => {
                 tracing::error!("Wallet not found: {}", name);
                 return HttpResponse::BadRequest().body(format!("Wallet '{}' not found", name));
@@ -111,111 +184,97 @@ pub async fn create_payment(
     } else {
         None
     };
-
-    // Create payment: accept either canonical `categoryId` (UUID) or legacy category name
-    let payment_data = payload.0;
-
-    // Resolve category identifier to canonical UUID
-    let resolved_category_id: Uuid = match payment_data.category_id.clone() {
-        CategoryIdentifier::Uid(uid) => uid,
-        CategoryIdentifier::Name(name) => {
-            match sqlx::query_scalar!(
-                "SELECT id FROM expenses.categories WHERE LOWER(name) = LOWER($1)",
-                name
-            )
-            .fetch_optional(connection_pool.get_ref())
-            .await
-            {
-                Ok(Some(id)) => id,
-                Ok(None) => {
-                    // Category not found: create it (safe due to unique index on lower(name)).
-                    match sqlx::query_scalar!(
-                        "INSERT INTO expenses.categories (name) VALUES ($1) RETURNING id",
-                        name
-                    )
-                    .fetch_one(connection_pool.get_ref())
-                    .await
-                    {
-                        Ok(new_id) => new_id,
-                        Err(insert_err) => {
-                            tracing::warn!(
-                                "Failed to insert category (maybe concurrent): {:?}",
-                                insert_err
-                            );
-                            // Try to select again in case another request inserted it concurrently
-                            match sqlx::query_scalar!(
-                                "SELECT id FROM expenses.categories WHERE LOWER(name) = LOWER($1)",
-                                name
-                            )
-                            .fetch_optional(connection_pool.get_ref())
-                            .await
-                            {
-                                Ok(Some(id)) => id,
-                                Ok(None) => {
-                                    tracing::error!(
-                                        "Failed to create category and it does not exist: {}",
-                                        name
-                                    );
-                                    return HttpResponse::InternalServerError().finish();
-                                }
-                                Err(e) => {
-                                    tracing::error!("Failed to lookup category by name after insert error: {:?}", e);
-                                    return HttpResponse::InternalServerError().finish();
-                                }
-                            }
-                        }
-                    }
-                }
-                Err(e) => {
-                    tracing::error!("Failed to lookup category by name: {:?}", e);
-                    return HttpResponse::InternalServerError().finish();
-                }
-            }
-        }
-    };
-
-    // Validate category exists when UUID was provided by client
-    match sqlx::query_scalar!(
-        "SELECT id FROM expenses.categories WHERE id = $1",
-        resolved_category_id
+    let wallet_id = wallet.as_ref().and_then(|w| w.id);
+    // Payments without an explicit currency are in the wallet's default currency
+    let default_currency = wallet.map(|w| w.default_currency).unwrap_or_default();
+
+    // Accept either the canonical `categoryId` (UUID) or a category name
+    let resolved_category_id = match resolve_category_id(
+        &payment_data.category_id,
+        &user_id,
+        connection_pool.get_ref(),
     )
-    .fetch_optional(connection_pool.get_ref())
     .await
     {
-        Ok(Some(_)) => {}
+        Ok(Some(id)) => id,
         Ok(None) => return HttpResponse::BadRequest().body("categoryId not found"),
-        Err(e) => {
-            tracing::error!("Failed to validate category: {:?}", e);
+        Err(CategoryResolutionError::InvalidName(e)) => return HttpResponse::BadRequest().body(e),
+        Err(CategoryResolutionError::Database(e)) => {
+            tracing::error!("Failed to resolve category: {:?}", e);
             return HttpResponse::InternalServerError().finish();
         }
+    };
+
+    let mut split_category_ids = Vec::new();
+    for split in payment_data.splits.iter().flatten() {
+        match resolve_category_id(&split.category_id, &user_id, connection_pool.get_ref()).await {
+            Ok(Some(id)) => split_category_ids.push(id),
+            Ok(None) => return HttpResponse::BadRequest().body("split categoryId not found"),
+            Err(CategoryResolutionError::InvalidName(e)) => {
+                return HttpResponse::BadRequest().body(e)
+            }
+            Err(CategoryResolutionError::Database(e)) => {
+                tracing::error!("Failed to resolve split category: {:?}", e);
+                return HttpResponse::InternalServerError().finish();
+            }
+        }
     }
 
-    let payment = match Payment::try_from_dto(
+    let mut payment = match Payment::try_from_dto(
         payment_data,
         wallet_id,
+        default_currency,
         resolved_category_id,
+        split_category_ids,
         user_id.clone(),
     ) {
         Ok(payment) => payment,
-        Err(_) => return HttpResponse::BadRequest().finish(),
+        Err(e) => return HttpResponse::BadRequest().body(e),
     };
+    if let Err(e) = use_canonical_merchant_name(&mut payment, connection_pool.get_ref()).await {
+        tracing::error!("Failed to resolve the merchant: {:?}", e);
+        return HttpResponse::InternalServerError().finish();
+    }
     // category_id already set on domain model via try_from_dto
 
+    // Refuse accidental double entries unless the client confirms them
+    if !query.force {
+        match find_duplicate_payments(&payment, connection_pool.get_ref()).await {
+            Ok(duplicates) if !duplicates.is_empty() => {
+                return HttpResponse::Conflict().json(DuplicatePaymentConflictDto {
+                    message:
+                        "This payment looks like a duplicate; use force=true to create it anyway"
+                            .to_string(),
+                    duplicates,
+                });
+            }
+            Ok(_) => {}
+            Err(e) => {
+                tracing::error!("Failed to look for duplicate payments: {:?}", e);
+                return HttpResponse::InternalServerError().finish();
+            }
+        }
+    }
+
     match insert_payment(&payment, connection_pool.get_ref()).await {
         Ok(payment_id) => {
-            // Insert tags if provided
-            if let Some(tags) = tags {
-                if let Err(e) = insert_payment_tags(
-                    payment_id,
-                    tags,
-                    connection_pool.get_ref(),
-                    payment.user_id.as_str(),
-                )
-                .await
-                {
-                    tracing::error!("Failed to insert tags: {:?}", e);
-                    // Continue anyway, tags are optional
-                }
+            spawn_budget_alerts(
+                payment.user_id.clone(),
+                payment.accounting_date.date(),
+                connection_pool.get_ref().clone(),
+                notifier.into_inner(),
+            );
+
+            if let Err(e) = insert_payment_tags(
+                payment_id,
+                &tags,
+                connection_pool.get_ref(),
+                payment.user_id.as_str(),
+            )
+            .await
+            {
+                tracing::error!("Failed to insert tags: {:?}", e);
+                // Continue anyway, tags are optional
             }
 
             // Fetch wallet name if wallet_id is provided
@@ -236,6 +295,13 @@ pub async fn create_payment(
             )
             .await
             .unwrap_or_default();
+            let response_splits = get_payment_splits(
+                payment_id,
+                connection_pool.get_ref(),
+                payment.user_id.as_str(),
+            )
+            .await
+            .unwrap_or_default();
 
             // Fetch category name and icon in a single query to avoid duplicate DB hits
             let (category_name, category_icon): (String, Option<String>) = match sqlx::query!(
@@ -259,7 +325,8 @@ pub async fn create_payment(
             let response = PaymentResponseDto {
                 id: payment_id,
                 description: payment.description.as_ref().map(|d| d.as_ref().to_string()),
-                amount_in_cents: payment.amount_in_cents,
+                amount_in_cents: payment.amount.amount(),
+                currency: payment.amount.currency().as_ref().to_string(),
                 merchant_name: payment.merchant_name.as_ref().to_string(),
                 accounting_date: payment.accounting_date,
                 category: category_name,
@@ -267,6 +334,8 @@ pub async fn create_payment(
                 category_icon,
                 wallet: wallet_name,
                 tags: response_tags,
+                transfer_id: None,
+                splits: response_splits,
             };
 
             HttpResponse::Ok().json(response)
@@ -283,26 +352,193 @@ pub async fn create_payment(
     skip(payment, connection_pool)
 )]
 async fn insert_payment(payment: &Payment, connection_pool: &PgPool) -> Result<Uuid, Error> {
+    let mut tx = connection_pool.begin().await?;
+    let id = insert_payment_in_transaction(&mut tx, payment).await?;
+    tx.commit().await?;
+    Ok(id)
+}
+
+/// Inserts a payment and its splits as part of a larger transaction (e.g. statement imports).
+/// The payment is attached to its canonical merchant, and named after it.
+pub(crate) async fn insert_payment_in_transaction(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    payment: &Payment,
+) -> Result<Uuid, Error> {
+    let merchant =
+        resolve_merchant(tx, payment.user_id.as_str(), payment.merchant_name.as_ref()).await?;
     let row = sqlx::query(
-        "insert into expenses.payments (category_id, description, merchant_name, accounting_date, amount, wallet_id, user_id) values ($1, $2, $3, $4, $5, $6, $7) RETURNING id",
+        "insert into expenses.payments (category_id, description, merchant_name, accounting_date, amount, wallet_id, user_id, currency, merchant_id) values ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING id",
     )
     .bind(payment.category_id)
     .bind(payment.description.as_ref().map(|d| d.as_ref()))
-    .bind(payment.merchant_name.as_ref())
+    .bind(merchant.name)
     .bind(payment.accounting_date)
-    .bind(payment.amount_in_cents)
+    .bind(payment.amount.amount().cents())
     .bind(payment.wallet_id)
     .bind(payment.user_id.as_str())
-    .fetch_one(connection_pool)
+    .bind(payment.amount.currency().as_ref())
+    .bind(merchant.id)
+    .fetch_one(&mut **tx)
     .await
     .map_err(|e| {
         tracing::error!("Failed to execute query: {:?}", e);
         e
     })?;
     let id: Uuid = row.try_get("id")?;
+    replace_payment_splits(tx, id, &payment.splits, payment.user_id.as_str()).await?;
     Ok(id)
 }
 
+/// Replaces the splits of a payment with `splits`, keeping their order.
+#[tracing::instrument(name = "Replacing payment splits", skip(tx, splits))]
+async fn replace_payment_splits(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    payment_id: Uuid,
+    splits: &[PaymentSplit],
+    user_id: &str,
+) -> Result<(), Error> {
+    sqlx::query!(
+        "DELETE FROM expenses.payment_splits WHERE payment_id = $1 AND user_id = $2",
+        payment_id,
+        user_id
+    )
+    .execute(&mut **tx)
+    .await?;
+    if splits.is_empty() {
+        return Ok(());
+    }
+
+    let category_ids: Vec<Uuid> = splits.iter().map(|split| split.category_id).collect();
+    let amounts: Vec<i64> = splits.iter().map(|split| split.amount.cents()).collect();
+    let descriptions: Vec<Option<String>> = splits
+        .iter()
+        .map(|split| split.description.as_ref().map(|d| d.as_ref().to_string()))
+        .collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO expenses.payment_splits (payment_id, category_id, amount, description, position, user_id)
+        SELECT $1, s.category_id, s.amount, s.description, s.position::int, $5
+        FROM UNNEST($2::uuid[], $3::bigint[], $4::varchar[])
+            WITH ORDINALITY AS s(category_id, amount, description, position)
+        "#,
+        payment_id,
+        &category_ids,
+        &amounts,
+        &descriptions as &[Option<String>],
+        user_id
+    )
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to insert payment splits: {:?}", e);
+        e
+    })?;
+    Ok(())
+}
+
+/// Why a category given by id or name could not be resolved.
+#[derive(Debug)]
+pub(crate) enum CategoryResolutionError {
+    /// The unknown name is not a valid name for a new category.
+    InvalidName(String),
+    Database(Error),
+}
+
+impl From<Error> for CategoryResolutionError {
+    fn from(e: Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+/// Resolves a category given by id or name among the categories of the user and
+/// the global defaults. Names match the user's own categories first; unknown names
+/// create a category of the user, when they are valid category names. Unknown ids,
+/// and ids of the categories of other users, resolve to `None`.
+#[tracing::instrument(name = "Resolving category", skip(executor))]
+pub(crate) async fn resolve_category_id(
+    identifier: &CategoryIdentifier,
+    user_id: &str,
+    executor: impl sqlx::PgExecutor<'_>,
+) -> Result<Option<Uuid>, CategoryResolutionError> {
+    match identifier {
+        CategoryIdentifier::Uid(_) => Ok(find_category_id(identifier, user_id, executor).await?),
+        CategoryIdentifier::Name(name) if name.trim().is_empty() => Ok(None),
+        CategoryIdentifier::Name(name) => {
+            // Existing categories may predate the validation of names: only new ones are checked
+            let parsed = PaymentCategory::parse(name.trim().to_string());
+            // A single statement, so that callers may resolve within their transaction.
+            // The no-op update returns the category created concurrently under the
+            // same name, which the unique name per user makes conflict.
+            let id = sqlx::query_scalar!(
+                r#"
+                WITH existing AS (
+                    SELECT id FROM expenses.categories
+                    WHERE lower(name) = lower($2) AND (user_id = $1 OR user_id IS NULL)
+                    ORDER BY user_id NULLS LAST
+                    LIMIT 1
+                ), created AS (
+                    INSERT INTO expenses.categories (user_id, name)
+                    SELECT $1, $2 WHERE $3 AND NOT EXISTS (SELECT 1 FROM existing)
+                    ON CONFLICT (user_id, lower(name)) WHERE user_id IS NOT NULL
+                    DO UPDATE SET name = expenses.categories.name
+                    RETURNING id
+                )
+                SELECT id as "id!" FROM existing
+                UNION ALL
+                SELECT id as "id!" FROM created
+                "#,
+                user_id,
+                name.trim(),
+                parsed.is_ok()
+            )
+            .fetch_optional(executor)
+            .await?;
+            match (id, parsed) {
+                (None, Err(e)) => Err(CategoryResolutionError::InvalidName(e)),
+                (id, _) => Ok(id),
+            }
+        }
+    }
+}
+
+/// Looks up a category given by id or name like `resolve_category_id`, without
+/// ever creating one: unknown names resolve to `None`.
+#[tracing::instrument(name = "Looking up category", skip(executor))]
+pub(crate) async fn find_category_id(
+    identifier: &CategoryIdentifier,
+    user_id: &str,
+    executor: impl sqlx::PgExecutor<'_>,
+) -> Result<Option<Uuid>, Error> {
+    match identifier {
+        CategoryIdentifier::Uid(uid) => {
+            sqlx::query_scalar!(
+                r#"
+                SELECT id FROM expenses.categories
+                WHERE id = $1 AND (user_id = $2 OR user_id IS NULL)
+                "#,
+                uid,
+                user_id
+            )
+            .fetch_optional(executor)
+            .await
+        }
+        CategoryIdentifier::Name(name) => {
+            sqlx::query_scalar!(
+                r#"
+                SELECT id FROM expenses.categories
+                WHERE lower(name) = lower($2) AND (user_id = $1 OR user_id IS NULL)
+                ORDER BY user_id NULLS LAST
+                LIMIT 1
+                "#,
+                user_id,
+                name.trim()
+            )
+            .fetch_optional(executor)
+            .await
+        }
+    }
+}
+
 #[tracing::instrument(
     name = "Deleting a payment",
     skip(path, connection_pool),
@@ -336,13 +572,30 @@ async fn delete_payment_query(
     payment_id: Uuid,
     user_id: &str,
 ) -> Result<(), Error> {
+    // Deleting one leg of a transfer deletes the other one as well
+    let mut tx = connection_pool.begin().await?;
+    let payment_ids = sqlx::query_scalar!(
+        r#"
+        SELECT p.id
+        FROM expenses.payments p
+        WHERE p.user_id = $2
+          AND (p.id = $1 OR p.transfer_id = (
+              SELECT transfer_id FROM expenses.payments WHERE id = $1 AND user_id = $2
+          ))
+        "#,
+        payment_id,
+        user_id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
     // First, delete all associated tags to avoid foreign key constraint violation
     sqlx::query!(
-        "delete from expenses.payments_tags where payment_id = $1 AND user_id = $2",
-        payment_id,
+        "delete from expenses.payments_tags where payment_id = ANY($1) AND user_id = $2",
+        &payment_ids,
         user_id
     )
-    .execute(connection_pool)
+    .execute(&mut *tx)
     .await
     .map_err(|e| {
         tracing::error!("Failed to delete payment tags: {:?}", e);
@@ -351,22 +604,23 @@ async fn delete_payment_query(
 
     // Then delete the payment itself
     sqlx::query!(
-        "delete from expenses.payments where id = $1 AND user_id = $2",
-        payment_id,
+        "delete from expenses.payments where id = ANY($1) AND user_id = $2",
+        &payment_ids,
         user_id
     )
-    .execute(connection_pool)
+    .execute(&mut *tx)
     .await
     .map_err(|e| {
         tracing::error!("Failed to delete payment: {:?}", e);
         e
     })?;
+    tx.commit().await?;
     Ok(())
 }
 
 #[tracing::instrument(
     name = "Updating a payment",
-    skip(path, payload, connection_pool),
+    skip(path, payload, connection_pool, notifier),
     fields(
         payment_id = %path.clone(),
         merchant_name = %payload.merchant_name,
@@ -378,19 +632,23 @@ pub async fn update_payment(
     payload: Json<PaymentDto>,
     user: crate::auth::AuthenticatedUser,
     connection_pool: web::Data<PgPool>,
+    notifier: web::Data<dyn Notifier>,
 ) -> impl Responder {
     let payment_id = path.into_inner();
     let user_id = user.sub;
-    let tags = payload.0.tags.clone();
+    let tags = match parse_tags(payload.0.tags.clone()) {
+        Ok(tags) => tags,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
     let wallet_name_input = payload.0.wallet.clone();
 
     // Audit log: log payment modification
     tracing::info!("Updating payment with id: {}", payment_id);
 
-    // Resolve wallet_id from wallet name
-    let wallet_id = if let Some(name) = &wallet_name_input {
-        match get_wallet_id_by_name(name, connection_pool.get_ref(), &user_id).await {
-            Ok(Some(id)) => Some(id),
+    // Resolve wallet from wallet name
+    let wallet = if let Some(name) = &wallet_name_input {
+        match get_wallet_by_name(name, connection_pool.get_ref(), &user_id).await {
+            Ok(Some(wallet)) => Some(wallet),
             Ok(None) => {
                 tracing::error!("Wallet not found: {}", name);
                 return HttpResponse::BadRequest().body(format!("Wallet '{}' not found", name));
@@ -403,97 +661,71 @@ pub async fn update_payment(
     } else {
         None
     };
+    let wallet_id = wallet.as_ref().and_then(|w| w.id);
+    // Payments without an explicit currency are in the wallet's default currency
+    let default_currency = wallet.map(|w| w.default_currency).unwrap_or_default();
 
     // Create payment with resolved wallet_id
     let payment_data = payload.0;
 
-    // Resolve category identifier to canonical UUID
-    let resolved_category_id: Uuid = match payment_data.category_id.clone() {
-        CategoryIdentifier::Uid(uid) => uid,
-        CategoryIdentifier::Name(name) => {
-            match sqlx::query_scalar!(
-                "SELECT id FROM expenses.categories WHERE LOWER(name) = LOWER($1)",
-                name
-            )
-            .fetch_optional(connection_pool.get_ref())
-            .await
-            {
-                Ok(Some(id)) => id,
-                Ok(None) => {
-                    // Category not found: create it and return new id
-                    match sqlx::query_scalar!(
-                        "INSERT INTO expenses.categories (name) VALUES ($1) RETURNING id",
-                        name
-                    )
-                    .fetch_one(connection_pool.get_ref())
-                    .await
-                    {
-                        Ok(new_id) => new_id,
-                        Err(insert_err) => {
-                            tracing::warn!(
-                                "Failed to insert category (maybe concurrent): {:?}",
-                                insert_err
-                            );
-                            match sqlx::query_scalar!(
-                                "SELECT id FROM expenses.categories WHERE LOWER(name) = LOWER($1)",
-                                name
-                            )
-                            .fetch_optional(connection_pool.get_ref())
-                            .await
-                            {
-                                Ok(Some(id)) => id,
-                                Ok(None) => {
-                                    tracing::error!(
-                                        "Failed to create category and it does not exist: {}",
-                                        name
-                                    );
-                                    return HttpResponse::InternalServerError().finish();
-                                }
-                                Err(e) => {
-                                    tracing::error!("Failed to lookup category by name after insert error: {:?}", e);
-                                    return HttpResponse::InternalServerError().finish();
-                                }
-                            }
-                        }
-                    }
-                }
-                Err(e) => {
-                    tracing::error!("Failed to lookup category by name: {:?}", e);
-                    return HttpResponse::InternalServerError().finish();
-                }
-            }
-        }
-    };
-
-    // Validate category exists when UUID was provided by client
-    match sqlx::query_scalar!(
-        "SELECT id FROM expenses.categories WHERE id = $1",
-        resolved_category_id
+    // Accept either the canonical `categoryId` (UUID) or a category name
+    let resolved_category_id = match resolve_category_id(
+        &payment_data.category_id,
+        &user_id,
+        connection_pool.get_ref(),
     )
-    .fetch_optional(connection_pool.get_ref())
     .await
     {
-        Ok(Some(_)) => {}
+        Ok(Some(id)) => id,
         Ok(None) => return HttpResponse::BadRequest().body("categoryId not found"),
-        Err(e) => {
-            tracing::error!("Failed to validate category: {:?}", e);
+        Err(CategoryResolutionError::InvalidName(e)) => return HttpResponse::BadRequest().body(e),
+        Err(CategoryResolutionError::Database(e)) => {
+            tracing::error!("Failed to resolve category: {:?}", e);
             return HttpResponse::InternalServerError().finish();
         }
     };
 
-    let payment = match Payment::try_from_dto(
+    let mut split_category_ids = Vec::new();
+    for split in payment_data.splits.iter().flatten() {
+        match resolve_category_id(&split.category_id, &user_id, connection_pool.get_ref()).await {
+            Ok(Some(id)) => split_category_ids.push(id),
+            Ok(None) => return HttpResponse::BadRequest().body("split categoryId not found"),
+            Err(CategoryResolutionError::InvalidName(e)) => {
+                return HttpResponse::BadRequest().body(e)
+            }
+            Err(CategoryResolutionError::Database(e)) => {
+                tracing::error!("Failed to resolve split category: {:?}", e);
+                return HttpResponse::InternalServerError().finish();
+            }
+        }
+    }
+
+    let mut payment = match Payment::try_from_dto(
         payment_data,
         wallet_id,
+        default_currency,
         resolved_category_id,
+        split_category_ids,
         user_id.clone(),
     ) {
         Ok(payment) => payment,
-        Err(_) => return HttpResponse::BadRequest().finish(),
+        Err(e) => return HttpResponse::BadRequest().body(e),
     };
+    if let Err(e) = use_canonical_merchant_name(&mut payment, connection_pool.get_ref()).await {
+        tracing::error!("Failed to resolve the merchant: {:?}", e);
+        return HttpResponse::InternalServerError().finish();
+    }
 
     // Update payment in database
     match update_payment_query(&payment, payment_id, connection_pool.get_ref()).await {
-        Ok(_) => {
+        Ok(transfer_id) => {
+            spawn_budget_alerts(
+                user_id.clone(),
+                payment.accounting_date.date(),
+                connection_pool.get_ref().clone(),
+                notifier.into_inner(),
+            );
+
             // Delete existing tags
             if let Err(e) =
                 delete_payment_tags(payment_id, connection_pool.get_ref(), user_id.as_str()).await
@@ -502,19 +734,16 @@ pub async fn update_payment(
                 return HttpResponse::InternalServerError().finish();
             }
 
-            // Insert new tags if provided
-            if let Some(tags) = tags {
-                if let Err(e) = insert_payment_tags(
-                    payment_id,
-                    tags,
-                    connection_pool.get_ref(),
-                    user_id.as_str(),
-                )
-                .await
-                {
-                    tracing::error!("Failed to insert tags: {:?}", e);
-                    return HttpResponse::InternalServerError().finish();
-                }
+            if let Err(e) = insert_payment_tags(
+                payment_id,
+                &tags,
+                connection_pool.get_ref(),
+                user_id.as_str(),
+            )
+            .await
+            {
+                tracing::error!("Failed to insert tags: {:?}", e);
+                return HttpResponse::InternalServerError().finish();
             }
 
             // Fetch wallet name if wallet_id is provided
@@ -532,6 +761,10 @@ pub async fn update_payment(
                 get_payment_tags(payment_id, connection_pool.get_ref(), user_id.as_str())
                     .await
                     .unwrap_or_default();
+            let response_splits =
+                get_payment_splits(payment_id, connection_pool.get_ref(), user_id.as_str())
+                    .await
+                    .unwrap_or_default();
 
             // Fetch category name and icon in a single query to avoid duplicate DB hits
             let (category_name, category_icon): (String, Option<String>) = match sqlx::query!(
@@ -555,7 +788,8 @@ pub async fn update_payment(
             let response = PaymentResponseDto {
                 id: payment_id,
                 description: payment.description.as_ref().map(|d| d.as_ref().to_string()),
-                amount_in_cents: payment.amount_in_cents,
+                amount_in_cents: payment.amount.amount(),
+                currency: payment.amount.currency().as_ref().to_string(),
                 merchant_name: payment.merchant_name.as_ref().to_string(),
                 accounting_date: payment.accounting_date,
                 category: category_name,
@@ -563,6 +797,8 @@ pub async fn update_payment(
                 category_icon,
                 wallet: wallet_name,
                 tags: response_tags,
+                transfer_id,
+                splits: response_splits,
             };
 
             tracing::info!("Successfully updated payment: {}", payment_id);
@@ -575,13 +811,17 @@ pub async fn update_payment(
     }
 }
 
+/// Updates the payment and, when it is a leg of a transfer, mirrors the amount,
+/// currency, date and description on the other leg.
+/// Returns the transfer id of the payment, if any.
 #[tracing::instrument(name = "Updating payment in database", skip(payment, connection_pool))]
 async fn update_payment_query(
     payment: &Payment,
     payment_id: Uuid,
     connection_pool: &PgPool,
-) -> Result<(), Error> {
-    sqlx::query(
+) -> Result<Option<Uuid>, Error> {
+    let mut tx = connection_pool.begin().await?;
+    let transfer_id: Option<Option<Uuid>> = sqlx::query_scalar(
         r#"
         UPDATE expenses.payments
         SET category_id = $1,
@@ -589,25 +829,86 @@ async fn update_payment_query(
                 merchant_name = $3,
                 accounting_date = $4,
                 amount = $5,
-                wallet_id = $6
+                wallet_id = $6,
+                currency = $9
             WHERE id = $7 AND user_id = $8
+            RETURNING transfer_id
         "#,
     )
     .bind(payment.category_id)
     .bind(payment.description.as_ref().map(|d| d.as_ref()))
     .bind(payment.merchant_name.as_ref())
     .bind(payment.accounting_date)
-    .bind(payment.amount_in_cents)
+    .bind(payment.amount.amount().cents())
     .bind(payment.wallet_id)
     .bind(payment_id)
     .bind(payment.user_id.as_str())
-    .execute(connection_pool)
+    .bind(payment.amount.currency().as_ref())
+    .fetch_optional(&mut *tx)
     .await
     .map_err(|e| {
         tracing::error!("Failed to execute update query: {:?}", e);
         e
     })?;
-    Ok(())
+
+    // Transfer legs name a wallet, not a merchant
+    if transfer_id == Some(None) {
+        let merchant = resolve_merchant(
+            &mut tx,
+            payment.user_id.as_str(),
+            payment.merchant_name.as_ref(),
+        )
+        .await?;
+        sqlx::query!(
+            r#"
+            UPDATE expenses.payments SET merchant_id = $3, merchant_name = $4
+            WHERE id = $1 AND user_id = $2
+            "#,
+            payment_id,
+            payment.user_id.as_str(),
+            merchant.id,
+            merchant.name
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    if transfer_id.is_some() {
+        replace_payment_splits(
+            &mut tx,
+            payment_id,
+            &payment.splits,
+            payment.user_id.as_str(),
+        )
+        .await?;
+    }
+    let transfer_id = transfer_id.flatten();
+    if let Some(transfer_id) = transfer_id {
+        sqlx::query!(
+            r#"
+            UPDATE expenses.payments
+            SET amount = -$1::bigint,
+                currency = $2,
+                accounting_date = $3,
+                description = $4
+            WHERE transfer_id = $5 AND id <> $6 AND user_id = $7
+            "#,
+            payment.amount.amount().cents(),
+            payment.amount.currency().as_ref(),
+            payment.accounting_date,
+            payment.description.as_ref().map(|d| d.as_ref()),
+            transfer_id,
+            payment_id,
+            payment.user_id.as_str()
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to update the linked transfer payment: {:?}", e);
+            e
+        })?;
+    }
+    tx.commit().await?;
+    Ok(transfer_id)
 }
 
 #[tracing::instrument(name = "Deleting payment tags", skip(connection_pool))]
@@ -631,76 +932,36 @@ async fn delete_payment_tags(
 }
 
 /*
- categories
+ get recent payments (paginated)
 */
-#[derive(Deserialize, Debug)]
-pub struct CategoryQuery {
-    #[serde(rename = "type")]
-    category_type: Option<String>,
-}
-
-use serde::Serialize as SerSerialize;
-
-#[derive(SerSerialize)]
-pub struct CategoryDto {
-    #[serde(rename = "id")]
-    pub id: Uuid,
-    pub name: String,
-    #[serde(rename = "icon", skip_serializing_if = "Option::is_none")]
-    pub icon: Option<String>,
-}
-
-#[tracing::instrument(name = "Retrieve all categories", skip(connection_pool))]
-pub async fn get_categories(
-    connection_pool: web::Data<PgPool>,
-    query: web::Query<CategoryQuery>,
-) -> impl Responder {
-    match get_categories_from_db(connection_pool.get_ref(), query.category_type.as_deref()).await {
-        Ok(categories) => HttpResponse::Ok().json(categories),
-        Err(e) => {
-            tracing::error!("Failed to execute query: {:?}", e);
-            HttpResponse::InternalServerError().finish()
-        }
-    }
-}
 
-#[tracing::instrument(
-    name = "Retrieving all categories from database",
-    skip(connection_pool)
-)]
-async fn get_categories_from_db(
+/// Ids of the categories of the user or global defaults matching the filter,
+/// by id or name, and of all their subcategories.
+async fn category_subtree(
     connection_pool: &PgPool,
-    category_type: Option<&str>,
-) -> Result<Vec<CategoryDto>, Error> {
-    // Build query to return category name and optional icon
-    let query = match category_type {
-        Some("expense") => {
-            "select c.id, c.name, c.icon from expenses.categories c where c.kind = 'expense'"
-        }
-        Some("income") => {
-            "select c.id, c.name, c.icon from expenses.categories c where c.kind = 'income'"
-        }
-        _ => "select c.id, c.name, c.icon from expenses.categories c",
-    };
-
-    let rows = sqlx::query_as::<_, (Uuid, String, Option<String>)>(query)
-        .fetch_all(connection_pool)
-        .await
-        .map_err(|e| {
-            tracing::error!("Failed to execute query: {:?}", e);
-            e
-        })?;
-    let categories = rows
-        .into_iter()
-        .map(|(id, name, icon)| CategoryDto { id, name, icon })
-        .collect();
-    Ok(categories)
+    user_id: &str,
+    category: &str,
+) -> Result<Vec<Uuid>, Error> {
+    sqlx::query_scalar!(
+        r#"
+        WITH RECURSIVE subtree AS (
+            SELECT id FROM expenses.categories
+            WHERE (user_id = $1 OR user_id IS NULL)
+              AND (id::text = lower($2) OR lower(name) = lower($2))
+            UNION
+            SELECT c.id FROM expenses.categories c
+            JOIN subtree s ON c.parent_id = s.id
+            WHERE c.user_id = $1 OR c.user_id IS NULL
+        )
+        SELECT id as "id!" FROM subtree
+        "#,
+        user_id,
+        category
+    )
+    .fetch_all(connection_pool)
+    .await
 }
 
-/*
- get recent payments (paginated)
-*/
-
 #[derive(Deserialize)]
 pub struct PaginationParams {
     #[serde(default)]
@@ -712,8 +973,24 @@ pub struct PaginationParams {
     #[serde(rename = "dateTo")]
     date_to: Option<String>,
     category: Option<String>,
+    /// Also match the subcategories of `category`.
+    #[serde(rename = "includeChildren", default)]
+    include_children: bool,
     wallet: Option<String>,
     search: Option<String>,
+    /// How the repeatable `tag=key:value` filters combine; they are read from
+    /// the raw query string as serde cannot deserialize repeated keys here.
+    #[serde(rename = "tagMode", default)]
+    tag_mode: TagMode,
+}
+
+/// Whether payments must carry all the filtered tags or any of them.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum TagMode {
+    #[default]
+    All,
+    Any,
 }
 
 #[derive(Clone, Debug)]
@@ -721,8 +998,11 @@ struct PaymentFilters {
     date_from: Option<String>,
     date_to: Option<String>,
     category: Option<String>,
+    include_children: bool,
     wallet: Option<String>,
     search: Option<String>,
+    tags: Vec<Tag>,
+    tag_mode: TagMode,
 }
 
 impl From<&PaginationParams> for PaymentFilters {
@@ -731,12 +1011,37 @@ impl From<&PaginationParams> for PaymentFilters {
             date_from: params.date_from.clone(),
             date_to: params.date_to.clone(),
             category: params.category.clone(),
+            include_children: params.include_children,
             wallet: params.wallet.clone(),
             search: params.search.clone(),
+            tags: Vec::new(),
+            tag_mode: params.tag_mode,
         }
     }
 }
 
+/// The `tag=key:value` filters of a query string, without repetitions.
+pub(crate) fn parse_tag_filters(query_string: &str) -> Result<Vec<Tag>, String> {
+    let pairs =
+        web::Query::<Vec<(String, String)>>::from_query(query_string).map_err(|e| e.to_string())?;
+    let tags = pairs
+        .into_inner()
+        .into_iter()
+        .filter(|(name, _)| name == "tag")
+        .map(|(_, tag)| Tag::parse_key_value(&tag))
+        .collect::<Result<Vec<_>, String>>()?;
+    let mut filters: Vec<Tag> = Vec::new();
+    for tag in tags {
+        if !filters
+            .iter()
+            .any(|t| t.key == tag.key && t.value == tag.value)
+        {
+            filters.push(tag);
+        }
+    }
+    Ok(filters)
+}
+
 fn default_size() -> i64 {
     10
 }
@@ -756,7 +1061,8 @@ pub struct PaymentResponseDto {
     #[serde(skip_serializing_if = "Option::is_none")]
     description: Option<String>,
     #[serde(rename = "amountInCents")]
-    amount_in_cents: i32,
+    amount_in_cents: AmountInCents,
+    currency: String,
     #[serde(rename = "merchantName")]
     merchant_name: String,
     #[serde(rename = "accountingDate")]
@@ -771,24 +1077,54 @@ pub struct PaymentResponseDto {
     #[serde(skip_serializing_if = "Option::is_none")]
     wallet: Option<String>,
     tags: Vec<TagResponseDto>,
+    /// Set on both legs of a transfer between wallets.
+    #[serde(rename = "transferId")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    transfer_id: Option<Uuid>,
+    splits: Vec<PaymentSplitResponseDto>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PaymentSplitResponseDto {
+    id: Uuid,
+    #[serde(rename = "categoryId")]
+    category_id: Uuid,
+    category: String,
+    #[serde(rename = "amountInCents")]
+    amount_in_cents: AmountInCents,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
 }
 
 #[derive(Serialize)]
 pub struct PagedResponse<T> {
-    content: Vec<T>,
-    page: i64,
-    size: i64,
+    pub(crate) content: Vec<T>,
+    pub(crate) page: i64,
+    pub(crate) size: i64,
 }
 
 #[tracing::instrument(name = "Retrieve recent payments", skip(connection_pool, params))]
 pub async fn get_recent_payments(
+    request: HttpRequest,
     params: web::Query<PaginationParams>,
+    user: crate::auth::AuthenticatedUser,
     connection_pool: web::Data<PgPool>,
 ) -> impl Responder {
     let offset = params.page * params.size;
-    let filters = PaymentFilters::from(params.deref());
+    let mut filters = PaymentFilters::from(params.deref());
+    filters.tags = match parse_tag_filters(request.query_string()) {
+        Ok(tags) => tags,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
 
-    match get_recent_payments_from_db(connection_pool.get_ref(), params.size, offset, filters).await
+    match get_recent_payments_from_db(
+        connection_pool.get_ref(),
+        &user.sub,
+        params.size,
+        offset,
+        filters,
+    )
+    .await
     {
         Ok(payments) => {
             let response = PagedResponse {
@@ -811,13 +1147,15 @@ pub async fn get_recent_payments(
 )]
 async fn get_recent_payments_from_db(
     connection_pool: &PgPool,
+    user_id: &str,
     limit: i64,
     offset: i64,
     filters: PaymentFilters,
 ) -> Result<Vec<PaymentResponseDto>, Error> {
-    // Build dynamic WHERE clause conditions with proper parameter indexing
-    let mut conditions = Vec::new();
-    let mut param_index = 3; // Start after limit ($1) and offset ($2)
+    // Build dynamic WHERE clause conditions with proper parameter indexing.
+    // Ownership is always enforced: every filter only narrows the user's own payments.
+    let mut conditions = vec!["p.user_id = $3".to_string()];
+    let mut param_index = 4; // Start after limit ($1), offset ($2) and user_id ($3)
 
     let date_from_param_idx = if filters.date_from.is_some() {
         let idx = param_index;
@@ -837,18 +1175,36 @@ async fn get_recent_payments_from_db(
         None
     };
 
+    // With `includeChildren`, the category and its subcategories are resolved upfront.
+    let category_subtree = match (&filters.category, filters.include_children) {
+        (Some(category), true) => Some(category_subtree(connection_pool, user_id, category).await?),
+        _ => None,
+    };
+
     let category_param_idx = if filters.category.is_some() {
         let idx = param_index;
-        // If the provided category filter is a UUID, filter by category_id, otherwise filter by category name
-        if filters
+        // If the provided category filter is a UUID, filter by category_id, otherwise filter by category name.
+        // Split payments match on the categories of their splits.
+        if category_subtree.is_some() {
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM expenses.payment_lines l WHERE l.payment_id = p.id AND l.category_id = ANY(${}))",
+                idx
+            ));
+        } else if filters
             .category
             .as_ref()
             .and_then(|s| s.parse::<Uuid>().ok())
             .is_some()
         {
-            conditions.push(format!("p.category_id = ${}", idx));
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM expenses.payment_lines l WHERE l.payment_id = p.id AND l.category_id = ${})",
+                idx
+            ));
         } else {
-            conditions.push(format!("LOWER(c.name) = LOWER(${})", idx));
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM expenses.payment_lines l JOIN expenses.categories lc ON lc.id = l.category_id WHERE l.payment_id = p.id AND LOWER(lc.name) = LOWER(${}))",
+                idx
+            ));
         }
         param_index += 1;
         Some(idx)
@@ -871,17 +1227,36 @@ async fn get_recent_payments_from_db(
             "(LOWER(p.merchant_name) LIKE ${} OR LOWER(p.description) LIKE ${})",
             idx, idx
         ));
+        param_index += 1;
         Some(idx)
     } else {
         None
     };
 
-    let where_clause = if conditions.is_empty() {
-        String::new()
+    // Tag filters are bound as two arrays of keys and values.
+    let tags_param_idx = if filters.tags.is_empty() {
+        None
     } else {
-        format!("WHERE {}", conditions.join(" AND "))
+        let idx = param_index;
+        let matching_tags = format!(
+            "SELECT DISTINCT t.key, t.value FROM expenses.payments_tags t \
+             JOIN unnest(${}::text[], ${}::text[]) f(key, value) ON f.key = t.key AND f.value = t.value \
+             WHERE t.payment_id = p.id AND t.user_id = p.user_id",
+            idx,
+            idx + 1
+        );
+        conditions.push(match filters.tag_mode {
+            TagMode::All => format!(
+                "(SELECT COUNT(*) FROM ({}) m) = cardinality(${})",
+                matching_tags, idx
+            ),
+            TagMode::Any => format!("EXISTS ({})", matching_tags),
+        });
+        Some(idx)
     };
 
+    let where_clause = format!("WHERE {}", conditions.join(" AND "));
+
     let query_str = format!(
         r#"
          SELECT p.id,
@@ -892,13 +1267,23 @@ async fn get_recent_payments_from_db(
                p.merchant_name,
                p.accounting_date,
                p.amount,
+               p.currency,
                w.name as wallet_name,
                COALESCE((SELECT json_agg(
                    json_build_object('id', pt.id, 'key', pt.key, 'value', pt.value)
-               ) FROM expenses.payments_tags pt WHERE pt.payment_id = p.id), '[]'::json) as tags
+               ) FROM expenses.payments_tags pt
+                 WHERE pt.payment_id = p.id AND pt.user_id = p.user_id), '[]'::json) as tags,
+               p.transfer_id,
+               COALESCE((SELECT json_agg(
+                   json_build_object('id', s.id, 'categoryId', s.category_id, 'category', sc.name,
+                                     'amountInCents', s.amount, 'description', s.description)
+                   ORDER BY s.position
+               ) FROM expenses.payment_splits s
+                 JOIN expenses.categories sc ON sc.id = s.category_id
+                 WHERE s.payment_id = p.id AND s.user_id = p.user_id), '[]'::json) as splits
         FROM expenses.payments p
         LEFT JOIN expenses.categories c ON p.category_id = c.id
-        LEFT JOIN expenses.wallets w ON p.wallet_id = w.id
+        LEFT JOIN expenses.wallets w ON p.wallet_id = w.id AND w.user_id = p.user_id
         {}
         ORDER BY p.accounting_date DESC
         LIMIT $1 OFFSET $2
@@ -917,13 +1302,17 @@ async fn get_recent_payments_from_db(
             Option<String>, // description
             Option<String>, // merchant_name
             Option<NaiveDateTime>,
-            Option<i32>,
+            Option<i64>,
+            String,            // currency
             Option<String>,    // wallet_name
             serde_json::Value, // tags as JSON array
+            Option<Uuid>,      // transfer_id
+            serde_json::Value, // splits as JSON array
         ),
     >(&query_str)
     .bind(limit)
-    .bind(offset);
+    .bind(offset)
+    .bind(user_id);
 
     if let (Some(_), Some(df)) = (date_from_param_idx, &filters.date_from) {
         query = query.bind(df);
@@ -933,7 +1322,9 @@ async fn get_recent_payments_from_db(
     }
     if let (Some(_), Some(cat)) = (category_param_idx, &filters.category) {
         // Bind as UUID when possible, otherwise bind as string
-        if let Ok(uid) = cat.parse::<Uuid>() {
+        if let Some(subtree) = category_subtree {
+            query = query.bind(subtree);
+        } else if let Ok(uid) = cat.parse::<Uuid>() {
             query = query.bind(uid);
         } else {
             query = query.bind(cat.clone());
@@ -946,6 +1337,14 @@ async fn get_recent_payments_from_db(
         let search_pattern = format!("%{}%", s.to_lowercase());
         query = query.bind(search_pattern);
     }
+    if tags_param_idx.is_some() {
+        let (keys, values): (Vec<String>, Vec<String>) = filters
+            .tags
+            .iter()
+            .map(|tag| (tag.key.as_ref().to_string(), tag.value.as_ref().to_string()))
+            .unzip();
+        query = query.bind(keys).bind(values);
+    }
 
     let records = query.fetch_all(connection_pool).await.map_err(|e| {
         tracing::error!("Failed to execute query: {:?}", e);
@@ -955,7 +1354,8 @@ async fn get_recent_payments_from_db(
     let mut result = Vec::new();
     for record in records {
         let payment_id = record.0;
-        let tags_json = record.9;
+        let tags_json = record.10;
+        let splits = parse_splits(payment_id, record.12);
 
         let tags: Vec<TagResponseDto> = match serde_json::from_value(tags_json) {
             Ok(tags) => tags,
@@ -968,14 +1368,17 @@ async fn get_recent_payments_from_db(
         result.push(PaymentResponseDto {
             id: payment_id,
             description: record.4,
-            amount_in_cents: record.7.unwrap_or(0),
+            amount_in_cents: record.7.map(AmountInCents::new).unwrap_or_default(),
+            currency: record.8,
             merchant_name: record.5.unwrap_or_default(),
             accounting_date: record.6.unwrap_or_default(),
             category: record.1.unwrap_or_default(),
             category_id: record.3,
             category_icon: record.2,
-            wallet: record.8,
+            wallet: record.9,
             tags,
+            transfer_id: record.11,
+            splits,
         });
     }
 
@@ -986,10 +1389,25 @@ async fn get_recent_payments_from_db(
  Helper functions for tags
 */
 
+/// Validates the tags of a payment, dropping repeated key and value pairs.
+pub(crate) fn parse_tags(tags: Option<Vec<TagDto>>) -> Result<Vec<Tag>, String> {
+    let mut parsed: Vec<Tag> = Vec::new();
+    for tag in tags.unwrap_or_default() {
+        let tag = Tag::parse(tag.key, tag.value)?;
+        if !parsed
+            .iter()
+            .any(|t| t.key == tag.key && t.value == tag.value)
+        {
+            parsed.push(tag);
+        }
+    }
+    Ok(parsed)
+}
+
 #[tracing::instrument(name = "Inserting payment tags", skip(connection_pool))]
 async fn insert_payment_tags(
     payment_id: Uuid,
-    tags: Vec<TagDto>,
+    tags: &[Tag],
     connection_pool: &PgPool,
     user_id: &str,
 ) -> Result<(), Error> {
@@ -1001,8 +1419,8 @@ async fn insert_payment_tags(
             VALUES ($1, $2, $3, $4)
             "#,
             payment_id,
-            tag.key,
-            tag.value,
+            tag.key.as_ref(),
+            tag.value.as_ref(),
             user_id
         )
         .execute(connection_pool)
@@ -1011,6 +1429,30 @@ async fn insert_payment_tags(
     Ok(())
 }
 
+/// Inserts tags within the transaction creating their payment (imports).
+pub(crate) async fn insert_payment_tags_in_transaction(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    payment_id: Uuid,
+    tags: &[Tag],
+    user_id: &str,
+) -> Result<(), Error> {
+    for tag in tags {
+        sqlx::query!(
+            r#"
+            INSERT INTO expenses.payments_tags (payment_id, key, value, user_id)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            payment_id,
+            tag.key.as_ref(),
+            tag.value.as_ref(),
+            user_id
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+    Ok(())
+}
+
 #[tracing::instrument(name = "Retrieving payment tags", skip(connection_pool))]
 async fn get_payment_tags(
     payment_id: Uuid,
@@ -1039,6 +1481,38 @@ async fn get_payment_tags(
     Ok(tags)
 }
 
+#[tracing::instrument(name = "Retrieving payment splits", skip(connection_pool))]
+async fn get_payment_splits(
+    payment_id: Uuid,
+    connection_pool: &PgPool,
+    user_id: &str,
+) -> Result<Vec<PaymentSplitResponseDto>, Error> {
+    let splits = sqlx::query!(
+        r#"
+        SELECT s.id, s.category_id, c.name as category_name, s.amount, s.description
+        FROM expenses.payment_splits s
+        JOIN expenses.categories c ON c.id = s.category_id
+        WHERE s.payment_id = $1 AND s.user_id = $2
+        ORDER BY s.position
+        "#,
+        payment_id,
+        user_id
+    )
+    .fetch_all(connection_pool)
+    .await?
+    .into_iter()
+    .map(|row| PaymentSplitResponseDto {
+        id: row.id,
+        category_id: row.category_id,
+        category: row.category_name,
+        amount_in_cents: AmountInCents::new(row.amount),
+        description: row.description,
+    })
+    .collect();
+
+    Ok(splits)
+}
+
 #[tracing::instrument(name = "Retrieving wallet name", skip(connection_pool))]
 async fn get_wallet_name(
     wallet_id: Uuid,
@@ -1101,10 +1575,19 @@ async fn get_payment_from_db(
                p.merchant_name,
                p.accounting_date,
                p.amount,
+               p.currency,
                w.name as "wallet_name!",
                COALESCE((SELECT json_agg(
                    json_build_object('id', pt.id, 'key', pt.key, 'value', pt.value)
-               ) FROM expenses.payments_tags pt WHERE pt.payment_id = p.id), '[]'::json) as tags
+               ) FROM expenses.payments_tags pt WHERE pt.payment_id = p.id), '[]'::json) as tags,
+               p.transfer_id,
+               COALESCE((SELECT json_agg(
+                   json_build_object('id', s.id, 'categoryId', s.category_id, 'category', sc.name,
+                                     'amountInCents', s.amount, 'description', s.description)
+                   ORDER BY s.position
+               ) FROM expenses.payment_splits s
+                 JOIN expenses.categories sc ON sc.id = s.category_id
+                 WHERE s.payment_id = p.id), '[]'::json) as splits
         FROM expenses.payments p
         LEFT JOIN expenses.categories c ON p.category_id = c.id
         LEFT JOIN expenses.wallets w ON p.wallet_id = w.id
@@ -1129,7 +1612,8 @@ async fn get_payment_from_db(
         Ok(Some(PaymentResponseDto {
             id: record.id,
             description: record.description,
-            amount_in_cents: record.amount.unwrap_or(0),
+            amount_in_cents: record.amount.map(AmountInCents::new).unwrap_or_default(),
+            currency: record.currency,
             merchant_name: record.merchant_name.unwrap_or_default(),
             accounting_date: record.accounting_date.unwrap_or_default(),
             category: record.category_name,
@@ -1137,8 +1621,20 @@ async fn get_payment_from_db(
             category_icon: record.category_icon,
             wallet: Some(record.wallet_name),
             tags,
+            transfer_id: record.transfer_id,
+            splits: parse_splits(payment_id, record.splits.unwrap_or_default()),
         }))
     } else {
         Ok(None)
     }
 }
+
+fn parse_splits(payment_id: Uuid, splits_json: serde_json::Value) -> Vec<PaymentSplitResponseDto> {
+    match serde_json::from_value(splits_json) {
+        Ok(splits) => splits,
+        Err(e) => {
+            tracing::error!("Failed to parse splits for payment {}: {:?}", payment_id, e);
+            Vec::new()
+        }
+    }
+}
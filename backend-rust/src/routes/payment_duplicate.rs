@@ -0,0 +1,168 @@
+use crate::auth::AuthenticatedUser;
+use crate::domain::{cluster_by_merchant_name, merchant_names_are_similar, Payment};
+use crate::routes::merchant::find_merchant;
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// A payment that may have been entered twice.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicatePaymentDto {
+    id: Uuid,
+    merchant_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    amount_in_cents: i64,
+    currency: String,
+    accounting_date: NaiveDateTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    wallet: Option<String>,
+}
+
+/// Payments of the same amount on the same day with similar merchants.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateClusterDto {
+    accounting_date: NaiveDate,
+    amount_in_cents: i64,
+    currency: String,
+    payments: Vec<DuplicatePaymentDto>,
+}
+
+/// Response of a payment creation refused because of possible duplicates.
+#[derive(Serialize, Debug)]
+pub struct DuplicatePaymentConflictDto {
+    pub(crate) message: String,
+    pub(crate) duplicates: Vec<DuplicatePaymentDto>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ForceQuery {
+    /// Creates the payment even if it looks like a duplicate.
+    #[serde(default)]
+    pub(crate) force: bool,
+}
+
+/// Existing payments of the user that `payment` probably duplicates: same
+/// amount and currency on the same day, with a merchant name similar to the
+/// name of the payment or to the name of its canonical merchant.
+/// Transfers between wallets are never considered duplicates.
+#[tracing::instrument(name = "Looking for duplicate payments", skip(payment, connection))]
+pub(crate) async fn find_duplicate_payments(
+    payment: &Payment,
+    connection: impl sqlx::Acquire<'_, Database = sqlx::Postgres>,
+) -> Result<Vec<DuplicatePaymentDto>, sqlx::Error> {
+    let mut connection = connection.acquire().await?;
+    let candidates = sqlx::query_as!(
+        DuplicatePaymentDto,
+        r#"
+        SELECT p.id, p.merchant_name as "merchant_name!", p.description,
+               p.amount as "amount_in_cents!", p.currency,
+               p.accounting_date as "accounting_date!", w.name as "wallet?"
+        FROM expenses.payments p
+        LEFT JOIN expenses.wallets w ON w.id = p.wallet_id
+        WHERE p.user_id = $1 AND p.amount = $2 AND p.currency = $3
+          AND p.accounting_date::date = $4 AND p.transfer_id IS NULL
+          AND p.merchant_name IS NOT NULL
+        ORDER BY p.accounting_date, p.id
+        "#,
+        payment.user_id,
+        payment.amount.amount().cents(),
+        payment.amount.currency().as_ref(),
+        payment.accounting_date.date()
+    )
+    .fetch_all(&mut *connection)
+    .await?;
+    let merchant = find_merchant(
+        &mut *connection,
+        &payment.user_id,
+        payment.merchant_name.as_ref(),
+    )
+    .await?;
+    Ok(candidates
+        .into_iter()
+        .filter(|candidate| {
+            merchant_names_are_similar(&candidate.merchant_name, payment.merchant_name.as_ref())
+                || merchant.as_ref().is_some_and(|merchant| {
+                    merchant_names_are_similar(&candidate.merchant_name, &merchant.name)
+                })
+        })
+        .collect())
+}
+
+/// Row error of imports refusing a likely duplicate.
+pub(crate) fn duplicate_error(duplicates: &[DuplicatePaymentDto]) -> String {
+    let ids: Vec<String> = duplicates.iter().map(|d| d.id.to_string()).collect();
+    format!("Possible duplicate of payment {}", ids.join(", "))
+}
+
+/// Clusters of likely duplicates across the whole history of the user.
+#[tracing::instrument(name = "Reporting duplicate payments", skip(user, connection_pool))]
+pub async fn get_duplicate_payments(
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    match get_duplicate_clusters(&user.sub, connection_pool.get_ref()).await {
+        Ok(clusters) => HttpResponse::Ok().json(clusters),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+async fn get_duplicate_clusters(
+    user_id: &str,
+    connection_pool: &PgPool,
+) -> Result<Vec<DuplicateClusterDto>, sqlx::Error> {
+    // Only payments sharing their day and amount with another one can be duplicates
+    let payments = sqlx::query_as!(
+        DuplicatePaymentDto,
+        r#"
+        SELECT p.id, p.merchant_name as "merchant_name!", p.description,
+               p.amount as "amount_in_cents!", p.currency,
+               p.accounting_date as "accounting_date!", w.name as "wallet?"
+        FROM expenses.payments p
+        LEFT JOIN expenses.wallets w ON w.id = p.wallet_id
+        WHERE p.user_id = $1 AND p.transfer_id IS NULL AND p.merchant_name IS NOT NULL
+          AND EXISTS (
+              SELECT 1 FROM expenses.payments other
+              WHERE other.user_id = p.user_id AND other.id <> p.id
+                AND other.amount = p.amount AND other.currency = p.currency
+                AND other.accounting_date::date = p.accounting_date::date
+                AND other.transfer_id IS NULL
+          )
+        ORDER BY p.accounting_date::date DESC, p.amount, p.currency, p.accounting_date, p.id
+        "#,
+        user_id
+    )
+    .fetch_all(connection_pool)
+    .await?;
+
+    let mut groups: Vec<Vec<DuplicatePaymentDto>> = Vec::new();
+    for payment in payments {
+        match groups.last_mut() {
+            Some(group)
+                if group[0].accounting_date.date() == payment.accounting_date.date()
+                    && group[0].amount_in_cents == payment.amount_in_cents
+                    && group[0].currency == payment.currency =>
+            {
+                group.push(payment)
+            }
+            _ => groups.push(vec![payment]),
+        }
+    }
+    Ok(groups
+        .into_iter()
+        .flat_map(|group| cluster_by_merchant_name(group, |p| &p.merchant_name))
+        .map(|payments| DuplicateClusterDto {
+            accounting_date: payments[0].accounting_date.date(),
+            amount_in_cents: payments[0].amount_in_cents,
+            currency: payments[0].currency.clone(),
+            payments,
+        })
+        .collect())
+}
@@ -0,0 +1,229 @@
+use crate::auth::AuthenticatedUser;
+use crate::domain::{payment_features, recency_weight, NaiveBayes};
+use crate::routes::merchant::find_merchant;
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+/// Suggestions returned per kind (categories, wallets, tags).
+const MAX_SUGGESTIONS: usize = 5;
+/// Most recent payments the suggestions learn from.
+const MAX_HISTORY: i64 = 5000;
+/// Confidence from which imports fill in the suggested category.
+const MIN_IMPORT_CATEGORY_CONFIDENCE: f64 = 0.5;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentSuggestionQuery {
+    merchant_name: String,
+    /// In cents, negative for expenses.
+    amount: Option<i64>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CategorySuggestionDto {
+    pub category_id: Uuid,
+    pub category: String,
+    pub confidence: f64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct WalletSuggestionDto {
+    wallet: String,
+    confidence: f64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TagSuggestionDto {
+    key: String,
+    value: String,
+    confidence: f64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PaymentSuggestionsDto {
+    categories: Vec<CategorySuggestionDto>,
+    wallets: Vec<WalletSuggestionDto>,
+    tags: Vec<TagSuggestionDto>,
+}
+
+#[derive(Deserialize)]
+struct HistoryTag {
+    key: String,
+    value: String,
+}
+
+/// Categories, wallets and tags of a user's payments, learnt from the merchant
+/// names and amounts of their history, recent payments weighing more.
+pub struct PaymentScorer {
+    categories: NaiveBayes<(Uuid, String)>,
+    wallets: NaiveBayes<Option<String>>,
+    /// `None` stands for the payments without tags.
+    tags: NaiveBayes<Option<(String, String)>>,
+}
+
+impl PaymentScorer {
+    /// Trains the scorer on the most recent payments of the user, transfers excluded.
+    #[tracing::instrument(name = "Training the payment scorer", skip(executor))]
+    pub async fn for_user(
+        executor: impl PgExecutor<'_>,
+        user_id: &str,
+        today: NaiveDate,
+    ) -> Result<Self, sqlx::Error> {
+        let history = sqlx::query!(
+            r#"
+            SELECT p.merchant_name as "merchant_name!", p.amount, p.accounting_date,
+                   p.category_id, c.name as category, w.name as "wallet?",
+                   COALESCE((SELECT json_agg(json_build_object('key', pt.key, 'value', pt.value))
+                             FROM expenses.payments_tags pt WHERE pt.payment_id = p.id),
+                            '[]'::json) as "tags!"
+            FROM expenses.payments p
+            JOIN expenses.categories c ON c.id = p.category_id
+            LEFT JOIN expenses.wallets w ON w.id = p.wallet_id
+            WHERE p.user_id = $1 AND p.transfer_id IS NULL AND p.merchant_name IS NOT NULL
+            ORDER BY p.accounting_date DESC NULLS LAST
+            LIMIT $2
+            "#,
+            user_id,
+            MAX_HISTORY
+        )
+        .fetch_all(executor)
+        .await?;
+
+        let mut scorer = Self {
+            categories: NaiveBayes::default(),
+            wallets: NaiveBayes::default(),
+            tags: NaiveBayes::default(),
+        };
+        for payment in history {
+            let features = payment_features(&payment.merchant_name, payment.amount);
+            let age = payment
+                .accounting_date
+                .map(|date| (today - date.date()).num_days())
+                .unwrap_or_default();
+            let weight = recency_weight(age);
+            scorer
+                .categories
+                .train(&features, (payment.category_id, payment.category), weight);
+            scorer.wallets.train(&features, payment.wallet, weight);
+            let tags: Vec<HistoryTag> = serde_json::from_value(payment.tags).unwrap_or_default();
+            if tags.is_empty() {
+                scorer.tags.train(&features, None, weight);
+            }
+            for tag in tags {
+                scorer
+                    .tags
+                    .train(&features, Some((tag.key, tag.value)), weight);
+            }
+        }
+        Ok(scorer)
+    }
+
+    /// Most likely categories of a payment, most likely first.
+    pub fn suggest_categories(
+        &self,
+        merchant_name: &str,
+        amount_in_cents: Option<i64>,
+    ) -> Vec<CategorySuggestionDto> {
+        self.categories
+            .rank(&payment_features(merchant_name, amount_in_cents))
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(
+                |((category_id, category), confidence)| CategorySuggestionDto {
+                    category_id,
+                    category,
+                    confidence,
+                },
+            )
+            .collect()
+    }
+
+    /// Name of the category of an imported transaction, when it is clear enough.
+    /// Merchants never seen before get none: the amount alone says little.
+    pub fn likely_category(&self, merchant_name: &str, amount_in_cents: i64) -> Option<String> {
+        let known_merchant = payment_features(merchant_name, None)
+            .iter()
+            .any(|feature| self.categories.knows(feature));
+        if !known_merchant {
+            return None;
+        }
+        self.suggest_categories(merchant_name, Some(amount_in_cents))
+            .into_iter()
+            .next()
+            .filter(|suggestion| suggestion.confidence >= MIN_IMPORT_CATEGORY_CONFIDENCE)
+            .map(|suggestion| suggestion.category)
+    }
+
+    fn suggest(&self, merchant_name: &str, amount_in_cents: Option<i64>) -> PaymentSuggestionsDto {
+        let features = payment_features(merchant_name, amount_in_cents);
+        PaymentSuggestionsDto {
+            categories: self.suggest_categories(merchant_name, amount_in_cents),
+            // Payments without a wallet or tags are not suggestions
+            wallets: self
+                .wallets
+                .rank(&features)
+                .into_iter()
+                .filter_map(|(wallet, confidence)| {
+                    wallet.map(|wallet| WalletSuggestionDto { wallet, confidence })
+                })
+                .take(MAX_SUGGESTIONS)
+                .collect(),
+            tags: self
+                .tags
+                .rank(&features)
+                .into_iter()
+                .filter_map(|(tag, confidence)| {
+                    tag.map(|(key, value)| TagSuggestionDto {
+                        key,
+                        value,
+                        confidence,
+                    })
+                })
+                .take(MAX_SUGGESTIONS)
+                .collect(),
+        }
+    }
+}
+
+#[tracing::instrument(
+    name = "Suggesting payment details",
+    skip(user, connection_pool),
+    fields(merchant_name = %query.merchant_name)
+)]
+pub async fn get_payment_suggestions(
+    query: web::Query<PaymentSuggestionQuery>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    let merchant_name = query.merchant_name.trim();
+    if merchant_name.is_empty() {
+        return HttpResponse::BadRequest().body("merchantName is required");
+    }
+    // Known spellings of a merchant learn from each other
+    let merchant = match find_merchant(connection_pool.get_ref(), &user.sub, merchant_name).await {
+        Ok(merchant) => merchant,
+        Err(e) => {
+            tracing::error!("Failed to resolve the merchant: {:?}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let merchant_name = merchant.as_ref().map_or(merchant_name, |m| m.name.as_str());
+
+    match PaymentScorer::for_user(
+        connection_pool.get_ref(),
+        &user.sub,
+        Utc::now().date_naive(),
+    )
+    .await
+    {
+        Ok(scorer) => HttpResponse::Ok().json(scorer.suggest(merchant_name, query.amount)),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
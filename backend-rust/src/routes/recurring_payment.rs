@@ -0,0 +1,725 @@
+use crate::auth::AuthenticatedUser;
+use crate::domain::{
+    AmountInCents, Currency, Money, PaymentDescription, PaymentMerchant, Recurrence,
+    RecurringPayment, RecurringPaymentStatus, TagKey, TagValue,
+};
+use crate::routes::merchant::resolve_merchant;
+use crate::routes::payment::{resolve_category_id, CategoryIdentifier, CategoryResolutionError};
+use crate::routes::wallet::get_wallet_by_name;
+use actix_web::web::Json;
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+#[derive(Deserialize, Debug)]
+pub struct RecurringPaymentDto {
+    description: Option<String>,
+    #[serde(rename = "categoryId")]
+    category_id: CategoryIdentifier,
+    #[serde(rename = "amountInCents")]
+    amount_in_cents: AmountInCents,
+    // ISO 4217 code; defaults to the wallet's currency when omitted.
+    currency: Option<String>,
+    #[serde(rename = "merchantName")]
+    merchant_name: String,
+    wallet: Option<String>,
+    tags: Option<Vec<RecurringPaymentTagDto>>,
+    // RRULE subset, e.g. "FREQ=MONTHLY;BYMONTHDAY=1"
+    recurrence: String,
+    #[serde(rename = "startDate")]
+    start_date: NaiveDate,
+    #[serde(default)]
+    status: RecurringPaymentStatus,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecurringPaymentTagDto {
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RecurringPaymentResponseDto {
+    pub id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "categoryId")]
+    pub category_id: Uuid,
+    pub category: String,
+    #[serde(rename = "amountInCents")]
+    pub amount_in_cents: AmountInCents,
+    pub currency: String,
+    #[serde(rename = "merchantName")]
+    pub merchant_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wallet: Option<String>,
+    pub tags: Vec<RecurringPaymentTagDto>,
+    pub recurrence: String,
+    #[serde(rename = "startDate")]
+    pub start_date: NaiveDate,
+    pub status: RecurringPaymentStatus,
+    /// `None` once the series has ended.
+    #[serde(rename = "nextOccurrence")]
+    pub next_occurrence: Option<NaiveDate>,
+}
+
+enum RecurringPaymentError {
+    Validation(String),
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for RecurringPaymentError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+impl From<CategoryResolutionError> for RecurringPaymentError {
+    fn from(e: CategoryResolutionError) -> Self {
+        match e {
+            CategoryResolutionError::InvalidName(e) => Self::Validation(e),
+            CategoryResolutionError::Database(e) => Self::Database(e),
+        }
+    }
+}
+
+impl RecurringPaymentError {
+    fn into_response(self) -> HttpResponse {
+        match self {
+            Self::Validation(e) => HttpResponse::BadRequest().body(e),
+            Self::Database(e) => {
+                tracing::error!("Failed to execute query: {:?}", e);
+                HttpResponse::InternalServerError().finish()
+            }
+        }
+    }
+}
+
+/// Validates the template and resolves its category and wallet.
+async fn parse_recurring_payment(
+    dto: RecurringPaymentDto,
+    user_id: String,
+    connection_pool: &PgPool,
+) -> Result<RecurringPayment, RecurringPaymentError> {
+    use RecurringPaymentError::Validation;
+
+    let (wallet_id, default_currency) = match &dto.wallet {
+        Some(name) => match get_wallet_by_name(name, connection_pool, &user_id).await? {
+            Some(wallet) => (wallet.id, wallet.default_currency),
+            None => return Err(Validation(format!("Wallet '{}' not found", name))),
+        },
+        None => (None, Currency::default()),
+    };
+    let category_id = resolve_category_id(&dto.category_id, &user_id, connection_pool)
+        .await?
+        .ok_or_else(|| Validation("categoryId not found".to_string()))?;
+    let currency = match dto.currency {
+        Some(code) => Currency::parse(code).map_err(Validation)?,
+        None => default_currency,
+    };
+    let description = dto
+        .description
+        .filter(|s| !s.trim().is_empty())
+        .map(PaymentDescription::parse)
+        .transpose()
+        .map_err(Validation)?;
+    let tags = dto
+        .tags
+        .unwrap_or_default()
+        .into_iter()
+        .map(|tag| Ok((TagKey::parse(tag.key)?, TagValue::parse(tag.value)?)))
+        .collect::<Result<Vec<_>, String>>()
+        .map_err(Validation)?;
+
+    Ok(RecurringPayment {
+        description,
+        category_id,
+        amount: Money::new(dto.amount_in_cents, currency),
+        merchant_name: PaymentMerchant::parse(dto.merchant_name).map_err(Validation)?,
+        wallet_id,
+        tags,
+        recurrence: Recurrence::parse(&dto.recurrence).map_err(Validation)?,
+        start_date: dto.start_date,
+        status: dto.status,
+        user_id,
+    })
+}
+
+fn tags_json(recurring_payment: &RecurringPayment) -> serde_json::Value {
+    serde_json::json!(recurring_payment
+        .tags
+        .iter()
+        .map(|(key, value)| RecurringPaymentTagDto {
+            key: key.as_ref().to_string(),
+            value: value.as_ref().to_string(),
+        })
+        .collect::<Vec<_>>())
+}
+
+#[tracing::instrument(
+    name = "Creating a recurring payment",
+    skip(payload, user, connection_pool),
+    fields(merchant_name = %payload.merchant_name, recurrence = %payload.recurrence)
+)]
+pub async fn create_recurring_payment(
+    payload: Json<RecurringPaymentDto>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    let user_id = user.sub;
+    let recurring_payment = match parse_recurring_payment(
+        payload.into_inner(),
+        user_id.clone(),
+        &connection_pool,
+    )
+    .await
+    {
+        Ok(recurring_payment) => recurring_payment,
+        Err(e) => return e.into_response(),
+    };
+
+    // Occurrences before today are not backfilled.
+    let today = Utc::now().date_naive();
+    let next_occurrence = recurring_payment.next_occurrence_from(today);
+    let id = match insert_recurring_payment(
+        &recurring_payment,
+        next_occurrence,
+        connection_pool.get_ref(),
+    )
+    .await
+    {
+        Ok(id) => id,
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    respond_with_recurring_payment(id, &user_id, &connection_pool).await
+}
+
+#[tracing::instrument(name = "Retrieving recurring payments", skip(user, connection_pool))]
+pub async fn get_recurring_payments(
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    match get_recurring_payments_from_db(None, &user.sub, &connection_pool).await {
+        Ok(recurring_payments) => HttpResponse::Ok().json(recurring_payments),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[tracing::instrument(name = "Retrieving a recurring payment", skip(user, connection_pool))]
+pub async fn get_recurring_payment(
+    path: web::Path<Uuid>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    respond_with_recurring_payment(path.into_inner(), &user.sub, &connection_pool).await
+}
+
+#[tracing::instrument(
+    name = "Updating a recurring payment",
+    skip(payload, user, connection_pool),
+    fields(merchant_name = %payload.merchant_name, recurrence = %payload.recurrence)
+)]
+pub async fn update_recurring_payment(
+    path: web::Path<Uuid>,
+    payload: Json<RecurringPaymentDto>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let user_id = user.sub;
+    let recurring_payment = match parse_recurring_payment(
+        payload.into_inner(),
+        user_id.clone(),
+        &connection_pool,
+    )
+    .await
+    {
+        Ok(recurring_payment) => recurring_payment,
+        Err(e) => return e.into_response(),
+    };
+
+    match update_recurring_payment_query(id, &recurring_payment, connection_pool.get_ref()).await {
+        Ok(true) => respond_with_recurring_payment(id, &user_id, &connection_pool).await,
+        Ok(false) => HttpResponse::NotFound().finish(),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Deletes the template; payments it already generated are kept.
+#[tracing::instrument(name = "Deleting a recurring payment", skip(user, connection_pool))]
+pub async fn delete_recurring_payment(
+    path: web::Path<Uuid>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    match sqlx::query!(
+        "DELETE FROM expenses.recurring_payments WHERE id = $1 AND user_id = $2",
+        path.into_inner(),
+        user.sub
+    )
+    .execute(connection_pool.get_ref())
+    .await
+    {
+        Ok(result) if result.rows_affected() == 0 => HttpResponse::NotFound().finish(),
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Skips the next occurrence: no payment will be generated for it.
+#[tracing::instrument(
+    name = "Skipping a recurring payment occurrence",
+    skip(user, connection_pool)
+)]
+pub async fn skip_recurring_payment(
+    path: web::Path<Uuid>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    let id = path.into_inner();
+    match skip_next_occurrence(id, &user.sub, connection_pool.get_ref()).await {
+        Ok(SkipOutcome::Skipped) => {
+            respond_with_recurring_payment(id, &user.sub, &connection_pool).await
+        }
+        Ok(SkipOutcome::NotFound) => HttpResponse::NotFound().finish(),
+        Ok(SkipOutcome::Ended) => {
+            HttpResponse::Conflict().body("The recurring payment has no next occurrence")
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+async fn respond_with_recurring_payment(
+    id: Uuid,
+    user_id: &str,
+    connection_pool: &PgPool,
+) -> HttpResponse {
+    match get_recurring_payments_from_db(Some(id), user_id, connection_pool).await {
+        Ok(mut recurring_payments) => match recurring_payments.pop() {
+            Some(recurring_payment) => HttpResponse::Ok().json(recurring_payment),
+            None => HttpResponse::NotFound().finish(),
+        },
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[tracing::instrument(
+    name = "Inserting a recurring payment in the database",
+    skip(recurring_payment, connection_pool)
+)]
+async fn insert_recurring_payment(
+    recurring_payment: &RecurringPayment,
+    next_occurrence: Option<NaiveDate>,
+    connection_pool: &PgPool,
+) -> Result<Uuid, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        INSERT INTO expenses.recurring_payments
+            (user_id, merchant_name, description, amount, currency, category_id, wallet_id,
+             tags, recurrence, start_date, status, next_occurrence)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+        RETURNING id
+        "#,
+        recurring_payment.user_id,
+        recurring_payment.merchant_name.as_ref(),
+        recurring_payment.description.as_ref().map(|d| d.as_ref()),
+        recurring_payment.amount.amount().cents(),
+        recurring_payment.amount.currency().as_ref(),
+        recurring_payment.category_id,
+        recurring_payment.wallet_id,
+        tags_json(recurring_payment),
+        recurring_payment.recurrence.to_string(),
+        recurring_payment.start_date,
+        recurring_payment.status.as_str(),
+        next_occurrence
+    )
+    .fetch_one(connection_pool)
+    .await
+}
+
+/// Replaces the template. The next occurrence is recomputed from today, so
+/// resuming a paused template does not backfill the paused period.
+#[tracing::instrument(
+    name = "Updating a recurring payment in the database",
+    skip(recurring_payment, connection_pool)
+)]
+async fn update_recurring_payment_query(
+    id: Uuid,
+    recurring_payment: &RecurringPayment,
+    connection_pool: &PgPool,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = connection_pool.begin().await?;
+    let next_occurrence = next_pending_occurrence(
+        &mut tx,
+        id,
+        &recurring_payment.recurrence,
+        recurring_payment.start_date,
+        Some(Utc::now().date_naive()),
+    )
+    .await?;
+    let result = sqlx::query!(
+        r#"
+        UPDATE expenses.recurring_payments
+        SET merchant_name = $3, description = $4, amount = $5, currency = $6, category_id = $7,
+            wallet_id = $8, tags = $9, recurrence = $10, start_date = $11, status = $12,
+            next_occurrence = $13
+        WHERE id = $1 AND user_id = $2
+        "#,
+        id,
+        recurring_payment.user_id,
+        recurring_payment.merchant_name.as_ref(),
+        recurring_payment.description.as_ref().map(|d| d.as_ref()),
+        recurring_payment.amount.amount().cents(),
+        recurring_payment.amount.currency().as_ref(),
+        recurring_payment.category_id,
+        recurring_payment.wallet_id,
+        tags_json(recurring_payment),
+        recurring_payment.recurrence.to_string(),
+        recurring_payment.start_date,
+        recurring_payment.status.as_str(),
+        next_occurrence
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[tracing::instrument(
+    name = "Retrieving recurring payments from the database",
+    skip(connection_pool)
+)]
+async fn get_recurring_payments_from_db(
+    id: Option<Uuid>,
+    user_id: &str,
+    connection_pool: &PgPool,
+) -> Result<Vec<RecurringPaymentResponseDto>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT r.id, r.description, r.category_id, c.name as category, r.amount, r.currency,
+               r.merchant_name, w.name as "wallet?", r.tags, r.recurrence, r.start_date,
+               r.status, r.next_occurrence
+        FROM expenses.recurring_payments r
+        JOIN expenses.categories c ON c.id = r.category_id
+        LEFT JOIN expenses.wallets w ON w.id = r.wallet_id
+        WHERE r.user_id = $1 AND ($2::uuid IS NULL OR r.id = $2)
+        ORDER BY r.next_occurrence NULLS LAST, r.created_at
+        "#,
+        user_id,
+        id
+    )
+    .fetch_all(connection_pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| RecurringPaymentResponseDto {
+            id: row.id,
+            description: row.description,
+            category_id: row.category_id,
+            category: row.category,
+            amount_in_cents: AmountInCents::new(row.amount),
+            currency: row.currency,
+            merchant_name: row.merchant_name,
+            wallet: row.wallet,
+            tags: serde_json::from_value(row.tags).unwrap_or_else(|e| {
+                tracing::error!(
+                    "Failed to parse tags of recurring payment {}: {:?}",
+                    row.id,
+                    e
+                );
+                Vec::new()
+            }),
+            recurrence: row.recurrence,
+            start_date: row.start_date,
+            // Guaranteed by the recurring_payments_status check constraint.
+            status: RecurringPaymentStatus::parse(&row.status).unwrap_or_default(),
+            next_occurrence: row.next_occurrence,
+        })
+        .collect())
+}
+
+enum SkipOutcome {
+    Skipped,
+    NotFound,
+    Ended,
+}
+
+#[tracing::instrument(
+    name = "Skipping the next occurrence in the database",
+    skip(connection_pool)
+)]
+async fn skip_next_occurrence(
+    id: Uuid,
+    user_id: &str,
+    connection_pool: &PgPool,
+) -> Result<SkipOutcome, sqlx::Error> {
+    let mut tx = connection_pool.begin().await?;
+    let Some(row) = sqlx::query!(
+        r#"
+        SELECT recurrence, start_date, next_occurrence
+        FROM expenses.recurring_payments
+        WHERE id = $1 AND user_id = $2
+        FOR UPDATE
+        "#,
+        id,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(SkipOutcome::NotFound);
+    };
+    let (Some(occurrence), Ok(recurrence)) =
+        (row.next_occurrence, Recurrence::parse(&row.recurrence))
+    else {
+        return Ok(SkipOutcome::Ended);
+    };
+
+    sqlx::query!(
+        r#"
+        INSERT INTO expenses.recurring_payment_occurrences (recurring_payment_id, occurrence_date, skipped)
+        VALUES ($1, $2, true)
+        ON CONFLICT DO NOTHING
+        "#,
+        id,
+        occurrence
+    )
+    .execute(&mut *tx)
+    .await?;
+    let next_occurrence = next_pending_occurrence(
+        &mut tx,
+        id,
+        &recurrence,
+        row.start_date,
+        occurrence.succ_opt(),
+    )
+    .await?;
+    sqlx::query!(
+        "UPDATE expenses.recurring_payments SET next_occurrence = $2 WHERE id = $1",
+        id,
+        next_occurrence
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(SkipOutcome::Skipped)
+}
+
+/// First occurrence on or after `from` that was neither generated nor skipped.
+async fn next_pending_occurrence(
+    tx: &mut Transaction<'_, Postgres>,
+    id: Uuid,
+    recurrence: &Recurrence,
+    start_date: NaiveDate,
+    from: Option<NaiveDate>,
+) -> Result<Option<NaiveDate>, sqlx::Error> {
+    let Some(from) = from else {
+        return Ok(None);
+    };
+    let handled: HashSet<NaiveDate> = sqlx::query_scalar!(
+        r#"
+        SELECT occurrence_date FROM expenses.recurring_payment_occurrences
+        WHERE recurring_payment_id = $1 AND occurrence_date >= $2
+        "#,
+        id,
+        from
+    )
+    .fetch_all(&mut **tx)
+    .await?
+    .into_iter()
+    .collect();
+
+    let mut occurrence = recurrence.next_on_or_after(start_date, from);
+    while let Some(date) = occurrence.filter(|date| handled.contains(date)) {
+        occurrence = recurrence.next_after(start_date, date);
+    }
+    Ok(occurrence)
+}
+
+/// Outcome of a materialisation run.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MaterialisationSummary {
+    /// Payments created.
+    pub created: usize,
+    /// Templates whose occurrences could not be generated; they are retried
+    /// by the next run.
+    pub failed: usize,
+}
+
+/// Generates the payments of every active template due on or before `today`.
+///
+/// Safe to run concurrently (in-process scheduler and CronJob) and to re-run:
+/// each occurrence is recorded once per template and date, and templates are
+/// locked while their occurrences are generated. A template that fails, e.g.
+/// because its wallet was deleted, is logged and does not stop the others.
+#[tracing::instrument(name = "Materialising recurring payments", skip(connection_pool))]
+pub async fn materialise_recurring_payments(
+    connection_pool: &PgPool,
+    today: NaiveDate,
+) -> Result<MaterialisationSummary, sqlx::Error> {
+    let due = sqlx::query_scalar!(
+        r#"
+        SELECT id FROM expenses.recurring_payments
+        WHERE status = 'active' AND next_occurrence <= $1
+        "#,
+        today
+    )
+    .fetch_all(connection_pool)
+    .await?;
+
+    let mut summary = MaterialisationSummary::default();
+    for id in due {
+        match materialise_recurring_payment(id, today, connection_pool).await {
+            Ok(created) => summary.created += created,
+            Err(e) => {
+                tracing::error!("Failed to materialise recurring payment {}: {:?}", id, e);
+                summary.failed += 1;
+            }
+        }
+    }
+    Ok(summary)
+}
+
+async fn materialise_recurring_payment(
+    id: Uuid,
+    today: NaiveDate,
+    connection_pool: &PgPool,
+) -> Result<usize, sqlx::Error> {
+    let mut tx = connection_pool.begin().await?;
+    // Re-checked under the lock: another run may have handled the template meanwhile.
+    let Some(row) = sqlx::query!(
+        r#"
+        SELECT recurrence, start_date, next_occurrence as "next_occurrence!"
+        FROM expenses.recurring_payments
+        WHERE id = $1 AND status = 'active' AND next_occurrence <= $2
+        FOR UPDATE
+        "#,
+        id,
+        today
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(0);
+    };
+    let recurrence = match Recurrence::parse(&row.recurrence) {
+        Ok(recurrence) => recurrence,
+        Err(e) => {
+            tracing::error!("Invalid recurrence for recurring payment {}: {}", id, e);
+            return Ok(0);
+        }
+    };
+
+    let mut created = 0;
+    let mut occurrence = Some(row.next_occurrence);
+    while let Some(date) = occurrence.filter(|date| *date <= today) {
+        let recorded = sqlx::query_scalar!(
+            r#"
+            INSERT INTO expenses.recurring_payment_occurrences (recurring_payment_id, occurrence_date)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING
+            RETURNING occurrence_date
+            "#,
+            id,
+            date
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        if recorded.is_some() {
+            insert_occurrence_payment(&mut tx, id, date).await?;
+            created += 1;
+        }
+        occurrence = recurrence.next_after(row.start_date, date);
+    }
+
+    let next_occurrence =
+        next_pending_occurrence(&mut tx, id, &recurrence, row.start_date, today.succ_opt()).await?;
+    sqlx::query!(
+        "UPDATE expenses.recurring_payments SET next_occurrence = $2 WHERE id = $1",
+        id,
+        next_occurrence
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(created)
+}
+
+/// Books the template as a payment on the occurrence date, with its tags.
+async fn insert_occurrence_payment(
+    tx: &mut Transaction<'_, Postgres>,
+    id: Uuid,
+    date: NaiveDate,
+) -> Result<(), sqlx::Error> {
+    let payment = sqlx::query!(
+        r#"
+        INSERT INTO expenses.payments
+            (category_id, description, merchant_name, accounting_date, amount, wallet_id, user_id, currency)
+        SELECT category_id, description, merchant_name, $2::date::timestamp, amount, wallet_id, user_id, currency
+        FROM expenses.recurring_payments
+        WHERE id = $1
+        RETURNING id, user_id, merchant_name as "merchant_name!"
+        "#,
+        id,
+        date
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    let payment_id = payment.id;
+    let merchant = resolve_merchant(tx, &payment.user_id, &payment.merchant_name).await?;
+    sqlx::query!(
+        "UPDATE expenses.payments SET merchant_id = $2, merchant_name = $3 WHERE id = $1",
+        payment_id,
+        merchant.id,
+        merchant.name
+    )
+    .execute(&mut **tx)
+    .await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO expenses.payments_tags (payment_id, key, value, user_id)
+        SELECT $1, t.key, t.value, r.user_id
+        FROM expenses.recurring_payments r,
+             jsonb_to_recordset(r.tags) AS t(key varchar, value varchar)
+        WHERE r.id = $2
+        "#,
+        payment_id,
+        id
+    )
+    .execute(&mut **tx)
+    .await?;
+    sqlx::query!(
+        r#"
+        UPDATE expenses.recurring_payment_occurrences SET payment_id = $3
+        WHERE recurring_payment_id = $1 AND occurrence_date = $2
+        "#,
+        id,
+        date,
+        payment_id
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use crate::auth::AuthenticatedUser;
+use crate::domain::{AmountInCents, TagKey, TagValue};
+use actix_web::{web, HttpResponse, Responder};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+#[derive(Deserialize, Debug)]
+pub struct TagReportQuery {
+    key: String,
+    /// Drills down into the categories of the payments tagged `key:value`.
+    value: Option<String>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+}
+
+/// Income and expenses of the payments tagged with `key`, per tag value, or
+/// per category within one tag value.
+///
+/// Transfers are left out, and amounts in different currencies are reported
+/// as separate entries. A payment tagged with several values of the key
+/// counts once for each.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TagReportResponse {
+    key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+    entries: Vec<TagReportEntry>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TagReportEntry {
+    /// Tag value, or category id when drilling down into a value.
+    key: Option<String>,
+    /// Tag value, or category name when drilling down into a value.
+    label: Option<String>,
+    currency: String,
+    total_in_cents: AmountInCents,
+    income_in_cents: AmountInCents,
+    expenses_in_cents: AmountInCents,
+    payment_count: i64,
+}
+
+#[tracing::instrument(name = "Retrieving the tag report", skip(user, connection_pool))]
+pub async fn get_tag_report(
+    query: web::Query<TagReportQuery>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    let query = query.into_inner();
+    let key = match TagKey::parse(query.key) {
+        Ok(key) => key,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    let value = match query.value.map(TagValue::parse).transpose() {
+        Ok(value) => value,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from > to {
+            return HttpResponse::BadRequest().body("from must not be after to");
+        }
+    }
+
+    match get_tag_report_from_db(
+        connection_pool.get_ref(),
+        &user.sub,
+        &key,
+        value.as_ref(),
+        query.from,
+        query.to,
+    )
+    .await
+    {
+        Ok(entries) => HttpResponse::Ok().json(TagReportResponse {
+            key: key.as_ref().to_string(),
+            value: value.map(|v| v.as_ref().to_string()),
+            entries,
+        }),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[tracing::instrument(
+    name = "Retrieving the tag report from database",
+    skip(connection_pool)
+)]
+async fn get_tag_report_from_db(
+    connection_pool: &PgPool,
+    user_id: &str,
+    key: &TagKey,
+    value: Option<&TagValue>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Vec<TagReportEntry>, sqlx::Error> {
+    // Tags are joined once per distinct value so that a tag repeated on a
+    // payment does not count its amount twice. Split payments contribute one
+    // line per split, so categories use the split amounts.
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            CASE WHEN $3::text IS NULL THEN t.value ELSE l.category_id::text END as key,
+            CASE WHEN $3::text IS NULL THEN t.value ELSE c.name END as label,
+            p.currency,
+            COALESCE(SUM(l.amount), 0)::bigint as "total!",
+            COALESCE(SUM(CASE WHEN l.amount > 0 THEN l.amount ELSE 0 END), 0)::bigint as "income!",
+            COALESCE(SUM(CASE WHEN l.amount < 0 THEN l.amount ELSE 0 END), 0)::bigint as "expenses!",
+            count(DISTINCT p.id) as "payment_count!"
+        FROM expenses.payments p
+        JOIN (
+            SELECT DISTINCT payment_id, value FROM expenses.payments_tags
+            WHERE user_id = $1 AND key = $2
+        ) t ON t.payment_id = p.id
+        JOIN expenses.payment_lines l ON l.payment_id = p.id
+        LEFT JOIN expenses.categories c ON c.id = l.category_id
+        WHERE p.user_id = $1
+          AND p.transfer_id IS NULL
+          AND ($3::text IS NULL OR t.value = $3)
+          AND ($4::date IS NULL OR DATE(p.accounting_date) >= $4)
+          AND ($5::date IS NULL OR DATE(p.accounting_date) <= $5)
+        GROUP BY 1, 2, 3
+        ORDER BY lower(CASE WHEN $3::text IS NULL THEN t.value ELSE c.name END) NULLS LAST, 1, 3
+        "#,
+        user_id,
+        key.as_ref(),
+        value.map(|v| v.as_ref()),
+        from,
+        to
+    )
+    .fetch_all(connection_pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| TagReportEntry {
+            key: row.key,
+            label: row.label,
+            currency: row.currency,
+            total_in_cents: AmountInCents::new(row.total),
+            income_in_cents: AmountInCents::new(row.income),
+            expenses_in_cents: AmountInCents::new(row.expenses),
+            payment_count: row.payment_count,
+        })
+        .collect())
+}
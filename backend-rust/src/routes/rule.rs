@@ -0,0 +1,659 @@
+use crate::auth::AuthenticatedUser;
+use crate::domain::{apply_rules, Rule, RuleAction, RuleCondition, RuleOutcome, RuleSubject, Tag};
+use crate::notifier::Notifier;
+use crate::routes::budget::spawn_budget_alerts_for_dates;
+use crate::routes::payment::{
+    find_category_id, insert_payment_tags_in_transaction, parse_tags, CategoryIdentifier,
+    PaymentDto, TagDto,
+};
+use crate::routes::wallet::get_wallet_by_name;
+use actix_web::web::Json;
+use actix_web::{web, HttpResponse, Responder};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+const MAX_NAME_LENGTH: usize = 100;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleDto {
+    name: String,
+    /// Rules apply in ascending position; new rules go last by default.
+    position: Option<i32>,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+    conditions: Vec<RuleCondition>,
+    actions: Vec<RuleActionDto>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// A rule action as sent by clients: categories are given by id or name.
+#[derive(Deserialize, Debug)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum RuleActionDto {
+    SetCategory { category_id: CategoryIdentifier },
+    SetWallet { wallet: String },
+    AddTag { key: String, value: String },
+    SetDescription { description: String },
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleResponseDto {
+    id: Uuid,
+    name: String,
+    position: i32,
+    enabled: bool,
+    conditions: Vec<RuleCondition>,
+    actions: Vec<RuleAction>,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RuleApplyQuery {
+    /// Updates the matching payments instead of returning a preview.
+    #[serde(default)]
+    commit: bool,
+}
+
+/// A payment the rule changes, with the new values of the changed fields.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleMatchDto {
+    payment_id: Uuid,
+    merchant_name: String,
+    accounting_date: Option<NaiveDateTime>,
+    amount_in_cents: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    category_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    wallet: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<TagDto>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RuleApplicationDto {
+    committed: bool,
+    payments: Vec<RuleMatchDto>,
+}
+
+enum RuleError {
+    Validation(String),
+    NotFound,
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for RuleError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+impl RuleError {
+    fn into_response(self) -> HttpResponse {
+        match self {
+            Self::Validation(e) => HttpResponse::BadRequest().body(e),
+            Self::NotFound => HttpResponse::NotFound().finish(),
+            Self::Database(e) => {
+                tracing::error!("Failed to execute query: {:?}", e);
+                HttpResponse::InternalServerError().finish()
+            }
+        }
+    }
+}
+
+struct RuleRow {
+    id: Uuid,
+    name: String,
+    position: i32,
+    enabled: bool,
+    conditions: serde_json::Value,
+    actions: serde_json::Value,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+}
+
+impl RuleRow {
+    fn into_dto(self) -> RuleResponseDto {
+        RuleResponseDto {
+            conditions: serde_json::from_value(self.conditions).unwrap_or_else(|e| {
+                tracing::error!("Failed to parse conditions of rule {}: {:?}", self.id, e);
+                Vec::new()
+            }),
+            actions: serde_json::from_value(self.actions).unwrap_or_else(|e| {
+                tracing::error!("Failed to parse actions of rule {}: {:?}", self.id, e);
+                Vec::new()
+            }),
+            id: self.id,
+            name: self.name,
+            position: self.position,
+            enabled: self.enabled,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+/// A validated rule ready to be stored.
+struct NewRule {
+    name: String,
+    position: Option<i32>,
+    enabled: bool,
+    conditions: Vec<RuleCondition>,
+    actions: Vec<RuleAction>,
+}
+
+/// Validates the rule, resolves its categories and checks that its wallets exist.
+async fn parse_rule(
+    dto: RuleDto,
+    user_id: &str,
+    connection_pool: &PgPool,
+) -> Result<NewRule, RuleError> {
+    use RuleError::Validation;
+
+    let name = dto.name.trim().to_string();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        return Err(Validation(format!(
+            "Rule names must have 1 to {} characters.",
+            MAX_NAME_LENGTH
+        )));
+    }
+    let mut conditions = Vec::new();
+    for condition in dto.conditions {
+        conditions.push(match condition {
+            RuleCondition::Wallet { wallet } => RuleCondition::Wallet {
+                wallet: existing_wallet(&wallet, user_id, connection_pool).await?,
+            },
+            condition => condition,
+        });
+    }
+    let mut actions = Vec::new();
+    for action in dto.actions {
+        actions.push(match action {
+            RuleActionDto::SetCategory { category_id } => RuleAction::SetCategory {
+                category_id: find_category_id(&category_id, user_id, connection_pool)
+                    .await?
+                    .ok_or_else(|| Validation("categoryId not found".to_string()))?,
+            },
+            RuleActionDto::SetWallet { wallet } => RuleAction::SetWallet {
+                wallet: existing_wallet(&wallet, user_id, connection_pool).await?,
+            },
+            RuleActionDto::AddTag { key, value } => {
+                let tag = Tag::parse(key, value).map_err(Validation)?;
+                RuleAction::AddTag {
+                    key: tag.key.as_ref().to_string(),
+                    value: tag.value.as_ref().to_string(),
+                }
+            }
+            RuleActionDto::SetDescription { description } => {
+                RuleAction::SetDescription { description }
+            }
+        });
+    }
+    // Checks the patterns, ranges and values
+    Rule::parse(conditions.clone(), actions.clone()).map_err(Validation)?;
+    Ok(NewRule {
+        name,
+        position: dto.position,
+        enabled: dto.enabled,
+        conditions,
+        actions,
+    })
+}
+
+async fn existing_wallet(
+    name: &str,
+    user_id: &str,
+    connection_pool: &PgPool,
+) -> Result<String, RuleError> {
+    get_wallet_by_name(name.trim(), connection_pool, user_id)
+        .await?
+        .map(|wallet| wallet.name.as_ref().to_string())
+        .ok_or_else(|| RuleError::Validation(format!("Wallet '{}' not found", name.trim())))
+}
+
+/// Enabled rules of the user, in the order they apply.
+#[tracing::instrument(name = "Loading rules", skip(executor))]
+pub(crate) async fn load_rules(
+    executor: impl PgExecutor<'_>,
+    user_id: &str,
+) -> Result<Vec<Rule>, sqlx::Error> {
+    let rows = sqlx::query_as!(
+        RuleRow,
+        r#"
+        SELECT id, name, position, enabled, conditions, actions, created_at, updated_at
+        FROM expenses.rules
+        WHERE user_id = $1 AND enabled
+        ORDER BY position, created_at, id
+        "#,
+        user_id
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| {
+            let id = row.id;
+            let rule = row.into_dto();
+            Rule::parse(rule.conditions, rule.actions)
+                .map_err(|e| tracing::error!("Skipping invalid rule {}: {}", id, e))
+                .ok()
+        })
+        .collect())
+}
+
+/// Applies the rules of the user to a payment about to be created. Rules take
+/// precedence over the category, wallet and description of the request; their
+/// tags replace the tags of the request with the same key.
+pub(crate) async fn apply_rules_to_payment(
+    payment: &mut PaymentDto,
+    user_id: &str,
+    connection_pool: &PgPool,
+) -> Result<(), sqlx::Error> {
+    let rules = load_rules(connection_pool, user_id).await?;
+    let outcome = apply_rules(
+        &rules,
+        &RuleSubject {
+            merchant_name: &payment.merchant_name,
+            description: payment.description.as_deref(),
+            amount_in_cents: payment.amount_in_cents.cents(),
+            wallet: payment.wallet.as_deref(),
+        },
+    );
+    if let Some(category_id) = outcome.category_id {
+        payment.category_id = CategoryIdentifier::Uid(category_id);
+    }
+    if let Some(wallet) = outcome.wallet {
+        payment.wallet = Some(wallet);
+    }
+    if let Some(description) = outcome.description {
+        payment.description = Some(description);
+    }
+    if !outcome.tags.is_empty() {
+        let mut tags = payment.tags.take().unwrap_or_default();
+        tags.retain(|tag| !outcome.tags.iter().any(|(key, _)| *key == tag.key));
+        tags.extend(outcome.tags.into_iter().map(TagDto::from));
+        payment.tags = Some(tags);
+    }
+    Ok(())
+}
+
+#[tracing::instrument(name = "Retrieving rules", skip(user, connection_pool))]
+pub async fn get_rules(
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    match sqlx::query_as!(
+        RuleRow,
+        r#"
+        SELECT id, name, position, enabled, conditions, actions, created_at, updated_at
+        FROM expenses.rules
+        WHERE user_id = $1
+        ORDER BY position, created_at, id
+        "#,
+        user.sub
+    )
+    .fetch_all(connection_pool.get_ref())
+    .await
+    {
+        Ok(rows) => {
+            HttpResponse::Ok().json(rows.into_iter().map(RuleRow::into_dto).collect::<Vec<_>>())
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+async fn get_rule_from_db(
+    executor: impl PgExecutor<'_>,
+    rule_id: Uuid,
+    user_id: &str,
+) -> Result<RuleResponseDto, RuleError> {
+    sqlx::query_as!(
+        RuleRow,
+        r#"
+        SELECT id, name, position, enabled, conditions, actions, created_at, updated_at
+        FROM expenses.rules
+        WHERE id = $1 AND user_id = $2
+        "#,
+        rule_id,
+        user_id
+    )
+    .fetch_optional(executor)
+    .await?
+    .map(RuleRow::into_dto)
+    .ok_or(RuleError::NotFound)
+}
+
+#[tracing::instrument(name = "Retrieving a rule", skip(user, connection_pool))]
+pub async fn get_rule(
+    path: web::Path<Uuid>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    match get_rule_from_db(connection_pool.get_ref(), path.into_inner(), &user.sub).await {
+        Ok(rule) => HttpResponse::Ok().json(rule),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[tracing::instrument(
+    name = "Creating a rule",
+    skip(payload, user, connection_pool),
+    fields(name = %payload.name)
+)]
+pub async fn create_rule(
+    payload: Json<RuleDto>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    match insert_rule(payload.into_inner(), &user.sub, connection_pool.get_ref()).await {
+        Ok(rule) => HttpResponse::Created().json(rule),
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn insert_rule(
+    dto: RuleDto,
+    user_id: &str,
+    connection_pool: &PgPool,
+) -> Result<RuleResponseDto, RuleError> {
+    let rule = parse_rule(dto, user_id, connection_pool).await?;
+    let row = sqlx::query_as!(
+        RuleRow,
+        r#"
+        INSERT INTO expenses.rules (user_id, name, position, enabled, conditions, actions)
+        VALUES ($1, $2,
+                COALESCE($3, (SELECT COALESCE(max(position) + 1, 0)
+                              FROM expenses.rules WHERE user_id = $1)),
+                $4, $5, $6)
+        RETURNING id, name, position, enabled, conditions, actions, created_at, updated_at
+        "#,
+        user_id,
+        rule.name,
+        rule.position,
+        rule.enabled,
+        serde_json::json!(rule.conditions),
+        serde_json::json!(rule.actions)
+    )
+    .fetch_one(connection_pool)
+    .await?;
+    Ok(row.into_dto())
+}
+
+#[tracing::instrument(
+    name = "Updating a rule",
+    skip(payload, user, connection_pool),
+    fields(name = %payload.name)
+)]
+pub async fn update_rule(
+    path: web::Path<Uuid>,
+    payload: Json<RuleDto>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    match replace_rule(
+        path.into_inner(),
+        payload.into_inner(),
+        &user.sub,
+        connection_pool.get_ref(),
+    )
+    .await
+    {
+        Ok(rule) => HttpResponse::Ok().json(rule),
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn replace_rule(
+    rule_id: Uuid,
+    dto: RuleDto,
+    user_id: &str,
+    connection_pool: &PgPool,
+) -> Result<RuleResponseDto, RuleError> {
+    let rule = parse_rule(dto, user_id, connection_pool).await?;
+    sqlx::query_as!(
+        RuleRow,
+        r#"
+        UPDATE expenses.rules
+        SET name = $3, position = COALESCE($4, position), enabled = $5, conditions = $6,
+            actions = $7, updated_at = now()
+        WHERE id = $1 AND user_id = $2
+        RETURNING id, name, position, enabled, conditions, actions, created_at, updated_at
+        "#,
+        rule_id,
+        user_id,
+        rule.name,
+        rule.position,
+        rule.enabled,
+        serde_json::json!(rule.conditions),
+        serde_json::json!(rule.actions)
+    )
+    .fetch_optional(connection_pool)
+    .await?
+    .map(RuleRow::into_dto)
+    .ok_or(RuleError::NotFound)
+}
+
+#[tracing::instrument(name = "Deleting a rule", skip(user, connection_pool))]
+pub async fn delete_rule(
+    path: web::Path<Uuid>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    match sqlx::query!(
+        "DELETE FROM expenses.rules WHERE id = $1 AND user_id = $2",
+        path.into_inner(),
+        user.sub
+    )
+    .execute(connection_pool.get_ref())
+    .await
+    {
+        Ok(result) if result.rows_affected() == 0 => HttpResponse::NotFound().finish(),
+        // Payments keep what the rule already changed
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Applies one rule to the existing payments, disabled or not. Without
+/// `commit=true` only previews the changes.
+#[tracing::instrument(
+    name = "Applying a rule to existing payments",
+    skip(user, connection_pool, notifier)
+)]
+pub async fn apply_rule(
+    path: web::Path<Uuid>,
+    query: web::Query<RuleApplyQuery>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+    notifier: web::Data<dyn Notifier>,
+) -> impl Responder {
+    match apply_rule_to_payments(
+        path.into_inner(),
+        query.commit,
+        &user.sub,
+        connection_pool.get_ref(),
+    )
+    .await
+    {
+        Ok((application, dates)) => {
+            if application.committed {
+                spawn_budget_alerts_for_dates(
+                    &user.sub,
+                    dates,
+                    connection_pool.get_ref(),
+                    notifier.into_inner(),
+                );
+            }
+            HttpResponse::Ok().json(application)
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct CurrentTag {
+    key: String,
+    value: String,
+}
+
+/// Also returns the accounting dates of the changed payments.
+async fn apply_rule_to_payments(
+    rule_id: Uuid,
+    commit: bool,
+    user_id: &str,
+    connection_pool: &PgPool,
+) -> Result<(RuleApplicationDto, Vec<chrono::NaiveDate>), RuleError> {
+    let stored = get_rule_from_db(connection_pool, rule_id, user_id).await?;
+    let rule = Rule::parse(stored.conditions, stored.actions).map_err(RuleError::Validation)?;
+
+    // Transfer legs are not payments to categorise
+    let query = sqlx::query!(
+        r#"
+        SELECT p.id, p.merchant_name as "merchant_name!", p.description, p.amount,
+               p.accounting_date, p.category_id, w.name as "wallet?",
+               COALESCE((SELECT json_agg(json_build_object('key', pt.key, 'value', pt.value))
+                         FROM expenses.payments_tags pt WHERE pt.payment_id = p.id),
+                        '[]'::json) as "tags!"
+        FROM expenses.payments p
+        LEFT JOIN expenses.wallets w ON w.id = p.wallet_id
+        WHERE p.user_id = $1 AND p.transfer_id IS NULL AND p.merchant_name IS NOT NULL
+        ORDER BY p.accounting_date DESC NULLS LAST, p.id
+        "#,
+        user_id
+    );
+    // A preview only reads; a commit locks the payments it may change until it is done
+    let mut tx = None;
+    let payments = if commit {
+        let tx = tx.insert(connection_pool.begin().await?);
+        sqlx::query!(
+            r#"
+            SELECT id FROM expenses.payments
+            WHERE user_id = $1 AND transfer_id IS NULL AND merchant_name IS NOT NULL
+            FOR UPDATE
+            "#,
+            user_id
+        )
+        .fetch_all(&mut **tx)
+        .await?;
+        query.fetch_all(&mut **tx).await?
+    } else {
+        query.fetch_all(connection_pool).await?
+    };
+
+    let mut matches = Vec::new();
+    let mut dates = Vec::new();
+    for payment in payments {
+        let RuleOutcome {
+            category_id,
+            wallet,
+            description,
+            tags,
+        } = apply_rules(
+            [&rule],
+            &RuleSubject {
+                merchant_name: &payment.merchant_name,
+                description: payment.description.as_deref(),
+                amount_in_cents: payment.amount.unwrap_or_default(),
+                wallet: payment.wallet.as_deref(),
+            },
+        );
+        let current_tags: Vec<CurrentTag> =
+            serde_json::from_value(payment.tags).unwrap_or_default();
+        // Only what the rule actually changes
+        let change = RuleMatchDto {
+            payment_id: payment.id,
+            merchant_name: payment.merchant_name,
+            accounting_date: payment.accounting_date,
+            amount_in_cents: payment.amount,
+            category_id: category_id.filter(|id| *id != payment.category_id),
+            wallet: wallet.filter(|w| Some(w) != payment.wallet.as_ref()),
+            description: description.filter(|d| Some(d) != payment.description.as_ref()),
+            tags: tags
+                .into_iter()
+                .filter(|(key, value)| {
+                    !current_tags
+                        .iter()
+                        .any(|tag| tag.key == *key && tag.value == *value)
+                })
+                .map(TagDto::from)
+                .collect(),
+        };
+        if change.category_id.is_none()
+            && change.wallet.is_none()
+            && change.description.is_none()
+            && change.tags.is_empty()
+        {
+            continue;
+        }
+        if let Some(tx) = tx.as_mut() {
+            let wallet_id = match &change.wallet {
+                Some(name) => Some(
+                    get_wallet_by_name(name, &mut **tx, user_id)
+                        .await?
+                        .and_then(|w| w.id)
+                        .ok_or_else(|| {
+                            RuleError::Validation(format!("Wallet '{}' not found", name))
+                        })?,
+                ),
+                None => None,
+            };
+            sqlx::query!(
+                r#"
+                UPDATE expenses.payments
+                SET category_id = COALESCE($2, category_id), wallet_id = COALESCE($3, wallet_id),
+                    description = COALESCE($4, description)
+                WHERE id = $1
+                "#,
+                change.payment_id,
+                change.category_id,
+                wallet_id,
+                change.description
+            )
+            .execute(&mut **tx)
+            .await?;
+            let tags = parse_tags(Some(change.tags.clone())).map_err(RuleError::Validation)?;
+            for tag in &tags {
+                sqlx::query!(
+                    "DELETE FROM expenses.payments_tags WHERE payment_id = $1 AND key = $2",
+                    change.payment_id,
+                    tag.key.as_ref()
+                )
+                .execute(&mut **tx)
+                .await?;
+            }
+            insert_payment_tags_in_transaction(tx, change.payment_id, &tags, user_id).await?;
+            dates.extend(change.accounting_date.map(|date| date.date()));
+        }
+        matches.push(change);
+    }
+    if let Some(tx) = tx {
+        tx.commit().await?;
+    }
+    Ok((
+        RuleApplicationDto {
+            committed: commit,
+            payments: matches,
+        },
+        dates,
+    ))
+}
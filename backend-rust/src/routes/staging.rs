@@ -0,0 +1,544 @@
+use crate::auth::AuthenticatedUser;
+use crate::domain::{
+    apply_rules, AmountInCents, Currency, Money, Payment, PaymentMerchant, Rule, RuleOutcome,
+    RuleSubject, StagingStatus, StagingTransaction,
+};
+use crate::notifier::Notifier;
+use crate::routes::budget::spawn_budget_alerts_for_dates;
+use crate::routes::payment::{
+    insert_payment_in_transaction, insert_payment_tags_in_transaction, parse_tags,
+    resolve_category_id, CategoryIdentifier, CategoryResolutionError, PagedResponse, PaymentDto,
+    TagDto,
+};
+use crate::routes::payment_duplicate::{duplicate_error, find_duplicate_payments};
+use crate::routes::rule::load_rules;
+use crate::routes::wallet::get_wallet_by_name;
+use actix_web::web::Json;
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const MAX_PAGE_SIZE: i64 = 100;
+
+#[derive(Deserialize, Debug)]
+pub struct StagingTransactionsQuery {
+    status: Option<String>,
+    #[serde(default)]
+    page: i64,
+    #[serde(default = "default_size")]
+    size: i64,
+}
+
+fn default_size() -> i64 {
+    20
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StagingTransactionDto {
+    pub id: Uuid,
+    pub bank_transaction_id: String,
+    pub amount_in_cents: i64,
+    pub currency: String,
+    pub booking_date: NaiveDate,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value_date: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub creditor_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debtor_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remittance_info: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggested_category: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggested_merchant: Option<String>,
+    pub status: StagingStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub imported_payment_id: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Review of a staging transaction: replaces its suggestions and status.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StagingTransactionReviewDto {
+    suggested_category: Option<String>,
+    suggested_merchant: Option<String>,
+    status: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct StagingImportDto {
+    /// Wallet receiving the payments.
+    wallet: Option<String>,
+    /// Transactions to import; every reviewed transaction when omitted.
+    ids: Option<Vec<Uuid>>,
+    /// Imports the transactions that look like payments already recorded.
+    #[serde(default)]
+    force: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedStagingTransactionDto {
+    pub id: Uuid,
+    pub payment_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RejectedStagingTransactionDto {
+    pub id: Uuid,
+    pub error: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StagingImportResponse {
+    pub imported: Vec<ImportedStagingTransactionDto>,
+    pub failed: Vec<RejectedStagingTransactionDto>,
+}
+
+enum StagingError {
+    Validation(String),
+    NotFound,
+    Conflict(String),
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for StagingError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+impl StagingError {
+    fn into_response(self) -> HttpResponse {
+        match self {
+            Self::Validation(e) => HttpResponse::BadRequest().body(e),
+            Self::NotFound => HttpResponse::NotFound().finish(),
+            Self::Conflict(e) => HttpResponse::Conflict().body(e),
+            Self::Database(e) => {
+                tracing::error!("Failed to execute query: {:?}", e);
+                HttpResponse::InternalServerError().finish()
+            }
+        }
+    }
+}
+
+struct StagingTransactionRow {
+    id: Uuid,
+    bank_transaction_id: String,
+    amount_in_cents: i64,
+    currency: String,
+    booking_date: NaiveDate,
+    value_date: Option<NaiveDate>,
+    creditor_name: Option<String>,
+    debtor_name: Option<String>,
+    remittance_info: Option<String>,
+    suggested_category: Option<String>,
+    suggested_merchant: Option<String>,
+    status: String,
+    imported_payment_id: Option<Uuid>,
+    updated_at: DateTime<Utc>,
+}
+
+impl StagingTransactionRow {
+    fn into_dto(self) -> StagingTransactionDto {
+        StagingTransactionDto {
+            id: self.id,
+            bank_transaction_id: self.bank_transaction_id,
+            amount_in_cents: self.amount_in_cents,
+            currency: self.currency,
+            booking_date: self.booking_date,
+            value_date: self.value_date,
+            creditor_name: self.creditor_name,
+            debtor_name: self.debtor_name,
+            remittance_info: self.remittance_info,
+            suggested_category: self.suggested_category,
+            suggested_merchant: self.suggested_merchant,
+            status: StagingStatus::parse(&self.status).expect("Stored status should be valid"),
+            imported_payment_id: self.imported_payment_id,
+            updated_at: self.updated_at,
+        }
+    }
+
+    fn into_domain(self, user_id: &str) -> StagingTransaction {
+        StagingTransaction {
+            bank_transaction_id: self.bank_transaction_id,
+            amount: Money::new(
+                AmountInCents::new(self.amount_in_cents),
+                Currency::parse(self.currency).expect("Stored currency should be valid"),
+            ),
+            booking_date: self.booking_date,
+            value_date: self.value_date,
+            creditor_name: self.creditor_name,
+            debtor_name: self.debtor_name,
+            remittance_info: self.remittance_info,
+            suggested_category: self.suggested_category,
+            suggested_merchant: self.suggested_merchant,
+            status: StagingStatus::parse(&self.status).expect("Stored status should be valid"),
+            user_id: user_id.to_string(),
+        }
+    }
+}
+
+#[tracing::instrument(name = "Retrieving staging transactions", skip(user, connection_pool))]
+pub async fn get_staging_transactions(
+    query: web::Query<StagingTransactionsQuery>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    let status = match query
+        .status
+        .as_deref()
+        .map(StagingStatus::parse)
+        .transpose()
+    {
+        Ok(status) => status,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    if query.page < 0 || !(1..=MAX_PAGE_SIZE).contains(&query.size) {
+        return HttpResponse::BadRequest().body(format!(
+            "page must not be negative and size must be between 1 and {}",
+            MAX_PAGE_SIZE
+        ));
+    }
+
+    let rows = sqlx::query_as!(
+        StagingTransactionRow,
+        r#"
+        SELECT id, bank_transaction_id, amount_in_cents, currency, booking_date, value_date,
+               creditor_name, debtor_name, remittance_info, suggested_category,
+               suggested_merchant, status, imported_payment_id, updated_at
+        FROM expenses.staging_transactions
+        WHERE user_id = $1 AND ($2::text IS NULL OR status = $2)
+        ORDER BY booking_date DESC, created_at DESC, id
+        LIMIT $3 OFFSET $4
+        "#,
+        user.sub,
+        status.map(|s| s.as_str()),
+        query.size,
+        query.page * query.size
+    )
+    .fetch_all(connection_pool.get_ref())
+    .await;
+
+    match rows {
+        Ok(rows) => HttpResponse::Ok().json(PagedResponse {
+            content: rows
+                .into_iter()
+                .map(StagingTransactionRow::into_dto)
+                .collect(),
+            page: query.page,
+            size: query.size,
+        }),
+        Err(e) => StagingError::from(e).into_response(),
+    }
+}
+
+#[tracing::instrument(
+    name = "Reviewing a staging transaction",
+    skip(path, payload, user, connection_pool),
+    fields(staging_transaction_id = %path)
+)]
+pub async fn update_staging_transaction(
+    path: web::Path<Uuid>,
+    payload: Json<StagingTransactionReviewDto>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    match review_staging_transaction(
+        path.into_inner(),
+        payload.into_inner(),
+        &user.sub,
+        connection_pool.get_ref(),
+    )
+    .await
+    {
+        Ok(transaction) => HttpResponse::Ok().json(transaction),
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn review_staging_transaction(
+    id: Uuid,
+    review: StagingTransactionReviewDto,
+    user_id: &str,
+    connection_pool: &PgPool,
+) -> Result<StagingTransactionDto, StagingError> {
+    use StagingError::Validation;
+
+    let status = StagingStatus::parse(review.status.trim()).map_err(Validation)?;
+    let suggested_merchant = review
+        .suggested_merchant
+        .filter(|m| !m.trim().is_empty())
+        .map(|m| PaymentMerchant::parse(m.trim().to_string()))
+        .transpose()
+        .map_err(Validation)?;
+    let suggested_category = review
+        .suggested_category
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty());
+
+    let mut tx = connection_pool.begin().await?;
+    let current = sqlx::query_scalar!(
+        "SELECT status FROM expenses.staging_transactions WHERE id = $1 AND user_id = $2 FOR UPDATE",
+        id,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(StagingError::NotFound)?;
+    let current = StagingStatus::parse(&current).expect("Stored status should be valid");
+    match current.can_be_reviewed_as(status) {
+        Ok(()) => {}
+        Err(e) if current == StagingStatus::Imported => return Err(StagingError::Conflict(e)),
+        Err(e) => return Err(Validation(e)),
+    }
+
+    let row = sqlx::query_as!(
+        StagingTransactionRow,
+        r#"
+        UPDATE expenses.staging_transactions
+        SET suggested_category = $3, suggested_merchant = $4, status = $5, updated_at = now()
+        WHERE id = $1 AND user_id = $2
+        RETURNING id, bank_transaction_id, amount_in_cents, currency, booking_date, value_date,
+                  creditor_name, debtor_name, remittance_info, suggested_category,
+                  suggested_merchant, status, imported_payment_id, updated_at
+        "#,
+        id,
+        user_id,
+        suggested_category,
+        suggested_merchant.as_ref().map(|m| m.as_ref()),
+        status.as_str()
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(row.into_dto())
+}
+
+#[tracing::instrument(
+    name = "Importing staging transactions",
+    skip(payload, user, connection_pool, notifier),
+    fields(user_id = %user.sub)
+)]
+pub async fn import_staging_transactions(
+    payload: Option<Json<StagingImportDto>>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+    notifier: web::Data<dyn Notifier>,
+) -> impl Responder {
+    let request = payload.map(Json::into_inner).unwrap_or_default();
+    match import_reviewed_transactions(request, &user.sub, connection_pool.get_ref()).await {
+        Ok((response, dates)) => {
+            spawn_budget_alerts_for_dates(
+                &user.sub,
+                dates,
+                connection_pool.get_ref(),
+                notifier.into_inner(),
+            );
+            HttpResponse::Ok().json(response)
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Imports the reviewed transactions in one database transaction. Transactions
+/// that fail the payment validation stay reviewed and are reported.
+/// Also returns the booking dates of the created payments.
+async fn import_reviewed_transactions(
+    request: StagingImportDto,
+    user_id: &str,
+    connection_pool: &PgPool,
+) -> Result<(StagingImportResponse, Vec<NaiveDate>), StagingError> {
+    let wallet = match &request.wallet {
+        Some(name) => Some(
+            get_wallet_by_name(name, connection_pool, user_id)
+                .await?
+                .ok_or_else(|| StagingError::Validation(format!("Wallet '{}' not found", name)))?,
+        ),
+        None => None,
+    };
+    let wallet_id = wallet.as_ref().and_then(|w| w.id);
+    let wallet_name = wallet.as_ref().map(|w| w.name.as_ref().to_string());
+    let default_currency = wallet.map(|w| w.default_currency).unwrap_or_default();
+    let rules = load_rules(connection_pool, user_id).await?;
+
+    let mut tx = connection_pool.begin().await?;
+    let rows = sqlx::query_as!(
+        StagingTransactionRow,
+        r#"
+        SELECT id, bank_transaction_id, amount_in_cents, currency, booking_date, value_date,
+               creditor_name, debtor_name, remittance_info, suggested_category,
+               suggested_merchant, status, imported_payment_id, updated_at
+        FROM expenses.staging_transactions
+        WHERE user_id = $1 AND status = 'reviewed' AND ($2::uuid[] IS NULL OR id = ANY($2))
+        ORDER BY booking_date, created_at, id
+        FOR UPDATE
+        "#,
+        user_id,
+        request.ids.as_deref()
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut response = StagingImportResponse {
+        imported: Vec::new(),
+        failed: Vec::new(),
+    };
+    for id in request.ids.iter().flatten() {
+        if !rows.iter().any(|row| row.id == *id) {
+            response.failed.push(RejectedStagingTransactionDto {
+                id: *id,
+                error: "Not found or not reviewed".to_string(),
+            });
+        }
+    }
+    let mut dates = Vec::new();
+    for row in rows {
+        let id = row.id;
+        let transaction = row.into_domain(user_id);
+        let outcome = rule_outcome(&rules, &transaction, wallet_name.as_deref());
+        let payment = match to_payment(
+            &transaction,
+            &outcome,
+            wallet_id,
+            default_currency.clone(),
+            &mut *tx,
+        )
+        .await?
+        {
+            Ok(payment) => payment,
+            Err(error) => {
+                response
+                    .failed
+                    .push(RejectedStagingTransactionDto { id, error });
+                continue;
+            }
+        };
+        let tags = match parse_tags(Some(outcome.tags.into_iter().map(TagDto::from).collect())) {
+            Ok(tags) => tags,
+            Err(error) => {
+                response
+                    .failed
+                    .push(RejectedStagingTransactionDto { id, error });
+                continue;
+            }
+        };
+        // Checked against committed payments only: bank transactions of the batch are distinct
+        if !request.force {
+            let duplicates = find_duplicate_payments(&payment, &mut *tx).await?;
+            if !duplicates.is_empty() {
+                response.failed.push(RejectedStagingTransactionDto {
+                    id,
+                    error: duplicate_error(&duplicates),
+                });
+                continue;
+            }
+        }
+        let payment_id = insert_payment_in_transaction(&mut tx, &payment).await?;
+        insert_payment_tags_in_transaction(&mut tx, payment_id, &tags, user_id).await?;
+        sqlx::query!(
+            r#"
+            UPDATE expenses.staging_transactions
+            SET status = 'imported', imported_payment_id = $2, updated_at = now()
+            WHERE id = $1
+            "#,
+            id,
+            payment_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        dates.push(transaction.booking_date);
+        response
+            .imported
+            .push(ImportedStagingTransactionDto { id, payment_id });
+    }
+    tx.commit().await?;
+    Ok((response, dates))
+}
+
+/// What the rules of the user change on a staging transaction. Imported
+/// payments stay in the wallet of the import: wallet actions are ignored.
+fn rule_outcome(
+    rules: &[Rule],
+    transaction: &StagingTransaction,
+    wallet: Option<&str>,
+) -> RuleOutcome {
+    let Some(merchant_name) = transaction.merchant_name() else {
+        return RuleOutcome::default();
+    };
+    apply_rules(
+        rules,
+        &RuleSubject {
+            merchant_name,
+            description: transaction.remittance_info.as_deref(),
+            amount_in_cents: transaction.amount.amount().cents(),
+            wallet,
+        },
+    )
+}
+
+/// Builds the payment of a staging transaction with the validation of payments
+/// created through the API. Rules only fill in the category and description left
+/// empty by the review and the bank. The inner error explains why it cannot be imported.
+async fn to_payment(
+    transaction: &StagingTransaction,
+    outcome: &RuleOutcome,
+    wallet_id: Option<Uuid>,
+    default_currency: Currency,
+    executor: impl sqlx::PgExecutor<'_>,
+) -> Result<Result<Payment, String>, sqlx::Error> {
+    let reviewed_category = transaction
+        .suggested_category
+        .as_deref()
+        .map(str::trim)
+        .filter(|category| !category.is_empty());
+    let category = match (reviewed_category, outcome.category_id) {
+        (Some(category), _) => category.to_string(),
+        (None, Some(category_id)) => category_id.to_string(),
+        (None, None) => return Ok(Err("No category".to_string())),
+    };
+    let Some(merchant_name) = transaction.merchant_name() else {
+        return Ok(Err("No merchant name".to_string()));
+    };
+    let category_identifier = CategoryIdentifier::from(category.as_str());
+    let category_id =
+        match resolve_category_id(&category_identifier, &transaction.user_id, executor).await {
+            Ok(Some(category_id)) => category_id,
+            Ok(None) => return Ok(Err(format!("Category '{}' not found", category))),
+            Err(CategoryResolutionError::InvalidName(e)) => return Ok(Err(e)),
+            Err(CategoryResolutionError::Database(e)) => return Err(e),
+        };
+    let dto = PaymentDto {
+        description: transaction
+            .remittance_info
+            .clone()
+            .filter(|description| !description.trim().is_empty())
+            .or_else(|| outcome.description.clone()),
+        category_id: category_identifier,
+        amount_in_cents: transaction.amount.amount(),
+        currency: Some(transaction.amount.currency().as_ref().to_string()),
+        merchant_name: merchant_name.to_string(),
+        accounting_date: transaction
+            .booking_date
+            .and_hms_opt(0, 0, 0)
+            .expect("midnight is valid"),
+        wallet: None,
+        tags: None,
+        splits: None,
+    };
+    Ok(Payment::try_from_dto(
+        dto,
+        wallet_id,
+        default_currency,
+        category_id,
+        Vec::new(),
+        transaction.user_id.clone(),
+    ))
+}
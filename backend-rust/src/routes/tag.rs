@@ -0,0 +1,230 @@
+use crate::auth::AuthenticatedUser;
+use crate::domain::{TagKey, TagValue};
+use actix_web::web::Json;
+use actix_web::{web, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashSet;
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TagKeyDto {
+    key: String,
+    /// Payments carrying the key, whatever its value.
+    payment_count: i64,
+    values: Vec<TagValueDto>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TagValueDto {
+    value: String,
+    payment_count: i64,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TagRenameDto {
+    key: String,
+    /// Renames only the tags with this value; every tag of the key without it.
+    value: Option<String>,
+    new_key: Option<String>,
+    /// Requires `value`.
+    new_value: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TagRenameResponseDto {
+    /// Payments whose tags were renamed.
+    payment_count: usize,
+}
+
+/// A validated rename of `key`, or of `key:value` when a value is given.
+struct TagRename {
+    key: TagKey,
+    value: Option<TagValue>,
+    new_key: TagKey,
+    new_value: Option<TagValue>,
+}
+
+impl TryFrom<TagRenameDto> for TagRename {
+    type Error = String;
+
+    fn try_from(dto: TagRenameDto) -> Result<Self, Self::Error> {
+        let key = TagKey::parse(dto.key)?;
+        let value = dto.value.map(TagValue::parse).transpose()?;
+        let new_key = dto.new_key.map(TagKey::parse).transpose()?;
+        let new_value = dto.new_value.map(TagValue::parse).transpose()?;
+        if new_key.is_none() && new_value.is_none() {
+            return Err("Either newKey or newValue is required".to_string());
+        }
+        if value.is_none() && new_value.is_some() {
+            return Err("newValue requires the value to rename".to_string());
+        }
+        let new_key = new_key.unwrap_or_else(|| key.clone());
+        if new_key == key && new_value == value {
+            return Err("The tag already has this name".to_string());
+        }
+        Ok(Self {
+            key,
+            value,
+            new_key,
+            new_value,
+        })
+    }
+}
+
+enum TagError {
+    Validation(String),
+    NotFound,
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for TagError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+impl TagError {
+    fn into_response(self) -> HttpResponse {
+        match self {
+            Self::Validation(e) => HttpResponse::BadRequest().body(e),
+            Self::NotFound => HttpResponse::NotFound().finish(),
+            Self::Database(e) => {
+                tracing::error!("Failed to execute query: {:?}", e);
+                HttpResponse::InternalServerError().finish()
+            }
+        }
+    }
+}
+
+#[tracing::instrument(name = "Retrieving tags", skip(user, connection_pool))]
+pub async fn get_tags(
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    match get_tags_from_db(connection_pool.get_ref(), &user.sub).await {
+        Ok(tags) => HttpResponse::Ok().json(tags),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Distinct keys of the user's tags with their values, alphabetically.
+async fn get_tags_from_db(
+    connection_pool: &PgPool,
+    user_id: &str,
+) -> Result<Vec<TagKeyDto>, sqlx::Error> {
+    // The grouping set without the value counts the payments per key.
+    let rows = sqlx::query!(
+        r#"
+        SELECT key as "key!", value as "value?", count(DISTINCT payment_id) as "payment_count!"
+        FROM expenses.payments_tags
+        WHERE user_id = $1
+        GROUP BY GROUPING SETS ((key, value), (key))
+        ORDER BY lower(key), key, value NULLS FIRST
+        "#,
+        user_id
+    )
+    .fetch_all(connection_pool)
+    .await?;
+
+    let mut keys: Vec<TagKeyDto> = Vec::new();
+    for row in rows {
+        // Each key comes before its values.
+        let Some(value) = row.value else {
+            keys.push(TagKeyDto {
+                key: row.key,
+                payment_count: row.payment_count,
+                values: Vec::new(),
+            });
+            continue;
+        };
+        if let Some(key) = keys.last_mut() {
+            key.values.push(TagValueDto {
+                value,
+                payment_count: row.payment_count,
+            });
+        }
+    }
+    Ok(keys)
+}
+
+#[tracing::instrument(
+    name = "Renaming tags",
+    skip(payload, user, connection_pool),
+    fields(key = %payload.key)
+)]
+pub async fn rename_tags(
+    payload: Json<TagRenameDto>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    let rename = match TagRename::try_from(payload.into_inner()) {
+        Ok(rename) => rename,
+        Err(e) => return TagError::Validation(e).into_response(),
+    };
+    match rename_tags_in_db(&rename, &user.sub, connection_pool.get_ref()).await {
+        Ok(payment_count) => HttpResponse::Ok().json(TagRenameResponseDto { payment_count }),
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Renames the matching tags of every payment of the user, returning how many
+/// payments changed. Tags the payment already carries under the new name are
+/// dropped rather than repeated.
+async fn rename_tags_in_db(
+    rename: &TagRename,
+    user_id: &str,
+    connection_pool: &PgPool,
+) -> Result<usize, TagError> {
+    let value = rename.value.as_ref().map(|v| v.as_ref().to_string());
+    let new_value = rename.new_value.as_ref().map(|v| v.as_ref().to_string());
+
+    let mut tx = connection_pool.begin().await?;
+    let dropped = sqlx::query_scalar!(
+        r#"
+        DELETE FROM expenses.payments_tags t
+        WHERE t.user_id = $1 AND t.key = $2 AND ($3::text IS NULL OR t.value = $3)
+          AND EXISTS (
+              SELECT 1 FROM expenses.payments_tags o
+              WHERE o.payment_id = t.payment_id AND o.user_id = t.user_id
+                AND o.key = $4 AND o.value = COALESCE($5, t.value)
+          )
+        RETURNING t.payment_id
+        "#,
+        user_id,
+        rename.key.as_ref(),
+        value,
+        rename.new_key.as_ref(),
+        new_value
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    let renamed = sqlx::query_scalar!(
+        r#"
+        UPDATE expenses.payments_tags
+        SET key = $4, value = COALESCE($5, value)
+        WHERE user_id = $1 AND key = $2 AND ($3::text IS NULL OR value = $3)
+        RETURNING payment_id
+        "#,
+        user_id,
+        rename.key.as_ref(),
+        value,
+        rename.new_key.as_ref(),
+        new_value
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    let payments: HashSet<_> = dropped.into_iter().chain(renamed).collect();
+    if payments.is_empty() {
+        return Err(TagError::NotFound);
+    }
+    Ok(payments.len())
+}
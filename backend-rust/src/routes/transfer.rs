@@ -0,0 +1,250 @@
+use crate::auth::AuthenticatedUser;
+use crate::domain::{AmountInCents, Currency, Money, PaymentDescription, Transfer, Wallet};
+use crate::routes::wallet::get_wallet_by_name;
+use actix_web::web::Json;
+use actix_web::{web, HttpResponse, Responder};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Category given to transfer legs when the client does not pick one.
+const TRANSFER_CATEGORY: &str = "Transfer";
+
+#[derive(Deserialize, Debug)]
+pub struct TransferDto {
+    #[serde(rename = "fromWallet")]
+    from_wallet: String,
+    #[serde(rename = "toWallet")]
+    to_wallet: String,
+    #[serde(rename = "amountInCents")]
+    amount_in_cents: AmountInCents,
+    // ISO 4217 code; defaults to the source wallet's currency when omitted.
+    currency: Option<String>,
+    #[serde(rename = "accountingDate")]
+    accounting_date: NaiveDateTime,
+    description: Option<String>,
+    #[serde(rename = "categoryId")]
+    category_id: Option<Uuid>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TransferResponseDto {
+    pub id: Uuid,
+    #[serde(rename = "fromWallet")]
+    pub from_wallet: String,
+    #[serde(rename = "toWallet")]
+    pub to_wallet: String,
+    #[serde(rename = "amountInCents")]
+    pub amount_in_cents: AmountInCents,
+    pub currency: String,
+    #[serde(rename = "accountingDate")]
+    pub accounting_date: NaiveDateTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "debitPaymentId")]
+    pub debit_payment_id: Uuid,
+    #[serde(rename = "creditPaymentId")]
+    pub credit_payment_id: Uuid,
+}
+
+#[tracing::instrument(
+    name = "Creating a new transfer",
+    skip(payload, user, connection_pool),
+    fields(
+        from_wallet = %payload.from_wallet,
+        to_wallet = %payload.to_wallet
+    )
+)]
+pub async fn create_transfer(
+    payload: Json<TransferDto>,
+    user: AuthenticatedUser,
+    connection_pool: web::Data<PgPool>,
+) -> impl Responder {
+    let user_id = user.sub;
+    let payload = payload.into_inner();
+
+    let mut wallets = Vec::with_capacity(2);
+    for name in [&payload.from_wallet, &payload.to_wallet] {
+        match get_wallet_by_name(name, connection_pool.get_ref(), &user_id).await {
+            Ok(Some(wallet)) => wallets.push(wallet),
+            Ok(None) => {
+                tracing::error!("Wallet not found: {}", name);
+                return HttpResponse::BadRequest().body(format!("Wallet '{}' not found", name));
+            }
+            Err(e) => {
+                tracing::error!("Failed to lookup wallet: {:?}", e);
+                return HttpResponse::InternalServerError().finish();
+            }
+        }
+    }
+    let to_wallet = wallets.pop().expect("two wallets were resolved");
+    let from_wallet = wallets.pop().expect("two wallets were resolved");
+
+    let currency = match payload.currency.map(Currency::parse) {
+        Some(Ok(currency)) => currency,
+        Some(Err(e)) => return HttpResponse::BadRequest().body(e),
+        None => from_wallet.default_currency.clone(),
+    };
+    let description = match payload
+        .description
+        .filter(|s| !s.trim().is_empty())
+        .map(PaymentDescription::parse)
+        .transpose()
+    {
+        Ok(description) => description,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    let transfer = match Transfer::parse(
+        from_wallet.id.expect("stored wallets have an id"),
+        to_wallet.id.expect("stored wallets have an id"),
+        Money::new(payload.amount_in_cents, currency),
+        payload.accounting_date,
+        description,
+        user_id,
+    ) {
+        Ok(transfer) => transfer,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+
+    let category_id = match payload.category_id {
+        Some(category_id) => {
+            match category_exists(category_id, &transfer.user_id, connection_pool.get_ref()).await {
+                Ok(true) => category_id,
+                Ok(false) => return HttpResponse::BadRequest().body("categoryId not found"),
+                Err(e) => {
+                    tracing::error!("Failed to validate category: {:?}", e);
+                    return HttpResponse::InternalServerError().finish();
+                }
+            }
+        }
+        None => match get_or_create_transfer_category(connection_pool.get_ref()).await {
+            Ok(category_id) => category_id,
+            Err(e) => {
+                tracing::error!("Failed to resolve the transfer category: {:?}", e);
+                return HttpResponse::InternalServerError().finish();
+            }
+        },
+    };
+
+    match insert_transfer(
+        &transfer,
+        &from_wallet,
+        &to_wallet,
+        category_id,
+        connection_pool.get_ref(),
+    )
+    .await
+    {
+        Ok((id, debit_payment_id, credit_payment_id)) => {
+            HttpResponse::Ok().json(TransferResponseDto {
+                id,
+                from_wallet: from_wallet.name.to_string(),
+                to_wallet: to_wallet.name.to_string(),
+                amount_in_cents: transfer.amount.amount(),
+                currency: transfer.amount.currency().to_string(),
+                accounting_date: transfer.accounting_date,
+                description: transfer.description.map(|d| d.as_ref().to_string()),
+                debit_payment_id,
+                credit_payment_id,
+            })
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[tracing::instrument(name = "Checking category exists", skip(connection_pool))]
+async fn category_exists(
+    category_id: Uuid,
+    user_id: &str,
+    connection_pool: &PgPool,
+) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query_scalar!(
+        "SELECT id FROM expenses.categories WHERE id = $1 AND (user_id = $2 OR user_id IS NULL)",
+        category_id,
+        user_id
+    )
+    .fetch_optional(connection_pool)
+    .await?;
+    Ok(row.is_some())
+}
+
+#[tracing::instrument(name = "Resolving the transfer category", skip(connection_pool))]
+async fn get_or_create_transfer_category(connection_pool: &PgPool) -> Result<Uuid, sqlx::Error> {
+    // A global default, shared by the transfers of every user. Safe under concurrency
+    // thanks to the unique index on the names of the global categories.
+    sqlx::query!(
+        r#"
+        INSERT INTO expenses.categories (name) VALUES ($1)
+        ON CONFLICT (lower(name)) WHERE user_id IS NULL DO NOTHING
+        "#,
+        TRANSFER_CATEGORY
+    )
+    .execute(connection_pool)
+    .await?;
+    sqlx::query_scalar!(
+        "SELECT id FROM expenses.categories WHERE LOWER(name) = LOWER($1) AND user_id IS NULL",
+        TRANSFER_CATEGORY
+    )
+    .fetch_one(connection_pool)
+    .await
+}
+
+/// Inserts both legs of the transfer in a single transaction.
+/// Returns the transfer id and the ids of the debit and credit payments.
+#[tracing::instrument(
+    name = "Inserting a transfer in the database",
+    skip(transfer, from_wallet, to_wallet, connection_pool)
+)]
+async fn insert_transfer(
+    transfer: &Transfer,
+    from_wallet: &Wallet,
+    to_wallet: &Wallet,
+    category_id: Uuid,
+    connection_pool: &PgPool,
+) -> Result<(Uuid, Uuid, Uuid), sqlx::Error> {
+    let transfer_id = Uuid::new_v4();
+    let legs = [
+        (
+            transfer.from_wallet_id,
+            transfer.debit(),
+            Transfer::leg_merchant(&to_wallet.name, true),
+        ),
+        (
+            transfer.to_wallet_id,
+            transfer.credit(),
+            Transfer::leg_merchant(&from_wallet.name, false),
+        ),
+    ];
+
+    let mut tx = connection_pool.begin().await?;
+    let mut payment_ids = Vec::with_capacity(legs.len());
+    for (wallet_id, amount, merchant_name) in legs {
+        let payment_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO expenses.payments
+                (category_id, description, merchant_name, accounting_date, amount, wallet_id, user_id, currency, transfer_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING id
+            "#,
+            category_id,
+            transfer.description.as_ref().map(|d| d.as_ref()),
+            merchant_name.as_ref(),
+            transfer.accounting_date,
+            amount.cents(),
+            wallet_id,
+            transfer.user_id,
+            transfer.amount.currency().as_ref(),
+            transfer_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        payment_ids.push(payment_id);
+    }
+    tx.commit().await?;
+
+    Ok((transfer_id, payment_ids[0], payment_ids[1]))
+}
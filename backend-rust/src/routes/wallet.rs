@@ -1,4 +1,4 @@
-use crate::domain::{Wallet, WalletName};
+use crate::domain::{Currency, Wallet, WalletName};
 use actix_web::{web, HttpResponse, Responder};
 use sqlx::PgPool;
 use std::ops::Deref;
@@ -8,6 +8,8 @@ use uuid::Uuid;
 pub struct WalletDto {
     pub id: Option<Uuid>,
     pub name: String,
+    #[serde(rename = "defaultCurrency")]
+    pub default_currency: Option<String>,
 }
 
 impl From<Wallet> for WalletDto {
@@ -15,6 +17,7 @@ impl From<Wallet> for WalletDto {
         Self {
             id: wallet.id,
             name: wallet.name.as_ref().to_string(),
+            default_currency: Some(wallet.default_currency.as_ref().to_string()),
         }
     }
 }
@@ -23,6 +26,18 @@ impl From<Wallet> for WalletDto {
 pub struct WalletResponseDto {
     pub id: Uuid,
     pub name: String,
+    #[serde(rename = "defaultCurrency")]
+    pub default_currency: String,
+}
+
+impl From<Wallet> for WalletResponseDto {
+    fn from(wallet: Wallet) -> Self {
+        Self {
+            id: wallet.id.unwrap(),
+            name: wallet.name.as_ref().to_string(),
+            default_currency: wallet.default_currency.as_ref().to_string(),
+        }
+    }
 }
 
 #[tracing::instrument(
@@ -39,6 +54,11 @@ pub async fn create_wallet(
         Ok(name) => name,
         Err(_) => return HttpResponse::BadRequest().finish(),
     };
+    let default_currency = match payload.default_currency.clone().map(Currency::parse) {
+        Some(Ok(currency)) => currency,
+        Some(Err(e)) => return HttpResponse::BadRequest().body(e),
+        None => Currency::default(),
+    };
 
     let user_id = user.sub;
 
@@ -46,13 +66,11 @@ pub async fn create_wallet(
         id: None,
         user_id,
         name: wallet_name,
+        default_currency,
     };
 
     match insert_wallet(&input_wallet, connection_pool.deref()).await {
-        Ok(wallet) => HttpResponse::Ok().json(WalletResponseDto {
-            id: wallet.id.unwrap(),
-            name: wallet.name.as_ref().to_string(),
-        }),
+        Ok(wallet) => HttpResponse::Ok().json(WalletResponseDto::from(wallet)),
         Err(e) => {
             tracing::error!("Failed to execute query: {:?}", e);
             // Check for unique constraint violation (Postgres error code 23505)
@@ -70,12 +88,13 @@ pub async fn create_wallet(
 async fn insert_wallet(wallet: &Wallet, pool: &PgPool) -> Result<Wallet, sqlx::Error> {
     let row = sqlx::query!(
         r#"
-        INSERT INTO expenses.wallets (name, user_id)
-        VALUES ($1, $2)
-        RETURNING id, name as "name!", user_id
+        INSERT INTO expenses.wallets (name, user_id, default_currency)
+        VALUES ($1, $2, $3)
+        RETURNING id, name as "name!", user_id, default_currency
         "#,
         wallet.name.as_ref(),
-        wallet.user_id
+        wallet.user_id,
+        wallet.default_currency.as_ref()
     )
     .fetch_one(pool)
     .await?;
@@ -84,6 +103,8 @@ async fn insert_wallet(wallet: &Wallet, pool: &PgPool) -> Result<Wallet, sqlx::E
         id: Some(row.id),
         user_id: row.user_id,
         name: WalletName::parse(row.name).expect("Stored name should be valid"),
+        default_currency: Currency::parse(row.default_currency)
+            .expect("Stored currency should be valid"),
     })
 }
 
@@ -96,13 +117,8 @@ pub async fn get_wallets(
 
     match get_wallets_from_db(&user_id, connection_pool.deref()).await {
         Ok(wallets) => {
-            let dtos: Vec<WalletResponseDto> = wallets
-                .into_iter()
-                .map(|w| WalletResponseDto {
-                    id: w.id.unwrap(),
-                    name: w.name.as_ref().to_string(),
-                })
-                .collect();
+            let dtos: Vec<WalletResponseDto> =
+                wallets.into_iter().map(WalletResponseDto::from).collect();
             HttpResponse::Ok().json(dtos)
         }
         Err(e) => {
@@ -116,7 +132,7 @@ pub async fn get_wallets(
 async fn get_wallets_from_db(user_id: &str, pool: &PgPool) -> Result<Vec<Wallet>, sqlx::Error> {
     let rows = sqlx::query!(
         r#"
-        SELECT id, name as "name!", user_id
+        SELECT id, name as "name!", user_id, default_currency
         FROM expenses.wallets
         WHERE user_id = $1
         ORDER BY name
@@ -132,6 +148,8 @@ async fn get_wallets_from_db(user_id: &str, pool: &PgPool) -> Result<Vec<Wallet>
             id: Some(row.id),
             user_id: row.user_id,
             name: WalletName::parse(row.name).expect("Stored name should be valid"),
+            default_currency: Currency::parse(row.default_currency)
+                .expect("Stored currency should be valid"),
         })
         .collect();
 
@@ -177,23 +195,29 @@ async fn delete_wallet_from_db(id: Uuid, pool: &PgPool, user_id: &str) -> Result
     Ok(())
 }
 
-#[tracing::instrument(name = "Get wallet ID by name", skip(pool))]
-pub async fn get_wallet_id_by_name(
+#[tracing::instrument(name = "Get wallet by name", skip(executor))]
+pub async fn get_wallet_by_name(
     name: &str,
-    pool: &PgPool,
+    executor: impl sqlx::PgExecutor<'_>,
     user_id: &str,
-) -> Result<Option<Uuid>, sqlx::Error> {
+) -> Result<Option<Wallet>, sqlx::Error> {
     let result = sqlx::query!(
         r#"
-        SELECT id
+        SELECT id, name as "name!", user_id, default_currency
         FROM expenses.wallets
         WHERE name = $1 AND user_id = $2
         "#,
         name,
         user_id
     )
-    .fetch_optional(pool)
+    .fetch_optional(executor)
     .await?;
 
-    Ok(result.map(|r| r.id))
+    Ok(result.map(|row| Wallet {
+        id: Some(row.id),
+        user_id: row.user_id,
+        name: WalletName::parse(row.name).expect("Stored name should be valid"),
+        default_currency: Currency::parse(row.default_currency)
+            .expect("Stored currency should be valid"),
+    }))
 }
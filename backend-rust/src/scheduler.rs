@@ -0,0 +1,31 @@
+use crate::routes::materialise_recurring_payments;
+use chrono::Utc;
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Generates due recurring payments every `period`, starting immediately.
+///
+/// Deployments that prefer a CronJob run the same job once with
+/// `expenses-monitor-be materialise-recurring-payments` instead.
+pub fn spawn_recurring_payments_scheduler(
+    connection_pool: PgPool,
+    period: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        // A slow run is not followed by a burst of catch-up runs.
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            match materialise_recurring_payments(&connection_pool, Utc::now().date_naive()).await {
+                Ok(summary) => tracing::info!(
+                    failed = summary.failed,
+                    "Created {} payments from recurring payments",
+                    summary.created
+                ),
+                Err(e) => tracing::error!("Failed to materialise recurring payments: {:?}", e),
+            }
+        }
+    })
+}
@@ -1,19 +1,40 @@
+use crate::auth::JwtVerifier;
+use crate::banking::BankProviders;
 use crate::configuration::Settings;
+use crate::crypto::KeyRing;
+use crate::idempotency::idempotent;
+use crate::notifier::{build_notifier, Notifier};
 use crate::routes::{
-    create_payment, create_wallet, delete_payment, delete_wallet, get_balance, get_categories,
-    get_payment, get_recent_payments, get_wallets, greet, health_check, metrics, update_payment,
+    add_merchant_alias, apply_rule, create_budget, create_category, create_payment,
+    create_recurring_payment, create_rule, create_transfer, create_wallet, delete_budget,
+    delete_category, delete_merchant_alias, delete_payment, delete_recurring_payment, delete_rule,
+    delete_wallet, get_balance, get_budget, get_budget_status, get_budgets, get_categories,
+    get_duplicate_payments, get_exchange_rate, get_merchants, get_payment, get_payment_suggestions,
+    get_recent_payments, get_recurring_payment, get_recurring_payments, get_rule, get_rules,
+    get_staging_transactions, get_tag_report, get_tags, get_wallets, greet, health_check,
+    import_csv, import_exchange_rates_file, import_staging_transactions, merge_categories,
+    merge_merchants, metrics, rename_tags, skip_recurring_payment, sync_bank_connection,
+    update_budget, update_category, update_payment, update_recurring_payment, update_rule,
+    update_staging_transaction, upsert_exchange_rates,
 };
+use crate::scheduler::spawn_recurring_payments_scheduler;
 use crate::telemetry::init_meter;
 use actix_cors::Cors;
 use actix_web::dev::Server;
+use actix_web::middleware::from_fn;
 use actix_web::{http, web, App, HttpServer};
 use opentelemetry_instrumentation_actix_web::RequestMetrics;
 use prometheus::Registry;
 use sqlx::postgres::PgPoolOptions;
 use sqlx::PgPool;
 use std::net::TcpListener;
+use std::sync::Arc;
 use tracing_actix_web::TracingLogger;
 
+const EXCHANGE_RATES_PAYLOAD_LIMIT: usize = 16 * 1024 * 1024;
+/// Statements of up to 5 MiB, with the other fields of the form.
+const CSV_IMPORT_PAYLOAD_LIMIT: usize = 6 * 1024 * 1024;
+
 pub struct Application {
     port: u16,
     server: Server,
@@ -31,7 +52,44 @@ impl Application {
         // database configuration
         let connection_pool = get_connection_pool(&configuration);
 
-        let server = run(listener, connection_pool, metrics_registry)?;
+        // exchange rates shipped as a file, for deployments without a rates provider
+        if let Some(path) = &configuration.exchange_rates.file {
+            let imported = import_exchange_rates_file(path, &connection_pool)
+                .await
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            tracing::info!("Loaded {} exchange rates from {}", imported, path);
+        }
+
+        if configuration.recurring_payments.scheduler_enabled {
+            spawn_recurring_payments_scheduler(
+                connection_pool.clone(),
+                std::time::Duration::from_secs(
+                    configuration.recurring_payments.scheduler_interval_seconds,
+                ),
+            );
+        }
+
+        // token verification configuration
+        let jwt_verifier = JwtVerifier::new(&configuration.auth)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+
+        // budget alerts delivery
+        let notifier = build_notifier(&configuration.notifications)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+
+        let bank_providers = BankProviders::from_settings(&configuration.banking);
+        let key_ring = KeyRing::from_settings(&configuration.encryption)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+
+        let server = run(
+            listener,
+            connection_pool,
+            metrics_registry,
+            jwt_verifier,
+            notifier,
+            bank_providers,
+            key_ring,
+        )?;
 
         Ok(Self { port, server })
     }
@@ -59,9 +117,17 @@ pub fn run(
     listener: TcpListener,
     connection_pool: PgPool,
     metrics_registry: Registry,
+    jwt_verifier: JwtVerifier,
+    notifier: Arc<dyn Notifier>,
+    bank_providers: BankProviders,
+    key_ring: KeyRing,
 ) -> Result<Server, std::io::Error> {
     let connection_pool = web::Data::new(connection_pool);
     let metrics_registry = web::Data::new(metrics_registry);
+    let jwt_verifier = web::Data::new(jwt_verifier);
+    let notifier: web::Data<dyn Notifier> = web::Data::from(notifier);
+    let bank_providers = web::Data::new(bank_providers);
+    let key_ring = web::Data::new(key_ring);
 
     let server = HttpServer::new(move || {
         // Configure CORS for local development and production
@@ -79,6 +145,7 @@ pub fn run(
                 http::header::AUTHORIZATION,
                 http::header::ACCEPT,
                 http::header::CONTENT_TYPE,
+                http::header::HeaderName::from_static("idempotency-key"),
             ])
             .max_age(3600);
 
@@ -92,16 +159,130 @@ pub fn run(
             .route("/api/payments/categories", web::get().to(get_categories))
             .route("/greet", web::get().to(greet))
             .route("/api/payments", web::get().to(get_recent_payments))
+            .route(
+                "/api/payments/duplicates",
+                web::get().to(get_duplicate_payments),
+            )
+            .route(
+                "/api/payments/suggest",
+                web::get().to(get_payment_suggestions),
+            )
             .route("/api/payments/{id}", web::get().to(get_payment))
-            .route("/api/payments", web::post().to(create_payment))
+            .route(
+                "/api/payments",
+                web::post().to(create_payment).wrap(from_fn(idempotent)),
+            )
             .route("/api/payments/{id}", web::put().to(update_payment))
             .route("/api/payments/{id}", web::delete().to(delete_payment))
             .route("/api/balance", web::get().to(get_balance))
+            .route(
+                "/api/transfers",
+                web::post().to(create_transfer).wrap(from_fn(idempotent)),
+            )
+            .route("/api/budgets", web::get().to(get_budgets))
+            .route("/api/budgets", web::post().to(create_budget))
+            // Registered before /api/budgets/{id} so that "status" is not taken for an id
+            .route("/api/budgets/status", web::get().to(get_budget_status))
+            .route("/api/budgets/{id}", web::get().to(get_budget))
+            .route("/api/budgets/{id}", web::put().to(update_budget))
+            .route("/api/budgets/{id}", web::delete().to(delete_budget))
+            .route(
+                "/api/recurring-payments",
+                web::get().to(get_recurring_payments),
+            )
+            .route(
+                "/api/recurring-payments",
+                web::post().to(create_recurring_payment),
+            )
+            .route(
+                "/api/recurring-payments/{id}",
+                web::get().to(get_recurring_payment),
+            )
+            .route(
+                "/api/recurring-payments/{id}",
+                web::put().to(update_recurring_payment),
+            )
+            .route(
+                "/api/recurring-payments/{id}",
+                web::delete().to(delete_recurring_payment),
+            )
+            .route(
+                "/api/recurring-payments/{id}/skip",
+                web::post().to(skip_recurring_payment),
+            )
+            .service(
+                web::resource("/api/imports/csv")
+                    // The idempotency middleware reads the whole upload to fingerprint it
+                    .app_data(web::PayloadConfig::new(CSV_IMPORT_PAYLOAD_LIMIT))
+                    .route(web::post().to(import_csv).wrap(from_fn(idempotent))),
+            )
+            .route(
+                "/api/staging/transactions",
+                web::get().to(get_staging_transactions),
+            )
+            .route(
+                "/api/staging/transactions/{id}",
+                web::put().to(update_staging_transaction),
+            )
+            .route(
+                "/api/staging/import",
+                web::post()
+                    .to(import_staging_transactions)
+                    .wrap(from_fn(idempotent)),
+            )
+            .route(
+                "/api/banking/sync/{connection_id}",
+                web::post().to(sync_bank_connection),
+            )
+            .route("/api/categories", web::get().to(get_categories))
+            .route("/api/categories", web::post().to(create_category))
+            .route("/api/categories/{id}", web::put().to(update_category))
+            .route("/api/categories/{id}", web::delete().to(delete_category))
+            .route(
+                "/api/categories/{id}/merge",
+                web::post().to(merge_categories),
+            )
+            .route("/api/merchants", web::get().to(get_merchants))
+            .route(
+                "/api/merchants/{id}/aliases",
+                web::post().to(add_merchant_alias),
+            )
+            .route(
+                "/api/merchants/{id}/aliases/{alias}",
+                web::delete().to(delete_merchant_alias),
+            )
+            .route("/api/merchants/{id}/merge", web::post().to(merge_merchants))
+            .route("/api/reports/tags", web::get().to(get_tag_report))
+            .route("/api/rules", web::get().to(get_rules))
+            .route("/api/rules", web::post().to(create_rule))
+            .route("/api/rules/{id}", web::get().to(get_rule))
+            .route("/api/rules/{id}", web::put().to(update_rule))
+            .route("/api/rules/{id}", web::delete().to(delete_rule))
+            .route("/api/rules/{id}/apply", web::post().to(apply_rule))
+            .route("/api/tags", web::get().to(get_tags))
+            .route("/api/tags/rename", web::post().to(rename_tags))
             .route("/api/wallets", web::get().to(get_wallets))
-            .route("/api/wallets", web::post().to(create_wallet))
+            .route(
+                "/api/wallets",
+                web::post().to(create_wallet).wrap(from_fn(idempotent)),
+            )
             .route("/api/wallets/{id}", web::delete().to(delete_wallet))
+            .route(
+                "/api/exchange-rates/{base}/{quote}",
+                web::get().to(get_exchange_rate),
+            )
+            .service(
+                web::resource("/api/admin/exchange-rates")
+                    // ECB history dumps are a few megabytes
+                    .app_data(web::PayloadConfig::new(EXCHANGE_RATES_PAYLOAD_LIMIT))
+                    .route(web::put().to(upsert_exchange_rates)),
+            )
             .app_data(metrics_registry.clone())
             .app_data(connection_pool.clone())
+            .app_data(jwt_verifier.clone())
+            .app_data(notifier.clone())
+            .app_data(bank_providers.clone())
+            .app_data(key_ring.clone())
     })
     .listen(listener)?
     .run();
@@ -1,35 +1,50 @@
-use crate::helpers::spawn_app;
+use crate::helpers::{
+    default_claims, mint_token, sign_token, spawn_app, spawn_app_with, TestApp, TEST_ES256_KEY,
+    TEST_ES256_KID, TEST_JWKS_PATH, TEST_RS256_KEY, TEST_RS256_KID, UNTRUSTED_RS256_KEY,
+};
 use base64::Engine;
+use jsonwebtoken::Algorithm;
 use serde_json::Value;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
 
-#[tokio::test]
-async fn forwarded_jwt_is_decoded_and_sub_returned() {
-    let app = spawn_app().await;
-
-    // craft a simple unsigned JWT (alg=none) with future exp and sub
-    let header = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#);
-    let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(
-        serde_json::json!({
-            "sub": "test-sub",
-            "exp": (chrono::Utc::now().timestamp() + 60)
-        })
-        .to_string(),
-    );
-    let token = format!("{}.{}.", header, payload);
-
-    let client = reqwest::Client::new();
-    let res = client
+async fn get_sub(app: &TestApp, token: &str) -> reqwest::Response {
+    reqwest::Client::new()
         .get(format!("{}/api/debug/sub", &app.address))
         .header("Authorization", format!("Bearer {}", token))
         .send()
         .await
-        .expect("failed to execute request");
+        .expect("failed to execute request")
+}
+
+#[tokio::test]
+async fn signed_rs256_token_is_verified_and_sub_returned() {
+    let app = spawn_app().await;
+
+    let res = get_sub(&app, &mint_token("test-sub")).await;
 
     assert!(res.status().is_success());
     let body: Value = res.json().await.expect("invalid json");
     assert_eq!(body.get("sub").and_then(Value::as_str), Some("test-sub"));
 }
 
+#[tokio::test]
+async fn signed_es256_token_is_verified() {
+    let app = spawn_app().await;
+    let token = sign_token(
+        Algorithm::ES256,
+        TEST_ES256_KID,
+        TEST_ES256_KEY,
+        &default_claims("es-sub"),
+    );
+
+    let res = get_sub(&app, &token).await;
+
+    assert!(res.status().is_success());
+    let body: Value = res.json().await.expect("invalid json");
+    assert_eq!(body.get("sub").and_then(Value::as_str), Some("es-sub"));
+}
+
 #[tokio::test]
 async fn missing_authorization_is_rejected() {
     let app = spawn_app().await;
@@ -44,50 +59,87 @@ async fn missing_authorization_is_rejected() {
 }
 
 #[tokio::test]
-async fn expired_token_is_rejected() {
+async fn unsigned_token_is_rejected() {
     let app = spawn_app().await;
 
-    let header = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#);
-    let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(
-        serde_json::json!({
-            "sub": "test-sub",
-            "exp": (chrono::Utc::now().timestamp() - 60)
-        })
-        .to_string(),
-    );
+    // an unsigned JWT (alg=none) with valid claims, as previously trusted from the gateway
+    let header = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .encode(format!(r#"{{"alg":"none","kid":"{}"}}"#, TEST_RS256_KID));
+    let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .encode(default_claims("test-sub").to_string());
     let token = format!("{}.{}.", header, payload);
 
-    let client = reqwest::Client::new();
-    let res = client
-        .get(format!("{}/api/debug/sub", &app.address))
-        .header("Authorization", format!("Bearer {}", token))
-        .send()
-        .await
-        .expect("failed to execute request");
+    let res = get_sub(&app, &token).await;
 
     assert_eq!(res.status().as_u16(), 401);
 }
 
 #[tokio::test]
-async fn token_missing_sub_is_rejected() {
+async fn token_signed_with_untrusted_key_is_rejected() {
     let app = spawn_app().await;
+    let token = sign_token(
+        Algorithm::RS256,
+        TEST_RS256_KID,
+        UNTRUSTED_RS256_KEY,
+        &default_claims("test-sub"),
+    );
+
+    let res = get_sub(&app, &token).await;
+
+    assert_eq!(res.status().as_u16(), 401);
+}
 
-    let header = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#);
-    let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(
-        serde_json::json!({
-            "exp": (chrono::Utc::now().timestamp() + 60)
-        })
-        .to_string(),
+#[tokio::test]
+async fn token_with_unknown_kid_is_rejected() {
+    let app = spawn_app().await;
+    let token = sign_token(
+        Algorithm::RS256,
+        "unknown-kid",
+        TEST_RS256_KEY,
+        &default_claims("test-sub"),
     );
-    let token = format!("{}.{}.", header, payload);
 
-    let client = reqwest::Client::new();
-    let res = client
-        .get(format!("{}/api/debug/sub", &app.address))
-        .header("Authorization", format!("Bearer {}", token))
-        .send()
-        .await
-        .expect("failed to execute request");
+    let res = get_sub(&app, &token).await;
+
+    assert_eq!(res.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn token_signed_with_a_kid_of_another_algorithm_is_rejected() {
+    let app = spawn_app().await;
+    // RSA signature presented under the EC key id
+    let token = sign_token(
+        Algorithm::RS256,
+        TEST_ES256_KID,
+        TEST_RS256_KEY,
+        &default_claims("test-sub"),
+    );
+
+    let res = get_sub(&app, &token).await;
+
+    assert_eq!(res.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn expired_token_is_rejected() {
+    let app = spawn_app().await;
+    let mut claims = default_claims("test-sub");
+    claims["exp"] = (chrono::Utc::now().timestamp() - 600).into();
+    let token = sign_token(Algorithm::RS256, TEST_RS256_KID, TEST_RS256_KEY, &claims);
+
+    let res = get_sub(&app, &token).await;
+
+    assert_eq!(res.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn token_missing_sub_is_rejected() {
+    let app = spawn_app().await;
+    let mut claims = default_claims("test-sub");
+    claims.as_object_mut().unwrap().remove("sub");
+    let token = sign_token(Algorithm::RS256, TEST_RS256_KID, TEST_RS256_KEY, &claims);
+
+    let res = get_sub(&app, &token).await;
 
     assert_eq!(res.status().as_u16(), 401);
 }
@@ -95,42 +147,122 @@ async fn token_missing_sub_is_rejected() {
 #[tokio::test]
 async fn token_with_nbf_in_future_is_rejected() {
     let app = spawn_app().await;
+    let mut claims = default_claims("test-sub");
+    claims["nbf"] = (chrono::Utc::now().timestamp() + 600).into();
+    let token = sign_token(Algorithm::RS256, TEST_RS256_KID, TEST_RS256_KEY, &claims);
 
-    let header = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#);
-    let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(
-        serde_json::json!({
-            "sub": "test-sub",
-            "nbf": (chrono::Utc::now().timestamp() + 60),
-            "exp": (chrono::Utc::now().timestamp() + 120)
-        })
-        .to_string(),
-    );
-    let token = format!("{}.{}.", header, payload);
+    let res = get_sub(&app, &token).await;
 
-    let client = reqwest::Client::new();
-    let res = client
-        .get(format!("{}/api/debug/sub", &app.address))
-        .header("Authorization", format!("Bearer {}", token))
-        .send()
-        .await
-        .expect("failed to execute request");
+    assert_eq!(res.status().as_u16(), 401);
+}
 
-    // Currently extractor only checks exp; nbf check not implemented, so expect success or rejection based on exp
-    // We expect success because exp is in the future and extractor doesn't check nbf.
-    assert!(res.status().is_success());
+#[tokio::test]
+async fn token_with_wrong_issuer_is_rejected() {
+    let app = spawn_app().await;
+    let mut claims = default_claims("test-sub");
+    claims["iss"] = "https://evil.example.com/realms/expenses-monitor".into();
+    let token = sign_token(Algorithm::RS256, TEST_RS256_KID, TEST_RS256_KEY, &claims);
+
+    let res = get_sub(&app, &token).await;
+
+    assert_eq!(res.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn token_with_wrong_audience_is_rejected() {
+    let app = spawn_app().await;
+    let mut claims = default_claims("test-sub");
+    claims["aud"] = "another-service".into();
+    let token = sign_token(Algorithm::RS256, TEST_RS256_KID, TEST_RS256_KEY, &claims);
+
+    let res = get_sub(&app, &token).await;
+
+    assert_eq!(res.status().as_u16(), 401);
 }
 
 #[tokio::test]
 async fn malformed_token_is_rejected() {
     let app = spawn_app().await;
 
-    let client = reqwest::Client::new();
-    let res = client
-        .get(format!("{}/api/debug/sub", &app.address))
-        .header("Authorization", "Bearer not-a.jwt.token")
+    let res = get_sub(&app, "not-a.jwt.token").await;
+
+    assert_eq!(res.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn jwks_is_fetched_from_url_and_cached() {
+    let jwks_server = MockServer::start().await;
+    let jwks = std::fs::read_to_string(TEST_JWKS_PATH).expect("Failed to read test JWKS");
+    Mock::given(method("GET"))
+        .and(path("/certs"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(jwks, "application/json"))
+        .expect(1)
+        .mount(&jwks_server)
+        .await;
+    let jwks_url = format!("{}/certs", jwks_server.uri());
+    let app = spawn_app_with(|config| {
+        config.auth.jwks_path = None;
+        config.auth.jwks_url = Some(jwks_url);
+    })
+    .await;
+
+    let first = get_sub(&app, &mint_token("test-sub")).await;
+    let second = get_sub(&app, &mint_token("test-sub")).await;
+
+    assert!(first.status().is_success());
+    assert!(second.status().is_success());
+    // `expect(1)` on the mock verifies the JWKS was fetched only once
+}
+
+#[tokio::test]
+async fn unreachable_jwks_is_reported_as_server_error() {
+    let jwks_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&jwks_server)
+        .await;
+    let jwks_url = format!("{}/certs", jwks_server.uri());
+    let app = spawn_app_with(|config| {
+        config.auth.jwks_path = None;
+        config.auth.jwks_url = Some(jwks_url);
+    })
+    .await;
+
+    let res = get_sub(&app, &mint_token("test-sub")).await;
+
+    assert_eq!(res.status().as_u16(), 500);
+}
+
+#[tokio::test]
+async fn payments_are_owned_by_the_verified_subject() {
+    let app = spawn_app().await;
+    app.create_wallet(r#"{"name": "Cash"}"#).await;
+    let payment = serde_json::json!({
+        "category": "test",
+        "wallet": "Cash",
+        "amountInCents": -100,
+        "merchantName": "Market",
+        "accountingDate": "2023-11-13T00:00:00.000"
+    });
+    let created: Value = app
+        .post_payment(&payment.to_string())
+        .await
+        .json()
+        .await
+        .expect("invalid json");
+    let id = uuid::Uuid::parse_str(created["id"].as_str().unwrap()).unwrap();
+
+    let own = app.get_payment(id).await;
+    let other = reqwest::Client::new()
+        .get(format!("{}/api/payments/{}", &app.address, id))
+        .header(
+            "Authorization",
+            format!("Bearer {}", mint_token("another-sub")),
+        )
         .send()
         .await
         .expect("failed to execute request");
 
-    assert_eq!(res.status().as_u16(), 401);
+    assert_eq!(own.status().as_u16(), 200);
+    assert_eq!(other.status().as_u16(), 404);
 }
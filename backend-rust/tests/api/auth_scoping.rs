@@ -1,5 +1,4 @@
-use crate::helpers::spawn_app;
-use base64::Engine;
+use crate::helpers::{mint_token, spawn_app, TestApp};
 use rstest::rstest;
 use uuid::Uuid;
 
@@ -13,12 +12,7 @@ async fn wallets_are_scoped_by_user() {
     assert_eq!(200, create_resp.status().as_u16());
 
     // Build a token for user B
-    let sub_b = Uuid::new_v4().to_string();
-    let header = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#);
-    let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(
-        serde_json::json!({ "sub": sub_b, "exp": (chrono::Utc::now().timestamp() + 60) }).to_string(),
-    );
-    let token_b = format!("{}.{}.", header, payload);
+    let token_b = mint_token(&Uuid::new_v4().to_string());
 
     // User B should see no wallets
     let get_resp = app.get_wallets_with_auth(&token_b).await;
@@ -45,17 +39,137 @@ async fn payments_are_scoped_by_user() {
     assert_eq!(200, create_resp.status().as_u16());
 
     // Build token for user B
-    let sub_b = Uuid::new_v4().to_string();
-    let header = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#);
-    let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(
-        serde_json::json!({ "sub": sub_b, "exp": (chrono::Utc::now().timestamp() + 60) }).to_string(),
-    );
-    let token_b = format!("{}.{}.", header, payload);
+    let token_b = mint_token(&Uuid::new_v4().to_string());
 
     // User B should not see user A's payments
-    let resp = app.get_payments_with_auth("?page=0&size=10", &token_b).await;
+    let resp = app
+        .get_payments_with_auth("?page=0&size=10", &token_b)
+        .await;
     assert_eq!(200, resp.status().as_u16());
     let json: serde_json::Value = resp.json().await.unwrap();
     let content = json["content"].as_array().unwrap();
     assert!(content.is_empty());
 }
+
+/// Creates one payment for user A (the app's default user) and one for user B,
+/// sharing category, date, merchant and description so that every filter matches both.
+async fn seed_payments_for_two_users(app: &TestApp, token_b: &str) {
+    let wallet_a = app.create_wallet(r#"{ "name": "wallet-a" }"#).await;
+    assert_eq!(200, wallet_a.status().as_u16());
+    let wallet_b = app
+        .create_wallet_with_auth(r#"{ "name": "wallet-b" }"#, token_b)
+        .await;
+    assert_eq!(200, wallet_b.status().as_u16());
+
+    for (wallet, token) in [("wallet-a", app.auth_token.as_str()), ("wallet-b", token_b)] {
+        let payment = serde_json::json!({
+            "description": "shared groceries",
+            "category": "groceries",
+            "amountInCents": -1500,
+            "merchantName": "Shared Market",
+            "accountingDate": "2024-03-10T12:00:00",
+            "wallet": wallet,
+            "tags": [{ "key": "owner", "value": wallet }]
+        });
+        let response = app
+            .post_payment_with_auth(&payment.to_string(), token)
+            .await;
+        assert_eq!(200, response.status().as_u16());
+    }
+}
+
+#[rstest]
+#[case::no_filters("?page=0&size=50")]
+#[case::date_from("?dateFrom=2024-03-01")]
+#[case::date_to("?dateTo=2024-03-31")]
+#[case::date_range("?dateFrom=2024-03-01&dateTo=2024-03-31")]
+#[case::category_name("?category=groceries")]
+#[case::wallet_a("?wallet=wallet-a")]
+#[case::wallet_b("?wallet=wallet-b")]
+#[case::search_merchant("?search=market")]
+#[case::search_description("?search=shared")]
+#[case::all_filters(
+    "?dateFrom=2024-03-01&dateTo=2024-03-31&category=groceries&wallet=wallet-b&search=shared"
+)]
+#[tokio::test]
+async fn payments_listing_never_returns_other_users_rows(#[case] query: &str) {
+    let app = spawn_app().await;
+    let sub_b = Uuid::new_v4().to_string();
+    let token_b = mint_token(&sub_b);
+    seed_payments_for_two_users(&app, &token_b).await;
+
+    for (sub, token, own_wallet) in [
+        (app.auth_sub.as_str(), app.auth_token.as_str(), "wallet-a"),
+        (sub_b.as_str(), token_b.as_str(), "wallet-b"),
+    ] {
+        let response = app.get_payments_with_auth(query, token).await;
+        assert_eq!(200, response.status().as_u16());
+        let json: serde_json::Value = response.json().await.unwrap();
+        let content = json["content"].as_array().unwrap();
+
+        assert!(content.len() <= 1, "{} saw more than its own payment", sub);
+        for payment in content {
+            let id = Uuid::parse_str(payment["id"].as_str().unwrap()).unwrap();
+            let owner =
+                sqlx::query_scalar!("SELECT user_id FROM expenses.payments WHERE id = $1", id)
+                    .fetch_one(&app.db_pool)
+                    .await
+                    .expect("Failed to fetch payment owner");
+            assert_eq!(owner, sub);
+            assert_eq!(payment["wallet"], own_wallet);
+            assert_eq!(payment["tags"][0]["value"], own_wallet);
+        }
+    }
+}
+
+#[tokio::test]
+async fn payments_listing_filters_by_category_id_within_the_user_scope() {
+    let app = spawn_app().await;
+    let token_b = mint_token(&Uuid::new_v4().to_string());
+    // A global default, so both users' payments share the category
+    let category_id = sqlx::query_scalar!(
+        "INSERT INTO expenses.categories (name) VALUES ('groceries') RETURNING id"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    seed_payments_for_two_users(&app, &token_b).await;
+
+    let response = app
+        .get_payments_with_auth(&format!("?category={}", category_id), &token_b)
+        .await;
+
+    let json: serde_json::Value = response.json().await.unwrap();
+    let content = json["content"].as_array().unwrap();
+    assert_eq!(1, content.len());
+    assert_eq!(content[0]["wallet"], "wallet-b");
+}
+
+#[tokio::test]
+async fn payments_listing_ignores_tags_owned_by_other_users() {
+    let app = spawn_app().await;
+    let token_b = mint_token(&Uuid::new_v4().to_string());
+    seed_payments_for_two_users(&app, &token_b).await;
+    let payment_a = sqlx::query_scalar!(
+        "SELECT id FROM expenses.payments WHERE user_id = $1",
+        app.auth_sub
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    // A tag row attached to A's payment but owned by another user must not leak
+    sqlx::query!(
+        "INSERT INTO expenses.payments_tags (payment_id, key, value, user_id) VALUES ($1, 'leak', 'yes', 'someone-else')",
+        payment_a
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let response = app.get_payments("?page=0&size=10").await;
+
+    let json: serde_json::Value = response.json().await.unwrap();
+    let tags = json["content"][0]["tags"].as_array().unwrap();
+    assert_eq!(1, tags.len());
+    assert_eq!(tags[0]["key"], "owner");
+}
@@ -35,9 +35,9 @@ async fn get_balance_returns_correct_amount() {
     // Assert
     assert_eq!(200, response.status().as_u16());
     let balance: BalanceResponse = response.json().await.unwrap();
-    assert_eq!(-500, balance.total_in_cents);
-    assert_eq!(500, balance.income_in_cents);
-    assert_eq!(-1000, balance.expenses_in_cents);
+    assert_eq!(-500, balance.total_in_cents.unwrap().cents());
+    assert_eq!(500, balance.income_in_cents.unwrap().cents());
+    assert_eq!(-1000, balance.expenses_in_cents.unwrap().cents());
 }
 
 #[tokio::test]
@@ -51,7 +51,7 @@ async fn get_balance_returns_zero_when_no_payments() {
     // Assert
     assert_eq!(200, response.status().as_u16());
     let balance: BalanceResponse = response.json().await.unwrap();
-    assert_eq!(0, balance.total_in_cents);
-    assert_eq!(0, balance.income_in_cents);
-    assert_eq!(0, balance.expenses_in_cents);
+    assert_eq!(0, balance.total_in_cents.unwrap().cents());
+    assert_eq!(0, balance.income_in_cents.unwrap().cents());
+    assert_eq!(0, balance.expenses_in_cents.unwrap().cents());
 }
@@ -1,4 +1,4 @@
-use crate::helpers::spawn_app;
+use crate::helpers::{mint_token, spawn_app};
 
 #[tokio::test]
 async fn get_balance_returns_zero_when_no_payments() {
@@ -191,3 +191,352 @@ async fn get_balance_filters_by_end_date() {
     assert_eq!(balance["incomeInCents"], 30000);
     assert_eq!(balance["expensesInCents"], 0);
 }
+
+async fn seed_wallets_and_payments(app: &crate::helpers::TestApp) {
+    app.create_wallet(r#"{"name": "Card"}"#).await;
+    app.create_wallet(r#"{"name": "Cash"}"#).await;
+    let payments = [
+        ("Card", "salary", 200000, "2026-01-05T09:00:00"),
+        ("Card", "food", -30000, "2026-01-20T19:00:00"),
+        ("Cash", "food", -5000, "2026-02-02T12:00:00"),
+        ("Cash", "transport", -2500, "2026-02-28T23:30:00"),
+    ];
+    for (wallet, category, amount, date) in payments {
+        let payload = serde_json::json!({
+            "merchantName": "Merchant",
+            "amountInCents": amount,
+            "category": category,
+            "accountingDate": date,
+            "wallet": wallet
+        });
+        let response = app.post_payment(&payload.to_string()).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+}
+
+#[tokio::test]
+async fn get_balance_only_sums_the_authenticated_users_payments() {
+    let app = spawn_app().await;
+    seed_wallets_and_payments(&app).await;
+    let token_b = mint_token(&uuid::Uuid::new_v4().to_string());
+    let payload = serde_json::json!({
+        "merchantName": "Other user",
+        "amountInCents": -99999,
+        "category": "food",
+        "accountingDate": "2026-01-20T10:00:00"
+    });
+    app.post_payment_with_auth(&payload.to_string(), &token_b)
+        .await;
+
+    let own: serde_json::Value = app.get_balance().await.json().await.unwrap();
+    let other: serde_json::Value = app
+        .get_balance_with_auth("", &token_b)
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(own["totalInCents"], 162500);
+    assert_eq!(own["expensesInCents"], -37500);
+    assert_eq!(other["totalInCents"], -99999);
+    assert_eq!(other["incomeInCents"], 0);
+}
+
+#[tokio::test]
+async fn get_balance_end_date_includes_the_whole_day() {
+    let app = spawn_app().await;
+    seed_wallets_and_payments(&app).await;
+
+    let response = app
+        .get_balance_with_query("?startDate=2026-02-01&endDate=2026-02-28")
+        .await;
+
+    let balance: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(balance["totalInCents"], -7500);
+}
+
+#[tokio::test]
+async fn get_balance_filters_by_wallet_and_category() {
+    let app = spawn_app().await;
+    seed_wallets_and_payments(&app).await;
+
+    let by_wallet: serde_json::Value = app
+        .get_balance_with_query("?wallet=Cash")
+        .await
+        .json()
+        .await
+        .unwrap();
+    let by_category: serde_json::Value = app
+        .get_balance_with_query("?category=FOOD")
+        .await
+        .json()
+        .await
+        .unwrap();
+    let by_both: serde_json::Value = app
+        .get_balance_with_query("?wallet=Card&category=food")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(by_wallet["totalInCents"], -7500);
+    assert_eq!(by_category["totalInCents"], -35000);
+    assert_eq!(by_both["totalInCents"], -30000);
+    assert_eq!(by_both["incomeInCents"], 0);
+    assert!(by_both.get("breakdown").is_none());
+}
+
+#[tokio::test]
+async fn get_balance_filters_by_category_id() {
+    let app = spawn_app().await;
+    seed_wallets_and_payments(&app).await;
+    let category_id =
+        sqlx::query_scalar!("SELECT id FROM expenses.categories WHERE lower(name) = 'transport'")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+
+    let balance: serde_json::Value = app
+        .get_balance_with_query(&format!("?category={}", category_id))
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(balance["totalInCents"], -2500);
+}
+
+#[tokio::test]
+async fn get_balance_groups_by_wallet() {
+    let app = spawn_app().await;
+    seed_wallets_and_payments(&app).await;
+
+    let balance: serde_json::Value = app
+        .get_balance_with_query("?groupBy=wallet")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(balance["totalInCents"], 162500);
+    let breakdown = balance["breakdown"].as_array().unwrap();
+    assert_eq!(breakdown.len(), 2);
+    assert_eq!(breakdown[0]["label"], "Card");
+    assert_eq!(breakdown[0]["totalInCents"], 170000);
+    assert_eq!(breakdown[0]["incomeInCents"], 200000);
+    assert_eq!(breakdown[0]["expensesInCents"], -30000);
+    assert_eq!(breakdown[1]["label"], "Cash");
+    assert_eq!(breakdown[1]["totalInCents"], -7500);
+}
+
+#[tokio::test]
+async fn get_balance_groups_by_category_with_filters() {
+    let app = spawn_app().await;
+    seed_wallets_and_payments(&app).await;
+
+    let balance: serde_json::Value = app
+        .get_balance_with_query("?groupBy=category&wallet=Cash")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(balance["totalInCents"], -7500);
+    let breakdown = balance["breakdown"].as_array().unwrap();
+    let labels: Vec<&str> = breakdown
+        .iter()
+        .map(|e| e["label"].as_str().unwrap())
+        .collect();
+    assert_eq!(labels, vec!["food", "transport"]);
+    assert_eq!(breakdown[0]["totalInCents"], -5000);
+    assert!(breakdown[0]["key"]
+        .as_str()
+        .unwrap()
+        .parse::<uuid::Uuid>()
+        .is_ok());
+}
+
+#[tokio::test]
+async fn get_balance_groups_by_month() {
+    let app = spawn_app().await;
+    seed_wallets_and_payments(&app).await;
+
+    let balance: serde_json::Value = app
+        .get_balance_with_query("?groupBy=month")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    let breakdown = balance["breakdown"].as_array().unwrap();
+    assert_eq!(breakdown.len(), 2);
+    assert_eq!(breakdown[0]["key"], "2026-01");
+    assert_eq!(breakdown[0]["totalInCents"], 170000);
+    assert_eq!(breakdown[1]["key"], "2026-02");
+    assert_eq!(breakdown[1]["expensesInCents"], -7500);
+}
+
+#[tokio::test]
+async fn get_balance_groups_payments_without_wallet_under_a_null_key() {
+    let app = spawn_app().await;
+    let payload = serde_json::json!({
+        "merchantName": "No wallet",
+        "amountInCents": -100,
+        "category": "food",
+        "accountingDate": "2026-01-20T10:00:00"
+    });
+    app.post_payment(&payload.to_string()).await;
+
+    let balance: serde_json::Value = app
+        .get_balance_with_query("?groupBy=wallet")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    let breakdown = balance["breakdown"].as_array().unwrap();
+    assert_eq!(breakdown.len(), 1);
+    assert!(breakdown[0]["key"].is_null());
+    assert_eq!(breakdown[0]["totalInCents"], -100);
+}
+
+#[tokio::test]
+async fn get_balance_rejects_unknown_group_by() {
+    let app = spawn_app().await;
+
+    let response = app.get_balance_with_query("?groupBy=merchant").await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn get_balance_sums_beyond_32_bits_without_truncation() {
+    let app = spawn_app().await;
+    app.create_wallet(r#"{"name": "Card"}"#).await;
+    for amount in [i32::MAX as i64, i32::MAX as i64, -(i32::MAX as i64) * 3] {
+        let payload = serde_json::json!({
+            "merchantName": "Merchant",
+            "amountInCents": amount,
+            "category": "misc",
+            "accountingDate": "2026-01-05T09:00:00",
+            "wallet": "Card"
+        });
+        // The two identical payments are deliberate
+        let response = app
+            .post_payment_with_query(&payload.to_string(), "?force=true")
+            .await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    let balance: serde_json::Value = app
+        .get_balance_with_query("?groupBy=wallet")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(balance["incomeInCents"].as_i64(), Some(2 * i32::MAX as i64));
+    assert_eq!(
+        balance["expensesInCents"].as_i64(),
+        Some(-3 * i32::MAX as i64)
+    );
+    assert_eq!(balance["totalInCents"].as_i64(), Some(-(i32::MAX as i64)));
+    assert_eq!(
+        balance["breakdown"][0]["incomeInCents"].as_i64(),
+        Some(2 * i32::MAX as i64)
+    );
+}
+
+#[tokio::test]
+async fn get_balance_reports_totals_per_currency() {
+    let app = spawn_app().await;
+    app.create_wallet(r#"{"name": "Euro card"}"#).await;
+    app.create_wallet(r#"{"name": "Dollar card", "defaultCurrency": "USD"}"#)
+        .await;
+    let payments = [
+        ("Euro card", None, 100000),
+        ("Euro card", None, -2500),
+        ("Dollar card", None, -4000),
+        ("Euro card", Some("GBP"), -1500),
+    ];
+    for (wallet, currency, amount) in payments {
+        let mut payload = serde_json::json!({
+            "merchantName": "Merchant",
+            "amountInCents": amount,
+            "category": "travel",
+            "accountingDate": "2026-03-01T10:00:00",
+            "wallet": wallet
+        });
+        if let Some(currency) = currency {
+            payload["currency"] = currency.into();
+        }
+        assert_eq!(
+            app.post_payment(&payload.to_string())
+                .await
+                .status()
+                .as_u16(),
+            200
+        );
+    }
+
+    let balance: serde_json::Value = app
+        .get_balance_with_query("?groupBy=wallet")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    // Amounts in different currencies are never added together
+    assert!(balance.get("totalInCents").is_none());
+    assert!(balance.get("currency").is_none());
+    let currencies = balance["currencies"].as_array().unwrap();
+    let codes: Vec<&str> = currencies
+        .iter()
+        .map(|c| c["currency"].as_str().unwrap())
+        .collect();
+    assert_eq!(codes, vec!["EUR", "GBP", "USD"]);
+    assert_eq!(currencies[0]["totalInCents"], 97500);
+    assert_eq!(currencies[0]["incomeInCents"], 100000);
+    assert_eq!(currencies[1]["totalInCents"], -1500);
+    assert_eq!(currencies[2]["expensesInCents"], -4000);
+
+    let breakdown = balance["breakdown"].as_array().unwrap();
+    let entries: Vec<(&str, &str)> = breakdown
+        .iter()
+        .map(|e| {
+            (
+                e["label"].as_str().unwrap(),
+                e["currency"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        entries,
+        vec![
+            ("Dollar card", "USD"),
+            ("Euro card", "EUR"),
+            ("Euro card", "GBP")
+        ]
+    );
+}
+
+#[tokio::test]
+async fn get_balance_single_currency_is_repeated_at_the_top_level() {
+    let app = spawn_app().await;
+    app.create_wallet(r#"{"name": "Dollar card", "defaultCurrency": "USD"}"#)
+        .await;
+    let payload = serde_json::json!({
+        "merchantName": "Merchant",
+        "amountInCents": -4000,
+        "category": "travel",
+        "accountingDate": "2026-03-01T10:00:00",
+        "wallet": "Dollar card"
+    });
+    app.post_payment(&payload.to_string()).await;
+
+    let balance: serde_json::Value = app.get_balance().await.json().await.unwrap();
+
+    assert_eq!(balance["currency"], "USD");
+    assert_eq!(balance["totalInCents"], -4000);
+    assert_eq!(balance["currencies"][0]["totalInCents"], -4000);
+}
@@ -0,0 +1,319 @@
+use crate::helpers::{
+    rotate_encryption_key, spawn_app, spawn_app_with, test_encryption_settings, TestApp,
+};
+use expenses_monitor_be::crypto::{decrypt_token, encrypt_token, KeyRing};
+use secrecy::{ExposeSecret, SecretString};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+const MOCK_BANK_FIXTURE: &str = "tests/api/fixtures/mock_bank.json";
+
+/// A private copy of the mock bank, so that tests can add transactions.
+fn mock_bank() -> PathBuf {
+    let path = std::env::temp_dir().join(format!("mock-bank-{}.json", Uuid::new_v4()));
+    std::fs::copy(MOCK_BANK_FIXTURE, &path).expect("Failed to copy the mock bank");
+    path
+}
+
+fn add_transaction(bank: &Path, account: usize, transaction: serde_json::Value) {
+    let mut document: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(bank).unwrap()).unwrap();
+    document["accounts"][account]["transactions"]
+        .as_array_mut()
+        .unwrap()
+        .push(transaction);
+    std::fs::write(bank, document.to_string()).unwrap();
+}
+
+async fn spawn_app_with_bank(bank: &Path) -> TestApp {
+    let path = bank.to_str().unwrap().to_string();
+    spawn_app_with(|config| config.banking.mock_provider_file = Some(path)).await
+}
+
+async fn connect(
+    app: &TestApp,
+    user_id: &str,
+    account_id: Option<&str>,
+    refresh_token: &str,
+) -> String {
+    sqlx::query_scalar!(
+        r#"
+        INSERT INTO expenses.bank_connections (user_id, provider, account_id, encrypted_refresh_token)
+        VALUES ($1, 'mock', $2, $3)
+        RETURNING id
+        "#,
+        user_id,
+        account_id,
+        encrypt_token(&app.key_ring, &SecretString::from(refresh_token)).unwrap()
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to create the bank connection")
+    .to_string()
+}
+
+async fn sync(app: &TestApp, connection_id: &str) -> serde_json::Value {
+    let response = app.post_bank_sync(connection_id).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.unwrap()
+}
+
+async fn staged(app: &TestApp) -> Vec<serde_json::Value> {
+    let page: serde_json::Value = app
+        .get_staging_transactions("?size=100")
+        .await
+        .json()
+        .await
+        .unwrap();
+    page["content"].as_array().unwrap().clone()
+}
+
+#[tokio::test]
+async fn sync_stages_the_new_transactions_of_every_account() {
+    let app = spawn_app_with_bank(&mock_bank()).await;
+    let connection_id = connect(&app, &app.auth_sub, None, "mock-refresh-token").await;
+
+    let summary = sync(&app, &connection_id).await;
+
+    assert_eq!(summary["connectionId"], connection_id);
+    assert_eq!(summary["accounts"], 2);
+    assert_eq!(summary["new"], 3);
+    assert_eq!(summary["duplicates"], 0);
+    assert_eq!(summary["errors"], 1);
+    let staged = staged(&app).await;
+    assert_eq!(staged.len(), 3);
+    let hotel = staged
+        .iter()
+        .find(|t| t["bankTransactionId"] == "trv-1")
+        .unwrap();
+    assert_eq!(hotel["currency"], "USD");
+    assert_eq!(hotel["creditorName"], "Hotel");
+    assert_eq!(hotel["status"], "pending");
+    let bakery = staged
+        .iter()
+        .find(|t| t["bankTransactionId"] == "chk-1")
+        .unwrap();
+    assert_eq!(bakery["valueDate"], "2026-03-02");
+    assert_eq!(bakery["remittanceInfo"], "Card payment");
+
+    let last_sync_at = sqlx::query_scalar!(
+        "SELECT last_sync_at FROM expenses.bank_connections WHERE id = $1",
+        connection_id.parse::<Uuid>().unwrap()
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert!(last_sync_at.is_some());
+}
+
+#[tokio::test]
+async fn sync_resumes_from_the_last_cursor() {
+    let bank = mock_bank();
+    let app = spawn_app_with_bank(&bank).await;
+    let connection_id = connect(&app, &app.auth_sub, Some("checking"), "mock-refresh-token").await;
+    let first = sync(&app, &connection_id).await;
+    assert_eq!(first["accounts"], 1);
+    assert_eq!(first["new"], 2);
+
+    add_transaction(
+        &bank,
+        0,
+        serde_json::json!({
+            "transactionId": "chk-4",
+            "bookingDate": "2026-03-05",
+            "amountInCents": -800,
+            "creditorName": "Cinema"
+        }),
+    );
+    let second = sync(&app, &connection_id).await;
+
+    assert_eq!(second["new"], 1);
+    assert_eq!(second["duplicates"], 0);
+    assert_eq!(second["errors"], 0);
+    assert_eq!(staged(&app).await.len(), 3);
+}
+
+#[tokio::test]
+async fn transactions_are_staged_once_per_user() {
+    let app = spawn_app_with_bank(&mock_bank()).await;
+    let first = connect(&app, &app.auth_sub, Some("checking"), "mock-refresh-token").await;
+    // Another connection to the same account, e.g. after a renewed consent
+    let second = connect(&app, &app.auth_sub, Some("checking"), "mock-refresh-token").await;
+    let other_user = connect(&app, "someone-else", Some("checking"), "mock-refresh-token").await;
+    sync(&app, &first).await;
+
+    let summary = sync(&app, &second).await;
+
+    assert_eq!(summary["new"], 0);
+    assert_eq!(summary["duplicates"], 2);
+    assert_eq!(staged(&app).await.len(), 2);
+    let staged_for_other_user = sqlx::query_scalar!(
+        r#"SELECT count(*) as "count!" FROM expenses.staging_transactions WHERE user_id = 'someone-else'"#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(staged_for_other_user, 0);
+    let response = app.post_bank_sync(&other_user).await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn syncing_with_a_revoked_consent_is_rejected() {
+    let app = spawn_app_with_bank(&mock_bank()).await;
+    let revoked = connect(&app, &app.auth_sub, None, "revoked-token").await;
+
+    let response = app.post_bank_sync(&revoked).await;
+
+    assert_eq!(response.status().as_u16(), 409);
+    assert!(staged(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn syncing_requires_a_configured_provider() {
+    let app = spawn_app().await;
+    let connection_id = connect(&app, &app.auth_sub, None, "mock-refresh-token").await;
+
+    let response = app.post_bank_sync(&connection_id).await;
+
+    assert_eq!(response.status().as_u16(), 503);
+}
+
+#[tokio::test]
+async fn syncing_an_unknown_connection_returns_404() {
+    let app = spawn_app_with_bank(&mock_bank()).await;
+
+    let response = app.post_bank_sync(&Uuid::new_v4().to_string()).await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn a_rotated_refresh_token_is_kept_when_the_sync_fails() {
+    let bank = mock_bank();
+    let mut document: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&bank).unwrap()).unwrap();
+    document["rotatedRefreshToken"] = serde_json::json!("rotated-refresh-token");
+    std::fs::write(&bank, document.to_string()).unwrap();
+    let app = spawn_app_with_bank(&bank).await;
+    let connection_id = connect(&app, &app.auth_sub, Some("checking"), "mock-refresh-token").await;
+    let id: Uuid = connection_id.parse().unwrap();
+    // The provider rejects the cursor after the token was refreshed
+    sqlx::query!(
+        r#"UPDATE expenses.bank_connections SET sync_cursors = '{"checking": "bogus"}' WHERE id = $1"#,
+        id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let response = app.post_bank_sync(&connection_id).await;
+
+    assert_eq!(response.status().as_u16(), 502);
+    let connection = sqlx::query!(
+        "SELECT encrypted_refresh_token, sync_started_at FROM expenses.bank_connections WHERE id = $1",
+        id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    let stored =
+        decrypt_token(&app.key_ring, &connection.encrypted_refresh_token.unwrap()).unwrap();
+    assert_eq!(stored.expose_secret(), "rotated-refresh-token");
+    assert!(connection.sync_started_at.is_none());
+    assert!(staged(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn a_connection_cannot_be_synced_while_another_sync_holds_it() {
+    let app = spawn_app_with_bank(&mock_bank()).await;
+    let connection_id = connect(&app, &app.auth_sub, None, "mock-refresh-token").await;
+    let id: Uuid = connection_id.parse().unwrap();
+    let claim = |started: &'static str| {
+        sqlx::query(
+            "UPDATE expenses.bank_connections SET sync_started_at = now() - $2::interval WHERE id = $1",
+        )
+        .bind(id)
+        .bind(started)
+        .execute(&app.db_pool)
+    };
+
+    claim("1 minute").await.unwrap();
+    let response = app.post_bank_sync(&connection_id).await;
+    assert_eq!(response.status().as_u16(), 409);
+    assert!(staged(&app).await.is_empty());
+
+    // Claims of syncs that never finished eventually expire
+    claim("1 hour").await.unwrap();
+    let summary = sync(&app, &connection_id).await;
+    assert_eq!(summary["new"], 3);
+}
+
+#[tokio::test]
+async fn refresh_tokens_encrypted_before_a_key_rotation_are_still_usable() {
+    let bank = mock_bank();
+    let path = bank.to_str().unwrap().to_string();
+    let app = spawn_app_with(|config| {
+        config.banking.mock_provider_file = Some(path);
+        rotate_encryption_key(config);
+    })
+    .await;
+    let key_ring_before_rotation = KeyRing::from_settings(&test_encryption_settings()).unwrap();
+    let connection_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO expenses.bank_connections (user_id, provider, encrypted_refresh_token)
+        VALUES ($1, 'mock', $2)
+        RETURNING id
+        "#,
+        app.auth_sub,
+        encrypt_token(
+            &key_ring_before_rotation,
+            &SecretString::from("mock-refresh-token")
+        )
+        .unwrap()
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+
+    let summary = sync(&app, &connection_id.to_string()).await;
+
+    assert_eq!(summary["new"], 3);
+}
+
+#[tokio::test]
+async fn staged_transactions_get_the_category_of_similar_payments() {
+    let app = spawn_app_with_bank(&mock_bank()).await;
+    for (merchant_name, amount, category, day) in [
+        ("Bakery Dupont", -450, "Food", 10),
+        ("Bakery Dupont", -620, "Food", 12),
+        ("Bakery", -380, "Food", 15),
+        ("Employer", 150000, "Salary", 1),
+    ] {
+        let body = serde_json::json!({
+            "merchantName": merchant_name,
+            "amountInCents": amount,
+            "categoryId": category,
+            "accountingDate": format!("2026-02-{}T10:00:00", day)
+        });
+        let response = app.post_payment(&body.to_string()).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+    let connection_id = connect(&app, &app.auth_sub, None, "mock-refresh-token").await;
+
+    sync(&app, &connection_id).await;
+
+    let staged = staged(&app).await;
+    let category = |id: &str| {
+        staged
+            .iter()
+            .find(|t| t["bankTransactionId"] == id)
+            .unwrap()
+            .get("suggestedCategory")
+            .cloned()
+    };
+    assert_eq!(category("chk-1"), Some("Food".into()));
+    assert_eq!(category("chk-2"), Some("Salary".into()));
+    // Never seen before: left to the review
+    assert_eq!(category("trv-1"), None);
+}
@@ -0,0 +1,268 @@
+use crate::helpers::{mint_admin_token, spawn_app, TestApp};
+
+async fn create_wallets(app: &TestApp) {
+    for body in [
+        r#"{"name": "Checking"}"#,
+        r#"{"name": "Travel", "defaultCurrency": "USD"}"#,
+    ] {
+        let response = app.create_wallet(body).await;
+        assert!(response.status().is_success());
+    }
+}
+
+async fn post_payment(app: &TestApp, body: serde_json::Value) {
+    let response = app.post_payment(&body.to_string()).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+fn payment(amount: i64, category: &str, wallet: &str, date: &str) -> serde_json::Value {
+    serde_json::json!({
+        "merchantName": "Shop",
+        "amountInCents": amount,
+        "category": category,
+        "wallet": wallet,
+        "accountingDate": format!("{date}T10:00:00"),
+    })
+}
+
+async fn create_budget(app: &TestApp, body: serde_json::Value) -> serde_json::Value {
+    let response = app.post_budget(&body.to_string()).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.expect("Failed to parse response")
+}
+
+async fn status(app: &TestApp, month: &str) -> Vec<serde_json::Value> {
+    let response = app.get_budget_status(&format!("?month={month}")).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["month"], month);
+    body["budgets"].as_array().unwrap().clone()
+}
+
+#[tokio::test]
+async fn create_budget_returns_the_budget() {
+    let app = spawn_app().await;
+    app.create_categories(&["groceries"]).await;
+    create_wallets(&app).await;
+
+    let budget = create_budget(
+        &app,
+        serde_json::json!({"categoryId": "groceries", "wallet": "Travel", "limitInCents": 30000}),
+    )
+    .await;
+
+    assert_eq!(budget["category"], "groceries");
+    assert_eq!(budget["wallet"], "Travel");
+    assert_eq!(budget["period"], "monthly");
+    assert_eq!(budget["limitInCents"], 30000);
+    // Defaults to the wallet's currency
+    assert_eq!(budget["currency"], "USD");
+    let budgets: Vec<serde_json::Value> = app.get_budgets().await.json().await.unwrap();
+    assert_eq!(budgets.len(), 1);
+}
+
+#[tokio::test]
+async fn create_budget_rejects_invalid_budgets() {
+    let app = spawn_app().await;
+    create_wallets(&app).await;
+
+    for (body, case) in [
+        (serde_json::json!({"limitInCents": 0}), "zero limit"),
+        (serde_json::json!({"limitInCents": -100}), "negative limit"),
+        (
+            serde_json::json!({"limitInCents": 100, "wallet": "Nope"}),
+            "unknown wallet",
+        ),
+        (
+            serde_json::json!({"limitInCents": 100, "period": "weekly"}),
+            "unknown period",
+        ),
+        (
+            serde_json::json!({"limitInCents": 100, "currency": "euro"}),
+            "invalid currency",
+        ),
+        (
+            serde_json::json!({"limitInCents": 100, "categoryId": "grocerys"}),
+            "unknown category",
+        ),
+    ] {
+        let response = app.post_budget(&body.to_string()).await;
+        assert_eq!(response.status().as_u16(), 400, "{}", case);
+    }
+    // Budgets do not create the categories they name
+    let categories: Vec<serde_json::Value> = app.get_categories().await.json().await.unwrap();
+    assert!(categories.iter().all(|c| c["name"] != "grocerys"));
+}
+
+#[tokio::test]
+async fn only_one_budget_per_scope_is_allowed() {
+    let app = spawn_app().await;
+    app.create_categories(&["groceries"]).await;
+    create_budget(&app, serde_json::json!({"limitInCents": 100000})).await;
+    create_budget(
+        &app,
+        serde_json::json!({"categoryId": "groceries", "limitInCents": 30000}),
+    )
+    .await;
+
+    let response = app
+        .post_budget(&serde_json::json!({"limitInCents": 50000}).to_string())
+        .await;
+
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+#[tokio::test]
+async fn budgets_can_be_updated_and_deleted() {
+    let app = spawn_app().await;
+    app.create_categories(&["fun"]).await;
+    let budget = create_budget(&app, serde_json::json!({"limitInCents": 100000})).await;
+    let id = budget["id"].as_str().unwrap();
+
+    let response = app
+        .update_budget(
+            id,
+            &serde_json::json!({"categoryId": "fun", "limitInCents": 5000}).to_string(),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let updated: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(updated["category"], "fun");
+    assert_eq!(updated["limitInCents"], 5000);
+
+    assert_eq!(app.delete_budget(id).await.status().as_u16(), 204);
+    assert_eq!(app.delete_budget(id).await.status().as_u16(), 404);
+    let response = app
+        .update_budget(id, &serde_json::json!({"limitInCents": 1}).to_string())
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn budget_status_counts_the_expenses_of_the_month() {
+    let app = spawn_app().await;
+    app.create_categories(&["groceries"]).await;
+    create_wallets(&app).await;
+    create_budget(
+        &app,
+        serde_json::json!({"categoryId": "groceries", "limitInCents": 30000}),
+    )
+    .await;
+    create_budget(
+        &app,
+        serde_json::json!({"wallet": "Checking", "limitInCents": 20000}),
+    )
+    .await;
+
+    post_payment(&app, payment(-10000, "groceries", "Checking", "2026-03-02")).await;
+    let mut travel = payment(-5000, "groceries", "Travel", "2026-03-31");
+    travel["currency"] = "EUR".into();
+    post_payment(&app, travel).await;
+    // Income, refunds and other months are not spending
+    post_payment(&app, payment(2000, "groceries", "Checking", "2026-03-05")).await;
+    post_payment(&app, payment(-7000, "groceries", "Checking", "2026-04-01")).await;
+    // Only the groceries split counts for the groceries budget
+    let mut receipt = payment(-10000, "household", "Checking", "2026-03-10");
+    receipt["splits"] = serde_json::json!([
+        {"categoryId": "groceries", "amountInCents": -3000},
+        {"categoryId": "household", "amountInCents": -7000}
+    ]);
+    post_payment(&app, receipt).await;
+
+    let budgets = status(&app, "2026-03").await;
+
+    assert_eq!(budgets.len(), 2);
+    let groceries = &budgets[1];
+    assert_eq!(groceries["category"], "groceries");
+    assert_eq!(groceries["spentInCents"], 18000);
+    assert_eq!(groceries["remainingInCents"], 12000);
+    assert_eq!(groceries["percentUsed"], 60.0);
+    // Past months are not projected
+    assert_eq!(groceries["projectedInCents"], 18000);
+    let checking = &budgets[0];
+    assert_eq!(checking["wallet"], "Checking");
+    assert_eq!(checking["spentInCents"], 20000);
+    assert_eq!(checking["remainingInCents"], 0);
+    assert_eq!(checking["percentUsed"], 100.0);
+}
+
+#[tokio::test]
+async fn transfers_are_not_budget_spending() {
+    let app = spawn_app().await;
+    create_wallets(&app).await;
+    create_budget(&app, serde_json::json!({"limitInCents": 20000})).await;
+    let transfer = serde_json::json!({
+        "fromWallet": "Checking",
+        "toWallet": "Travel",
+        "amountInCents": 15000,
+        "currency": "EUR",
+        "accountingDate": "2026-03-02T09:00:00"
+    });
+    assert_eq!(
+        app.post_transfer(&transfer.to_string())
+            .await
+            .status()
+            .as_u16(),
+        200
+    );
+
+    let budgets = status(&app, "2026-03").await;
+
+    assert_eq!(budgets[0]["spentInCents"], 0);
+}
+
+#[tokio::test]
+async fn spending_in_other_currencies_is_converted() {
+    let app = spawn_app().await;
+    create_wallets(&app).await;
+    create_budget(
+        &app,
+        serde_json::json!({"limitInCents": 20000, "currency": "EUR"}),
+    )
+    .await;
+    post_payment(&app, payment(-12500, "food", "Travel", "2026-03-02")).await;
+
+    // Without a rate the spending cannot be evaluated
+    let response = app.get_budget_status("?month=2026-03").await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    let rates = serde_json::json!({"base": "EUR", "date": "2026-03-02", "rates": {"USD": 1.25}});
+    let response = app
+        .put_exchange_rates(
+            "",
+            "application/json",
+            &rates.to_string(),
+            &mint_admin_token("admin"),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let budgets = status(&app, "2026-03").await;
+    assert_eq!(budgets[0]["spentInCents"], 10000);
+}
+
+#[tokio::test]
+async fn budget_status_rejects_invalid_months() {
+    let app = spawn_app().await;
+
+    for month in ["2026-13", "march", "2026-03-01"] {
+        let response = app.get_budget_status(&format!("?month={month}")).await;
+        assert_eq!(response.status().as_u16(), 400, "{}", month);
+    }
+}
+
+#[tokio::test]
+async fn budget_status_defaults_to_the_current_month() {
+    let app = spawn_app().await;
+    create_budget(&app, serde_json::json!({"limitInCents": 20000})).await;
+
+    let response = app.get_budget_status("").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        body["month"],
+        chrono::Utc::now().date_naive().format("%Y-%m").to_string()
+    );
+    assert_eq!(body["budgets"][0]["spentInCents"], 0);
+}
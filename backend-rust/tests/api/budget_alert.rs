@@ -0,0 +1,243 @@
+use crate::helpers::{spawn_app, spawn_app_with, TestApp};
+use expenses_monitor_be::configuration::{NotifierSettings, SmtpSettings};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn spawn_app_with_webhook(server: &MockServer) -> TestApp {
+    let url = format!("{}/alerts", server.uri());
+    spawn_app_with(|config| {
+        config.notifications = NotifierSettings::Webhook {
+            url,
+            timeout_seconds: 5,
+        };
+    })
+    .await
+}
+
+async fn create_groceries_budget(app: &TestApp) {
+    app.create_categories(&["groceries"]).await;
+    let body = serde_json::json!({"categoryId": "groceries", "limitInCents": 10000});
+    let response = app.post_budget(&body.to_string()).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+async fn spend(app: &TestApp, amount: i64) -> serde_json::Value {
+    let body = serde_json::json!({
+        "merchantName": "Supermarket",
+        "amountInCents": amount,
+        "category": "groceries",
+        "accountingDate": "2026-03-10T10:00:00",
+    });
+    let response = app.post_payment(&body.to_string()).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.unwrap()
+}
+
+/// Alert events as (threshold, notified), waiting for `count` of them to be recorded.
+async fn alert_events(app: &TestApp, count: usize) -> Vec<(i16, bool)> {
+    let mut events = Vec::new();
+    for _ in 0..50 {
+        events = sqlx::query!(
+            r#"
+            SELECT threshold, notified_at IS NOT NULL as "notified!"
+            FROM expenses.budget_alert_events
+            ORDER BY threshold
+            "#
+        )
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| (row.threshold, row.notified))
+        .collect();
+        if events.len() >= count && events.iter().all(|(_, notified)| *notified) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    events
+}
+
+async fn webhook_alerts(server: &MockServer, count: usize) -> Vec<serde_json::Value> {
+    let mut alerts = Vec::new();
+    for _ in 0..50 {
+        alerts = server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .map(|request| serde_json::from_slice(&request.body).unwrap())
+            .collect();
+        if alerts.len() >= count {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    alerts
+}
+
+#[tokio::test]
+async fn crossing_a_threshold_posts_an_alert_to_the_webhook() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/alerts"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+    let app = spawn_app_with_webhook(&server).await;
+    create_groceries_budget(&app).await;
+
+    spend(&app, -8500).await;
+
+    let alerts = webhook_alerts(&server, 1).await;
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0]["threshold"], 80);
+    assert_eq!(alerts[0]["month"], "2026-03");
+    assert_eq!(alerts[0]["spentInCents"], 8500);
+    assert_eq!(alerts[0]["limitInCents"], 10000);
+    assert_eq!(alerts[0]["category"], "groceries");
+    assert_eq!(alerts[0]["userId"], app.auth_sub);
+}
+
+#[tokio::test]
+async fn each_threshold_fires_once_per_period() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/alerts"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+    let app = spawn_app_with_webhook(&server).await;
+    create_groceries_budget(&app).await;
+
+    spend(&app, -8500).await;
+    assert_eq!(webhook_alerts(&server, 1).await.len(), 1);
+    spend(&app, -500).await;
+    spend(&app, -2000).await;
+    assert_eq!(webhook_alerts(&server, 2).await.len(), 2);
+    spend(&app, -100).await;
+
+    assert_eq!(alert_events(&app, 2).await, vec![(80, true), (100, true)]);
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let alerts = webhook_alerts(&server, 2).await;
+    assert_eq!(alerts.len(), 2);
+    assert_eq!(alerts[1]["threshold"], 100);
+}
+
+#[tokio::test]
+async fn updating_a_payment_evaluates_the_budgets() {
+    let app = spawn_app().await;
+    create_groceries_budget(&app).await;
+    let payment = spend(&app, -1000).await;
+    let id = payment["id"].as_str().unwrap().parse().unwrap();
+
+    let body = serde_json::json!({
+        "merchantName": "Supermarket",
+        "amountInCents": -12000,
+        "category": "groceries",
+        "accountingDate": "2026-03-10T10:00:00",
+    });
+    let response = app.update_payment(id, &body.to_string()).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The default log notifier delivers every alert
+    assert_eq!(alert_events(&app, 2).await, vec![(80, true), (100, true)]);
+}
+
+#[tokio::test]
+async fn undelivered_alerts_are_recorded_but_not_marked_as_notified() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&server)
+        .await;
+    let app = spawn_app_with_webhook(&server).await;
+    create_groceries_budget(&app).await;
+
+    let payment = spend(&app, -9000).await;
+
+    assert_eq!(payment["amountInCents"], -9000);
+    webhook_alerts(&server, 1).await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(alert_events(&app, 0).await, vec![(80, false)]);
+}
+
+#[tokio::test]
+async fn payments_below_the_thresholds_do_not_alert() {
+    let app = spawn_app().await;
+    create_groceries_budget(&app).await;
+
+    spend(&app, -7999).await;
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(alert_events(&app, 0).await.is_empty());
+}
+
+/// Accepts a single SMTP session and returns the message data.
+async fn smtp_stub(listener: TcpListener) -> String {
+    let (socket, _) = listener.accept().await.unwrap();
+    let (reader, mut writer) = socket.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    writer.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+    let mut data = String::new();
+    let mut in_data = false;
+    while let Some(line) = lines.next_line().await.unwrap() {
+        if in_data {
+            if line == "." {
+                in_data = false;
+                writer.write_all(b"250 OK\r\n").await.unwrap();
+            } else {
+                data.push_str(&line);
+                data.push('\n');
+            }
+            continue;
+        }
+        let command = line.to_ascii_uppercase();
+        let reply: &[u8] = if command.starts_with("EHLO") {
+            b"250 localhost\r\n"
+        } else if command.starts_with("DATA") {
+            in_data = true;
+            b"354 End data with <CR><LF>.<CR><LF>\r\n"
+        } else if command.starts_with("QUIT") {
+            writer.write_all(b"221 Bye\r\n").await.unwrap();
+            break;
+        } else {
+            b"250 OK\r\n"
+        };
+        writer.write_all(reply).await.unwrap();
+    }
+    data
+}
+
+#[tokio::test]
+async fn alerts_can_be_sent_by_email() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let session = tokio::spawn(smtp_stub(listener));
+    let app = spawn_app_with(|config| {
+        config.notifications = NotifierSettings::Smtp(SmtpSettings {
+            host: "127.0.0.1".to_string(),
+            port,
+            username: None,
+            password: None,
+            from: "Expenses Monitor <alerts@example.com>".to_string(),
+            to: "me@example.com".to_string(),
+            starttls: false,
+        });
+    })
+    .await;
+    create_groceries_budget(&app).await;
+
+    spend(&app, -8000).await;
+
+    let data = tokio::time::timeout(Duration::from_secs(10), session)
+        .await
+        .expect("No e-mail was sent")
+        .unwrap();
+    assert!(data.contains("To: me@example.com"));
+    assert!(data.contains("Subject: Budget for groceries at 80% in 2026-03"));
+    assert_eq!(alert_events(&app, 1).await, vec![(80, true)]);
+}
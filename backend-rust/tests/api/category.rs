@@ -0,0 +1,553 @@
+use crate::helpers::{mint_token, spawn_app, TestApp};
+use uuid::Uuid;
+
+/// Creates a payment whose category is given by name, resolved by the server.
+async fn pay_in(app: &TestApp, token: &str, category: &str) -> serde_json::Value {
+    let body = serde_json::json!({
+        "categoryId": category,
+        "amountInCents": -1000,
+        "merchantName": category,
+        "accountingDate": "2026-03-01T10:00:00"
+    });
+    let response = app.post_payment_with_auth(&body.to_string(), token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.unwrap()
+}
+
+async fn categories(app: &TestApp, token: &str) -> Vec<serde_json::Value> {
+    let response = app.get_categories_with_auth(token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.unwrap()
+}
+
+fn find<'a>(categories: &'a [serde_json::Value], name: &str) -> Option<&'a serde_json::Value> {
+    categories.iter().find(|c| c["name"] == name)
+}
+
+async fn create_global_category(app: &TestApp, name: &str) -> Uuid {
+    sqlx::query_scalar!(
+        "INSERT INTO expenses.categories (name) VALUES ($1) RETURNING id",
+        name
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to create a global category")
+}
+
+#[tokio::test]
+async fn categories_created_by_name_belong_to_their_user() {
+    let app = spawn_app().await;
+    let token_b = mint_token(&Uuid::new_v4().to_string());
+
+    let payment_a = pay_in(&app, &app.auth_token, "Grocerys").await;
+    let payment_b = pay_in(&app, &token_b, "grocerys").await;
+
+    // The typo of one user does not show up in the categories of the other
+    let own = categories(&app, &app.auth_token).await;
+    let category = find(&own, "Grocerys").expect("Missing own category");
+    assert_eq!(category["global"], false);
+    assert_eq!(category["id"], payment_a["categoryId"]);
+    let others = categories(&app, &token_b).await;
+    assert!(find(&others, "Grocerys").is_none());
+    assert_eq!(
+        find(&others, "grocerys").unwrap()["id"],
+        payment_b["categoryId"]
+    );
+    assert_ne!(payment_a["categoryId"], payment_b["categoryId"]);
+}
+
+#[tokio::test]
+async fn global_defaults_are_shared_and_own_categories_come_first() {
+    let app = spawn_app().await;
+    let token_b = mint_token(&Uuid::new_v4().to_string());
+    let groceries = create_global_category(&app, "Groceries").await;
+    let food = create_global_category(&app, "Food").await;
+    let own_food = sqlx::query_scalar!(
+        "INSERT INTO expenses.categories (user_id, name) VALUES ($1, 'FOOD') RETURNING id",
+        app.auth_sub
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+
+    for token in [app.auth_token.as_str(), token_b.as_str()] {
+        let payment = pay_in(&app, token, "groceries").await;
+        assert_eq!(payment["categoryId"], groceries.to_string());
+        let listed = categories(&app, token).await;
+        assert_eq!(find(&listed, "Groceries").unwrap()["global"], true);
+    }
+    let payment_a = pay_in(&app, &app.auth_token, "Food").await;
+    let payment_b = pay_in(&app, &token_b, "Food").await;
+    assert_eq!(payment_a["categoryId"], own_food.to_string());
+    assert_eq!(payment_b["categoryId"], food.to_string());
+}
+
+#[tokio::test]
+async fn categories_of_other_users_cannot_be_used() {
+    let app = spawn_app().await;
+    let token_b = mint_token(&Uuid::new_v4().to_string());
+    let hobby = pay_in(&app, &token_b, "Hobby").await["categoryId"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let payment = serde_json::json!({
+        "categoryId": hobby,
+        "amountInCents": -1000,
+        "merchantName": "Shop",
+        "accountingDate": "2026-03-01T10:00:00"
+    });
+    let response = app.post_payment(&payment.to_string()).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let budget = serde_json::json!({
+        "limitInCents": 10000,
+        "categoryId": hobby
+    });
+    let response = app.post_budget(&budget.to_string()).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let rule = serde_json::json!({
+        "name": "Hobby",
+        "conditions": [{"type": "merchant", "pattern": "shop"}],
+        "actions": [{"type": "setCategory", "categoryId": hobby}]
+    });
+    let response = app.post_rule(&rule.to_string()).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    // Referring to it by name creates a category of the user instead
+    let own = pay_in(&app, &app.auth_token, "Hobby").await;
+    assert_ne!(own["categoryId"], hobby.as_str());
+}
+
+#[tokio::test]
+async fn invalid_category_names_are_not_created_by_payments() {
+    let app = spawn_app().await;
+    // Existing categories keep resolving, whatever their name
+    let legacy = create_global_category(&app, "Food (legacy)").await;
+    let body = |category: &str| {
+        serde_json::json!({
+            "categoryId": category,
+            "amountInCents": -1000,
+            "merchantName": "Bakery",
+            "accountingDate": "2026-03-01T10:00:00"
+        })
+        .to_string()
+    };
+
+    let response = app
+        .post_payment_with_query(&body("Bad (name) <x>"), "?force=true")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let listed = categories(&app, &app.auth_token).await;
+    assert!(find(&listed, "Bad (name) <x>").is_none());
+    let response = app
+        .post_payment_with_query(&body("food (LEGACY)"), "?force=true")
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let payment: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(payment["categoryId"], legacy.to_string());
+}
+
+async fn create_category(app: &TestApp, body: serde_json::Value) -> serde_json::Value {
+    let response = app.post_category(&body.to_string()).await;
+    assert_eq!(response.status().as_u16(), 201);
+    response.json().await.unwrap()
+}
+
+async fn payment_category_ids(app: &TestApp) -> Vec<String> {
+    let page: serde_json::Value = app.get_payments("").await.json().await.unwrap();
+    page["content"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["categoryId"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn create_category_returns_the_category() {
+    let app = spawn_app().await;
+
+    let category = create_category(
+        &app,
+        serde_json::json!({"name": "Pets", "icon": "🐶", "colour": "#AA00FF", "kind": "expense"}),
+    )
+    .await;
+
+    assert_eq!(category["name"], "Pets");
+    assert_eq!(category["icon"], "🐶");
+    assert_eq!(category["colour"], "#aa00ff");
+    assert_eq!(category["kind"], "expense");
+    assert_eq!(category["global"], false);
+    let listed = categories(&app, &app.auth_token).await;
+    assert_eq!(find(&listed, "Pets").unwrap()["id"], category["id"]);
+    // Payments referring to the name use it
+    let payment = pay_in(&app, &app.auth_token, "pets").await;
+    assert_eq!(payment["categoryId"], category["id"]);
+}
+
+#[tokio::test]
+async fn create_category_rejects_invalid_and_duplicate_categories() {
+    let app = spawn_app().await;
+    create_category(&app, serde_json::json!({"name": "Pets"})).await;
+
+    for (body, status, case) in [
+        (serde_json::json!({"name": " "}), 400, "empty name"),
+        (
+            serde_json::json!({"name": "a/b"}),
+            400,
+            "forbidden character",
+        ),
+        (
+            serde_json::json!({"name": "Toys", "colour": "red"}),
+            400,
+            "invalid colour",
+        ),
+        (
+            serde_json::json!({"name": "Toys", "icon": "<svg>"}),
+            400,
+            "invalid icon",
+        ),
+        (
+            serde_json::json!({"name": "Toys", "kind": "transfer"}),
+            400,
+            "unknown kind",
+        ),
+        (serde_json::json!({"name": "PETS"}), 409, "duplicate name"),
+    ] {
+        let response = app.post_category(&body.to_string()).await;
+        assert_eq!(response.status().as_u16(), status, "{}", case);
+    }
+}
+
+#[tokio::test]
+async fn update_category_changes_own_categories_only() {
+    let app = spawn_app().await;
+    let token_b = mint_token(&Uuid::new_v4().to_string());
+    let pets = create_category(&app, serde_json::json!({"name": "Pets"})).await;
+    create_category(&app, serde_json::json!({"name": "Toys"})).await;
+    let global = create_global_category(&app, "Groceries").await;
+    let others = pay_in(&app, &token_b, "Hobby").await;
+    let id = pets["id"].as_str().unwrap();
+
+    let response = app
+        .update_category(
+            id,
+            r##"{"name": "Animals", "colour": "#00ff00", "kind": "income"}"##,
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let updated: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(updated["id"], pets["id"]);
+    assert_eq!(updated["name"], "Animals");
+    assert_eq!(updated["colour"], "#00ff00");
+    assert_eq!(updated["kind"], "income");
+
+    let body = r#"{"name": "Renamed"}"#;
+    let response = app.update_category(id, r#"{"name": "toys"}"#).await;
+    assert_eq!(response.status().as_u16(), 409);
+    let response = app.update_category(&global.to_string(), body).await;
+    assert_eq!(response.status().as_u16(), 403);
+    let others = others["categoryId"].as_str().unwrap();
+    let response = app.update_category(others, body).await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn delete_category_requires_a_target_while_in_use() {
+    let app = spawn_app().await;
+    let unused = create_category(&app, serde_json::json!({"name": "Unused"})).await;
+    let pets = pay_in(&app, &app.auth_token, "Pets").await["categoryId"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let animals = create_category(&app, serde_json::json!({"name": "Animals"})).await;
+    let animals = animals["id"].as_str().unwrap();
+    let global = create_global_category(&app, "Groceries").await;
+
+    let response = app
+        .delete_category(unused["id"].as_str().unwrap(), "")
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+    let response = app.delete_category(&global.to_string(), "").await;
+    assert_eq!(response.status().as_u16(), 403);
+    let response = app.delete_category(&pets, "").await;
+    assert_eq!(response.status().as_u16(), 409);
+    let response = app
+        .delete_category(&pets, &format!("?reassignTo={}", pets))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    let response = app
+        .delete_category(&pets, &format!("?reassignTo={}", Uuid::new_v4()))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app
+        .delete_category(&pets, &format!("?reassignTo={}", animals))
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+    assert_eq!(payment_category_ids(&app).await, vec![animals.to_string()]);
+    let listed = categories(&app, &app.auth_token).await;
+    assert!(find(&listed, "Pets").is_none());
+    assert!(find(&listed, "Unused").is_none());
+}
+
+#[tokio::test]
+async fn merge_categories_moves_everything_to_the_target() {
+    let app = spawn_app().await;
+    let pets = pay_in(&app, &app.auth_token, "Pets").await["categoryId"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let animals = create_category(&app, serde_json::json!({"name": "Animals"})).await;
+    let animals = animals["id"].as_str().unwrap();
+    let response = app
+        .post_budget(&serde_json::json!({"categoryId": pets, "limitInCents": 5000}).to_string())
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let rule = serde_json::json!({
+        "name": "Vet",
+        "conditions": [{"type": "merchant", "pattern": "vet"}],
+        "actions": [
+            {"type": "addTag", "key": "kind", "value": "pets"},
+            {"type": "setCategory", "categoryId": pets}
+        ]
+    });
+    let response = app.post_rule(&rule.to_string()).await;
+    assert_eq!(response.status().as_u16(), 201);
+    let rule: serde_json::Value = response.json().await.unwrap();
+
+    let response = app
+        .merge_categories(
+            animals,
+            &serde_json::json!({"categoryId": pets}).to_string(),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let merged: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(merged["id"], animals);
+    assert_eq!(payment_category_ids(&app).await, vec![animals.to_string()]);
+    let budgets: Vec<serde_json::Value> = app.get_budgets().await.json().await.unwrap();
+    assert_eq!(budgets[0]["categoryId"], animals);
+    let rule: serde_json::Value = app
+        .get_rule(rule["id"].as_str().unwrap())
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(rule["actions"][0]["type"], "addTag");
+    assert_eq!(rule["actions"][1]["categoryId"], animals);
+    let listed = categories(&app, &app.auth_token).await;
+    assert!(find(&listed, "Pets").is_none());
+}
+
+#[tokio::test]
+async fn merge_categories_refuses_conflicting_budgets() {
+    let app = spawn_app().await;
+    let pets = create_category(&app, serde_json::json!({"name": "Pets"})).await;
+    let animals = create_category(&app, serde_json::json!({"name": "Animals"})).await;
+    for category in [&pets, &animals] {
+        let budget = serde_json::json!({"categoryId": category["id"], "limitInCents": 5000});
+        let response = app.post_budget(&budget.to_string()).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+    let body = serde_json::json!({"categoryId": pets["id"]}).to_string();
+
+    let response = app
+        .merge_categories(animals["id"].as_str().unwrap(), &body)
+        .await;
+
+    assert_eq!(response.status().as_u16(), 409);
+    // Nothing was changed
+    let listed = categories(&app, &app.auth_token).await;
+    assert!(find(&listed, "Pets").is_some());
+    let response = app
+        .merge_categories(pets["id"].as_str().unwrap(), &body)
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+/// Food > Restaurants > Pizza, and Travel.
+async fn create_category_tree(app: &TestApp) -> [String; 4] {
+    let mut ids: Vec<String> = Vec::new();
+    for (name, parent) in [
+        ("Food", None),
+        ("Restaurants", Some(0)),
+        ("Pizza", Some(1)),
+        ("Travel", None),
+    ] {
+        let parent_id = parent.map(|i: usize| ids[i].clone());
+        let category = create_category(
+            app,
+            serde_json::json!({"name": name, "parentId": parent_id}),
+        )
+        .await;
+        ids.push(category["id"].as_str().unwrap().to_string());
+    }
+    ids.try_into().unwrap()
+}
+
+async fn pay(app: &TestApp, category_id: &str, amount: i64, merchant: &str) {
+    let body = serde_json::json!({
+        "categoryId": category_id,
+        "amountInCents": amount,
+        "merchantName": merchant,
+        "accountingDate": "2026-03-01T10:00:00"
+    });
+    let response = app.post_payment(&body.to_string()).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn categories_are_listed_with_paths_or_as_a_tree() {
+    let app = spawn_app().await;
+    let [food, restaurants, pizza, travel] = create_category_tree(&app).await;
+
+    let listed: Vec<serde_json::Value> = app
+        .get_categories_with_query("")
+        .await
+        .json()
+        .await
+        .unwrap();
+    let paths: Vec<&str> = listed.iter().map(|c| c["path"].as_str().unwrap()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            "Food",
+            "Food > Restaurants",
+            "Food > Restaurants > Pizza",
+            "Travel"
+        ]
+    );
+    assert_eq!(listed[2]["parentId"], restaurants.as_str());
+
+    let tree: Vec<serde_json::Value> = app
+        .get_categories_with_query("?view=tree")
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(tree.len(), 2);
+    assert_eq!(tree[0]["id"], food.as_str());
+    assert_eq!(tree[0]["children"][0]["id"], restaurants.as_str());
+    assert_eq!(tree[0]["children"][0]["children"][0]["id"], pizza.as_str());
+    assert_eq!(tree[1]["id"], travel.as_str());
+    assert_eq!(tree[1]["children"], serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn category_parents_cannot_form_cycles() {
+    let app = spawn_app().await;
+    let token_b = mint_token(&Uuid::new_v4().to_string());
+    let [food, restaurants, pizza, _] = create_category_tree(&app).await;
+    let others = pay_in(&app, &token_b, "Hobby").await;
+    let global = create_global_category(&app, "Groceries").await;
+
+    for (parent, case) in [
+        (food.clone(), "itself"),
+        (pizza.clone(), "a subcategory"),
+        (
+            others["categoryId"].as_str().unwrap().to_string(),
+            "another user's",
+        ),
+        (Uuid::new_v4().to_string(), "unknown"),
+    ] {
+        let body = serde_json::json!({"name": "Food", "parentId": parent});
+        let response = app.update_category(&food, &body.to_string()).await;
+        assert_eq!(response.status().as_u16(), 400, "{}", case);
+    }
+    // Global defaults can have subcategories of the user
+    let body = serde_json::json!({"name": "Food", "parentId": global});
+    let response = app.update_category(&food, &body.to_string()).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let food_dto: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(food_dto["path"], "Groceries > Food");
+
+    // Nor can merges into a subcategory
+    let body = serde_json::json!({"categoryId": food}).to_string();
+    let response = app.merge_categories(&pizza, &body).await;
+    assert_eq!(response.status().as_u16(), 400);
+    // Subcategories of a deleted category move up to its parent
+    let response = app.delete_category(&restaurants, "").await;
+    assert_eq!(response.status().as_u16(), 204);
+    let listed: Vec<serde_json::Value> = app
+        .get_categories_with_query("")
+        .await
+        .json()
+        .await
+        .unwrap();
+    let pizza = listed.iter().find(|c| c["id"] == pizza.as_str()).unwrap();
+    assert_eq!(pizza["path"], "Groceries > Food > Pizza");
+}
+
+#[tokio::test]
+async fn payments_can_be_filtered_by_a_category_and_its_subcategories() {
+    let app = spawn_app().await;
+    let [food, restaurants, pizza, travel] = create_category_tree(&app).await;
+    pay(&app, &food, -1000, "Market").await;
+    pay(&app, &restaurants, -2000, "Bistro").await;
+    pay(&app, &pizza, -3000, "Pizzeria").await;
+    pay(&app, &travel, -4000, "Airline").await;
+
+    for (query, expected) in [
+        (format!("?category={}", food), 1),
+        (format!("?category={}&includeChildren=true", food), 3),
+        ("?category=restaurants&includeChildren=true".to_string(), 2),
+        (format!("?category={}&includeChildren=true", travel), 1),
+    ] {
+        let page: serde_json::Value = app.get_payments(&query).await.json().await.unwrap();
+        assert_eq!(
+            page["content"].as_array().unwrap().len(),
+            expected,
+            "{}",
+            query
+        );
+    }
+}
+
+#[tokio::test]
+async fn breakdowns_and_budgets_roll_subcategories_up() {
+    let app = spawn_app().await;
+    let [food, restaurants, pizza, travel] = create_category_tree(&app).await;
+    pay(&app, &food, -1000, "Market").await;
+    pay(&app, &pizza, -3000, "Pizzeria").await;
+    pay(&app, &travel, -4000, "Airline").await;
+    let budget = serde_json::json!({"categoryId": food, "limitInCents": 10000});
+    let response = app.post_budget(&budget.to_string()).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let balance: serde_json::Value = app
+        .get_balance_with_query("?groupBy=category")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(balance["totalInCents"], -8000);
+    let entry = |id: &str| {
+        balance["breakdown"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|e| e["key"] == id)
+            .unwrap()
+            .clone()
+    };
+    assert_eq!(entry(&food)["totalInCents"], -4000);
+    assert!(entry(&food).get("parentKey").is_none());
+    assert_eq!(entry(&restaurants)["totalInCents"], -3000);
+    assert_eq!(entry(&restaurants)["parentKey"], food.as_str());
+    assert_eq!(entry(&pizza)["totalInCents"], -3000);
+    assert_eq!(entry(&travel)["totalInCents"], -4000);
+
+    let status: serde_json::Value = app
+        .get_budget_status("?month=2026-03")
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(status["budgets"][0]["spentInCents"], 4000);
+}
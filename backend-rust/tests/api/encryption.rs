@@ -0,0 +1,91 @@
+use crate::helpers::{
+    rotate_encryption_key, spawn_app_with, test_encryption_settings, TestApp,
+    ROTATED_ENCRYPTION_KEY_ID,
+};
+use expenses_monitor_be::crypto::{
+    decrypt_token, encrypt_token, key_id, reencrypt_secrets, KeyRing,
+};
+use secrecy::{ExposeSecret, SecretString};
+use uuid::Uuid;
+
+async fn connect(app: &TestApp, stored_refresh_token: Option<Vec<u8>>) -> Uuid {
+    sqlx::query_scalar!(
+        r#"
+        INSERT INTO expenses.bank_connections (user_id, provider, encrypted_refresh_token)
+        VALUES ($1, 'mock', $2)
+        RETURNING id
+        "#,
+        app.auth_sub,
+        stored_refresh_token
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to create the bank connection")
+}
+
+async fn stored_refresh_token(app: &TestApp, connection_id: Uuid) -> Option<Vec<u8>> {
+    sqlx::query_scalar!(
+        "SELECT encrypted_refresh_token FROM expenses.bank_connections WHERE id = $1",
+        connection_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn reencryption_moves_every_secret_to_the_newest_key() {
+    let app = spawn_app_with(rotate_encryption_key).await;
+    let key_ring_before_rotation = KeyRing::from_settings(&test_encryption_settings()).unwrap();
+    let legacy = connect(
+        &app,
+        Some(encrypt_token(&key_ring_before_rotation, &SecretString::from("legacy")).unwrap()),
+    )
+    .await;
+    // Stored before refresh tokens were encrypted
+    let unencrypted = connect(&app, Some(b"unencrypted".to_vec())).await;
+    let current = connect(
+        &app,
+        Some(encrypt_token(&app.key_ring, &SecretString::from("current")).unwrap()),
+    )
+    .await;
+    let disconnected = connect(&app, None).await;
+
+    let reencrypted = reencrypt_secrets(&app.db_pool, &app.key_ring)
+        .await
+        .unwrap();
+
+    assert_eq!(reencrypted, 2);
+    for (connection_id, token) in [
+        (legacy, "legacy"),
+        (unencrypted, "unencrypted"),
+        (current, "current"),
+    ] {
+        let ciphertext = stored_refresh_token(&app, connection_id).await.unwrap();
+        assert_eq!(key_id(&ciphertext), Ok(ROTATED_ENCRYPTION_KEY_ID));
+        let decrypted = decrypt_token(&app.key_ring, &ciphertext).unwrap();
+        assert_eq!(decrypted.expose_secret(), token);
+    }
+    assert!(stored_refresh_token(&app, disconnected).await.is_none());
+    let reencrypted = reencrypt_secrets(&app.db_pool, &app.key_ring)
+        .await
+        .unwrap();
+    assert_eq!(reencrypted, 0);
+}
+
+#[tokio::test]
+async fn reencryption_fails_without_the_key_of_a_secret() {
+    let app = spawn_app_with(rotate_encryption_key).await;
+    let legacy = encrypt_token(&app.key_ring, &SecretString::from("legacy")).unwrap();
+    let connection_id = connect(&app, Some(legacy.clone())).await;
+    // The rotated key was removed before re-encrypting
+    let key_ring = KeyRing::from_settings(&test_encryption_settings()).unwrap();
+
+    let result = reencrypt_secrets(&app.db_pool, &key_ring).await;
+
+    assert!(result.is_err());
+    assert_eq!(
+        stored_refresh_token(&app, connection_id).await,
+        Some(legacy)
+    );
+}
@@ -0,0 +1,261 @@
+use crate::helpers::{mint_admin_token, spawn_app, spawn_app_with, TestApp};
+
+const ECB_FIXTURE: &str = "tests/api/fixtures/eurofxref-hist.csv";
+
+async fn import_ecb_fixture(app: &TestApp) {
+    let sheet = std::fs::read_to_string(ECB_FIXTURE).expect("Failed to read fixture");
+    let response = app
+        .put_exchange_rates("", "text/csv", &sheet, &mint_admin_token("admin"))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+async fn post_payment_in(app: &TestApp, amount: i64, currency: &str, date: &str) {
+    let body = serde_json::json!({
+        "merchantName": "Shop",
+        "amountInCents": amount,
+        "currency": currency,
+        "category": "misc",
+        "accountingDate": format!("{date}T10:00:00"),
+    });
+    let response = app.post_payment(&body.to_string()).await;
+    assert!(response.status().is_success());
+}
+
+#[tokio::test]
+async fn admin_imports_an_ecb_sheet() {
+    let app = spawn_app().await;
+    let sheet = std::fs::read_to_string(ECB_FIXTURE).expect("Failed to read fixture");
+
+    let response = app
+        .put_exchange_rates("", "text/csv", &sheet, &mint_admin_token("admin"))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    // JPY is not available on 2026-01-02
+    assert_eq!(body["imported"], 5);
+}
+
+#[tokio::test]
+async fn admin_imports_json_records_and_replaces_existing_rates() {
+    let app = spawn_app().await;
+    let token = mint_admin_token("admin");
+    let first = serde_json::json!({"base": "USD", "date": "2026-03-02", "rates": {"CHF": 0.9}});
+    let second = serde_json::json!([
+        {"baseCurrency": "USD", "quoteCurrency": "CHF", "date": "2026-03-02", "rate": 0.88}
+    ]);
+
+    let response = app
+        .put_exchange_rates("", "application/json", &first.to_string(), &token)
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = app
+        .put_exchange_rates("", "application/json", &second.to_string(), &token)
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let rate: serde_json::Value = app
+        .get_exchange_rate("USD", "CHF", "?date=2026-03-02")
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(rate["rate"], 0.88);
+}
+
+#[tokio::test]
+async fn importing_exchange_rates_requires_the_admin_role() {
+    let app = spawn_app().await;
+
+    let response = app
+        .put_exchange_rates(
+            "",
+            "text/csv",
+            "date,base,quote,rate\n2026-01-02,EUR,USD,1.1\n",
+            &app.auth_token,
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn importing_exchange_rates_rejects_invalid_documents() {
+    let app = spawn_app().await;
+    let token = mint_admin_token("admin");
+    let test_cases = vec![
+        (
+            "",
+            "date,base,quote,rate\n2026-01-02,EUR,USD,-1\n",
+            "negative rate",
+        ),
+        (
+            "",
+            "date,base,quote,rate\n2026-01-02,EUR,usd,1.1\n",
+            "invalid currency",
+        ),
+        (
+            "",
+            "date,base,quote,rate\nyesterday,EUR,USD,1.1\n",
+            "invalid date",
+        ),
+        ("", "", "empty document"),
+        ("?base=euro", "Date,USD\n2026-01-02,1.1\n", "invalid base"),
+    ];
+
+    for (query, body, description) in test_cases {
+        let response = app
+            .put_exchange_rates(query, "text/csv", body, &token)
+            .await;
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not reject a document with {}",
+            description
+        );
+    }
+}
+
+#[tokio::test]
+async fn importing_exchange_rates_rejects_unsupported_content_types() {
+    let app = spawn_app().await;
+
+    let response = app
+        .put_exchange_rates(
+            "",
+            "application/xml",
+            "<rates/>",
+            &mint_admin_token("admin"),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 415);
+}
+
+#[tokio::test]
+async fn exchange_rate_falls_back_to_the_nearest_previous_date() {
+    let app = spawn_app().await;
+    import_ecb_fixture(&app).await;
+
+    let response = app
+        .get_exchange_rate("EUR", "USD", "?date=2026-01-04")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let rate: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(rate["date"], "2026-01-04");
+    assert_eq!(rate["rateDate"], "2026-01-02");
+    assert_eq!(rate["rate"], 1.25);
+}
+
+#[tokio::test]
+async fn exchange_rate_is_not_found_before_the_first_published_date() {
+    let app = spawn_app().await;
+    import_ecb_fixture(&app).await;
+
+    let response = app
+        .get_exchange_rate("EUR", "JPY", "?date=2026-01-04")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn exchange_rates_file_is_imported_on_startup() {
+    let app = spawn_app_with(|config| {
+        config.exchange_rates.file = Some(ECB_FIXTURE.to_string());
+    })
+    .await;
+
+    let response = app
+        .get_exchange_rate("EUR", "JPY", "?date=2026-01-06")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let rate: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(rate["rateDate"], "2026-01-05");
+    assert_eq!(rate["rate"], 170.0);
+}
+
+#[tokio::test]
+async fn balance_is_converted_at_the_rate_of_each_payment_date() {
+    let app = spawn_app().await;
+    import_ecb_fixture(&app).await;
+    post_payment_in(&app, 10000, "EUR", "2026-01-03").await;
+    // 1.10 USD per EUR on 2026-01-05
+    post_payment_in(&app, -5500, "USD", "2026-01-05").await;
+    // Sunday: falls back to 1.25 USD per EUR from 2026-01-02
+    post_payment_in(&app, -2500, "USD", "2026-01-04").await;
+    post_payment_in(&app, -800, "GBP", "2026-01-02").await;
+
+    let response = app
+        .get_balance_with_query("?targetCurrency=EUR&groupBy=month")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let balance: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(balance["currency"], "EUR");
+    assert_eq!(balance["incomeInCents"], 10000);
+    assert_eq!(balance["expensesInCents"], -8000);
+    assert_eq!(balance["totalInCents"], 2000);
+    assert_eq!(balance["currencies"].as_array().unwrap().len(), 1);
+    let breakdown = balance["breakdown"].as_array().unwrap();
+    assert_eq!(breakdown.len(), 1);
+    assert_eq!(breakdown[0]["key"], "2026-01");
+    assert_eq!(breakdown[0]["currency"], "EUR");
+    assert_eq!(breakdown[0]["totalInCents"], 2000);
+}
+
+#[tokio::test]
+async fn balance_is_converted_through_cross_rates() {
+    let app = spawn_app().await;
+    import_ecb_fixture(&app).await;
+    post_payment_in(&app, 10000, "EUR", "2026-01-03").await;
+    // 1 GBP = 1.25 / 0.80 USD on 2026-01-02
+    post_payment_in(&app, -800, "GBP", "2026-01-02").await;
+
+    let response = app.get_balance_with_query("?targetCurrency=USD").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let balance: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(balance["currency"], "USD");
+    assert_eq!(balance["incomeInCents"], 12500);
+    assert_eq!(balance["expensesInCents"], -1250);
+    assert_eq!(balance["totalInCents"], 11250);
+}
+
+#[tokio::test]
+async fn balance_conversion_without_a_rate_is_unprocessable() {
+    let app = spawn_app().await;
+    import_ecb_fixture(&app).await;
+    // The first JPY rate is published on 2026-01-05
+    post_payment_in(&app, -100000, "JPY", "2026-01-02").await;
+
+    let response = app.get_balance_with_query("?targetCurrency=EUR").await;
+
+    assert_eq!(response.status().as_u16(), 422);
+    let body = response.text().await.unwrap();
+    assert!(body.contains("JPY"), "unexpected body: {body}");
+}
+
+#[tokio::test]
+async fn balance_rejects_an_invalid_target_currency() {
+    let app = spawn_app().await;
+
+    let response = app.get_balance_with_query("?targetCurrency=euro").await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn empty_balance_is_reported_in_the_target_currency() {
+    let app = spawn_app().await;
+
+    let response = app.get_balance_with_query("?targetCurrency=CHF").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let balance: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(balance["currency"], "CHF");
+    assert_eq!(balance["totalInCents"], 0);
+}
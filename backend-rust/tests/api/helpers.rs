@@ -1,9 +1,12 @@
-use base64::Engine;
-use expenses_monitor_be::configuration::{get_configuration, DatabaseSettings, TelemetrySettings};
+use expenses_monitor_be::configuration::{
+    get_configuration, DatabaseSettings, EncryptionSettings, Settings, TelemetrySettings,
+};
+use expenses_monitor_be::crypto::KeyRing;
 use expenses_monitor_be::startup::{get_connection_pool, Application};
 use expenses_monitor_be::telemetry::{get_subscriber, init_subscriber};
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
 use once_cell::sync::Lazy;
-use secrecy::ExposeSecret;
+use secrecy::{ExposeSecret, SecretString};
 use sqlx::{Executor, PgPool};
 use uuid::Uuid;
 
@@ -37,27 +40,102 @@ static TRACING: Lazy<()> = Lazy::new(|| {
     };
 });
 
+pub const TEST_ISSUER: &str = "http://localhost/realms/expenses-monitor-test";
+pub const TEST_AUDIENCE: &str = "expenses-monitor-be";
+pub const TEST_JWKS_PATH: &str = "tests/api/fixtures/jwks.json";
+pub const TEST_RS256_KID: &str = "test-rs256";
+pub const TEST_ES256_KID: &str = "test-es256";
+pub const TEST_RS256_KEY: &str = include_str!("fixtures/jwt_rs256.pem");
+pub const TEST_ES256_KEY: &str = include_str!("fixtures/jwt_es256.pem");
+pub const TEST_ENCRYPTION_KEY_ID: &str = "test";
+pub const TEST_ENCRYPTION_KEY: &str = "MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=";
+pub const ROTATED_ENCRYPTION_KEY_ID: &str = "rotated";
+pub const ROTATED_ENCRYPTION_KEY: &str = "ZmVkY2JhOTg3NjU0MzIxMGZlZGNiYTk4NzY1NDMyMTA=";
+/// RSA key that is not published in the test JWKS.
+pub const UNTRUSTED_RS256_KEY: &str = include_str!("fixtures/jwt_untrusted.pem");
+
+/// Claims accepted by the test application for the given subject.
+pub fn default_claims(sub: &str) -> serde_json::Value {
+    let now = chrono::Utc::now().timestamp();
+    serde_json::json!({
+        "sub": sub,
+        "iss": TEST_ISSUER,
+        "aud": TEST_AUDIENCE,
+        "iat": now,
+        "exp": now + 300
+    })
+}
+
+/// Signs `claims` with the given PEM key, setting `kid` in the header.
+pub fn sign_token(
+    algorithm: Algorithm,
+    kid: &str,
+    key_pem: &str,
+    claims: &serde_json::Value,
+) -> String {
+    let key = match algorithm {
+        Algorithm::ES256 => EncodingKey::from_ec_pem(key_pem.as_bytes()),
+        _ => EncodingKey::from_rsa_pem(key_pem.as_bytes()),
+    }
+    .expect("Failed to load test signing key");
+    let mut header = Header::new(algorithm);
+    header.kid = Some(kid.to_string());
+    encode(&header, claims, &key).expect("Failed to sign test token")
+}
+
+/// Mints a valid RS256 token for `sub`, signed with the test key.
+pub fn mint_token(sub: &str) -> String {
+    sign_token(
+        Algorithm::RS256,
+        TEST_RS256_KID,
+        TEST_RS256_KEY,
+        &default_claims(sub),
+    )
+}
+
+/// Mints a valid RS256 token for `sub` carrying the admin realm role.
+pub fn mint_admin_token(sub: &str) -> String {
+    let mut claims = default_claims(sub);
+    claims["realm_access"] = serde_json::json!({ "roles": ["admin"] });
+    sign_token(Algorithm::RS256, TEST_RS256_KID, TEST_RS256_KEY, &claims)
+}
+
 pub struct TestApp {
     pub address: String,
     pub db_pool: PgPool,
     pub auth_token: String,
     pub auth_sub: String,
+    /// The encryption keys of the application, to store bank refresh tokens.
+    pub key_ring: KeyRing,
 }
 
 impl TestApp {
     pub async fn post_payment(&self, body: &str) -> reqwest::Response {
+        self.post_payment_with_auth(body, &self.auth_token).await
+    }
+
+    pub async fn post_payment_with_auth(&self, body: &str, token: &str) -> reqwest::Response {
+        self.send_payment(body, "", token).await
+    }
+
+    /// Posts a payment with a query string, e.g. `?force=true`.
+    pub async fn post_payment_with_query(&self, body: &str, query: &str) -> reqwest::Response {
+        self.send_payment(body, query, &self.auth_token).await
+    }
+
+    async fn send_payment(&self, body: &str, query: &str, token: &str) -> reqwest::Response {
         // Tests historically posted `category` as a name. After API change we
-        // require `categoryId` (UUID). To keep tests concise we transform the
-        // body: if it contains `category` but not `categoryId`, ensure the
-        // category exists in the DB and replace it with `categoryId`.
+        // require `categoryId`. To keep tests concise we transform the body:
+        // if it contains `category` but not `categoryId`, the name is sent as
+        // `categoryId` and resolved by the server for the posting user.
         let mut payload: serde_json::Value = match serde_json::from_str(body) {
             Ok(v) => v,
             Err(_) => {
                 // Not JSON (some tests send empty string) - forward as-is
                 return reqwest::Client::new()
-                    .post(format!("{}/api/payments", &self.address))
+                    .post(format!("{}/api/payments{}", &self.address, query))
                     .header("Content-Type", "application/json")
-                    .header("Authorization", format!("Bearer {}", self.auth_token))
+                    .header("Authorization", format!("Bearer {}", token))
                     .body(body.to_owned())
                     .send()
                     .await
@@ -66,41 +144,13 @@ impl TestApp {
         };
 
         if payload.get("categoryId").is_none() {
-            if let Some(cat_val) = payload.get("category") {
-                if let Some(cat_name) = cat_val.as_str() {
-                    let cat_trimmed = cat_name.trim();
-                    // Only map non-empty category names to ids. Empty category should be
-                    // left as-is so server validation can reject it.
-                    if !cat_trimmed.is_empty() {
-                        // Try to find existing category (case-insensitive)
-                        if let Ok(row) = sqlx::query_scalar!(
-                            "SELECT id FROM expenses.categories WHERE lower(name) = lower($1)",
-                            cat_trimmed
-                        )
-                        .fetch_optional(&self.db_pool)
-                        .await
-                        {
-                            let id = if let Some(id) = row {
-                                id
-                            } else {
-                                // Insert and return id
-                                sqlx::query_scalar!(
-                                    "INSERT INTO expenses.categories (name) VALUES ($1) RETURNING id",
-                                    cat_trimmed
-                                )
-                                .fetch_one(&self.db_pool)
-                                .await
-                                .expect("Failed to insert category in test helper")
-                            };
-                            // Replace payload
-                            if let Some(m) = payload.as_object_mut() {
-                                m.remove("category");
-                                m.insert(
-                                    "categoryId".to_string(),
-                                    serde_json::Value::String(id.to_string()),
-                                );
-                            }
-                        }
+            if let Some(m) = payload.as_object_mut() {
+                // Only forward non-empty names, so the server rejects empty ones
+                if let Some(name) = m.remove("category") {
+                    if name.as_str().is_some_and(|name| !name.trim().is_empty()) {
+                        m.insert("categoryId".to_string(), name);
+                    } else {
+                        m.insert("category".to_string(), name);
                     }
                 }
             }
@@ -108,19 +158,41 @@ impl TestApp {
 
         let body = serde_json::to_string(&payload).expect("Failed to serialize payload");
         reqwest::Client::new()
-            .post(format!("{}/api/payments", &self.address))
+            .post(format!("{}/api/payments{}", &self.address, query))
             .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .header("Authorization", format!("Bearer {}", token))
             .body(body)
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
+    pub async fn get_duplicate_payments(&self) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/api/payments/duplicates", &self.address))
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_payment_suggestions(&self, query: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/api/payments/suggest{}", &self.address, query))
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn get_categories(&self) -> reqwest::Response {
+        self.get_categories_with_auth(&self.auth_token).await
+    }
+
+    pub async fn get_categories_with_auth(&self, token: &str) -> reqwest::Response {
         reqwest::Client::new()
             .get(format!("{}/api/payments/categories", &self.address))
-            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .header("Authorization", format!("Bearer {}", token))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -135,6 +207,15 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn get_payments_with_auth(&self, query: &str, token: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/api/payments{}", &self.address, query))
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn get_balance(&self) -> reqwest::Response {
         reqwest::Client::new()
             .get(format!("{}/api/balance", &self.address))
@@ -153,6 +234,449 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn get_balance_with_auth(&self, query: &str, token: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/api/balance{}", &self.address, query))
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn put_exchange_rates(
+        &self,
+        query: &str,
+        content_type: &str,
+        body: &str,
+        token: &str,
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .put(format!(
+                "{}/api/admin/exchange-rates{}",
+                &self.address, query
+            ))
+            .header("Content-Type", content_type)
+            .header("Authorization", format!("Bearer {}", token))
+            .body(body.to_owned())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_exchange_rate(
+        &self,
+        base: &str,
+        quote: &str,
+        query: &str,
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!(
+                "{}/api/exchange-rates/{}/{}{}",
+                &self.address, base, quote, query
+            ))
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_transfer(&self, body: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/api/transfers", &self.address))
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .body(body.to_owned())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_budget(&self, body: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/api/budgets", &self.address))
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .body(body.to_owned())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_budgets(&self) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/api/budgets", &self.address))
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn update_budget(&self, id: &str, body: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .put(format!("{}/api/budgets/{}", &self.address, id))
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .body(body.to_owned())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_budget(&self, id: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .delete(format!("{}/api/budgets/{}", &self.address, id))
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_budget_status(&self, query: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/api/budgets/status{}", &self.address, query))
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_csv_import(
+        &self,
+        form: reqwest::multipart::Form,
+        query: &str,
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/api/imports/csv{}", &self.address, query))
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .multipart(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Sends a multipart `body` delimited by `boundary`, so that retries are identical.
+    pub async fn post_csv_import_with_idempotency_key(
+        &self,
+        body: &str,
+        boundary: &str,
+        query: &str,
+        key: &str,
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/api/imports/csv{}", &self.address, query))
+            .header(
+                "Content-Type",
+                format!("multipart/form-data; boundary={}", boundary),
+            )
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .header("Idempotency-Key", key)
+            .body(body.to_owned())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_bank_sync(&self, connection_id: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!(
+                "{}/api/banking/sync/{}",
+                &self.address, connection_id
+            ))
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_staging_transactions(&self, query: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!(
+                "{}/api/staging/transactions{}",
+                &self.address, query
+            ))
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn update_staging_transaction(&self, id: &str, body: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .put(format!("{}/api/staging/transactions/{}", &self.address, id))
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .body(body.to_owned())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_staging_import(&self, body: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/api/staging/import", &self.address))
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .body(body.to_owned())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Lists the categories with a query string, e.g. `?view=tree`.
+    pub async fn get_categories_with_query(&self, query: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/api/categories{}", &self.address, query))
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_category(&self, body: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/api/categories", &self.address))
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .body(body.to_owned())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Creates categories of the test user, for budgets and rules referencing them by name.
+    pub async fn create_categories(&self, names: &[&str]) {
+        for name in names {
+            let response = self
+                .post_category(&serde_json::json!({ "name": name }).to_string())
+                .await;
+            assert!(response.status().is_success());
+        }
+    }
+
+    pub async fn update_category(&self, id: &str, body: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .put(format!("{}/api/categories/{}", &self.address, id))
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .body(body.to_owned())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Deletes a category with a query string, e.g. `?reassignTo=<id>`.
+    pub async fn delete_category(&self, id: &str, query: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .delete(format!("{}/api/categories/{}{}", &self.address, id, query))
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn merge_categories(&self, id: &str, body: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/api/categories/{}/merge", &self.address, id))
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .body(body.to_owned())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_merchants(&self) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/api/merchants", &self.address))
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_merchant_alias(&self, id: &str, body: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/api/merchants/{}/aliases", &self.address, id))
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .body(body.to_owned())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_merchant_alias(&self, id: &str, alias: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .delete(format!(
+                "{}/api/merchants/{}/aliases/{}",
+                &self.address, id, alias
+            ))
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn merge_merchants(&self, id: &str, body: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/api/merchants/{}/merge", &self.address, id))
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .body(body.to_owned())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_tags_with_auth(&self, token: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/api/tags", &self.address))
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn rename_tags(&self, body: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/api/tags/rename", &self.address))
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .body(body.to_owned())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_tag_report(&self, query: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/api/reports/tags{}", &self.address, query))
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_rules(&self) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/api/rules", &self.address))
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_rule(&self, body: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/api/rules", &self.address))
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .body(body.to_owned())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_rule(&self, id: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/api/rules/{}", &self.address, id))
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn update_rule(&self, id: &str, body: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .put(format!("{}/api/rules/{}", &self.address, id))
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .body(body.to_owned())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_rule(&self, id: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .delete(format!("{}/api/rules/{}", &self.address, id))
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Applies a rule to the existing payments, e.g. with `?commit=true`.
+    pub async fn apply_rule(&self, id: &str, query: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/api/rules/{}/apply{}", &self.address, id, query))
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_recurring_payment(&self, body: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/api/recurring-payments", &self.address))
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .body(body.to_owned())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_recurring_payments(&self) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/api/recurring-payments", &self.address))
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_recurring_payment(&self, id: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/api/recurring-payments/{}", &self.address, id))
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn update_recurring_payment(&self, id: &str, body: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .put(format!("{}/api/recurring-payments/{}", &self.address, id))
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .body(body.to_owned())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_recurring_payment(&self, id: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .delete(format!("{}/api/recurring-payments/{}", &self.address, id))
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn skip_recurring_payment(&self, id: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!(
+                "{}/api/recurring-payments/{}/skip",
+                &self.address, id
+            ))
+            .header("Authorization", format!("Bearer {}", self.auth_token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn create_wallet(&self, body: &str) -> reqwest::Response {
         reqwest::Client::new()
             .post(format!("{}/api/wallets", &self.address))
@@ -164,6 +688,44 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    /// Creates a wallet of the test user in the default currency.
+    pub async fn create_wallet_named(&self, name: &str) {
+        let response = self
+            .create_wallet(&serde_json::json!({ "name": name }).to_string())
+            .await;
+        assert!(response.status().is_success());
+    }
+
+    /// Payments of the first page listed for the wallet.
+    pub async fn wallet_payments(&self, wallet: &str) -> Vec<serde_json::Value> {
+        let page: serde_json::Value = self
+            .get_payments(&format!("?wallet={}", wallet))
+            .await
+            .json()
+            .await
+            .expect("Failed to parse response");
+        page["content"].as_array().unwrap().clone()
+    }
+
+    /// POSTs a JSON body to `path` with an `Idempotency-Key` header.
+    pub async fn post_with_idempotency_key(
+        &self,
+        path: &str,
+        body: &str,
+        key: &str,
+        token: &str,
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}{}", &self.address, path))
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Idempotency-Key", key)
+            .body(body.to_owned())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn create_wallet_with_auth(&self, body: &str, token: &str) -> reqwest::Response {
         reqwest::Client::new()
             .post(format!("{}/api/wallets", &self.address))
@@ -224,36 +786,13 @@ impl TestApp {
         };
 
         if payload.get("categoryId").is_none() {
-            if let Some(cat_val) = payload.get("category") {
-                if let Some(cat_name) = cat_val.as_str() {
-                    let cat_trimmed = cat_name.trim();
-                    if !cat_trimmed.is_empty() {
-                        if let Ok(row) = sqlx::query_scalar!(
-                            "SELECT id FROM expenses.categories WHERE lower(name) = lower($1)",
-                            cat_trimmed
-                        )
-                        .fetch_optional(&self.db_pool)
-                        .await
-                        {
-                            let cat_id = if let Some(id) = row {
-                                id
-                            } else {
-                                sqlx::query_scalar!(
-                                    "INSERT INTO expenses.categories (name) VALUES ($1) RETURNING id",
-                                    cat_trimmed
-                                )
-                                .fetch_one(&self.db_pool)
-                                .await
-                                .expect("Failed to insert category in update test helper")
-                            };
-                            if let Some(m) = payload.as_object_mut() {
-                                m.remove("category");
-                                m.insert(
-                                    "categoryId".to_string(),
-                                    serde_json::Value::String(cat_id.to_string()),
-                                );
-                            }
-                        }
+            if let Some(m) = payload.as_object_mut() {
+                // Only forward non-empty names, so the server rejects empty ones
+                if let Some(name) = m.remove("category") {
+                    if name.as_str().is_some_and(|name| !name.trim().is_empty()) {
+                        m.insert("categoryId".to_string(), name);
+                    } else {
+                        m.insert("category".to_string(), name);
                     }
                 }
             }
@@ -280,7 +819,33 @@ impl TestApp {
     }
 }
 
+/// The encryption keys of test applications, before any rotation.
+pub fn test_encryption_settings() -> EncryptionSettings {
+    EncryptionSettings {
+        keys: [(
+            TEST_ENCRYPTION_KEY_ID.to_string(),
+            SecretString::from(TEST_ENCRYPTION_KEY),
+        )]
+        .into(),
+        current_key_id: Some(TEST_ENCRYPTION_KEY_ID.to_string()),
+    }
+}
+
+/// Adds a newer encryption key and makes it current, keeping the test key.
+pub fn rotate_encryption_key(config: &mut Settings) {
+    config.encryption.keys.insert(
+        ROTATED_ENCRYPTION_KEY_ID.to_string(),
+        SecretString::from(ROTATED_ENCRYPTION_KEY),
+    );
+    config.encryption.current_key_id = Some(ROTATED_ENCRYPTION_KEY_ID.to_string());
+}
+
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+/// Spawns the application after applying `customise` to the test configuration.
+pub async fn spawn_app_with(customise: impl FnOnce(&mut Settings)) -> TestApp {
     // let subscriber = get_subscriber("test".into(), "info".into());
     // init_subscriber(subscriber);
     Lazy::force(&TRACING);
@@ -291,6 +856,13 @@ pub async fn spawn_app() -> TestApp {
         config.database.database_name = Uuid::new_v4().to_string();
         // Use a random OS port
         config.application.port = 0;
+        // Verify tokens against the local test JWKS
+        config.auth.jwks_url = None;
+        config.auth.jwks_path = Some(TEST_JWKS_PATH.to_string());
+        config.auth.issuer = TEST_ISSUER.to_string();
+        config.auth.audience = TEST_AUDIENCE.to_string();
+        config.encryption = test_encryption_settings();
+        customise(&mut config);
         config
     };
 
@@ -303,24 +875,18 @@ pub async fn spawn_app() -> TestApp {
     // Get the port before spawning the application
     let address = format!("http://127.0.0.1:{}", application.port());
     tokio::spawn(application.run_until_stopped());
-    // Create a default unsigned JWT token for tests to use when hitting
-    // endpoints that expect an authenticated user (gateway-forwarded JWT).
+    // Create a default signed JWT token for tests to use when hitting
+    // endpoints that expect an authenticated user.
     let sub = Uuid::new_v4().to_string();
-    let header = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#);
-    let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(
-        serde_json::json!({
-            "sub": sub,
-            "exp": (chrono::Utc::now().timestamp() + 60)
-        })
-        .to_string(),
-    );
-    let token = format!("{}.{}.", header, payload);
+    let token = mint_token(&sub);
 
     TestApp {
         address,
         db_pool: get_connection_pool(&configuration),
         auth_token: token,
         auth_sub: sub,
+        key_ring: KeyRing::from_settings(&configuration.encryption)
+            .expect("Failed to build the key ring."),
     }
 }
 
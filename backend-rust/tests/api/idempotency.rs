@@ -0,0 +1,216 @@
+use crate::helpers::{mint_token, spawn_app, TestApp};
+
+const PAYMENT: &str = r#"{
+    "merchantName": "Bakery",
+    "amountInCents": -1250,
+    "categoryId": "Food",
+    "accountingDate": "2026-03-05T09:00:00"
+}"#;
+
+async fn post_payment(app: &TestApp, path: &str, body: &str, key: &str) -> reqwest::Response {
+    app.post_with_idempotency_key(path, body, key, &app.auth_token)
+        .await
+}
+
+async fn payment_count(app: &TestApp) -> i64 {
+    sqlx::query_scalar!(r#"SELECT count(*) as "count!" FROM expenses.payments"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn retried_payment_creations_replay_the_first_response() {
+    let app = spawn_app().await;
+
+    let first = post_payment(&app, "/api/payments", PAYMENT, "retry-1").await;
+    let retry = post_payment(&app, "/api/payments", PAYMENT, "retry-1").await;
+
+    assert_eq!(first.status().as_u16(), 200);
+    assert!(first.headers().get("Idempotent-Replayed").is_none());
+    assert_eq!(retry.status().as_u16(), 200);
+    assert_eq!(retry.headers()["Idempotent-Replayed"], "true");
+    assert_eq!(retry.headers()["Content-Type"], "application/json");
+    let first: serde_json::Value = first.json().await.unwrap();
+    let retry: serde_json::Value = retry.json().await.unwrap();
+    assert_eq!(first, retry);
+    assert_eq!(payment_count(&app).await, 1);
+}
+
+#[tokio::test]
+async fn client_errors_are_replayed_too() {
+    let app = spawn_app().await;
+    let invalid = PAYMENT.replace("Bakery", "");
+
+    let first = post_payment(&app, "/api/payments", &invalid, "invalid-1").await;
+    let retry = post_payment(&app, "/api/payments", &invalid, "invalid-1").await;
+
+    assert_eq!(first.status().as_u16(), 400);
+    assert_eq!(retry.status().as_u16(), 400);
+    assert_eq!(retry.headers()["Idempotent-Replayed"], "true");
+    assert_eq!(first.text().await.unwrap(), retry.text().await.unwrap());
+}
+
+#[tokio::test]
+async fn reusing_a_key_for_another_request_returns_422() {
+    let app = spawn_app().await;
+    let response = post_payment(&app, "/api/payments", PAYMENT, "reused").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let other_body = PAYMENT.replace("-1250", "-1300");
+    let cases = [
+        ("/api/payments", other_body.as_str()),
+        ("/api/payments?force=true", PAYMENT),
+        ("/api/wallets", r#"{"name": "Checking"}"#),
+    ];
+    for (path, body) in cases {
+        let response = post_payment(&app, path, body, "reused").await;
+        assert_eq!(response.status().as_u16(), 422, "{}", path);
+    }
+    assert_eq!(payment_count(&app).await, 1);
+}
+
+#[tokio::test]
+async fn keys_are_scoped_to_the_user() {
+    let app = spawn_app().await;
+    let response = post_payment(&app, "/api/payments", PAYMENT, "shared").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_with_idempotency_key(
+            "/api/payments",
+            PAYMENT,
+            "shared",
+            &mint_token("someone-else"),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.headers().get("Idempotent-Replayed").is_none());
+    assert_eq!(payment_count(&app).await, 2);
+}
+
+#[tokio::test]
+async fn retried_wallet_creations_replay_the_first_response() {
+    let app = spawn_app().await;
+    let body = r#"{"name": "Checking"}"#;
+
+    let first = app
+        .post_with_idempotency_key("/api/wallets", body, "wallet-1", &app.auth_token)
+        .await;
+    let retry = app
+        .post_with_idempotency_key("/api/wallets", body, "wallet-1", &app.auth_token)
+        .await;
+
+    assert!(first.status().is_success());
+    assert_eq!(retry.status(), first.status());
+    assert_eq!(retry.headers()["Idempotent-Replayed"], "true");
+    // Without the key, the same creation is a conflict
+    let response = app.create_wallet(body).await;
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+#[tokio::test]
+async fn expired_keys_are_processed_again() {
+    let app = spawn_app().await;
+    let path = "/api/payments?force=true";
+    let first: serde_json::Value = post_payment(&app, path, PAYMENT, "expiring")
+        .await
+        .json()
+        .await
+        .unwrap();
+    sqlx::query!("UPDATE expenses.idempotency_keys SET expires_at = now() - interval '1 second'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = post_payment(&app, path, PAYMENT, "expiring").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.headers().get("Idempotent-Replayed").is_none());
+    let second: serde_json::Value = response.json().await.unwrap();
+    assert_ne!(first["id"], second["id"]);
+}
+
+#[tokio::test]
+async fn requests_still_being_processed_return_409() {
+    let app = spawn_app().await;
+    let response = post_payment(&app, "/api/payments", PAYMENT, "in-progress").await;
+    assert_eq!(response.status().as_u16(), 200);
+    sqlx::query!(
+        "UPDATE expenses.idempotency_keys SET response_status = NULL, response_body = NULL"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let response = post_payment(&app, "/api/payments", PAYMENT, "in-progress").await;
+
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+#[tokio::test]
+async fn invalid_keys_are_rejected_with_400() {
+    let app = spawn_app().await;
+
+    for key in ["", "two words", &"k".repeat(256)] {
+        let response = post_payment(&app, "/api/payments", PAYMENT, key).await;
+        assert_eq!(response.status().as_u16(), 400, "{:?}", key);
+    }
+    assert_eq!(payment_count(&app).await, 0);
+}
+
+#[tokio::test]
+async fn requests_with_a_key_still_require_authentication() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_with_idempotency_key("/api/payments", PAYMENT, "anonymous", "invalid-token")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn retried_csv_imports_do_not_import_the_statement_twice() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+    let boundary = "statement-boundary";
+    // Part headers and content of each field
+    let parts = [
+        (
+            r#"name="file"; filename="statement.csv""#.to_string() + "\r\nContent-Type: text/csv",
+            "Date,Amount,Payee\n2026-03-05,-12.50,Bakery\n2026-03-06,-8.00,Pharmacy\n",
+        ),
+        (
+            r#"name="mapping""#.to_string(),
+            r#"{"date": "Date", "amount": "Amount", "merchant": "Payee"}"#,
+        ),
+        (r#"name="wallet""#.to_string(), "Checking"),
+        (r#"name="category""#.to_string(), "Imported"),
+    ];
+    let mut body = String::new();
+    for (headers, content) in parts {
+        body.push_str(&format!(
+            "--{boundary}\r\nContent-Disposition: form-data; {headers}\r\n\r\n{content}\r\n"
+        ));
+    }
+    body.push_str(&format!("--{boundary}--\r\n"));
+    let query = "?commit=true&force=true";
+
+    let first = app
+        .post_csv_import_with_idempotency_key(&body, boundary, query, "import-1")
+        .await;
+    let retry = app
+        .post_csv_import_with_idempotency_key(&body, boundary, query, "import-1")
+        .await;
+
+    assert_eq!(first.status().as_u16(), 200);
+    assert_eq!(retry.status().as_u16(), 200);
+    assert_eq!(retry.headers()["Idempotent-Replayed"], "true");
+    let first: serde_json::Value = first.json().await.unwrap();
+    let retry: serde_json::Value = retry.json().await.unwrap();
+    assert_eq!(first["acceptedRows"], 2);
+    assert_eq!(first, retry);
+    assert_eq!(payment_count(&app).await, 2);
+}
@@ -0,0 +1,274 @@
+use crate::helpers::spawn_app;
+use reqwest::multipart::{Form, Part};
+
+const STATEMENT: &str = "Date,Amount,Payee,Memo\n\
+                         2026-03-05,-12.50,Bakery,Croissants\n\
+                         2026-03-06,\"1,500.00\",Employer,March salary\n\
+                         2026-03-07,abc,Bad (merchant),\n";
+
+/// A statement upload, with `fields` replacing or adding to the default ones.
+fn statement_form(statement: &str, fields: &[(&'static str, &'static str)]) -> Form {
+    let mut values = vec![
+        (
+            "mapping",
+            r#"{"date": "Date", "amount": "Amount", "merchant": "Payee", "description": "Memo"}"#,
+        ),
+        ("wallet", "Checking"),
+        ("category", "Imported"),
+    ];
+    for &(name, value) in fields {
+        values.retain(|&(existing, _)| existing != name);
+        values.push((name, value));
+    }
+    let file = Part::text(statement.to_string())
+        .file_name("statement.csv")
+        .mime_str("text/csv")
+        .unwrap();
+    values
+        .into_iter()
+        .filter(|(_, value)| !value.is_empty())
+        .fold(Form::new().part("file", file), |form, (name, value)| {
+            form.text(name, value)
+        })
+}
+
+#[tokio::test]
+async fn preview_annotates_rows_without_creating_payments() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+
+    let response = app
+        .post_csv_import(statement_form(STATEMENT, &[]), "")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let preview: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(preview["committed"], false);
+    assert_eq!(preview["currency"], "EUR");
+    assert_eq!(preview["acceptedRows"], 2);
+    assert_eq!(preview["rejectedRows"], 1);
+    let rows = preview["rows"].as_array().unwrap();
+    assert_eq!(rows[0]["line"], 2);
+    assert_eq!(rows[0]["accountingDate"], "2026-03-05T00:00:00");
+    assert_eq!(rows[0]["amountInCents"], -1250);
+    assert_eq!(rows[0]["merchantName"], "Bakery");
+    assert_eq!(rows[0]["description"], "Croissants");
+    assert_eq!(rows[0]["category"], "Imported");
+    assert_eq!(rows[0]["errors"], serde_json::json!([]));
+    assert_eq!(rows[1]["amountInCents"], 150000);
+    assert_eq!(rows[2]["line"], 4);
+    let errors = rows[2]["errors"].as_array().unwrap();
+    assert_eq!(errors.len(), 2);
+    assert!(errors[1]
+        .as_str()
+        .unwrap()
+        .contains("is not a valid merchant name"));
+    assert!(app.wallet_payments("Checking").await.is_empty());
+}
+
+#[tokio::test]
+async fn commit_imports_the_accepted_rows_into_the_wallet() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+
+    let response = app
+        .post_csv_import(statement_form(STATEMENT, &[]), "?commit=true")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let result: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(result["committed"], true);
+    assert_eq!(result["acceptedRows"], 2);
+    assert!(result["rows"][0]["paymentId"].is_string());
+    assert!(result["rows"][2].get("paymentId").is_none());
+    let mut payments = app.wallet_payments("Checking").await;
+    payments.sort_by_key(|p| p["accountingDate"].as_str().unwrap().to_string());
+    assert_eq!(payments.len(), 2);
+    assert_eq!(payments[0]["id"], result["rows"][0]["paymentId"]);
+    assert_eq!(payments[0]["merchantName"], "Bakery");
+    assert_eq!(payments[0]["amountInCents"], -1250);
+    assert_eq!(payments[0]["category"], "Imported");
+    assert_eq!(payments[1]["amountInCents"], 150000);
+    assert_eq!(payments[1]["currency"], "EUR");
+}
+
+#[tokio::test]
+async fn statement_formats_can_be_configured() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+    let statement = "Booking date;Debit;Counterparty;Category\n\
+                     05.03.2026;1.234,56;Furniture store;Home\n\
+                     06.03.2026;-20;Refund shop;\n";
+    let form = statement_form(
+        statement,
+        &[
+            (
+                "mapping",
+                r#"{"date": "Booking date", "amount": "Debit", "merchant": "Counterparty", "category": "Category"}"#,
+            ),
+            ("dateFormat", "%d.%m.%Y"),
+            ("decimalSeparator", ","),
+            ("signConvention", "expensesPositive"),
+            ("delimiter", ";"),
+        ],
+    );
+
+    let response = app.post_csv_import(form, "?commit=true").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let result: serde_json::Value = response.json().await.unwrap();
+    let rows = result["rows"].as_array().unwrap();
+    assert_eq!(rows[0]["amountInCents"], -123456);
+    assert_eq!(rows[0]["category"], "Home");
+    assert_eq!(rows[1]["amountInCents"], 2000);
+    assert_eq!(rows[1]["category"], "Imported");
+    let categories: Vec<String> = app
+        .wallet_payments("Checking")
+        .await
+        .iter()
+        .map(|p| p["category"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(categories.len(), 2);
+    assert!(categories.contains(&"Home".to_string()));
+}
+
+#[tokio::test]
+async fn rows_without_a_category_are_rejected() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+    // An empty value leaves the field out
+    let form = statement_form(STATEMENT, &[("category", "")]);
+
+    let response = app.post_csv_import(form, "?commit=true").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let result: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(result["acceptedRows"], 0);
+    assert!(app.wallet_payments("Checking").await.is_empty());
+}
+
+#[tokio::test]
+async fn invalid_imports_are_rejected_with_400() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+
+    let cases = [
+        (
+            statement_form(STATEMENT, &[("wallet", "Savings")]),
+            "unknown wallet",
+        ),
+        (
+            statement_form(STATEMENT, &[("mapping", r#"{"date": "Date"}"#)]),
+            "incomplete mapping",
+        ),
+        (
+            statement_form(
+                STATEMENT,
+                &[(
+                    "mapping",
+                    r#"{"date": "Date", "amount": "Value", "merchant": "Payee"}"#,
+                )],
+            ),
+            "unknown column",
+        ),
+        (
+            statement_form(STATEMENT, &[("decimalSeparator", "_")]),
+            "invalid decimal separator",
+        ),
+        (
+            statement_form(STATEMENT, &[("signConvention", "inverted")]),
+            "invalid sign convention",
+        ),
+        (
+            Form::new().text("mapping", "{}").text("wallet", "Checking"),
+            "missing file",
+        ),
+    ];
+    for (form, case) in cases {
+        let response = app.post_csv_import(form, "").await;
+        assert_eq!(response.status().as_u16(), 400, "{}", case);
+    }
+}
+
+#[tokio::test]
+async fn imports_require_authentication() {
+    let app = spawn_app().await;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/api/imports/csv", app.address))
+        .multipart(statement_form(STATEMENT, &[]))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn rows_already_recorded_are_rejected_unless_forced() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+    let body = serde_json::json!({
+        "merchantName": "bakery",
+        "amountInCents": -1250,
+        "category": "Food",
+        "accountingDate": "2026-03-05T08:30:00",
+        "wallet": "Checking"
+    });
+    let existing: serde_json::Value = app
+        .post_payment(&body.to_string())
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    let response = app
+        .post_csv_import(statement_form(STATEMENT, &[]), "?commit=true")
+        .await;
+
+    let result: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(result["acceptedRows"], 1);
+    assert_eq!(
+        result["rows"][0]["errors"][0],
+        format!(
+            "Possible duplicate of payment {}",
+            existing["id"].as_str().unwrap()
+        )
+    );
+    assert_eq!(app.wallet_payments("Checking").await.len(), 2);
+
+    let response = app
+        .post_csv_import(statement_form(STATEMENT, &[]), "?force=true")
+        .await;
+
+    let preview: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(preview["acceptedRows"], 2);
+}
+
+#[tokio::test]
+async fn a_failed_import_does_not_create_its_categories() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+    // Makes the import fail once its categories are resolved
+    for statement in [
+        r#"CREATE FUNCTION expenses.reject_payments() RETURNS trigger
+           LANGUAGE plpgsql AS $$ BEGIN RAISE EXCEPTION 'rejected'; END $$"#,
+        r#"CREATE TRIGGER reject_payments BEFORE INSERT ON expenses.payments
+           FOR EACH ROW EXECUTE FUNCTION expenses.reject_payments()"#,
+    ] {
+        sqlx::query(statement).execute(&app.db_pool).await.unwrap();
+    }
+
+    let response = app
+        .post_csv_import(statement_form(STATEMENT, &[]), "?commit=true")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 500);
+    let created = sqlx::query_scalar!(
+        "SELECT count(*) FROM expenses.categories WHERE lower(name) = 'imported'"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(created, Some(0));
+}
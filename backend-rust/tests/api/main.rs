@@ -1,7 +1,26 @@
 mod auth;
+mod auth_scoping;
 mod balance;
 mod balance_test;
+mod banking;
+mod budget;
+mod budget_alert;
+mod category;
+mod encryption;
+mod exchange_rate;
 mod health_check;
 mod helpers;
+mod idempotency;
+mod import;
+mod merchant;
 mod payment;
+mod payment_duplicate;
+mod payment_split;
+mod payment_suggestion;
+mod recurring_payment;
+mod report;
+mod rule;
+mod staging;
+mod tag;
+mod transfer;
 mod wallet;
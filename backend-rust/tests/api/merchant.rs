@@ -0,0 +1,262 @@
+use crate::helpers::{mint_token, spawn_app, TestApp};
+use expenses_monitor_be::routes::materialise_recurring_payments;
+
+fn payment(merchant_name: &str, accounting_date: &str) -> String {
+    serde_json::json!({
+        "merchantName": merchant_name,
+        "amountInCents": -1250,
+        "category": "Shopping",
+        "accountingDate": accounting_date
+    })
+    .to_string()
+}
+
+async fn create(app: &TestApp, merchant_name: &str, accounting_date: &str) -> serde_json::Value {
+    let response = app
+        .post_payment_with_query(&payment(merchant_name, accounting_date), "?force=true")
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.unwrap()
+}
+
+async fn merchants(app: &TestApp) -> Vec<serde_json::Value> {
+    let response = app.get_merchants().await;
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.unwrap()
+}
+
+async fn merchant_id(app: &TestApp, name: &str) -> String {
+    merchants(app)
+        .await
+        .into_iter()
+        .find(|m| m["name"] == name)
+        .unwrap_or_else(|| panic!("No merchant named {}", name))["id"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn payments_resolve_to_merchants_ignoring_case_and_whitespace() {
+    let app = spawn_app().await;
+    create(&app, "Amazon EU", "2026-03-01T10:00:00").await;
+
+    let payment = create(&app, "  AMAZON   eu", "2026-03-02T10:00:00").await;
+    create(&app, "Bakery", "2026-03-03T10:00:00").await;
+
+    assert_eq!(payment["merchantName"], "Amazon EU");
+    let merchants = merchants(&app).await;
+    assert_eq!(merchants.len(), 2);
+    assert_eq!(merchants[0]["name"], "Amazon EU");
+    assert_eq!(merchants[0]["paymentCount"], 2);
+    assert_eq!(merchants[0]["aliases"], serde_json::json!([]));
+    assert_eq!(merchants[1]["name"], "Bakery");
+    assert_eq!(merchants[1]["paymentCount"], 1);
+}
+
+#[tokio::test]
+async fn payments_resolve_through_aliases_on_create_and_update() {
+    let app = spawn_app().await;
+    create(&app, "Amazon EU", "2026-03-01T10:00:00").await;
+    let amazon = merchant_id(&app, "Amazon EU").await;
+
+    let response = app
+        .post_merchant_alias(&amazon, r#"{"alias": " Amazon.IT "}"#)
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let merchant: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(merchant["aliases"], serde_json::json!(["amazon.it"]));
+
+    let created = create(&app, "amazon.it", "2026-03-02T10:00:00").await;
+    assert_eq!(created["merchantName"], "Amazon EU");
+    let other = create(&app, "Bookshop", "2026-03-03T10:00:00").await;
+    let id: uuid::Uuid = other["id"].as_str().unwrap().parse().unwrap();
+    let response = app
+        .update_payment(id, &payment("AMAZON.it", "2026-03-03T10:00:00"))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let updated: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(updated["merchantName"], "Amazon EU");
+
+    let merchants = merchants(&app).await;
+    assert_eq!(merchants[0]["name"], "Amazon EU");
+    assert_eq!(merchants[0]["paymentCount"], 3);
+}
+
+#[tokio::test]
+async fn merging_a_merchant_rewrites_its_payments_and_keeps_its_spellings() {
+    let app = spawn_app().await;
+    create(&app, "Amazon EU", "2026-03-01T10:00:00").await;
+    let merged = create(&app, "amazon", "2026-03-02T10:00:00").await;
+    let amazon = merchant_id(&app, "Amazon EU").await;
+    let other = merchant_id(&app, "amazon").await;
+    let response = app
+        .post_merchant_alias(&other, r#"{"alias": "AMZN"}"#)
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .merge_merchants(&amazon, &format!(r#"{{"merchantId": "{}"}}"#, other))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let merchant: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(merchant["id"], amazon.as_str());
+    assert_eq!(merchant["aliases"], serde_json::json!(["amazon", "amzn"]));
+    assert_eq!(merchant["paymentCount"], 2);
+    assert_eq!(merchants(&app).await.len(), 1);
+    let id: uuid::Uuid = merged["id"].as_str().unwrap().parse().unwrap();
+    let merchant_name = sqlx::query_scalar!(
+        "SELECT merchant_name FROM expenses.payments WHERE id = $1",
+        id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(merchant_name.as_deref(), Some("Amazon EU"));
+    // Both spellings of the merged merchant now resolve to the target
+    for spelling in ["Amazon", "amzn"] {
+        let created = create(&app, spelling, "2026-03-05T10:00:00").await;
+        assert_eq!(created["merchantName"], "Amazon EU", "{}", spelling);
+    }
+}
+
+#[tokio::test]
+async fn merging_validates_the_merchants() {
+    let app = spawn_app().await;
+    create(&app, "Amazon EU", "2026-03-01T10:00:00").await;
+    let amazon = merchant_id(&app, "Amazon EU").await;
+    let unknown = uuid::Uuid::new_v4();
+
+    let response = app
+        .merge_merchants(&amazon, &format!(r#"{{"merchantId": "{}"}}"#, amazon))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    let response = app
+        .merge_merchants(&amazon, &format!(r#"{{"merchantId": "{}"}}"#, unknown))
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+    let response = app
+        .merge_merchants(
+            &unknown.to_string(),
+            &format!(r#"{{"merchantId": "{}"}}"#, amazon),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn aliases_of_another_merchant_are_rejected_with_409() {
+    let app = spawn_app().await;
+    create(&app, "Amazon EU", "2026-03-01T10:00:00").await;
+    create(&app, "Bakery", "2026-03-02T10:00:00").await;
+    let amazon = merchant_id(&app, "Amazon EU").await;
+    let bakery = merchant_id(&app, "Bakery").await;
+
+    let response = app
+        .post_merchant_alias(&amazon, r#"{"alias": "bakery"}"#)
+        .await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    let response = app
+        .post_merchant_alias(&amazon, r#"{"alias": "amzn"}"#)
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = app
+        .post_merchant_alias(&bakery, r#"{"alias": "AMZN"}"#)
+        .await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    // Adding an alias twice to the same merchant is harmless
+    let response = app
+        .post_merchant_alias(&amazon, r#"{"alias": "Amzn"}"#)
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = app
+        .post_merchant_alias(&amazon, r#"{"alias": "(invalid)"}"#)
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn removed_aliases_no_longer_resolve() {
+    let app = spawn_app().await;
+    create(&app, "Amazon EU", "2026-03-01T10:00:00").await;
+    let amazon = merchant_id(&app, "Amazon EU").await;
+    app.post_merchant_alias(&amazon, r#"{"alias": "amzn mktp"}"#)
+        .await;
+
+    let response = app.delete_merchant_alias(&amazon, "AMZN%20Mktp").await;
+    assert_eq!(response.status().as_u16(), 204);
+    let response = app.delete_merchant_alias(&amazon, "amzn mktp").await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let created = create(&app, "AMZN Mktp", "2026-03-02T10:00:00").await;
+    assert_eq!(created["merchantName"], "AMZN Mktp");
+    assert_eq!(merchants(&app).await.len(), 2);
+}
+
+#[tokio::test]
+async fn merchants_are_scoped_to_the_user() {
+    let app = spawn_app().await;
+    create(&app, "Amazon EU", "2026-03-01T10:00:00").await;
+    let amazon = merchant_id(&app, "Amazon EU").await;
+    app.post_merchant_alias(&amazon, r#"{"alias": "amzn"}"#)
+        .await;
+    let body = payment("AMZN", "2026-03-02T10:00:00");
+    let response = app
+        .post_payment_with_auth(&body, &mint_token("someone-else"))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let created: serde_json::Value = response.json().await.unwrap();
+
+    assert_eq!(created["merchantName"], "AMZN");
+    let merchants = merchants(&app).await;
+    assert_eq!(merchants.len(), 1);
+    assert_eq!(merchants[0]["paymentCount"], 1);
+    let response = app.delete_merchant_alias(&amazon, "amzn").await;
+    assert_eq!(response.status().as_u16(), 204);
+}
+
+#[tokio::test]
+async fn recurring_payments_resolve_to_merchants() {
+    let app = spawn_app().await;
+    create(&app, "Netflix", "2026-03-01T10:00:00").await;
+    let netflix = merchant_id(&app, "Netflix").await;
+    app.post_merchant_alias(&netflix, r#"{"alias": "netflix.com"}"#)
+        .await;
+    let response = app
+        .post_recurring_payment(
+            r#"{
+                "merchantName": "NETFLIX.COM",
+                "amountInCents": -1299,
+                "categoryId": "Subscriptions",
+                "recurrence": "FREQ=MONTHLY;BYMONTHDAY=5",
+                "startDate": "2030-01-05"
+            }"#,
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let created = materialise_recurring_payments(
+        &app.db_pool,
+        chrono::NaiveDate::from_ymd_opt(2030, 1, 31).unwrap(),
+    )
+    .await
+    .unwrap()
+    .created;
+
+    assert_eq!(created, 1);
+    let names: Vec<String> = sqlx::query_scalar!(
+        r#"
+        SELECT p.merchant_name as "merchant_name!"
+        FROM expenses.payments p JOIN expenses.merchants m ON m.id = p.merchant_id
+        WHERE m.name = 'Netflix'
+        ORDER BY p.accounting_date
+        "#
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(names, vec!["Netflix", "Netflix"]);
+}
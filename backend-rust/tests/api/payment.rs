@@ -1024,3 +1024,155 @@ async fn get_payments_combines_multiple_filters() {
         .to_lowercase()
         .contains("restaurant"));
 }
+
+#[tokio::test]
+async fn payment_amounts_beyond_32_bits_are_stored_and_returned_exactly() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_wallet(r#"{"name": "Savings"}"#).await;
+    let amount: i64 = 5_000_000_000;
+    let body = serde_json::json!({
+        "category": "property",
+        "amountInCents": amount,
+        "merchantName": "Notary",
+        "accountingDate": "2024-05-01T10:00:00",
+        "wallet": "Savings"
+    });
+
+    // Act
+    let response = app.post_payment(&body.to_string()).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let created: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(created["amountInCents"].as_i64(), Some(amount));
+    let id = uuid::Uuid::parse_str(created["id"].as_str().unwrap()).unwrap();
+
+    let fetched: serde_json::Value = app.get_payment(id).await.json().await.unwrap();
+    assert_eq!(fetched["amountInCents"].as_i64(), Some(amount));
+
+    let listed: serde_json::Value = app
+        .get_payments("?page=0&size=10")
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(listed["content"][0]["amountInCents"].as_i64(), Some(amount));
+}
+
+#[tokio::test]
+async fn create_payment_currency_defaults_to_the_wallet_currency() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_wallet(r#"{"name": "US Card", "defaultCurrency": "USD"}"#)
+        .await;
+    let payment = |wallet: Option<&str>, currency: Option<&str>| {
+        let mut body = serde_json::json!({
+            "category": "travel",
+            "amountInCents": -1200,
+            "merchantName": "Diner",
+            "accountingDate": "2024-07-04T12:00:00"
+        });
+        if let Some(wallet) = wallet {
+            body["wallet"] = wallet.into();
+        }
+        if let Some(currency) = currency {
+            body["currency"] = currency.into();
+        }
+        body.to_string()
+    };
+
+    // Act
+    let from_wallet: serde_json::Value = app
+        .post_payment(&payment(Some("US Card"), None))
+        .await
+        .json()
+        .await
+        .unwrap();
+    let explicit: serde_json::Value = app
+        .post_payment(&payment(Some("US Card"), Some("GBP")))
+        .await
+        .json()
+        .await
+        .unwrap();
+    let without_wallet: serde_json::Value = app
+        .post_payment(&payment(None, None))
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(from_wallet["currency"], "USD");
+    assert_eq!(explicit["currency"], "GBP");
+    assert_eq!(without_wallet["currency"], "EUR");
+    let listed: serde_json::Value = app
+        .get_payments("?wallet=US%20Card")
+        .await
+        .json()
+        .await
+        .unwrap();
+    let mut currencies: Vec<&str> = listed["content"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["currency"].as_str().unwrap())
+        .collect();
+    currencies.sort();
+    assert_eq!(currencies, vec!["GBP", "USD"]);
+}
+
+#[rstest]
+#[case("usd")]
+#[case("EURO")]
+#[case("12E")]
+#[tokio::test]
+async fn create_payment_returns_a_400_for_an_invalid_currency(#[case] currency: &str) {
+    // Arrange
+    let app = spawn_app().await;
+    let body = serde_json::json!({
+        "category": "test",
+        "amountInCents": -100,
+        "currency": currency,
+        "merchantName": "Market",
+        "accountingDate": "2023-11-13T00:00:00.000"
+    });
+
+    // Act
+    let response = app.post_payment(&body.to_string()).await;
+
+    // Assert
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn update_payment_changes_the_currency() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_wallet(r#"{"name": "Cash"}"#).await;
+    let body = r#"{
+        "category": "test",
+        "amountInCents": -100,
+        "merchantName": "Market",
+        "accountingDate": "2023-11-13T00:00:00.000",
+        "wallet": "Cash"
+    }"#;
+    let created: serde_json::Value = app.post_payment(body).await.json().await.unwrap();
+    let id = uuid::Uuid::parse_str(created["id"].as_str().unwrap()).unwrap();
+
+    // Act
+    let update = r#"{
+        "category": "test",
+        "amountInCents": -100,
+        "currency": "CHF",
+        "merchantName": "Market",
+        "accountingDate": "2023-11-13T00:00:00.000",
+        "wallet": "Cash"
+    }"#;
+    let response = app.update_payment(id, update).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let fetched: serde_json::Value = app.get_payment(id).await.json().await.unwrap();
+    assert_eq!(fetched["currency"], "CHF");
+}
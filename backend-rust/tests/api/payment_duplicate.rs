@@ -0,0 +1,162 @@
+use crate::helpers::{mint_token, spawn_app, TestApp};
+
+fn payment(merchant_name: &str, amount_in_cents: i64, accounting_date: &str) -> String {
+    serde_json::json!({
+        "merchantName": merchant_name,
+        "amountInCents": amount_in_cents,
+        "category": "Food",
+        "accountingDate": accounting_date,
+        "wallet": "Checking"
+    })
+    .to_string()
+}
+
+async fn create(app: &TestApp, body: &str) -> serde_json::Value {
+    let response = app.post_payment_with_query(body, "?force=true").await;
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn likely_duplicates_are_rejected_with_their_candidates() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+    let first = create(&app, &payment("Corner Shop", -1250, "2026-03-05T09:00:00")).await;
+
+    let body = payment(" corner  shop", -1250, "2026-03-05T18:30:00");
+    let response = app.post_payment(&body).await;
+
+    assert_eq!(response.status().as_u16(), 409);
+    let conflict: serde_json::Value = response.json().await.unwrap();
+    let duplicates = conflict["duplicates"].as_array().unwrap();
+    assert_eq!(duplicates.len(), 1);
+    assert_eq!(duplicates[0]["id"], first["id"]);
+    assert_eq!(duplicates[0]["merchantName"], "Corner Shop");
+    assert_eq!(duplicates[0]["amountInCents"], -1250);
+    assert_eq!(duplicates[0]["accountingDate"], "2026-03-05T09:00:00");
+    assert_eq!(duplicates[0]["wallet"], "Checking");
+
+    let response = app.post_payment_with_query(&body, "?force=true").await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn similar_merchants_within_the_edit_distance_are_duplicates() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+    create(&app, &payment("Supermarket", -4599, "2026-03-05T09:00:00")).await;
+
+    let response = app
+        .post_payment(&payment("Supermarkt", -4599, "2026-03-05T10:00:00"))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+#[tokio::test]
+async fn legacy_payments_without_merchant_are_not_duplicates() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+    let other = create(&app, &payment("Bakery", -300, "2026-03-05T07:00:00")).await;
+    let category_id: uuid::Uuid = other["categoryId"].as_str().unwrap().parse().unwrap();
+    sqlx::query!(
+        r#"
+        INSERT INTO expenses.payments (accounting_date, merchant_name, amount, category_id, user_id)
+        VALUES ('2026-03-05T08:00:00', NULL, -1250, $1, $2)
+        "#,
+        category_id,
+        app.auth_sub
+    )
+    .execute(&app.db_pool)
+    .await
+    .expect("Failed to insert a legacy payment");
+
+    let response = app
+        .post_payment(&payment("Corner Shop", -1250, "2026-03-05T09:00:00"))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn payments_that_differ_are_not_duplicates() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+    create(&app, &payment("Bakery", -1250, "2026-03-05T09:00:00")).await;
+
+    let cases = [
+        (payment("Bakery", -1300, "2026-03-05T09:00:00"), "amount"),
+        (payment("Bakery", -1250, "2026-03-06T09:00:00"), "day"),
+        (payment("Butcher", -1250, "2026-03-05T09:00:00"), "merchant"),
+    ];
+    for (body, case) in cases {
+        let response = app.post_payment(&body).await;
+        assert_eq!(response.status().as_u16(), 200, "{}", case);
+    }
+    let mut other_currency: serde_json::Value =
+        serde_json::from_str(&payment("Bakery", -1250, "2026-03-05T09:00:00")).unwrap();
+    other_currency["currency"] = "USD".into();
+    let response = app.post_payment(&other_currency.to_string()).await;
+    assert_eq!(response.status().as_u16(), 200, "currency");
+
+    // Payments of other users are not candidates
+    let body = serde_json::json!({
+        "merchantName": "Bakery",
+        "amountInCents": -1250,
+        "category": "Food",
+        "accountingDate": "2026-03-05T09:00:00"
+    });
+    let response = app
+        .post_payment_with_auth(&body.to_string(), &mint_token("someone-else"))
+        .await;
+    assert_eq!(response.status().as_u16(), 200, "user");
+}
+
+#[tokio::test]
+async fn duplicates_report_lists_clusters_of_likely_duplicates() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+    let first = create(&app, &payment("Bakery", -1250, "2026-03-05T09:00:00")).await;
+    let second = create(&app, &payment("BAKERY ", -1250, "2026-03-05T17:00:00")).await;
+    create(&app, &payment("Butcher", -1250, "2026-03-05T09:00:00")).await;
+    create(&app, &payment("Bakery", -1250, "2026-03-06T09:00:00")).await;
+    let older = create(&app, &payment("Gas station", -6000, "2026-02-01T08:00:00")).await;
+    let older_twin = create(&app, &payment("Gas Station", -6000, "2026-02-01T08:05:00")).await;
+
+    let response = app.get_duplicate_payments().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let clusters: serde_json::Value = response.json().await.unwrap();
+    let clusters = clusters.as_array().unwrap();
+    assert_eq!(clusters.len(), 2);
+    assert_eq!(clusters[0]["accountingDate"], "2026-03-05");
+    assert_eq!(clusters[0]["amountInCents"], -1250);
+    assert_eq!(clusters[0]["currency"], "EUR");
+    let ids: Vec<&serde_json::Value> = clusters[0]["payments"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| &p["id"])
+        .collect();
+    assert_eq!(ids, vec![&first["id"], &second["id"]]);
+    let ids: Vec<&serde_json::Value> = clusters[1]["payments"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| &p["id"])
+        .collect();
+    assert_eq!(ids, vec![&older["id"], &older_twin["id"]]);
+}
+
+#[tokio::test]
+async fn duplicates_report_is_empty_without_duplicates() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+    create(&app, &payment("Bakery", -1250, "2026-03-05T09:00:00")).await;
+
+    let response = app.get_duplicate_payments().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let clusters: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(clusters, serde_json::json!([]));
+}
@@ -0,0 +1,245 @@
+use crate::helpers::{spawn_app, TestApp};
+use uuid::Uuid;
+
+fn receipt(splits: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+        "merchantName": "Supermarket",
+        "amountInCents": -10000,
+        "category": "groceries",
+        "accountingDate": "2026-03-07T18:00:00",
+        "wallet": "Card",
+        "splits": splits
+    })
+}
+
+fn receipt_splits() -> serde_json::Value {
+    serde_json::json!([
+        {"categoryId": "groceries", "amountInCents": -6000},
+        {"categoryId": "household", "amountInCents": -2500, "description": "Detergent"},
+        {"categoryId": "pharmacy", "amountInCents": -1500}
+    ])
+}
+
+async fn post_receipt(app: &TestApp) -> serde_json::Value {
+    app.create_wallet(r#"{"name": "Card"}"#).await;
+    let response = app
+        .post_payment(&receipt(receipt_splits()).to_string())
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.expect("Failed to parse response")
+}
+
+fn id_of(payment: &serde_json::Value) -> Uuid {
+    payment["id"].as_str().unwrap().parse().unwrap()
+}
+
+#[tokio::test]
+async fn create_payment_stores_splits_in_order() {
+    let app = spawn_app().await;
+
+    let created = post_receipt(&app).await;
+
+    let splits = created["splits"].as_array().unwrap();
+    assert_eq!(splits.len(), 3);
+    assert_eq!(splits[0]["category"], "groceries");
+    assert_eq!(splits[1]["category"], "household");
+    assert_eq!(splits[1]["amountInCents"], -2500);
+    assert_eq!(splits[1]["description"], "Detergent");
+    assert_eq!(splits[2]["category"], "pharmacy");
+
+    let fetched: serde_json::Value = app.get_payment(id_of(&created)).await.json().await.unwrap();
+    assert_eq!(fetched["splits"], created["splits"]);
+}
+
+#[tokio::test]
+async fn payments_without_splits_have_an_empty_list() {
+    let app = spawn_app().await;
+    app.create_wallet(r#"{"name": "Card"}"#).await;
+    let mut body = receipt(serde_json::Value::Null);
+    body.as_object_mut().unwrap().remove("splits");
+
+    let created: serde_json::Value = app
+        .post_payment(&body.to_string())
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(created["splits"], serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn create_payment_returns_a_400_when_splits_are_invalid() {
+    let app = spawn_app().await;
+    app.create_wallet(r#"{"name": "Card"}"#).await;
+    let test_cases = vec![
+        (
+            serde_json::json!([
+                {"categoryId": "groceries", "amountInCents": -6000},
+                {"categoryId": "household", "amountInCents": -2500}
+            ]),
+            "splits not adding up to the amount",
+        ),
+        (
+            serde_json::json!([
+                {"categoryId": "groceries", "amountInCents": -10000},
+                {"categoryId": "household", "amountInCents": 0}
+            ]),
+            "a zero split",
+        ),
+        (
+            serde_json::json!([
+                {"categoryId": Uuid::new_v4(), "amountInCents": -10000}
+            ]),
+            "an unknown split category",
+        ),
+        (
+            serde_json::json!([
+                {"categoryId": "", "amountInCents": -10000}
+            ]),
+            "an empty split category",
+        ),
+    ];
+
+    for (splits, description) in test_cases {
+        let response = app.post_payment(&receipt(splits).to_string()).await;
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not fail with 400 Bad Request when the payload had {}.",
+            description
+        );
+    }
+    let saved = sqlx::query!("SELECT COUNT(*) as \"count!\" FROM expenses.payments")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.count, 0);
+}
+
+#[tokio::test]
+async fn update_payment_replaces_splits() {
+    let app = spawn_app().await;
+    let created = post_receipt(&app).await;
+    let update = receipt(serde_json::json!([
+        {"categoryId": "groceries", "amountInCents": -9000},
+        {"categoryId": "pharmacy", "amountInCents": -1000}
+    ]));
+
+    let response = app
+        .update_payment(id_of(&created), &update.to_string())
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let updated: serde_json::Value = response.json().await.unwrap();
+    let splits = updated["splits"].as_array().unwrap();
+    assert_eq!(splits.len(), 2);
+    assert_eq!(splits[0]["amountInCents"], -9000);
+    assert_eq!(splits[1]["category"], "pharmacy");
+}
+
+#[tokio::test]
+async fn update_payment_without_splits_removes_them() {
+    let app = spawn_app().await;
+    let created = post_receipt(&app).await;
+    let mut update = receipt(serde_json::Value::Null);
+    update.as_object_mut().unwrap().remove("splits");
+
+    let response = app
+        .update_payment(id_of(&created), &update.to_string())
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let updated: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(updated["splits"], serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn update_payment_rejects_splits_not_matching_the_new_amount() {
+    let app = spawn_app().await;
+    let created = post_receipt(&app).await;
+    let mut update = receipt(receipt_splits());
+    update["amountInCents"] = serde_json::json!(-12000);
+
+    let response = app
+        .update_payment(id_of(&created), &update.to_string())
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn deleting_a_payment_deletes_its_splits() {
+    let app = spawn_app().await;
+    let created = post_receipt(&app).await;
+
+    let response = app.delete_payment(id_of(&created)).await;
+
+    assert_eq!(response.status().as_u16(), 204);
+    let remaining = sqlx::query!("SELECT COUNT(*) as \"count!\" FROM expenses.payment_splits")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(remaining.count, 0);
+}
+
+#[tokio::test]
+async fn category_filter_matches_split_categories() {
+    let app = spawn_app().await;
+    let created = post_receipt(&app).await;
+
+    for category in ["household", "pharmacy", "groceries"] {
+        let page: serde_json::Value = app
+            .get_payments(&format!("?category={}", category))
+            .await
+            .json()
+            .await
+            .unwrap();
+        let content = page["content"].as_array().unwrap();
+        assert_eq!(content.len(), 1, "no payment found for {}", category);
+        assert_eq!(content[0]["id"], created["id"]);
+        // The listing still shows the whole payment
+        assert_eq!(content[0]["amountInCents"], -10000);
+    }
+}
+
+#[tokio::test]
+async fn balance_uses_split_amounts_for_categories() {
+    let app = spawn_app().await;
+    post_receipt(&app).await;
+
+    let balance: serde_json::Value = app
+        .get_balance_with_query("?category=household")
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(balance["expensesInCents"], -2500);
+
+    let balance: serde_json::Value = app
+        .get_balance_with_query("?groupBy=category")
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(balance["expensesInCents"], -10000);
+    let breakdown: Vec<(String, i64)> = balance["breakdown"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| {
+            (
+                entry["label"].as_str().unwrap().to_string(),
+                entry["expensesInCents"].as_i64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        breakdown,
+        vec![
+            ("groceries".to_string(), -6000),
+            ("household".to_string(), -2500),
+            ("pharmacy".to_string(), -1500),
+        ]
+    );
+}
@@ -0,0 +1,180 @@
+use crate::helpers::{mint_token, spawn_app, TestApp};
+use chrono::{Duration, Utc};
+
+/// Records a payment `days_ago` days before today.
+async fn record(app: &TestApp, merchant_name: &str, amount: i64, category: &str, days_ago: i64) {
+    record_with(
+        app,
+        merchant_name,
+        amount,
+        category,
+        days_ago,
+        serde_json::json!({}),
+    )
+    .await
+}
+
+async fn record_with(
+    app: &TestApp,
+    merchant_name: &str,
+    amount: i64,
+    category: &str,
+    days_ago: i64,
+    extra: serde_json::Value,
+) {
+    let mut body = serde_json::json!({
+        "merchantName": merchant_name,
+        "amountInCents": amount,
+        "categoryId": category,
+        "accountingDate": (Utc::now() - Duration::days(days_ago)).format("%Y-%m-%dT10:00:00").to_string()
+    });
+    body.as_object_mut()
+        .unwrap()
+        .extend(extra.as_object().unwrap().clone());
+    let response = app
+        .post_payment_with_query(&body.to_string(), "?force=true")
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+async fn suggestions(app: &TestApp, query: &str) -> serde_json::Value {
+    let response = app.get_payment_suggestions(query).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.unwrap()
+}
+
+fn confidence(suggestion: &serde_json::Value) -> f64 {
+    suggestion["confidence"].as_f64().unwrap()
+}
+
+#[tokio::test]
+async fn categories_are_ranked_from_the_merchant_history() {
+    let app = spawn_app().await;
+    for days_ago in [3, 10, 17, 24] {
+        record(&app, "Carrefour Market", -4599, "Groceries", days_ago).await;
+    }
+    record(&app, "Shell Station", -6000, "Transport", 5).await;
+    record(&app, "Cinema Pathé", -1200, "Leisure", 8).await;
+
+    let suggestions = suggestions(&app, "?merchantName=CARREFOUR%20city&amount=-3250").await;
+
+    let categories = suggestions["categories"].as_array().unwrap();
+    assert_eq!(categories.len(), 3);
+    assert_eq!(categories[0]["category"], "Groceries");
+    assert!(categories[0]["categoryId"].is_string());
+    assert!(confidence(&categories[0]) > 0.8, "{:?}", categories);
+    assert!(confidence(&categories[0]) >= confidence(&categories[1]));
+    let total: f64 = categories.iter().map(confidence).sum();
+    assert!((total - 1.0).abs() < 1e-6);
+}
+
+#[tokio::test]
+async fn recent_habits_outweigh_old_ones() {
+    let app = spawn_app().await;
+    for days_ago in [700, 730, 760] {
+        record(&app, "Bakery", -450, "Food", days_ago).await;
+    }
+    record(&app, "Bakery", -450, "Snacks", 2).await;
+
+    let suggestions = suggestions(&app, "?merchantName=Bakery&amount=-450").await;
+
+    assert_eq!(suggestions["categories"][0]["category"], "Snacks");
+}
+
+#[tokio::test]
+async fn wallets_and_tags_are_suggested() {
+    let app = spawn_app().await;
+    let response = app.create_wallet(r#"{"name": "Joint account"}"#).await;
+    assert!(response.status().is_success());
+    let extra = serde_json::json!({
+        "wallet": "Joint account",
+        "tags": [{"key": "shop", "value": "weekly"}]
+    });
+    for days_ago in [1, 8] {
+        record_with(
+            &app,
+            "Carrefour",
+            -5000,
+            "Groceries",
+            days_ago,
+            extra.clone(),
+        )
+        .await;
+    }
+    record(&app, "Pharmacy", -1500, "Health", 3).await;
+
+    let suggestions = suggestions(&app, "?merchantName=carrefour").await;
+
+    assert_eq!(suggestions["wallets"][0]["wallet"], "Joint account");
+    assert!(confidence(&suggestions["wallets"][0]) > 0.5);
+    assert_eq!(suggestions["tags"][0]["key"], "shop");
+    assert_eq!(suggestions["tags"][0]["value"], "weekly");
+    assert!(confidence(&suggestions["tags"][0]) > 0.5);
+}
+
+#[tokio::test]
+async fn aliases_of_a_merchant_share_its_history() {
+    let app = spawn_app().await;
+    for days_ago in [1, 2] {
+        record(&app, "Amazon EU", -2500, "Shopping", days_ago).await;
+    }
+    record(&app, "Metro", -200, "Transport", 1).await;
+    let merchants: Vec<serde_json::Value> = app.get_merchants().await.json().await.unwrap();
+    let amazon = merchants.iter().find(|m| m["name"] == "Amazon EU").unwrap();
+    let response = app
+        .post_merchant_alias(amazon["id"].as_str().unwrap(), r#"{"alias": "AMZN Mktp"}"#)
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let suggestions = suggestions(&app, "?merchantName=amzn%20mktp").await;
+
+    assert_eq!(suggestions["categories"][0]["category"], "Shopping");
+}
+
+#[tokio::test]
+async fn suggestions_only_learn_from_the_user_history() {
+    let app = spawn_app().await;
+    record(&app, "Bakery", -450, "Food", 1).await;
+    let body = serde_json::json!({
+        "merchantName": "Bakery",
+        "amountInCents": -450,
+        "categoryId": "Snacks",
+        "accountingDate": "2026-03-05T10:00:00"
+    });
+    let response = app
+        .post_payment_with_auth(&body.to_string(), &mint_token("someone-else"))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let suggestions = suggestions(&app, "?merchantName=Bakery").await;
+
+    let categories = suggestions["categories"].as_array().unwrap();
+    assert_eq!(categories.len(), 1);
+    assert_eq!(categories[0]["category"], "Food");
+}
+
+#[tokio::test]
+async fn suggestions_are_empty_without_history() {
+    let app = spawn_app().await;
+
+    let suggestions = suggestions(&app, "?merchantName=Bakery&amount=-450").await;
+
+    assert_eq!(
+        suggestions,
+        serde_json::json!({"categories": [], "wallets": [], "tags": []})
+    );
+}
+
+#[tokio::test]
+async fn a_merchant_name_is_required() {
+    let app = spawn_app().await;
+
+    for query in [
+        "",
+        "?merchantName=%20%20",
+        "?merchantName=Bakery&amount=ten",
+    ] {
+        let response = app.get_payment_suggestions(query).await;
+        assert_eq!(response.status().as_u16(), 400, "{}", query);
+    }
+}
@@ -0,0 +1,301 @@
+use crate::helpers::{spawn_app, spawn_app_with, TestApp};
+use chrono::NaiveDate;
+use expenses_monitor_be::routes::materialise_recurring_payments;
+
+fn date(s: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+}
+
+fn template(recurrence: &str, start_date: &str) -> serde_json::Value {
+    serde_json::json!({
+        "description": "Flat rent",
+        "categoryId": "Housing",
+        "amountInCents": -90000,
+        "merchantName": "Landlord",
+        "wallet": "Checking",
+        "tags": [{ "key": "kind", "value": "fixed" }],
+        "recurrence": recurrence,
+        "startDate": start_date
+    })
+}
+
+async fn create(app: &TestApp, body: serde_json::Value) -> serde_json::Value {
+    let response = app.post_recurring_payment(&body.to_string()).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.expect("Failed to parse response")
+}
+
+fn id(value: &serde_json::Value) -> String {
+    value["id"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn create_recurring_payment_returns_the_template() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+
+    let created = create(
+        &app,
+        template("RRULE:freq=monthly;interval=1;bymonthday=5", "2030-01-10"),
+    )
+    .await;
+
+    assert_eq!(created["recurrence"], "FREQ=MONTHLY;BYMONTHDAY=5");
+    assert_eq!(created["category"], "Housing");
+    assert_eq!(created["wallet"], "Checking");
+    assert_eq!(created["currency"], "EUR");
+    assert_eq!(created["status"], "active");
+    assert_eq!(created["tags"][0]["key"], "kind");
+    // The 5th is before the start date, so the series begins in February.
+    assert_eq!(created["nextOccurrence"], "2030-02-05");
+}
+
+#[tokio::test]
+async fn create_recurring_payment_rejects_invalid_templates() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+
+    let invalid_recurrence = template("FREQ=HOURLY", "2030-01-01");
+    let mut unknown_wallet = template("FREQ=MONTHLY", "2030-01-01");
+    unknown_wallet["wallet"] = "Nope".into();
+    let mut invalid_status = template("FREQ=MONTHLY", "2030-01-01");
+    invalid_status["status"] = "cancelled".into();
+    let mut empty_tag = template("FREQ=MONTHLY", "2030-01-01");
+    empty_tag["tags"] = serde_json::json!([{ "key": "kind", "value": " " }]);
+
+    for (body, case) in [
+        (invalid_recurrence, "invalid recurrence"),
+        (unknown_wallet, "unknown wallet"),
+        (invalid_status, "invalid status"),
+        (empty_tag, "empty tag value"),
+    ] {
+        let response = app.post_recurring_payment(&body.to_string()).await;
+        assert_eq!(response.status().as_u16(), 400, "{}", case);
+    }
+}
+
+#[tokio::test]
+async fn recurring_payments_can_be_listed_updated_and_deleted() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+    let created = create(&app, template("FREQ=MONTHLY", "2030-01-31")).await;
+    let template_id = id(&created);
+
+    let listed: Vec<serde_json::Value> = app.get_recurring_payments().await.json().await.unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0]["id"], created["id"]);
+
+    let mut update = template("FREQ=WEEKLY;INTERVAL=2", "2030-03-02");
+    update["amountInCents"] = (-95000).into();
+    update["status"] = "paused".into();
+    let response = app
+        .update_recurring_payment(&template_id, &update.to_string())
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let updated: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(updated["amountInCents"], -95000);
+    assert_eq!(updated["status"], "paused");
+    assert_eq!(updated["recurrence"], "FREQ=WEEKLY;INTERVAL=2");
+    assert_eq!(updated["nextOccurrence"], "2030-03-02");
+
+    let response = app.delete_recurring_payment(&template_id).await;
+    assert_eq!(response.status().as_u16(), 204);
+    let response = app.get_recurring_payment(&template_id).await;
+    assert_eq!(response.status().as_u16(), 404);
+    let response = app
+        .update_recurring_payment(&template_id, &update.to_string())
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn materialising_creates_one_payment_per_occurrence_once() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+    let created = create(&app, template("FREQ=MONTHLY", "2030-01-31")).await;
+
+    let first = materialise_recurring_payments(&app.db_pool, date("2030-03-31"))
+        .await
+        .unwrap()
+        .created;
+    let second = materialise_recurring_payments(&app.db_pool, date("2030-03-31"))
+        .await
+        .unwrap()
+        .created;
+
+    assert_eq!(first, 3);
+    assert_eq!(second, 0);
+    let payments = app.wallet_payments("Checking").await;
+    let mut dates: Vec<&str> = payments
+        .iter()
+        .map(|p| p["accountingDate"].as_str().unwrap())
+        .collect();
+    dates.sort();
+    assert_eq!(
+        dates,
+        vec![
+            "2030-01-31T00:00:00",
+            "2030-02-28T00:00:00",
+            "2030-03-31T00:00:00"
+        ]
+    );
+    assert!(payments.iter().all(|p| p["amountInCents"] == -90000
+        && p["merchantName"] == "Landlord"
+        && p["category"] == "Housing"
+        && p["tags"][0]["value"] == "fixed"));
+
+    let template: serde_json::Value = app
+        .get_recurring_payment(&id(&created))
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(template["nextOccurrence"], "2030-04-30");
+}
+
+#[tokio::test]
+async fn a_failing_template_does_not_stop_the_others() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+    let broken = create(&app, template("FREQ=MONTHLY", "2030-01-01")).await;
+    let mut body = template("FREQ=MONTHLY", "2030-01-01");
+    body["merchantName"] = "Gym".into();
+    create(&app, body).await;
+    // Tags that are not an array make the generated payment fail
+    sqlx::query!(
+        r#"UPDATE expenses.recurring_payments SET tags = '"corrupt"' WHERE id = $1"#,
+        id(&broken).parse::<uuid::Uuid>().unwrap()
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let summary = materialise_recurring_payments(&app.db_pool, date("2030-01-15"))
+        .await
+        .unwrap();
+
+    assert_eq!(summary.created, 1);
+    assert_eq!(summary.failed, 1);
+    let payments = app.wallet_payments("Checking").await;
+    assert_eq!(payments.len(), 1);
+    assert_eq!(payments[0]["merchantName"], "Gym");
+    let template: serde_json::Value = app
+        .get_recurring_payment(&id(&broken))
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(template["nextOccurrence"], "2030-01-01");
+}
+
+#[tokio::test]
+async fn skipped_occurrences_are_not_materialised() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+    let created = create(&app, template("FREQ=MONTHLY", "2030-01-01")).await;
+
+    let response = app.skip_recurring_payment(&id(&created)).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let skipped: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(skipped["nextOccurrence"], "2030-02-01");
+
+    let created_payments = materialise_recurring_payments(&app.db_pool, date("2030-02-15"))
+        .await
+        .unwrap()
+        .created;
+
+    assert_eq!(created_payments, 1);
+    let payments = app.wallet_payments("Checking").await;
+    assert_eq!(payments.len(), 1);
+    assert_eq!(payments[0]["accountingDate"], "2030-02-01T00:00:00");
+}
+
+#[tokio::test]
+async fn paused_recurring_payments_are_not_materialised() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+    let mut body = template("FREQ=DAILY", "2030-01-01");
+    body["status"] = "paused".into();
+    create(&app, body).await;
+
+    let created_payments = materialise_recurring_payments(&app.db_pool, date("2030-01-10"))
+        .await
+        .unwrap()
+        .created;
+
+    assert_eq!(created_payments, 0);
+    assert!(app.wallet_payments("Checking").await.is_empty());
+}
+
+#[tokio::test]
+async fn finished_series_have_no_next_occurrence() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+    let created = create(&app, template("FREQ=WEEKLY;COUNT=2", "2030-01-01")).await;
+
+    let created_payments = materialise_recurring_payments(&app.db_pool, date("2030-02-01"))
+        .await
+        .unwrap()
+        .created;
+
+    assert_eq!(created_payments, 2);
+    let template: serde_json::Value = app
+        .get_recurring_payment(&id(&created))
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(template["nextOccurrence"], serde_json::Value::Null);
+    let response = app.skip_recurring_payment(&id(&created)).await;
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+#[tokio::test]
+async fn deleting_a_template_keeps_its_payments() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+    let created = create(&app, template("FREQ=MONTHLY", "2030-01-01")).await;
+    materialise_recurring_payments(&app.db_pool, date("2030-01-01"))
+        .await
+        .unwrap();
+
+    let response = app.delete_recurring_payment(&id(&created)).await;
+
+    assert_eq!(response.status().as_u16(), 204);
+    assert_eq!(app.wallet_payments("Checking").await.len(), 1);
+}
+
+#[tokio::test]
+async fn skipping_an_unknown_recurring_payment_returns_404() {
+    let app = spawn_app().await;
+
+    let response = app
+        .skip_recurring_payment(&uuid::Uuid::new_v4().to_string())
+        .await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn the_scheduler_materialises_due_payments() {
+    let app = spawn_app_with(|config| {
+        config.recurring_payments.scheduler_enabled = true;
+        config.recurring_payments.scheduler_interval_seconds = 1;
+    })
+    .await;
+    app.create_wallet_named("Checking").await;
+    let today = chrono::Utc::now().date_naive().to_string();
+    create(&app, template("FREQ=MONTHLY", &today)).await;
+
+    let mut payments = Vec::new();
+    for _ in 0..50 {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        payments = app.wallet_payments("Checking").await;
+        if !payments.is_empty() {
+            break;
+        }
+    }
+
+    assert_eq!(payments.len(), 1);
+    assert_eq!(payments[0]["accountingDate"], format!("{}T00:00:00", today));
+}
@@ -0,0 +1,170 @@
+use crate::helpers::{mint_token, spawn_app, TestApp};
+use uuid::Uuid;
+
+/// Creates a payment tagged `trip` with the given value.
+async fn pay_on_trip(
+    app: &TestApp,
+    token: &str,
+    trip: &str,
+    payment: serde_json::Value,
+) -> serde_json::Value {
+    let mut body = serde_json::json!({
+        "category": "groceries",
+        "accountingDate": "2026-03-01T10:00:00",
+        "tags": [{"key": "trip", "value": trip}]
+    });
+    body.as_object_mut()
+        .unwrap()
+        .extend(payment.as_object().unwrap().clone());
+    let response = app.post_payment_with_auth(&body.to_string(), token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.unwrap()
+}
+
+async fn report(app: &TestApp, query: &str) -> serde_json::Value {
+    let response = app.get_tag_report(query).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn tag_report_totals_each_value_of_the_key() {
+    let app = spawn_app().await;
+    let token_b = mint_token(&Uuid::new_v4().to_string());
+    let token = app.auth_token.clone();
+    pay_on_trip(
+        &app,
+        &token,
+        "japan-2026",
+        serde_json::json!({"merchantName": "Hotel", "amountInCents": -30000}),
+    )
+    .await;
+    pay_on_trip(
+        &app,
+        &token,
+        "japan-2026",
+        serde_json::json!({"merchantName": "Refund", "amountInCents": 5000}),
+    )
+    .await;
+    pay_on_trip(
+        &app,
+        &token,
+        "italy-2025",
+        serde_json::json!({"merchantName": "Museum", "amountInCents": -2000}),
+    )
+    .await;
+    // Outside of the period, untagged or of another user
+    pay_on_trip(
+        &app,
+        &token,
+        "japan-2026",
+        serde_json::json!({
+            "merchantName": "Airline",
+            "amountInCents": -90000,
+            "accountingDate": "2026-01-15T10:00:00"
+        }),
+    )
+    .await;
+    pay_on_trip(
+        &app,
+        &token_b,
+        "japan-2026",
+        serde_json::json!({"merchantName": "Hotel", "amountInCents": -40000}),
+    )
+    .await;
+    app.post_payment(
+        r#"{"category": "groceries", "amountInCents": -700, "merchantName": "Bakery",
+            "accountingDate": "2026-03-01T10:00:00"}"#,
+    )
+    .await;
+
+    let report = report(&app, "?key=trip&from=2026-02-01&to=2026-03-31").await;
+
+    assert_eq!(report["key"], "trip");
+    assert_eq!(
+        report["entries"],
+        serde_json::json!([
+            {
+                "key": "italy-2025",
+                "label": "italy-2025",
+                "currency": "EUR",
+                "totalInCents": -2000,
+                "incomeInCents": 0,
+                "expensesInCents": -2000,
+                "paymentCount": 1
+            },
+            {
+                "key": "japan-2026",
+                "label": "japan-2026",
+                "currency": "EUR",
+                "totalInCents": -25000,
+                "incomeInCents": 5000,
+                "expensesInCents": -30000,
+                "paymentCount": 2
+            }
+        ])
+    );
+}
+
+#[tokio::test]
+async fn tag_report_drills_down_into_the_categories_of_a_value() {
+    let app = spawn_app().await;
+    let token = app.auth_token.clone();
+    let receipt = pay_on_trip(
+        &app,
+        &token,
+        "japan-2026",
+        serde_json::json!({
+            "merchantName": "Konbini",
+            "amountInCents": -3000,
+            "splits": [
+                {"categoryId": "groceries", "amountInCents": -1000},
+                {"categoryId": "souvenirs", "amountInCents": -2000}
+            ]
+        }),
+    )
+    .await;
+    pay_on_trip(
+        &app,
+        &token,
+        "japan-2026",
+        serde_json::json!({"merchantName": "Sushi bar", "amountInCents": -4500}),
+    )
+    .await;
+    pay_on_trip(
+        &app,
+        &token,
+        "italy-2025",
+        serde_json::json!({"merchantName": "Gelato", "amountInCents": -500}),
+    )
+    .await;
+
+    let report = report(&app, "?key=trip&value=japan-2026").await;
+
+    assert_eq!(report["value"], "japan-2026");
+    let entries = report["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["label"], "groceries");
+    assert_eq!(entries[0]["key"], receipt["splits"][0]["categoryId"]);
+    assert_eq!(entries[0]["expensesInCents"], -5500);
+    assert_eq!(entries[0]["paymentCount"], 2);
+    assert_eq!(entries[1]["label"], "souvenirs");
+    assert_eq!(entries[1]["expensesInCents"], -2000);
+    assert_eq!(entries[1]["paymentCount"], 1);
+}
+
+#[tokio::test]
+async fn tag_report_rejects_invalid_queries() {
+    let app = spawn_app().await;
+    let cases = [
+        ("", "missing key"),
+        ("?key=%20", "empty key"),
+        ("?key=trip&value=%20", "empty value"),
+        ("?key=trip&from=2026-03-01&to=2026-02-01", "inverted period"),
+    ];
+
+    for (query, case) in cases {
+        let response = app.get_tag_report(query).await;
+        assert_eq!(response.status().as_u16(), 400, "{}", case);
+    }
+}
@@ -0,0 +1,476 @@
+use crate::helpers::{spawn_app, TestApp};
+use chrono::NaiveDate;
+use reqwest::multipart::{Form, Part};
+use uuid::Uuid;
+
+fn netflix_rule() -> serde_json::Value {
+    serde_json::json!({
+        "name": "Netflix",
+        "conditions": [
+            {"type": "merchant", "pattern": "netflix"},
+            {"type": "amount", "maxInCents": -1}
+        ],
+        "actions": [
+            {"type": "setCategory", "categoryId": "Subscriptions"},
+            {"type": "setWallet", "wallet": "Card"},
+            {"type": "addTag", "key": "provider", "value": "netflix"}
+        ]
+    })
+}
+
+async fn create_rule(app: &TestApp, rule: serde_json::Value) -> serde_json::Value {
+    let response = app.post_rule(&rule.to_string()).await;
+    assert_eq!(response.status().as_u16(), 201);
+    response.json().await.unwrap()
+}
+
+async fn create_payment(app: &TestApp, merchant_name: &str, amount: i64) -> serde_json::Value {
+    let body = serde_json::json!({
+        "merchantName": merchant_name,
+        "amountInCents": amount,
+        "category": "Shopping",
+        "accountingDate": "2026-03-01T10:00:00"
+    });
+    let response = app
+        .post_payment_with_query(&body.to_string(), "?force=true")
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.unwrap()
+}
+
+fn tags(payment: &serde_json::Value) -> Vec<(String, String)> {
+    let mut tags: Vec<(String, String)> = payment["tags"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tag| {
+            (
+                tag["key"].as_str().unwrap().to_string(),
+                tag["value"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+    tags.sort();
+    tags
+}
+
+#[tokio::test]
+async fn rules_are_created_listed_updated_and_deleted() {
+    let app = spawn_app().await;
+    app.create_categories(&["Subscriptions"]).await;
+    app.create_wallet_named("Card").await;
+
+    let netflix = create_rule(&app, netflix_rule()).await;
+    let mut second = netflix_rule();
+    second["name"] = "Spotify".into();
+    second["conditions"][0]["pattern"] = "spotify".into();
+    let spotify = create_rule(&app, second.clone()).await;
+
+    assert_eq!(netflix["position"], 0);
+    assert_eq!(netflix["enabled"], true);
+    assert_eq!(spotify["position"], 1);
+    // Categories given by name are stored by id
+    assert!(netflix["actions"][0]["categoryId"]
+        .as_str()
+        .unwrap()
+        .parse::<Uuid>()
+        .is_ok());
+    assert_eq!(
+        netflix["conditions"],
+        serde_json::json!([
+            {"type": "merchant", "pattern": "netflix"},
+            {"type": "amount", "maxInCents": -1}
+        ])
+    );
+
+    let spotify_id = spotify["id"].as_str().unwrap();
+    second["position"] = (-1).into();
+    second["enabled"] = false.into();
+    let response = app.update_rule(spotify_id, &second.to_string()).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let updated: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(updated["enabled"], false);
+
+    let rules: Vec<serde_json::Value> = app.get_rules().await.json().await.unwrap();
+    let names: Vec<&str> = rules.iter().map(|r| r["name"].as_str().unwrap()).collect();
+    assert_eq!(names, vec!["Spotify", "Netflix"]);
+
+    assert_eq!(app.delete_rule(spotify_id).await.status().as_u16(), 204);
+    assert_eq!(app.get_rule(spotify_id).await.status().as_u16(), 404);
+    assert_eq!(app.delete_rule(spotify_id).await.status().as_u16(), 404);
+    let response = app.get_rule(netflix["id"].as_str().unwrap()).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn invalid_rules_are_rejected() {
+    let app = spawn_app().await;
+    app.create_categories(&["Subscriptions"]).await;
+    app.create_wallet_named("Card").await;
+
+    let mut invalid_pattern = netflix_rule();
+    invalid_pattern["conditions"][0]["pattern"] = "(netflix".into();
+    let mut unknown_wallet = netflix_rule();
+    unknown_wallet["actions"][1]["wallet"] = "Savings".into();
+    let mut unknown_category = netflix_rule();
+    unknown_category["actions"][0]["categoryId"] = Uuid::new_v4().to_string().into();
+    let mut unknown_category_name = netflix_rule();
+    unknown_category_name["actions"][0]["categoryId"] = "Subscriptons".into();
+    let mut no_condition = netflix_rule();
+    no_condition["conditions"] = serde_json::json!([]);
+    let mut empty_range = netflix_rule();
+    empty_range["conditions"][1] =
+        serde_json::json!({"type": "amount", "minInCents": 10, "maxInCents": -10});
+    let mut blank_name = netflix_rule();
+    blank_name["name"] = " ".into();
+
+    for (rule, description) in [
+        (invalid_pattern, "an invalid pattern"),
+        (unknown_wallet, "an unknown wallet"),
+        (unknown_category, "an unknown category"),
+        (unknown_category_name, "an unknown category name"),
+        (no_condition, "no condition"),
+        (empty_range, "an empty amount range"),
+        (blank_name, "a blank name"),
+    ] {
+        let response = app.post_rule(&rule.to_string()).await;
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not reject a rule with {}",
+            description
+        );
+    }
+
+    let unknown_type = serde_json::json!({
+        "name": "Unknown",
+        "conditions": [{"type": "weekday", "day": "monday"}],
+        "actions": [{"type": "addTag", "key": "a", "value": "b"}]
+    });
+    let response = app.post_rule(&unknown_type.to_string()).await;
+    assert!(response.status().is_client_error());
+    // Rules do not create the categories they name
+    let categories: Vec<serde_json::Value> = app.get_categories().await.json().await.unwrap();
+    assert!(categories.iter().all(|c| c["name"] != "Subscriptons"));
+}
+
+#[tokio::test]
+async fn rules_apply_to_new_payments() {
+    let app = spawn_app().await;
+    app.create_categories(&["Subscriptions"]).await;
+    app.create_wallet_named("Card").await;
+    create_rule(&app, netflix_rule()).await;
+
+    let netflix = create_payment(&app, "NETFLIX.COM", -1299).await;
+    let refund = create_payment(&app, "Netflix", 1299).await;
+    let bakery = create_payment(&app, "Bakery", -450).await;
+
+    assert_eq!(netflix["category"], "Subscriptions");
+    assert_eq!(netflix["wallet"], "Card");
+    assert_eq!(
+        tags(&netflix),
+        vec![("provider".to_string(), "netflix".to_string())]
+    );
+    for payment in [refund, bakery] {
+        assert_eq!(payment["category"], "Shopping");
+        assert!(payment.get("wallet").is_none());
+        assert!(tags(&payment).is_empty());
+    }
+}
+
+#[tokio::test]
+async fn earlier_rules_take_precedence_and_disabled_rules_do_not_apply() {
+    let app = spawn_app().await;
+    app.create_categories(&["Subscriptions", "Entertainment"])
+        .await;
+    app.create_wallet_named("Card").await;
+    create_rule(&app, netflix_rule()).await;
+    create_rule(
+        &app,
+        serde_json::json!({
+            "name": "Streaming",
+            "conditions": [{"type": "merchant", "pattern": "netflix|spotify"}],
+            "actions": [
+                {"type": "setCategory", "categoryId": "Entertainment"},
+                {"type": "addTag", "key": "provider", "value": "streaming"},
+                {"type": "setDescription", "description": "Streaming"}
+            ]
+        }),
+    )
+    .await;
+    create_rule(
+        &app,
+        serde_json::json!({
+            "name": "Disabled",
+            "enabled": false,
+            "conditions": [{"type": "merchant", "pattern": "."}],
+            "actions": [{"type": "addTag", "key": "disabled", "value": "yes"}]
+        }),
+    )
+    .await;
+
+    let netflix = create_payment(&app, "Netflix", -1299).await;
+    let spotify = create_payment(&app, "Spotify", -999).await;
+
+    assert_eq!(netflix["category"], "Subscriptions");
+    assert_eq!(netflix["description"], "Streaming");
+    assert_eq!(
+        tags(&netflix),
+        vec![("provider".to_string(), "netflix".to_string())]
+    );
+    assert_eq!(spotify["category"], "Entertainment");
+    assert_eq!(
+        tags(&spotify),
+        vec![("provider".to_string(), "streaming".to_string())]
+    );
+}
+
+#[tokio::test]
+async fn rules_apply_to_csv_imports_in_the_wallet_of_the_import() {
+    let app = spawn_app().await;
+    app.create_categories(&["Subscriptions"]).await;
+    app.create_wallet_named("Card").await;
+    app.create_wallet_named("Checking").await;
+    create_rule(&app, netflix_rule()).await;
+    let statement = "Date,Amount,Payee\n\
+                     2026-03-05,-12.99,NETFLIX.COM\n\
+                     2026-03-06,-4.50,Bakery\n";
+    let file = Part::text(statement.to_string())
+        .file_name("statement.csv")
+        .mime_str("text/csv")
+        .unwrap();
+    let form = Form::new()
+        .part("file", file)
+        .text(
+            "mapping",
+            r#"{"date": "Date", "amount": "Amount", "merchant": "Payee"}"#,
+        )
+        .text("wallet", "Checking")
+        .text("category", "Imported");
+
+    let response = app.post_csv_import(form, "?commit=true").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let result: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(result["acceptedRows"], 2);
+    assert_eq!(
+        result["rows"][0]["tags"],
+        serde_json::json!([{"key": "provider", "value": "netflix"}])
+    );
+    let page: serde_json::Value = app
+        .get_payments("?wallet=Checking")
+        .await
+        .json()
+        .await
+        .unwrap();
+    let payments = page["content"].as_array().unwrap();
+    let netflix = payments
+        .iter()
+        .find(|p| p["merchantName"] == "NETFLIX.COM")
+        .unwrap();
+    assert_eq!(netflix["category"], "Subscriptions");
+    assert_eq!(
+        tags(netflix),
+        vec![("provider".to_string(), "netflix".to_string())]
+    );
+    let bakery = payments
+        .iter()
+        .find(|p| p["merchantName"] == "Bakery")
+        .unwrap();
+    assert_eq!(bakery["category"], "Imported");
+}
+
+#[tokio::test]
+async fn rules_categorise_staging_transactions_on_import() {
+    let app = spawn_app().await;
+    app.create_categories(&["Food"]).await;
+    app.create_wallet_named("Checking").await;
+    create_rule(
+        &app,
+        serde_json::json!({
+            "name": "Bakery",
+            "conditions": [
+                {"type": "merchant", "pattern": "^bakery$"},
+                {"type": "wallet", "wallet": "Checking"}
+            ],
+            "actions": [
+                {"type": "setCategory", "categoryId": "Food"},
+                {"type": "addTag", "key": "shop", "value": "bakery"}
+            ]
+        }),
+    )
+    .await;
+    // Reviewed without a category: only the rule can categorise it
+    sqlx::query!(
+        r#"
+        INSERT INTO expenses.staging_transactions
+            (user_id, bank_transaction_id, amount_in_cents, booking_date, creditor_name, status)
+        VALUES ($1, 'tx-1', -450, $2, 'Bakery', 'reviewed')
+        "#,
+        app.auth_sub,
+        NaiveDate::from_ymd_opt(2026, 3, 1).unwrap()
+    )
+    .execute(&app.db_pool)
+    .await
+    .expect("Failed to stage a transaction");
+
+    let response = app.post_staging_import(r#"{"wallet": "Checking"}"#).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let result: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(result["failed"], serde_json::json!([]));
+    let payment_id: Uuid = result["imported"][0]["paymentId"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let payment: serde_json::Value = app.get_payment(payment_id).await.json().await.unwrap();
+    assert_eq!(payment["category"], "Food");
+    assert_eq!(
+        tags(&payment),
+        vec![("shop".to_string(), "bakery".to_string())]
+    );
+}
+
+#[tokio::test]
+async fn rules_do_not_override_the_review_of_staging_transactions() {
+    let app = spawn_app().await;
+    app.create_categories(&["Food"]).await;
+    app.create_wallet_named("Checking").await;
+    create_rule(
+        &app,
+        serde_json::json!({
+            "name": "Bakery",
+            "conditions": [{"type": "merchant", "pattern": "^bakery$"}],
+            "actions": [
+                {"type": "setCategory", "categoryId": "Food"},
+                {"type": "setDescription", "description": "Bread"}
+            ]
+        }),
+    )
+    .await;
+    sqlx::query!(
+        r#"
+        INSERT INTO expenses.staging_transactions
+            (user_id, bank_transaction_id, amount_in_cents, booking_date, creditor_name,
+             remittance_info, suggested_category, status)
+        VALUES ($1, 'tx-1', -450, $2, 'Bakery', 'Birthday cake', 'Gifts', 'reviewed'),
+               ($1, 'tx-2', -250, $2, 'Bakery', NULL, 'Treats', 'reviewed')
+        "#,
+        app.auth_sub,
+        NaiveDate::from_ymd_opt(2026, 3, 1).unwrap()
+    )
+    .execute(&app.db_pool)
+    .await
+    .expect("Failed to stage transactions");
+
+    let response = app.post_staging_import(r#"{"wallet": "Checking"}"#).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let result: serde_json::Value = response.json().await.unwrap();
+    let mut payments = Vec::new();
+    for imported in result["imported"].as_array().unwrap() {
+        let payment_id: Uuid = imported["paymentId"].as_str().unwrap().parse().unwrap();
+        let payment: serde_json::Value = app.get_payment(payment_id).await.json().await.unwrap();
+        payments.push(payment);
+    }
+    payments.sort_by_key(|p| p["amountInCents"].as_i64().unwrap());
+    assert_eq!(payments.len(), 2);
+    assert_eq!(payments[0]["category"], "Gifts");
+    assert_eq!(payments[0]["description"], "Birthday cake");
+    // Only the fields left empty are filled in by the rule
+    assert_eq!(payments[1]["category"], "Treats");
+    assert_eq!(payments[1]["description"], "Bread");
+}
+
+#[tokio::test]
+async fn applying_a_rule_previews_then_commits_the_changes() {
+    let app = spawn_app().await;
+    app.create_categories(&["Subscriptions"]).await;
+    app.create_wallet_named("Card").await;
+    let netflix = create_payment(&app, "Netflix", -1299).await;
+    create_payment(&app, "Bakery", -450).await;
+    let rule = create_rule(&app, netflix_rule()).await;
+    let rule_id = rule["id"].as_str().unwrap();
+
+    let response = app.apply_rule(rule_id, "").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let preview: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(preview["committed"], false);
+    let changes = preview["payments"].as_array().unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0]["paymentId"], netflix["id"]);
+    assert_eq!(changes[0]["wallet"], "Card");
+    assert_eq!(changes[0]["categoryId"], rule["actions"][0]["categoryId"]);
+    // Payments without a wallet are only listed
+    let page: serde_json::Value = app.get_payments("").await.json().await.unwrap();
+    let unchanged = page["content"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|p| p["id"] == netflix["id"])
+        .unwrap()
+        .clone();
+    assert_eq!(unchanged["category"], "Shopping");
+    let payment_id: Uuid = netflix["id"].as_str().unwrap().parse().unwrap();
+
+    let response = app.apply_rule(rule_id, "?commit=true").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let applied: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(applied["committed"], true);
+    assert_eq!(applied["payments"].as_array().unwrap().len(), 1);
+    let changed: serde_json::Value = app.get_payment(payment_id).await.json().await.unwrap();
+    assert_eq!(changed["category"], "Subscriptions");
+    assert_eq!(changed["wallet"], "Card");
+    assert_eq!(
+        tags(&changed),
+        vec![("provider".to_string(), "netflix".to_string())]
+    );
+
+    // Nothing left to change
+    let preview: serde_json::Value = app.apply_rule(rule_id, "").await.json().await.unwrap();
+    assert_eq!(preview["payments"], serde_json::json!([]));
+    let missing = Uuid::new_v4().to_string();
+    assert_eq!(app.apply_rule(&missing, "").await.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn rule_tags_are_stored_trimmed() {
+    let app = spawn_app().await;
+    app.create_categories(&["Subscriptions"]).await;
+    app.create_wallet_named("Card").await;
+    let netflix = create_payment(&app, "Netflix", -1299).await;
+    let mut padded = netflix_rule();
+    padded["actions"][2] = serde_json::json!(
+        {"type": "addTag", "key": " provider ", "value": "netflix  "}
+    );
+    let rule = create_rule(&app, padded).await;
+    assert_eq!(rule["actions"][2]["key"], "provider");
+    assert_eq!(rule["actions"][2]["value"], "netflix");
+
+    let response = app
+        .apply_rule(rule["id"].as_str().unwrap(), "?commit=true")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let stored = sqlx::query!(
+        "SELECT key, value FROM expenses.payments_tags WHERE payment_id = $1",
+        Uuid::parse_str(netflix["id"].as_str().unwrap()).unwrap()
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(
+        (stored.key.as_str(), stored.value.as_str()),
+        ("provider", "netflix")
+    );
+    let page: serde_json::Value = app
+        .get_payments("?tag=provider:netflix")
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(page["content"][0]["id"], netflix["id"]);
+}
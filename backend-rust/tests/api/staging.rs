@@ -0,0 +1,319 @@
+use crate::helpers::{spawn_app, TestApp};
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+/// Stages a bank transaction for `user_id`, as a bank sync would.
+async fn stage(
+    app: &TestApp,
+    user_id: &str,
+    bank_transaction_id: &str,
+    amount_in_cents: i64,
+    booking_date: &str,
+    status: &str,
+) -> Uuid {
+    sqlx::query_scalar!(
+        r#"
+        INSERT INTO expenses.staging_transactions
+            (user_id, bank_transaction_id, amount_in_cents, booking_date, creditor_name,
+             debtor_name, remittance_info, status)
+        VALUES ($1, $2, $3, $4, 'Bakery', 'Employer', 'Card payment', $5)
+        RETURNING id
+        "#,
+        user_id,
+        bank_transaction_id,
+        amount_in_cents,
+        NaiveDate::parse_from_str(booking_date, "%Y-%m-%d").unwrap(),
+        status
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to stage a transaction")
+}
+
+async fn review(app: &TestApp, id: Uuid, body: serde_json::Value) -> reqwest::Response {
+    app.update_staging_transaction(&id.to_string(), &body.to_string())
+        .await
+}
+
+async fn list(app: &TestApp, query: &str) -> Vec<serde_json::Value> {
+    let response = app.get_staging_transactions(query).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let page: serde_json::Value = response.json().await.unwrap();
+    page["content"].as_array().unwrap().clone()
+}
+
+#[tokio::test]
+async fn staging_transactions_are_paginated_and_filtered_by_status() {
+    let app = spawn_app().await;
+    let sub = app.auth_sub.clone();
+    stage(&app, &sub, "tx-1", -1250, "2026-03-01", "pending").await;
+    stage(&app, &sub, "tx-2", -500, "2026-03-03", "pending").await;
+    stage(&app, &sub, "tx-3", 150000, "2026-03-02", "rejected").await;
+    stage(&app, "someone-else", "tx-4", -100, "2026-03-04", "pending").await;
+
+    let all = list(&app, "").await;
+    let ids: Vec<&str> = all
+        .iter()
+        .map(|t| t["bankTransactionId"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, vec!["tx-2", "tx-3", "tx-1"]);
+    assert_eq!(all[0]["amountInCents"], -500);
+    assert_eq!(all[0]["bookingDate"], "2026-03-03");
+    assert_eq!(all[0]["creditorName"], "Bakery");
+    assert_eq!(all[0]["status"], "pending");
+
+    let pending = list(&app, "?status=pending").await;
+    assert_eq!(pending.len(), 2);
+    let second_page = list(&app, "?status=pending&page=1&size=1").await;
+    assert_eq!(second_page.len(), 1);
+    assert_eq!(second_page[0]["bankTransactionId"], "tx-1");
+
+    for query in ["?status=approved", "?size=0", "?page=-1"] {
+        let response = app.get_staging_transactions(query).await;
+        assert_eq!(response.status().as_u16(), 400, "{}", query);
+    }
+}
+
+#[tokio::test]
+async fn reviewing_updates_the_suggestions_and_status() {
+    let app = spawn_app().await;
+    let id = stage(&app, &app.auth_sub, "tx-1", -1250, "2026-03-01", "pending").await;
+
+    let response = review(
+        &app,
+        id,
+        serde_json::json!({
+            "suggestedCategory": " Food ",
+            "suggestedMerchant": "Local bakery",
+            "status": "reviewed"
+        }),
+    )
+    .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let reviewed: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(reviewed["suggestedCategory"], "Food");
+    assert_eq!(reviewed["suggestedMerchant"], "Local bakery");
+    assert_eq!(reviewed["status"], "reviewed");
+    assert_eq!(list(&app, "?status=reviewed").await.len(), 1);
+}
+
+#[tokio::test]
+async fn invalid_reviews_are_rejected() {
+    let app = spawn_app().await;
+    let id = stage(&app, &app.auth_sub, "tx-1", -1250, "2026-03-01", "pending").await;
+    let imported = stage(&app, &app.auth_sub, "tx-2", -100, "2026-03-01", "imported").await;
+    let foreign = stage(&app, "someone-else", "tx-3", -100, "2026-03-01", "pending").await;
+
+    let cases = [
+        (id, serde_json::json!({"status": "approved"}), 400),
+        (id, serde_json::json!({"status": "imported"}), 400),
+        (
+            id,
+            serde_json::json!({"status": "reviewed", "suggestedMerchant": "Bad (merchant)"}),
+            400,
+        ),
+        (imported, serde_json::json!({"status": "pending"}), 409),
+        (foreign, serde_json::json!({"status": "rejected"}), 404),
+        (
+            Uuid::new_v4(),
+            serde_json::json!({"status": "rejected"}),
+            404,
+        ),
+    ];
+    for (id, body, status) in cases {
+        let response = review(&app, id, body.clone()).await;
+        assert_eq!(response.status().as_u16(), status, "{}", body);
+    }
+}
+
+#[tokio::test]
+async fn import_converts_reviewed_transactions_into_payments() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+    let expense = stage(&app, &app.auth_sub, "tx-1", -1250, "2026-03-01", "pending").await;
+    let income = stage(&app, &app.auth_sub, "tx-2", 150000, "2026-03-02", "pending").await;
+    let pending = stage(&app, &app.auth_sub, "tx-3", -100, "2026-03-03", "pending").await;
+    let uncategorised = stage(&app, &app.auth_sub, "tx-4", -100, "2026-03-04", "pending").await;
+    review(
+        &app,
+        expense,
+        serde_json::json!({"status": "reviewed", "suggestedCategory": "Food"}),
+    )
+    .await;
+    review(
+        &app,
+        income,
+        serde_json::json!({"status": "reviewed", "suggestedCategory": "Salary"}),
+    )
+    .await;
+    review(
+        &app,
+        uncategorised,
+        serde_json::json!({"status": "reviewed"}),
+    )
+    .await;
+
+    let response = app.post_staging_import(r#"{"wallet": "Checking"}"#).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let result: serde_json::Value = response.json().await.unwrap();
+    let imported = result["imported"].as_array().unwrap();
+    assert_eq!(imported.len(), 2);
+    assert_eq!(imported[0]["id"], expense.to_string());
+    assert_eq!(result["failed"][0]["id"], uncategorised.to_string());
+    assert_eq!(result["failed"][0]["error"], "No category");
+
+    let payment_id: Uuid = imported[0]["paymentId"].as_str().unwrap().parse().unwrap();
+    let payment: serde_json::Value = app.get_payment(payment_id).await.json().await.unwrap();
+    assert_eq!(payment["merchantName"], "Bakery");
+    assert_eq!(payment["amountInCents"], -1250);
+    assert_eq!(payment["description"], "Card payment");
+    assert_eq!(payment["category"], "Food");
+    assert_eq!(payment["wallet"], "Checking");
+    assert_eq!(payment["accountingDate"], "2026-03-01T00:00:00");
+
+    let staged = list(&app, "?status=imported").await;
+    assert_eq!(staged.len(), 2);
+    let linked = staged
+        .iter()
+        .find(|t| t["id"] == expense.to_string())
+        .unwrap();
+    assert_eq!(linked["importedPaymentId"], payment_id.to_string());
+    let salary = staged
+        .iter()
+        .find(|t| t["id"] == income.to_string())
+        .unwrap();
+    let salary: serde_json::Value = app
+        .get_payment(
+            salary["importedPaymentId"]
+                .as_str()
+                .unwrap()
+                .parse()
+                .unwrap(),
+        )
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(salary["merchantName"], "Employer");
+    assert_eq!(
+        list(&app, "?status=pending").await[0]["id"],
+        pending.to_string()
+    );
+
+    // Imported transactions are not imported twice
+    let response = app.post_staging_import(r#"{"wallet": "Checking"}"#).await;
+    let result: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(result["imported"], serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn import_can_be_limited_to_some_transactions() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+    let first = stage(&app, &app.auth_sub, "tx-1", -1250, "2026-03-01", "reviewed").await;
+    let second = stage(&app, &app.auth_sub, "tx-2", -500, "2026-03-02", "reviewed").await;
+    let pending = stage(&app, &app.auth_sub, "tx-3", -500, "2026-03-02", "pending").await;
+    for id in [first, second] {
+        sqlx::query!(
+            "UPDATE expenses.staging_transactions SET suggested_category = 'Food' WHERE id = $1",
+            id
+        )
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    }
+
+    let body = serde_json::json!({"wallet": "Checking", "ids": [second, pending]});
+    let response = app.post_staging_import(&body.to_string()).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let result: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(result["imported"].as_array().unwrap().len(), 1);
+    assert_eq!(result["imported"][0]["id"], second.to_string());
+    assert_eq!(result["failed"][0]["id"], pending.to_string());
+    assert_eq!(list(&app, "?status=reviewed").await.len(), 1);
+}
+
+#[tokio::test]
+async fn import_into_an_unknown_wallet_is_rejected() {
+    let app = spawn_app().await;
+
+    let response = app.post_staging_import(r#"{"wallet": "Nope"}"#).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn import_skips_transactions_already_recorded_unless_forced() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+    let body = serde_json::json!({
+        "merchantName": "Bakery",
+        "amountInCents": -1250,
+        "category": "Food",
+        "accountingDate": "2026-03-01T12:00:00",
+        "wallet": "Checking"
+    });
+    let response = app.post_payment(&body.to_string()).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let id = stage(&app, &app.auth_sub, "tx-1", -1250, "2026-03-01", "pending").await;
+    review(
+        &app,
+        id,
+        serde_json::json!({"status": "reviewed", "suggestedCategory": "Food"}),
+    )
+    .await;
+
+    let response = app.post_staging_import(r#"{"wallet": "Checking"}"#).await;
+
+    let result: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(result["imported"], serde_json::json!([]));
+    assert!(result["failed"][0]["error"]
+        .as_str()
+        .unwrap()
+        .starts_with("Possible duplicate of payment"));
+    assert_eq!(list(&app, "?status=reviewed").await.len(), 1);
+
+    let response = app
+        .post_staging_import(r#"{"wallet": "Checking", "force": true}"#)
+        .await;
+
+    let result: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(result["imported"][0]["id"], id.to_string());
+}
+
+#[tokio::test]
+async fn a_failed_import_does_not_create_its_categories() {
+    let app = spawn_app().await;
+    app.create_wallet_named("Checking").await;
+    let id = stage(&app, &app.auth_sub, "tx-1", -1250, "2026-03-01", "pending").await;
+    review(
+        &app,
+        id,
+        serde_json::json!({"status": "reviewed", "suggestedCategory": "Pastries"}),
+    )
+    .await;
+    // Makes the import fail once its categories are resolved
+    for statement in [
+        r#"CREATE FUNCTION expenses.reject_payments() RETURNS trigger
+           LANGUAGE plpgsql AS $$ BEGIN RAISE EXCEPTION 'rejected'; END $$"#,
+        r#"CREATE TRIGGER reject_payments BEFORE INSERT ON expenses.payments
+           FOR EACH ROW EXECUTE FUNCTION expenses.reject_payments()"#,
+    ] {
+        sqlx::query(statement).execute(&app.db_pool).await.unwrap();
+    }
+
+    let response = app.post_staging_import(r#"{"wallet": "Checking"}"#).await;
+
+    assert_eq!(response.status().as_u16(), 500);
+    let created = sqlx::query_scalar!(
+        "SELECT count(*) FROM expenses.categories WHERE lower(name) = 'pastries'"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(created, Some(0));
+    assert_eq!(list(&app, "?status=reviewed").await.len(), 1);
+}
@@ -0,0 +1,246 @@
+use crate::helpers::{mint_token, spawn_app, TestApp};
+use uuid::Uuid;
+
+/// Creates a payment carrying `tags`, given as `(key, value)` pairs.
+async fn pay_with_tags(
+    app: &TestApp,
+    token: &str,
+    merchant: &str,
+    tags: &[(&str, &str)],
+) -> serde_json::Value {
+    let tags: Vec<_> = tags
+        .iter()
+        .map(|(key, value)| serde_json::json!({"key": key, "value": value}))
+        .collect();
+    let body = serde_json::json!({
+        "category": "groceries",
+        "amountInCents": -1000,
+        "merchantName": merchant,
+        "accountingDate": "2026-03-01T10:00:00",
+        "tags": tags
+    });
+    let response = app.post_payment_with_auth(&body.to_string(), token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.unwrap()
+}
+
+async fn tags(app: &TestApp, token: &str) -> Vec<serde_json::Value> {
+    let response = app.get_tags_with_auth(token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.unwrap()
+}
+
+/// Merchants of the payments listed for `query`, alphabetically.
+async fn merchants_for(app: &TestApp, query: &str) -> Vec<String> {
+    let response = app.get_payments(query).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    let mut merchants: Vec<String> = body["content"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["merchantName"].as_str().unwrap().to_string())
+        .collect();
+    merchants.sort();
+    merchants
+}
+
+#[tokio::test]
+async fn invalid_tags_are_rejected() {
+    let app = spawn_app().await;
+    let cases = [
+        (
+            serde_json::json!({"key": "trip", "value": " "}),
+            "empty value",
+        ),
+        (serde_json::json!({"key": "", "value": "rome"}), "empty key"),
+        (
+            serde_json::json!({"key": "a:b", "value": "rome"}),
+            "separator",
+        ),
+        (
+            serde_json::json!({"key": "trip", "value": "a".repeat(65)}),
+            "too long",
+        ),
+    ];
+
+    for (tag, case) in cases {
+        let body = serde_json::json!({
+            "category": "groceries",
+            "amountInCents": -1000,
+            "merchantName": "Bakery",
+            "accountingDate": "2026-03-01T10:00:00",
+            "tags": [tag]
+        });
+        let response = app.post_payment(&body.to_string()).await;
+        assert_eq!(response.status().as_u16(), 400, "{}", case);
+    }
+}
+
+#[tokio::test]
+async fn tags_are_trimmed_and_not_repeated() {
+    let app = spawn_app().await;
+
+    let payment = pay_with_tags(
+        &app,
+        &app.auth_token,
+        "Bakery",
+        &[(" trip ", "rome"), ("trip", "rome ")],
+    )
+    .await;
+
+    let tags = payment["tags"].as_array().unwrap();
+    assert_eq!(tags.len(), 1);
+    assert_eq!(tags[0]["key"], "trip");
+    assert_eq!(tags[0]["value"], "rome");
+}
+
+#[tokio::test]
+async fn tags_are_listed_with_their_usage_per_user() {
+    let app = spawn_app().await;
+    let token_b = mint_token(&Uuid::new_v4().to_string());
+    pay_with_tags(&app, &app.auth_token, "Hotel", &[("trip", "rome")]).await;
+    pay_with_tags(
+        &app,
+        &app.auth_token,
+        "Museum",
+        &[("trip", "rome"), ("project", "home")],
+    )
+    .await;
+    pay_with_tags(&app, &app.auth_token, "Airline", &[("trip", "paris")]).await;
+    pay_with_tags(&app, &token_b, "Hotel", &[("trip", "lisbon")]).await;
+
+    let tags = tags(&app, &app.auth_token).await;
+
+    assert_eq!(
+        tags,
+        vec![
+            serde_json::json!({
+                "key": "project",
+                "paymentCount": 1,
+                "values": [{"value": "home", "paymentCount": 1}]
+            }),
+            serde_json::json!({
+                "key": "trip",
+                "paymentCount": 3,
+                "values": [
+                    {"value": "paris", "paymentCount": 1},
+                    {"value": "rome", "paymentCount": 2}
+                ]
+            }),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn renaming_a_key_renames_it_on_every_payment() {
+    let app = spawn_app().await;
+    let token_b = mint_token(&Uuid::new_v4().to_string());
+    pay_with_tags(&app, &app.auth_token, "Hotel", &[("trip", "rome")]).await;
+    pay_with_tags(&app, &app.auth_token, "Airline", &[("trip", "paris")]).await;
+    pay_with_tags(&app, &token_b, "Hotel", &[("trip", "lisbon")]).await;
+
+    let response = app
+        .rename_tags(r#"{"key": "trip", "newKey": "holiday"}"#)
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["paymentCount"], 2);
+    let own = tags(&app, &app.auth_token).await;
+    assert_eq!(own.len(), 1);
+    assert_eq!(own[0]["key"], "holiday");
+    assert_eq!(own[0]["values"].as_array().unwrap().len(), 2);
+    // Other users keep their tags
+    assert_eq!(tags(&app, &token_b).await[0]["key"], "trip");
+}
+
+#[tokio::test]
+async fn renaming_a_value_onto_an_existing_one_does_not_repeat_it() {
+    let app = spawn_app().await;
+    pay_with_tags(
+        &app,
+        &app.auth_token,
+        "Hotel",
+        &[("trip", "Rome"), ("trip", "rome")],
+    )
+    .await;
+    pay_with_tags(&app, &app.auth_token, "Museum", &[("trip", "Rome")]).await;
+
+    let response = app
+        .rename_tags(r#"{"key": "trip", "value": "Rome", "newValue": "rome"}"#)
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let tags = tags(&app, &app.auth_token).await;
+    assert_eq!(
+        tags[0]["values"],
+        serde_json::json!([{"value": "rome", "paymentCount": 2}])
+    );
+    let hotel = app.get_payments("?search=hotel").await;
+    let hotel: serde_json::Value = hotel.json().await.unwrap();
+    assert_eq!(hotel["content"][0]["tags"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn invalid_or_unknown_renames_are_rejected() {
+    let app = spawn_app().await;
+    pay_with_tags(&app, &app.auth_token, "Hotel", &[("trip", "rome")]).await;
+    let cases = [
+        (r#"{"key": "trip"}"#, 400, "nothing to rename to"),
+        (r#"{"key": "trip", "newKey": "trip"}"#, 400, "same name"),
+        (r#"{"key": "trip", "newValue": "paris"}"#, 400, "no value"),
+        (r#"{"key": "trip", "newKey": "a:b"}"#, 400, "invalid key"),
+        (r#"{"key": "visit", "newKey": "trip"}"#, 404, "unknown key"),
+        (
+            r#"{"key": "trip", "value": "paris", "newValue": "rome"}"#,
+            404,
+            "unknown value",
+        ),
+    ];
+
+    for (body, status, case) in cases {
+        let response = app.rename_tags(body).await;
+        assert_eq!(response.status().as_u16(), status, "{}", case);
+    }
+}
+
+#[tokio::test]
+async fn payments_are_filtered_by_all_or_any_of_the_tags() {
+    let app = spawn_app().await;
+    pay_with_tags(
+        &app,
+        &app.auth_token,
+        "Hotel",
+        &[("trip", "rome"), ("paid", "card")],
+    )
+    .await;
+    pay_with_tags(&app, &app.auth_token, "Museum", &[("trip", "rome")]).await;
+    pay_with_tags(&app, &app.auth_token, "Airline", &[("paid", "card")]).await;
+    pay_with_tags(&app, &app.auth_token, "Meeting", &[("time", "10:30")]).await;
+
+    assert_eq!(
+        merchants_for(&app, "?tag=trip:rome").await,
+        vec!["Hotel", "Museum"]
+    );
+    assert_eq!(
+        merchants_for(&app, "?tag=trip:rome&tag=paid:card").await,
+        vec!["Hotel"]
+    );
+    assert_eq!(
+        merchants_for(&app, "?tag=trip:rome&tag=paid:card&tagMode=any").await,
+        vec!["Airline", "Hotel", "Museum"]
+    );
+    // Repeated filters count once, and values may contain the separator
+    assert_eq!(
+        merchants_for(&app, "?tag=trip:rome&tag=trip:rome").await,
+        vec!["Hotel", "Museum"]
+    );
+    assert_eq!(
+        merchants_for(&app, "?tag=time:10%3A30").await,
+        vec!["Meeting"]
+    );
+
+    let response = app.get_payments("?tag=trip").await;
+    assert_eq!(response.status().as_u16(), 400);
+}
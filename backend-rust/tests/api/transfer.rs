@@ -0,0 +1,217 @@
+use crate::helpers::{mint_token, spawn_app, TestApp};
+use uuid::Uuid;
+
+async fn create_wallets(app: &TestApp) {
+    for name in ["Checking", "Savings"] {
+        let response = app
+            .create_wallet(&serde_json::json!({ "name": name }).to_string())
+            .await;
+        assert!(response.status().is_success());
+    }
+}
+
+async fn transfer(app: &TestApp, amount: i64) -> serde_json::Value {
+    let body = serde_json::json!({
+        "fromWallet": "Checking",
+        "toWallet": "Savings",
+        "amountInCents": amount,
+        "accountingDate": "2026-02-01T09:00:00",
+        "description": "Monthly savings"
+    });
+    let response = app.post_transfer(&body.to_string()).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.expect("Failed to parse response")
+}
+
+fn payment_id(value: &serde_json::Value) -> Uuid {
+    value.as_str().unwrap().parse().unwrap()
+}
+
+#[tokio::test]
+async fn transfer_creates_a_debit_and_a_credit_payment() {
+    let app = spawn_app().await;
+    create_wallets(&app).await;
+
+    let transfer = transfer(&app, 30000).await;
+
+    assert_eq!(transfer["fromWallet"], "Checking");
+    assert_eq!(transfer["toWallet"], "Savings");
+    assert_eq!(transfer["amountInCents"], 30000);
+    assert_eq!(transfer["currency"], "EUR");
+
+    let debit = app.wallet_payments("Checking").await;
+    assert_eq!(debit.len(), 1);
+    assert_eq!(debit[0]["id"], transfer["debitPaymentId"]);
+    assert_eq!(debit[0]["amountInCents"], -30000);
+    assert_eq!(debit[0]["merchantName"], "Transfer to Savings");
+    assert_eq!(debit[0]["transferId"], transfer["id"]);
+
+    let credit = app.wallet_payments("Savings").await;
+    assert_eq!(credit.len(), 1);
+    assert_eq!(credit[0]["id"], transfer["creditPaymentId"]);
+    assert_eq!(credit[0]["amountInCents"], 30000);
+    assert_eq!(credit[0]["merchantName"], "Transfer from Checking");
+    assert_eq!(credit[0]["transferId"], transfer["id"]);
+}
+
+#[tokio::test]
+async fn transfers_are_neither_income_nor_expenses() {
+    let app = spawn_app().await;
+    create_wallets(&app).await;
+    let salary = serde_json::json!({
+        "merchantName": "Employer",
+        "amountInCents": 100000,
+        "category": "salary",
+        "accountingDate": "2026-01-31T09:00:00",
+        "wallet": "Checking"
+    });
+    app.post_payment(&salary.to_string()).await;
+    transfer(&app, 30000).await;
+
+    let balance: serde_json::Value = app
+        .get_balance_with_query("?groupBy=wallet")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(balance["incomeInCents"], 100000);
+    assert_eq!(balance["expensesInCents"], 0);
+    assert_eq!(balance["transfersInCents"], 0);
+    assert_eq!(balance["totalInCents"], 100000);
+    let breakdown = balance["breakdown"].as_array().unwrap();
+    assert_eq!(breakdown.len(), 2);
+    assert_eq!(breakdown[0]["label"], "Checking");
+    assert_eq!(breakdown[0]["incomeInCents"], 100000);
+    assert_eq!(breakdown[0]["transfersInCents"], -30000);
+    assert_eq!(breakdown[0]["totalInCents"], 70000);
+    assert_eq!(breakdown[1]["label"], "Savings");
+    assert_eq!(breakdown[1]["incomeInCents"], 0);
+    assert_eq!(breakdown[1]["transfersInCents"], 30000);
+    assert_eq!(breakdown[1]["totalInCents"], 30000);
+}
+
+#[tokio::test]
+async fn wallet_balance_includes_transfers() {
+    let app = spawn_app().await;
+    create_wallets(&app).await;
+    transfer(&app, 30000).await;
+
+    let balance: serde_json::Value = app
+        .get_balance_with_query("?wallet=Savings")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(balance["incomeInCents"], 0);
+    assert_eq!(balance["transfersInCents"], 30000);
+    assert_eq!(balance["totalInCents"], 30000);
+}
+
+#[tokio::test]
+async fn create_transfer_returns_a_400_when_data_is_invalid() {
+    let app = spawn_app().await;
+    create_wallets(&app).await;
+    let test_cases = vec![
+        (
+            serde_json::json!({"fromWallet": "Checking", "toWallet": "Checking", "amountInCents": 100, "accountingDate": "2026-02-01T09:00:00"}),
+            "the same wallet on both sides",
+        ),
+        (
+            serde_json::json!({"fromWallet": "Checking", "toWallet": "Savings", "amountInCents": 0, "accountingDate": "2026-02-01T09:00:00"}),
+            "a zero amount",
+        ),
+        (
+            serde_json::json!({"fromWallet": "Checking", "toWallet": "Savings", "amountInCents": -100, "accountingDate": "2026-02-01T09:00:00"}),
+            "a negative amount",
+        ),
+        (
+            serde_json::json!({"fromWallet": "Checking", "toWallet": "Holidays", "amountInCents": 100, "accountingDate": "2026-02-01T09:00:00"}),
+            "an unknown wallet",
+        ),
+        (
+            serde_json::json!({"fromWallet": "Checking", "toWallet": "Savings", "amountInCents": 100, "currency": "euro", "accountingDate": "2026-02-01T09:00:00"}),
+            "an invalid currency",
+        ),
+    ];
+
+    for (body, description) in test_cases {
+        let response = app.post_transfer(&body.to_string()).await;
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not fail with 400 Bad Request when the payload had {}.",
+            description
+        );
+    }
+    assert!(app.wallet_payments("Checking").await.is_empty());
+}
+
+#[tokio::test]
+async fn transfer_cannot_use_another_users_wallet() {
+    let app = spawn_app().await;
+    create_wallets(&app).await;
+    app.create_wallet_with_auth(
+        &serde_json::json!({ "name": "Not mine" }).to_string(),
+        &mint_token("someone-else"),
+    )
+    .await;
+
+    let body = serde_json::json!({
+        "fromWallet": "Checking",
+        "toWallet": "Not mine",
+        "amountInCents": 100,
+        "accountingDate": "2026-02-01T09:00:00"
+    });
+    let response = app.post_transfer(&body.to_string()).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn deleting_one_leg_deletes_the_transfer() {
+    let app = spawn_app().await;
+    create_wallets(&app).await;
+    let transfer = transfer(&app, 30000).await;
+
+    let response = app
+        .delete_payment(payment_id(&transfer["creditPaymentId"]))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 204);
+    assert!(app.wallet_payments("Checking").await.is_empty());
+    assert!(app.wallet_payments("Savings").await.is_empty());
+}
+
+#[tokio::test]
+async fn updating_one_leg_updates_the_other() {
+    let app = spawn_app().await;
+    create_wallets(&app).await;
+    let transfer = transfer(&app, 30000).await;
+    let debit_id = payment_id(&transfer["debitPaymentId"]);
+    let update = serde_json::json!({
+        "merchantName": "Transfer to Savings",
+        "amountInCents": -45000,
+        "category": "Transfer",
+        "accountingDate": "2026-02-03T09:00:00",
+        "description": "Bonus savings",
+        "wallet": "Checking"
+    });
+
+    let response = app.update_payment(debit_id, &update.to_string()).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let updated: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(updated["transferId"], transfer["id"]);
+    let credit = app
+        .get_payment(payment_id(&transfer["creditPaymentId"]))
+        .await
+        .json::<serde_json::Value>()
+        .await
+        .unwrap();
+    assert_eq!(credit["amountInCents"], 45000);
+    assert_eq!(credit["accountingDate"], "2026-02-03T09:00:00");
+    assert_eq!(credit["description"], "Bonus savings");
+    assert_eq!(credit["wallet"], "Savings");
+}
@@ -1,6 +1,4 @@
-use crate::helpers::spawn_app;
-use base64::Engine;
-use chrono;
+use crate::helpers::{mint_token, spawn_app};
 use uuid::Uuid;
 
 #[tokio::test]
@@ -15,15 +13,7 @@ async fn create_wallet_returns_200() {
     }
     "#;
 
-    let header = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#);
-    let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(
-        serde_json::json!({
-            "sub": "test-sub",
-            "exp": (chrono::Utc::now().timestamp() + 60)
-        })
-        .to_string(),
-    );
-    let token = format!("{}.{}.", header, payload);
+    let token = mint_token("test-sub");
 
     let response = app.create_wallet_with_auth(body, &token).await;
 
@@ -46,15 +36,7 @@ async fn create_wallet_returns_409_when_duplicate() {
     // Arrange
     let app = spawn_app().await;
     let body = r#"{"name": "My Wallet"}"#;
-    let header = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#);
-    let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(
-        serde_json::json!({
-            "sub": "dup-sub",
-            "exp": (chrono::Utc::now().timestamp() + 60)
-        })
-        .to_string(),
-    );
-    let token = format!("{}.{}.", header, payload);
+    let token = mint_token("dup-sub");
 
     app.create_wallet_with_auth(body, &token).await;
 
@@ -71,15 +53,7 @@ async fn get_wallets_returns_list() {
     let app = spawn_app().await;
     let body1 = r#"{"name": "Wallet A"}"#;
     let body2 = r#"{"name": "Wallet B"}"#;
-    let header = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#);
-    let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(
-        serde_json::json!({
-            "sub": "list-sub",
-            "exp": (chrono::Utc::now().timestamp() + 60)
-        })
-        .to_string(),
-    );
-    let token = format!("{}.{}.", header, payload);
+    let token = mint_token("list-sub");
 
     app.create_wallet_with_auth(body1, &token).await;
     app.create_wallet_with_auth(body2, &token).await;
@@ -99,15 +73,7 @@ async fn delete_wallet_returns_200() {
     // Arrange
     let app = spawn_app().await;
     let body = r#"{"name": "To Delete"}"#;
-    let header = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#);
-    let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(
-        serde_json::json!({
-            "sub": "del-sub",
-            "exp": (chrono::Utc::now().timestamp() + 60)
-        })
-        .to_string(),
-    );
-    let token = format!("{}.{}.", header, payload);
+    let token = mint_token("del-sub");
 
     let create_response = app.create_wallet_with_auth(body, &token).await;
     let json: serde_json::Value = create_response.json().await.unwrap();
@@ -126,3 +92,50 @@ async fn delete_wallet_returns_200() {
         .unwrap();
     assert!(exists.is_none());
 }
+
+#[tokio::test]
+async fn create_wallet_stores_the_default_currency() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = mint_token("currency-sub");
+
+    // Act
+    let response = app
+        .create_wallet_with_auth(r#"{"name": "Dollars", "defaultCurrency": "USD"}"#, &token)
+        .await;
+    let default_response = app
+        .create_wallet_with_auth(r#"{"name": "Euros"}"#, &token)
+        .await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let created: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(created["defaultCurrency"], "USD");
+    let created_default: serde_json::Value = default_response.json().await.unwrap();
+    assert_eq!(created_default["defaultCurrency"], "EUR");
+
+    let wallets: Vec<serde_json::Value> = app
+        .get_wallets_with_auth(&token)
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(wallets[0]["name"], "Dollars");
+    assert_eq!(wallets[0]["defaultCurrency"], "USD");
+}
+
+#[tokio::test]
+async fn create_wallet_rejects_an_invalid_currency() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = mint_token("currency-sub");
+
+    for currency in ["usd", "EURO", "€"] {
+        // Act
+        let body = serde_json::json!({ "name": "Invalid", "defaultCurrency": currency });
+        let response = app.create_wallet_with_auth(&body.to_string(), &token).await;
+
+        // Assert
+        assert_eq!(400, response.status().as_u16(), "{} was accepted", currency);
+    }
+}